@@ -1,16 +1,23 @@
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use gb_core::bus::Bus;
+use gb_core::bus::{Bus, EmulationMode};
 use gb_core::cartridge::Cartridge;
+use gb_core::cpu::cpu::OpcodeHistogram;
 use gb_core::cpu::Cpu;
 use gb_core::gb::GameBoy;
+use gb_core::ppu::Framebuffer;
 
 #[derive(Debug)]
 enum Command {
     Run(RunArgs),
     Suite(SuiteArgs),
     SelfTest(SelfTestArgs),
+    Bench(BenchArgs),
+    Dump(DumpArgs),
+    SaveDump(SaveDumpArgs),
+    SaveInfo(SaveInfoArgs),
 }
 
 #[derive(Debug)]
@@ -24,7 +31,16 @@ struct RunArgs {
     trace_ppu: bool,
     log_serial: bool,
     print_serial: bool,
+    serial_out: Option<PathBuf>,
     print_vram: bool,
+    mode: Option<EmulationMode>,
+    patch_path: Option<PathBuf>,
+    profile: bool,
+    stuck_cycles: Option<u64>,
+    gdb_port: Option<u16>,
+    vgm_out: Option<PathBuf>,
+    fb_hash_at: Option<u64>,
+    fb_hash_every: Option<u64>,
 }
 
 #[derive(Debug)]
@@ -37,6 +53,8 @@ struct SuiteArgs {
     fail_text: Vec<String>,
     print_serial: bool,
     print_vram: bool,
+    mode: Option<EmulationMode>,
+    stuck_cycles: Option<u64>,
 }
 
 #[derive(Debug)]
@@ -46,6 +64,46 @@ struct SelfTestArgs {
     fail_text: Vec<String>,
     print_serial: bool,
     print_vram: bool,
+    stuck_cycles: Option<u64>,
+}
+
+#[derive(Debug)]
+struct BenchArgs {
+    rom_path: PathBuf,
+    seconds: f64,
+    no_render: bool,
+    mode: Option<EmulationMode>,
+}
+
+#[derive(Debug)]
+struct DumpArgs {
+    rom_path: PathBuf,
+    at_pc: Option<u16>,
+    start: u16,
+    end: u16,
+    max_cycles: u64,
+    mode: Option<EmulationMode>,
+}
+
+#[derive(Debug)]
+struct SaveDumpArgs {
+    rom_path: PathBuf,
+    state_path: Option<PathBuf>,
+    frames: Option<u64>,
+    out_path: PathBuf,
+    mode: Option<EmulationMode>,
+}
+
+#[derive(Debug)]
+struct SaveInfoArgs {
+    sav_path: PathBuf,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct BenchStats {
+    frames: u64,
+    cycles: u64,
+    elapsed: Duration,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -53,6 +111,10 @@ enum RomResult {
     Pass,
     Fail,
     Timeout,
+    /// Spinning in a tight loop at `pc` (see `LoopDetector`).
+    Stuck {
+        pc: u16,
+    },
 }
 
 impl RomResult {
@@ -61,24 +123,88 @@ impl RomResult {
             RomResult::Pass => "PASS",
             RomResult::Fail => "FAIL",
             RomResult::Timeout => "TIMEOUT",
+            RomResult::Stuck { .. } => "STUCK",
+        }
+    }
+
+    /// `as_str` plus the loop address for `Stuck`.
+    fn describe(self) -> String {
+        match self {
+            RomResult::Stuck { pc } => format!("STUCK at PC={pc:04X}"),
+            _ => self.as_str().to_string(),
         }
     }
 }
 
+/// Detects a ROM spinning forever: PC stays inside a small window for `threshold_cycles`
+/// without the caller reporting progress (e.g. serial output).
+struct LoopDetector {
+    threshold_cycles: u64,
+    lo: u16,
+    hi: u16,
+    since_cycles: u64,
+}
+
+impl LoopDetector {
+    /// Largest PC span still treated as one tight loop.
+    const WINDOW_BYTES: u16 = 16;
+
+    fn new(threshold_cycles: u64) -> Self {
+        Self {
+            threshold_cycles,
+            lo: 0,
+            hi: 0,
+            since_cycles: 0,
+        }
+    }
+
+    fn reset(&mut self, pc: u16, cycles: u64) {
+        self.lo = pc;
+        self.hi = pc;
+        self.since_cycles = cycles;
+    }
+
+    /// Records the PC after an instruction; returns the lowest PC of the loop once it has
+    /// been spinning for the threshold.
+    fn observe(&mut self, pc: u16, cycles: u64) -> Option<u16> {
+        let (lo, hi) = (self.lo.min(pc), self.hi.max(pc));
+        if hi - lo >= Self::WINDOW_BYTES {
+            self.reset(pc, cycles);
+            return None;
+        }
+        self.lo = lo;
+        self.hi = hi;
+        (cycles - self.since_cycles >= self.threshold_cycles).then_some(lo)
+    }
+}
+
 fn print_usage() {
     eprintln!(
         "Usage:\n\
   gb-cli <rom.gb> [--frames N] [--cycles N] [--headless] [-v|--verbose]\n\
-        [--trace-cpu] [--trace-ppu] [--log-serial] [--print-serial]\n\
+        [--trace-cpu] [--trace-ppu] [--log-serial] [--print-serial] [--serial-out FILE]\n\
+        [--mode M] [--patch FILE] [--profile] [--stuck-cycles N] [--gdb PORT] [--vgm-out FILE]\n\
+        [--fb-hash-at N] [--fb-hash-every N]\n\
   gb-cli run <rom.gb> [--frames N] [--cycles N] [--headless] [-v|--verbose]\n\
-        [--trace-cpu] [--trace-ppu] [--log-serial] [--print-serial]\n\
-  gb-cli suite [--rom-dir DIR] [--frames N] [--cycles N] [--pass-text S] [--fail-text S] [--print-serial] [ROM...]+\n\
-  gb-cli self-test [--cycles N] [--pass-text S] [--fail-text S] [--print-serial]\n\
+        [--trace-cpu] [--trace-ppu] [--log-serial] [--print-serial] [--serial-out FILE]\n\
+        [--mode M] [--patch FILE] [--profile] [--stuck-cycles N] [--gdb PORT] [--vgm-out FILE]\n\
+        [--fb-hash-at N] [--fb-hash-every N]\n\
+  gb-cli suite [--rom-dir DIR] [--frames N] [--cycles N] [--pass-text S] [--fail-text S] [--print-serial] [--mode M] [--stuck-cycles N] [ROM...]+\n\
+  gb-cli self-test [--cycles N] [--pass-text S] [--fail-text S] [--print-serial] [--stuck-cycles N]\n\
+  gb-cli bench <rom.gb> [--seconds N] [--no-render] [--mode M]\n\
+  gb-cli dump <rom.gb> --range START:END [--at-pc ADDR] [--cycles N] [--mode M]\n\
+  gb-cli savedump <rom.gb> (--state FILE | --frames N) --out FILE [--mode M]\n\
+  gb-cli saveinfo <file.sav>\n\
 \n\
 Commands:\n\
   run        Run a single ROM (default if no subcommand is given).\n\
   suite      Discover and run a set of ROMs (default dir: ./roms).\n\
   self-test  Run a tiny built-in ROM that prints 'Passed' via serial.\n\
+  bench      Run a ROM unthrottled for N seconds (default 5) and report emulated FPS.\n\
+  dump       Run a ROM until PC reaches ADDR, then hex-dump memory (xxd format).\n\
+  savedump   Write the cartridge RAM of a save state (or of a run) as a raw .sav,\n\
+             without the GBSV1 trailer, for use with other emulators.\n\
+  saveinfo   Report a .sav file's size and whether it carries a GBSV1 or BGB RTC trailer.\n\
 \n\
 Optional debug output (run command):\n\
   -v, --verbose   Print ROM metadata + run summary (stderr).\n\
@@ -86,6 +212,32 @@ Optional debug output (run command):\n\
   --trace-ppu     Print PPU LY/mode transitions (stderr).\n\
   --log-serial    Stream serial output to stdout as it is produced.\n\
   --print-serial  Print captured serial output at the end.\n\
+  --serial-out FILE  Append raw serial output to FILE as it is produced.\n\
+  --profile       Print the most executed opcodes at the end (stderr).\n\
+  --gdb PORT      Wait for a gdb connection on 127.0.0.1:PORT and run under its control;\n\
+                  the run continues normally once gdb detaches.\n\
+  --vgm-out FILE  Record every APU register write and save the run's audio as a VGM file.\n\
+  --fb-hash-at N  Print a CRC-32 of the framebuffer when frame N completes, then stop.\n\
+  --fb-hash-every N  Print a framebuffer CRC-32 every N frames, for diffing across commits.\n\
+\n\
+Hang detection (run/suite/self-test):\n\
+  --stuck-cycles N  Report STUCK (with the loop PC) if PC stays within 16 bytes for N\n\
+                    cycles without serial output. Off by default.\n\
+\n\
+Benchmark (bench):\n\
+  --seconds N     Wall-clock seconds to run for (fractions allowed).\n\
+  --no-render     Skip scanline rendering to measure CPU/timing throughput only.\n\
+\n\
+Memory dump (dump):\n\
+  --range S:E     Inclusive address range to dump, in hex (e.g. C000:C0FF).\n\
+  --at-pc ADDR    Stop when PC reaches ADDR (hex); without it, run until the cycle cap.\n\
+  --cycles N      Cycle cap (default 100000000); dumps anyway if ADDR is never reached.\n\
+\n\
+Hardware mode (run/suite/bench/dump/savedump):\n\
+  --mode M        One of 'auto' (default, from cartridge header), 'dmg', or 'cgb'.\n\
+\n\
+ROM patches (run):\n\
+  --patch FILE    Apply an IPS or BPS patch to the ROM before loading it.\n\
 \n\
 Suite pass/fail detection:\n\
   - Captures bytes written to SB (0xFF01) when SC (0xFF02) is written with bit7 set\n\
@@ -94,6 +246,7 @@ Suite pass/fail detection:\n\
         which is used by suites like cgb_sound for deterministic PASS/FAIL reporting.\n\
   - Marks PASS if output contains any --pass-text (default: 'passed').\n\
   - Marks FAIL if output contains any --fail-text (default: 'failed', 'fail').\n\
+  - Marks STUCK if --stuck-cycles is set and the ROM spins in a tight loop.\n\
   - Otherwise stops at limits and marks TIMEOUT.\n"
     );
     eprintln!("  --print-vram    Print scraped BG tilemap text on FAIL/TIMEOUT.");
@@ -113,10 +266,25 @@ fn parse_args() -> Result<Command, String> {
         "run" => parse_run_args(&args[1..]).map(Command::Run),
         "suite" => parse_suite_args(&args[1..]).map(Command::Suite),
         "self-test" => parse_self_test_args(&args[1..]).map(Command::SelfTest),
+        "bench" => parse_bench_args(&args[1..]).map(Command::Bench),
+        "dump" => parse_dump_args(&args[1..]).map(Command::Dump),
+        "savedump" => parse_savedump_args(&args[1..]).map(Command::SaveDump),
+        "saveinfo" => parse_saveinfo_args(&args[1..]).map(Command::SaveInfo),
         _ => parse_run_args(&args).map(Command::Run),
     }
 }
 
+/// Takes the value of `--stuck-cycles` from `it`: a nonzero cycle count.
+fn parse_stuck_cycles<'a>(it: &mut impl Iterator<Item = &'a String>) -> Result<u64, String> {
+    let v = it
+        .next()
+        .ok_or_else(|| "--stuck-cycles requires a value".to_string())?;
+    v.parse::<u64>()
+        .ok()
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("invalid --stuck-cycles value: {v}"))
+}
+
 fn parse_run_args(args: &[String]) -> Result<RunArgs, String> {
     if args.is_empty() {
         return Err("missing ROM path".to_string());
@@ -133,7 +301,16 @@ fn parse_run_args(args: &[String]) -> Result<RunArgs, String> {
     let mut trace_ppu = false;
     let mut log_serial = false;
     let mut print_serial = false;
+    let mut serial_out: Option<PathBuf> = None;
     let mut print_vram = false;
+    let mut mode: Option<EmulationMode> = None;
+    let mut patch_path: Option<PathBuf> = None;
+    let mut profile = false;
+    let mut stuck_cycles: Option<u64> = None;
+    let mut gdb_port: Option<u16> = None;
+    let mut vgm_out: Option<PathBuf> = None;
+    let mut fb_hash_at: Option<u64> = None;
+    let mut fb_hash_every: Option<u64> = None;
 
     while let Some(arg) = it.next() {
         match arg.as_str() {
@@ -148,6 +325,20 @@ fn parse_run_args(args: &[String]) -> Result<RunArgs, String> {
             "--log-serial" => log_serial = true,
             "--print-serial" => print_serial = true,
             "--print-vram" => print_vram = true,
+            "--profile" => profile = true,
+            "--stuck-cycles" => stuck_cycles = Some(parse_stuck_cycles(&mut it)?),
+            "--mode" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--mode requires a value".to_string())?;
+                mode = EmulationMode::parse_override(v)?;
+            }
+            "--patch" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--patch requires a value".to_string())?;
+                patch_path = Some(PathBuf::from(v));
+            }
             "--frames" => {
                 let v = it
                     .next()
@@ -166,6 +357,52 @@ fn parse_run_args(args: &[String]) -> Result<RunArgs, String> {
                         .map_err(|_| format!("invalid --cycles value: {v}"))?,
                 );
             }
+            "--gdb" => {
+                if !cfg!(feature = "gdb") {
+                    return Err("--gdb requires gb-cli built with the 'gdb' feature".to_string());
+                }
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--gdb requires a value".to_string())?;
+                gdb_port = Some(
+                    v.parse::<u16>()
+                        .map_err(|_| format!("invalid --gdb value: {v}"))?,
+                );
+            }
+            "--serial-out" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--serial-out requires a value".to_string())?;
+                serial_out = Some(PathBuf::from(v));
+            }
+            "--vgm-out" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--vgm-out requires a value".to_string())?;
+                vgm_out = Some(PathBuf::from(v));
+            }
+            "--fb-hash-at" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--fb-hash-at requires a value".to_string())?;
+                fb_hash_at = Some(
+                    v.parse::<u64>()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("invalid --fb-hash-at value: {v}"))?,
+                );
+            }
+            "--fb-hash-every" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--fb-hash-every requires a value".to_string())?;
+                fb_hash_every = Some(
+                    v.parse::<u64>()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("invalid --fb-hash-every value: {v}"))?,
+                );
+            }
             _ if arg.starts_with('-') => return Err(format!("unknown flag: {arg}")),
             _ => return Err(format!("unexpected extra positional arg: {arg}")),
         }
@@ -181,7 +418,16 @@ fn parse_run_args(args: &[String]) -> Result<RunArgs, String> {
         trace_ppu,
         log_serial,
         print_serial,
+        serial_out,
         print_vram,
+        mode,
+        patch_path,
+        profile,
+        stuck_cycles,
+        gdb_port,
+        vgm_out,
+        fb_hash_at,
+        fb_hash_every,
     })
 }
 
@@ -194,6 +440,8 @@ fn parse_suite_args(args: &[String]) -> Result<SuiteArgs, String> {
     let mut fail_text = vec!["failed".to_string(), "fail".to_string()];
     let mut print_serial = false;
     let mut print_vram = false;
+    let mut mode: Option<EmulationMode> = None;
+    let mut stuck_cycles: Option<u64> = None;
 
     let mut it = args.iter();
     while let Some(arg) = it.next() {
@@ -240,6 +488,13 @@ fn parse_suite_args(args: &[String]) -> Result<SuiteArgs, String> {
             }
             "--print-serial" => print_serial = true,
             "--print-vram" => print_vram = true,
+            "--mode" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--mode requires a value".to_string())?;
+                mode = EmulationMode::parse_override(v)?;
+            }
+            "--stuck-cycles" => stuck_cycles = Some(parse_stuck_cycles(&mut it)?),
             _ if arg.starts_with('-') => return Err(format!("unknown flag: {arg}")),
             _ => rom_paths.push(PathBuf::from(arg)),
         }
@@ -254,6 +509,8 @@ fn parse_suite_args(args: &[String]) -> Result<SuiteArgs, String> {
         fail_text,
         print_serial,
         print_vram,
+        mode,
+        stuck_cycles,
     })
 }
 
@@ -263,6 +520,7 @@ fn parse_self_test_args(args: &[String]) -> Result<SelfTestArgs, String> {
     let mut fail_text = vec!["failed".to_string(), "fail".to_string()];
     let mut print_serial = false;
     let mut print_vram = false;
+    let mut stuck_cycles: Option<u64> = None;
 
     let mut it = args.iter();
     while let Some(arg) = it.next() {
@@ -294,6 +552,7 @@ fn parse_self_test_args(args: &[String]) -> Result<SelfTestArgs, String> {
             }
             "--print-serial" => print_serial = true,
             "--print-vram" => print_vram = true,
+            "--stuck-cycles" => stuck_cycles = Some(parse_stuck_cycles(&mut it)?),
             _ if arg.starts_with('-') => return Err(format!("unknown flag: {arg}")),
             _ => return Err(format!("unexpected positional arg: {arg}")),
         }
@@ -305,119 +564,229 @@ fn parse_self_test_args(args: &[String]) -> Result<SelfTestArgs, String> {
         fail_text,
         print_serial,
         print_vram,
+        stuck_cycles,
     })
 }
 
-fn init_common_io_post_boot(gb: &mut GameBoy) {
-    // Initialize key IO registers (enough for typical test ROMs).
-    // Use bus writes to respect any masking side effects.
-    let io_inits: &[(u16, u8)] = &[
-        (0xFF00, 0xCF),
-        (0xFF05, 0x00),
-        (0xFF06, 0x00),
-        (0xFF07, 0x00),
-        (0xFF10, 0x80),
-        (0xFF11, 0xBF),
-        (0xFF12, 0xF3),
-        (0xFF14, 0xBF),
-        (0xFF16, 0x3F),
-        (0xFF17, 0x00),
-        (0xFF19, 0xBF),
-        (0xFF1A, 0x7F),
-        (0xFF1B, 0xFF),
-        (0xFF1C, 0x9F),
-        (0xFF1E, 0xBF),
-        (0xFF20, 0xFF),
-        (0xFF21, 0x00),
-        (0xFF22, 0x00),
-        (0xFF23, 0xBF),
-        (0xFF24, 0x77),
-        (0xFF25, 0xF3),
-        (0xFF26, 0xF1),
-        (0xFF40, 0x91),
-        (0xFF42, 0x00),
-        (0xFF43, 0x00),
-        (0xFF45, 0x00),
-        (0xFF47, 0xFC),
-        (0xFF48, 0xFF),
-        (0xFF49, 0xFF),
-        (0xFF4A, 0x00),
-        (0xFF4B, 0x00),
-    ];
-
-    for &(addr, val) in io_inits {
-        gb.bus.write8(addr, val);
+fn parse_bench_args(args: &[String]) -> Result<BenchArgs, String> {
+    let mut rom_path: Option<PathBuf> = None;
+    let mut seconds = 5.0;
+    let mut no_render = false;
+    let mut mode: Option<EmulationMode> = None;
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print_usage();
+                std::process::exit(0);
+            }
+            "--seconds" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--seconds requires a value".to_string())?;
+                seconds = v
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|s| s.is_finite() && *s > 0.0)
+                    .ok_or_else(|| format!("invalid --seconds value: {v}"))?;
+            }
+            "--no-render" => no_render = true,
+            "--mode" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--mode requires a value".to_string())?;
+                mode = EmulationMode::parse_override(v)?;
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown flag: {arg}")),
+            _ => {
+                if rom_path.is_some() {
+                    return Err(format!("unexpected extra positional arg: {arg}"));
+                }
+                rom_path = Some(PathBuf::from(arg));
+            }
+        }
     }
+
+    Ok(BenchArgs {
+        rom_path: rom_path.ok_or_else(|| "missing ROM path".to_string())?,
+        seconds,
+        no_render,
+        mode,
+    })
 }
 
-fn init_dmg_post_boot(gb: &mut GameBoy) {
-    // DMG (no-boot-rom) register values commonly used by emulators.
-    gb.cpu.a = 0x01;
-    gb.cpu.f = 0xB0;
-    gb.cpu.b = 0x00;
-    gb.cpu.c = 0x13;
-    gb.cpu.d = 0x00;
-    gb.cpu.e = 0xD8;
-    gb.cpu.h = 0x01;
-    gb.cpu.l = 0x4D;
-    gb.cpu.sp = 0xFFFE;
-    gb.cpu.pc = 0x0100;
-
-    gb.bus.ie = 0x00;
-    gb.bus.iflag = 0x00;
-
-    init_common_io_post_boot(gb);
+/// Parses a 16-bit address in hex, with an optional `0x` or `$` prefix.
+fn parse_addr(v: &str) -> Result<u16, String> {
+    let digits = v
+        .strip_prefix("0x")
+        .or_else(|| v.strip_prefix("0X"))
+        .or_else(|| v.strip_prefix('$'))
+        .unwrap_or(v);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid address: {v}"))
 }
 
-fn init_cgb_post_boot(gb: &mut GameBoy) {
-    // CGB (no-boot-rom) register values commonly used by emulators.
-    // These are important for CGB-only test ROMs that expect boot ROM side effects.
-    gb.cpu.a = 0x11;
-    gb.cpu.f = 0x80;
-    gb.cpu.b = 0x00;
-    gb.cpu.c = 0x00;
-    gb.cpu.d = 0xFF;
-    gb.cpu.e = 0x56;
-    gb.cpu.h = 0x00;
-    gb.cpu.l = 0x0D;
-    gb.cpu.sp = 0xFFFE;
-    gb.cpu.pc = 0x0100;
-
-    gb.bus.ie = 0x00;
-    gb.bus.iflag = 0x00;
-
-    // Use the shared IO init list for the common registers (sound/timer/LCDC).
-    init_common_io_post_boot(gb);
-
-    let cgb_io_inits: &[(u16, u8)] = &[
-        (0xFF4D, 0x00), // KEY1 (speed switch prepare cleared)
-        (0xFF4F, 0x00), // VBK
-        (0xFF70, 0x01), // SVBK (bank 1)
-        (0xFF68, 0x00), // BCPS
-        (0xFF69, 0x00), // BCPD
-        (0xFF6A, 0x00), // OCPS
-        (0xFF6B, 0x00), // OCPD
-    ];
-    for &(addr, val) in cgb_io_inits {
-        gb.bus.write8(addr, val);
-    }
-
-    // CGB boot ROM sets BG palette 0 color 0 to white (0x7FFF).
-    // Without this, many CGB games start with a black screen because
-    // palette RAM defaults to zero.
-    gb.bus.ppu.write_bgpi(0x80); // auto-increment, index 0
-    gb.bus.ppu.write_bgpd(0xFF); // low byte of 0x7FFF
-    gb.bus.ppu.write_bgpd(0x7F); // high byte of 0x7FFF
+fn parse_dump_args(args: &[String]) -> Result<DumpArgs, String> {
+    let mut rom_path: Option<PathBuf> = None;
+    let mut at_pc: Option<u16> = None;
+    let mut range: Option<(u16, u16)> = None;
+    let mut max_cycles: u64 = 100_000_000;
+    let mut mode: Option<EmulationMode> = None;
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print_usage();
+                std::process::exit(0);
+            }
+            "--at-pc" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--at-pc requires a value".to_string())?;
+                at_pc = Some(parse_addr(v)?);
+            }
+            "--range" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--range requires a value".to_string())?;
+                let (start, end) = v
+                    .split_once(':')
+                    .ok_or_else(|| format!("invalid --range value (expected START:END): {v}"))?;
+                let (start, end) = (parse_addr(start)?, parse_addr(end)?);
+                if end < start {
+                    return Err(format!("invalid --range value (END before START): {v}"));
+                }
+                range = Some((start, end));
+            }
+            "--cycles" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--cycles requires a value".to_string())?;
+                max_cycles = v
+                    .parse::<u64>()
+                    .map_err(|_| format!("invalid --cycles value: {v}"))?;
+            }
+            "--mode" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--mode requires a value".to_string())?;
+                mode = EmulationMode::parse_override(v)?;
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown flag: {arg}")),
+            _ => {
+                if rom_path.is_some() {
+                    return Err(format!("unexpected extra positional arg: {arg}"));
+                }
+                rom_path = Some(PathBuf::from(arg));
+            }
+        }
+    }
+
+    let (start, end) = range.ok_or_else(|| "dump requires --range START:END".to_string())?;
+    Ok(DumpArgs {
+        rom_path: rom_path.ok_or_else(|| "missing ROM path".to_string())?,
+        at_pc,
+        start,
+        end,
+        max_cycles,
+        mode,
+    })
 }
 
-fn init_post_boot(gb: &mut GameBoy) {
-    // The bus decides DMG vs CGB from the cartridge header.
-    // Use a matching "post-boot" register init to avoid CGB-specific test flakiness.
-    if gb.bus.mode == gb_core::bus::EmulationMode::Cgb {
-        init_cgb_post_boot(gb);
-    } else {
-        init_dmg_post_boot(gb);
+fn parse_savedump_args(args: &[String]) -> Result<SaveDumpArgs, String> {
+    let mut rom_path: Option<PathBuf> = None;
+    let mut state_path: Option<PathBuf> = None;
+    let mut frames: Option<u64> = None;
+    let mut out_path: Option<PathBuf> = None;
+    let mut mode: Option<EmulationMode> = None;
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print_usage();
+                std::process::exit(0);
+            }
+            "--state" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--state requires a value".to_string())?;
+                state_path = Some(PathBuf::from(v));
+            }
+            "--frames" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--frames requires a value".to_string())?;
+                frames = Some(
+                    v.parse::<u64>()
+                        .map_err(|_| format!("invalid --frames value: {v}"))?,
+                );
+            }
+            "--out" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--out requires a value".to_string())?;
+                out_path = Some(PathBuf::from(v));
+            }
+            "--mode" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--mode requires a value".to_string())?;
+                mode = EmulationMode::parse_override(v)?;
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown flag: {arg}")),
+            _ => {
+                if rom_path.is_some() {
+                    return Err(format!("unexpected extra positional arg: {arg}"));
+                }
+                rom_path = Some(PathBuf::from(arg));
+            }
+        }
+    }
+
+    if state_path.is_some() == frames.is_some() {
+        return Err("savedump requires exactly one of --state FILE or --frames N".to_string());
     }
+    Ok(SaveDumpArgs {
+        rom_path: rom_path.ok_or_else(|| "missing ROM path".to_string())?,
+        state_path,
+        frames,
+        out_path: out_path.ok_or_else(|| "savedump requires --out FILE".to_string())?,
+        mode,
+    })
+}
+
+fn parse_saveinfo_args(args: &[String]) -> Result<SaveInfoArgs, String> {
+    let mut sav_path: Option<PathBuf> = None;
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print_usage();
+                std::process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown flag: {arg}")),
+            _ => {
+                if sav_path.is_some() {
+                    return Err(format!("unexpected extra positional arg: {arg}"));
+                }
+                sav_path = Some(PathBuf::from(arg));
+            }
+        }
+    }
+    Ok(SaveInfoArgs {
+        sav_path: sav_path.ok_or_else(|| "missing .sav path".to_string())?,
+    })
+}
+
+fn new_gameboy(cart: Cartridge, mode: Option<EmulationMode>) -> GameBoy {
+    let bus = match mode {
+        Some(mode) => Bus::new_with_mode(cart, mode),
+        None => Bus::new(cart),
+    };
+    let mut gb = GameBoy::new(Cpu::new(), bus);
+    gb.init_post_boot();
+    gb
 }
 
 fn discover_roms(dir: &Path) -> Result<Vec<PathBuf>, String> {
@@ -486,8 +855,8 @@ fn scrape_bg_tilemap_text_lower(vram: &[u8], map_offset: usize) -> String {
 
 fn scrape_all_bg_text_lower(bus: &Bus) -> String {
     // Try both BG tilemaps. Some ROMs use LCDC bit3 to pick one; others might write either.
-    let t9800 = scrape_bg_tilemap_text_lower(&bus.vram, 0x1800);
-    let t9c00 = scrape_bg_tilemap_text_lower(&bus.vram, 0x1C00);
+    let t9800 = scrape_bg_tilemap_text_lower(&bus.vram[..], 0x1800);
+    let t9c00 = scrape_bg_tilemap_text_lower(&bus.vram[..], 0x1C00);
     // Keep it simple: concatenate so substring search can hit either.
     // (Separators are spaces/newlines to avoid accidentally concatenating words.)
     format!("{t9800}\n{t9c00}")
@@ -509,8 +878,8 @@ fn scrape_bg_tilemap_text(vram: &[u8], map_offset: usize) -> String {
 }
 
 fn scrape_all_bg_text(bus: &Bus) -> String {
-    let t9800 = scrape_bg_tilemap_text(&bus.vram, 0x1800);
-    let t9c00 = scrape_bg_tilemap_text(&bus.vram, 0x1C00);
+    let t9800 = scrape_bg_tilemap_text(&bus.vram[..], 0x1800);
+    let t9c00 = scrape_bg_tilemap_text(&bus.vram[..], 0x1C00);
     format!("{t9800}\n{t9c00}")
 }
 
@@ -577,22 +946,22 @@ fn maybe_print_blargg_cart_ram_output(label: &str, out: &BlarggCartRamOutput) {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_for_serial_result(
     cart: Cartridge,
+    mode: Option<EmulationMode>,
     max_frames: Option<u64>,
     max_cycles: Option<u64>,
     pass_text: &[String],
     fail_text: &[String],
     print_vram: bool,
+    stuck_cycles: Option<u64>,
 ) -> (RomResult, Vec<u8>, u64, u64) {
-    let mut gb = GameBoy {
-        cpu: Cpu::new(),
-        bus: Bus::new(cart),
-    };
-    init_post_boot(&mut gb);
+    let mut gb = new_gameboy(cart, mode);
+    // Pass/fail comes from serial, cart RAM and VRAM text; nobody looks at the pixels.
+    gb.bus.ppu.set_rendering_enabled(false);
+    let mut loop_detector = stuck_cycles.map(LoopDetector::new);
 
-    let mut frames: u64 = 0;
-    let mut cycles: u64 = 0;
     let mut output: Vec<u8> = Vec::new();
 
     // Poll blargg cart-RAM ($A000) output at a fixed cadence so CGB sound tests can
@@ -600,7 +969,9 @@ fn run_for_serial_result(
     const CART_RAM_POLL_PERIOD_CPU_CYCLES: u64 = 200_000;
 
     loop {
-        if max_frames.is_some_and(|m| frames >= m) || max_cycles.is_some_and(|m| cycles >= m) {
+        if max_frames.is_some_and(|m| gb.frames() >= m)
+            || max_cycles.is_some_and(|m| gb.cycles() >= m)
+        {
             // blargg cart-RAM output (last-chance): some suites (notably cgb_sound) write
             // deterministic results to $A000 rather than serial.
             if let Some(out) = read_blargg_cart_ram_output(&mut gb.bus) {
@@ -615,7 +986,7 @@ fn run_for_serial_result(
                             merged.push(b'\n');
                         }
                     }
-                    return (res, merged, frames, cycles);
+                    return (res, merged, gb.frames(), gb.cycles());
                 }
             }
 
@@ -628,10 +999,10 @@ fn run_for_serial_result(
                         scrape_all_bg_text(&gb.bus)
                     );
                 }
-                return (RomResult::Fail, output, frames, cycles);
+                return (RomResult::Fail, output, gb.frames(), gb.cycles());
             }
             if contains_any(&screen_lower, pass_text) {
-                return (RomResult::Pass, output, frames, cycles);
+                return (RomResult::Pass, output, gb.frames(), gb.cycles());
             }
             if print_vram {
                 println!(
@@ -639,12 +1010,19 @@ fn run_for_serial_result(
                     scrape_all_bg_text(&gb.bus)
                 );
             }
-            return (RomResult::Timeout, output, frames, cycles);
+            return (RomResult::Timeout, output, gb.frames(), gb.cycles());
         }
 
-        cycles += gb.step() as u64;
+        gb.step();
 
         let new = gb.bus.serial.take_output();
+        if let Some(detector) = loop_detector.as_mut() {
+            if !new.is_empty() {
+                detector.reset(gb.cpu.pc, gb.cycles());
+            } else if let Some(pc) = detector.observe(gb.cpu.pc, gb.cycles()) {
+                return (RomResult::Stuck { pc }, output, gb.frames(), gb.cycles());
+            }
+        }
         if !new.is_empty() {
             output.extend_from_slice(&new);
             let out_lower = String::from_utf8_lossy(&output).to_ascii_lowercase();
@@ -655,14 +1033,15 @@ fn run_for_serial_result(
                         scrape_all_bg_text(&gb.bus)
                     );
                 }
-                return (RomResult::Fail, output, frames, cycles);
+                return (RomResult::Fail, output, gb.frames(), gb.cycles());
             }
             if contains_any(&out_lower, pass_text) {
-                return (RomResult::Pass, output, frames, cycles);
+                return (RomResult::Pass, output, gb.frames(), gb.cycles());
             }
         }
 
         // Check blargg cart-RAM status periodically as an alternative to serial output.
+        let cycles = gb.cycles();
         if cycles != 0 && cycles.is_multiple_of(CART_RAM_POLL_PERIOD_CPU_CYCLES) {
             if let Some(out) = read_blargg_cart_ram_output(&mut gb.bus) {
                 if let Some(res) = blargg_cart_ram_result(out.status) {
@@ -681,14 +1060,14 @@ fn run_for_serial_result(
                             merged.push(b'\n');
                         }
                     }
-                    return (res, merged, frames, cycles);
+                    return (res, merged, gb.frames(), gb.cycles());
                 }
             }
         }
 
         if gb.bus.ppu.frame_ready() {
-            frames += 1;
             gb.bus.ppu.clear_frame_ready();
+            let frames = gb.frames();
 
             // VRAM fallback: check for on-screen "Passed"/"Failed" text.
             // Keep it cheap-ish: check early frames and then every few frames.
@@ -701,10 +1080,10 @@ fn run_for_serial_result(
                             scrape_all_bg_text(&gb.bus)
                         );
                     }
-                    return (RomResult::Fail, output, frames, cycles);
+                    return (RomResult::Fail, output, gb.frames(), gb.cycles());
                 }
                 if contains_any(&screen_lower, pass_text) {
-                    return (RomResult::Pass, output, frames, cycles);
+                    return (RomResult::Pass, output, gb.frames(), gb.cycles());
                 }
             }
         }
@@ -740,6 +1119,9 @@ fn make_self_test_rom() -> Vec<u8> {
         rom[pc + 1] = 0x02;
         rom[pc + 2] = 0xFF;
         pc += 3;
+        // Wait for the transfer to finish: LDH A, (02) ; BIT 7, A ; JR NZ, -6
+        rom[pc..pc + 6].copy_from_slice(&[0xF0, 0x02, 0xCB, 0x7F, 0x20, 0xFA]);
+        pc += 6;
     }
     // JR -2 (infinite loop)
     rom[pc] = 0x18;
@@ -753,10 +1135,52 @@ fn make_self_test_rom() -> Vec<u8> {
     rom
 }
 
+fn print_opcode_profile(hist: &OpcodeHistogram) {
+    const TOP_OPCODES: usize = 20;
+
+    let total: u64 = hist.base.iter().sum();
+    eprintln!("--- opcode profile ({total} instructions) ---");
+    for (op, count) in hist.top(TOP_OPCODES) {
+        let pct = count as f64 * 100.0 / total.max(1) as f64;
+        if op > 0xFF {
+            eprintln!("  CB {:02X}  {count:>12}  {pct:5.1}%", op & 0xFF);
+        } else {
+            eprintln!("  {op:02X}     {count:>12}  {pct:5.1}%");
+        }
+    }
+}
+
+/// Blocks until a gdb client connects on `port`, then lets it drive `gb` until it detaches.
+#[cfg(feature = "gdb")]
+fn run_gdb_session(gb: &mut GameBoy, port: u16) -> Result<gb_core::debug::gdb::SessionEnd, String> {
+    let listener = std::net::TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| format!("failed to listen on port {port}: {e}"))?;
+    eprintln!("Waiting for gdb on 127.0.0.1:{port} (target remote :{port})...");
+    let (stream, peer) = listener
+        .accept()
+        .map_err(|e| format!("failed to accept gdb connection: {e}"))?;
+    eprintln!("gdb connected from {peer}");
+    let end = gb_core::debug::gdb::GdbStub::new(stream)
+        .serve(gb)
+        .map_err(|e| format!("gdb connection failed: {e}"))?;
+    eprintln!("gdb session ended: {end:?}");
+    Ok(end)
+}
+
 fn run_single(args: RunArgs) -> Result<i32, String> {
-    let rom = std::fs::read(&args.rom_path)
+    let mut rom = std::fs::read(&args.rom_path)
         .map_err(|e| format!("failed to read ROM {}: {e}", args.rom_path.display()))?;
-    let cart = Cartridge::from_rom(rom).map_err(|e| format!("invalid ROM: {e:?}"))?;
+    if let Some(patch_path) = &args.patch_path {
+        let patch = std::fs::read(patch_path)
+            .map_err(|e| format!("failed to read patch {}: {e}", patch_path.display()))?;
+        gb_core::patch::apply_patch(&mut rom, &patch)
+            .map_err(|e| format!("failed to apply patch {}: {e:?}", patch_path.display()))?;
+    }
+    let (cart, warning) =
+        Cartridge::from_rom_checked(rom).map_err(|e| format!("invalid ROM: {e:?}"))?;
+    if let Some(warning) = warning {
+        eprintln!("warning: {warning}");
+    }
 
     if args.verbose {
         eprintln!(
@@ -768,25 +1192,42 @@ fn run_single(args: RunArgs) -> Result<i32, String> {
         );
     }
 
-    let mut gb = GameBoy {
-        cpu: Cpu::new(),
-        bus: Bus::new(cart),
-    };
-    init_post_boot(&mut gb);
+    let mut gb = new_gameboy(cart, args.mode);
+    gb.cpu.set_opcode_profiling(args.profile);
+    gb.bus.apu.set_vgm_capture(args.vgm_out.is_some());
 
-    let mut frames: u64 = 0;
-    let mut cycles: u64 = 0;
+    if args.verbose {
+        eprintln!("Emulation mode: {:?}", gb.bus.mode);
+    }
+
+    #[cfg(feature = "gdb")]
+    if let Some(port) = args.gdb_port {
+        if run_gdb_session(&mut gb, port)? == gb_core::debug::gdb::SessionEnd::Killed {
+            return Ok(0);
+        }
+    }
 
-    let mut last_ly: u8 = gb.bus.io[0x44];
-    let mut last_mode: u8 = gb.bus.io[0x41] & 0x03;
+    let mut last_ppu = gb.bus.ppu_status();
 
     let mut serial_out: Vec<u8> = Vec::new();
     let mut serial_batch: Vec<u8> = Vec::new();
     let mut stdout = std::io::stdout();
+    let mut serial_file = match &args.serial_out {
+        Some(path) => Some(
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("failed to open {}: {e}", path.display()))?,
+        ),
+        None => None,
+    };
+    let mut loop_detector = args.stuck_cycles.map(LoopDetector::new);
+    let mut stuck_at: Option<u16> = None;
 
     loop {
-        if args.max_frames.is_some_and(|m| frames >= m)
-            || args.max_cycles.is_some_and(|m| cycles >= m)
+        if args.max_frames.is_some_and(|m| gb.frames() >= m)
+            || args.max_cycles.is_some_and(|m| gb.cycles() >= m)
         {
             if args.print_vram {
                 println!(
@@ -794,47 +1235,42 @@ fn run_single(args: RunArgs) -> Result<i32, String> {
                     scrape_all_bg_text(&gb.bus)
                 );
             }
+            if args.verbose {
+                eprint!("--- Machine state (on TIMEOUT) ---\n{}", gb.debug_dump());
+            }
             break;
         }
 
         if args.trace_cpu {
+            let cycles = gb.cycles();
             let pc = gb.cpu.pc;
             let b0 = gb.bus.read8(pc);
             let b1 = gb.bus.read8(pc.wrapping_add(1));
             let b2 = gb.bus.read8(pc.wrapping_add(2));
             eprintln!(
-                "CYC={cycles:010} PC={pc:04X} OP={b0:02X} {b1:02X} {b2:02X} AF={:02X}{:02X} BC={:02X}{:02X} DE={:02X}{:02X} HL={:02X}{:02X} SP={:04X} IME={} HALT={} IE={:02X} IF={:02X}",
-                gb.cpu.a,
-                gb.cpu.f,
-                gb.cpu.b,
-                gb.cpu.c,
-                gb.cpu.d,
-                gb.cpu.e,
-                gb.cpu.h,
-                gb.cpu.l,
-                gb.cpu.sp,
-                gb.cpu.ime,
-                gb.cpu.halted,
-                gb.bus.ie,
-                gb.bus.iflag
+                "CYC={cycles:010} PC={pc:04X} OP={b0:02X} {b1:02X} {b2:02X} {}",
+                gb.register_summary()
             );
-            let step_cycles = gb.cpu.step(&mut gb.bus);
-            cycles += step_cycles as u64;
-        } else {
-            cycles += gb.step() as u64;
         }
+        gb.step();
 
         if args.trace_ppu {
-            let ly = gb.bus.io[0x44];
-            let mode = gb.bus.io[0x41] & 0x03;
-            if ly != last_ly || mode != last_mode {
-                eprintln!("PPU ly={ly} mode={mode}");
-                last_ly = ly;
-                last_mode = mode;
+            let ppu = gb.bus.ppu_status();
+            if (ppu.ly, ppu.mode) != (last_ppu.ly, last_ppu.mode) {
+                eprintln!("PPU ly={} mode={}", ppu.ly, ppu.mode);
+                last_ppu = ppu;
             }
         }
 
         serial_batch.extend(gb.bus.serial.drain_output());
+        if let Some(detector) = loop_detector.as_mut() {
+            if !serial_batch.is_empty() {
+                detector.reset(gb.cpu.pc, gb.cycles());
+            } else if let Some(pc) = detector.observe(gb.cpu.pc, gb.cycles()) {
+                stuck_at = Some(pc);
+                break;
+            }
+        }
         if !serial_batch.is_empty() {
             if args.log_serial {
                 stdout
@@ -844,6 +1280,10 @@ fn run_single(args: RunArgs) -> Result<i32, String> {
                     .flush()
                     .map_err(|e| format!("failed to flush serial output: {e}"))?;
             }
+            if let Some(file) = serial_file.as_mut() {
+                file.write_all(&serial_batch)
+                    .map_err(|e| format!("failed to write serial output: {e}"))?;
+            }
             if args.print_serial {
                 serial_out.extend_from_slice(&serial_batch);
             }
@@ -851,31 +1291,63 @@ fn run_single(args: RunArgs) -> Result<i32, String> {
         }
 
         if gb.bus.ppu.frame_ready() {
-            frames += 1;
             gb.bus.ppu.clear_frame_ready();
 
             if args.verbose && !args.headless {
-                let checksum: u64 = gb
-                    .bus
-                    .ppu
-                    .framebuffer()
-                    .iter()
-                    .fold(0u64, |acc, &px| acc.wrapping_add(px as u64));
-                eprintln!("frame {frames} (cycles={cycles}) fb_checksum=0x{checksum:016x}");
+                eprintln!(
+                    "frame {} (cycles={}) fb_hash={:08x}",
+                    gb.frames(),
+                    gb.cycles(),
+                    framebuffer_hash(gb.bus.ppu.framebuffer())
+                );
+            }
+            let frame = gb.frames();
+            let hash_at = args.fb_hash_at == Some(frame);
+            if hash_at || args.fb_hash_every.is_some_and(|n| frame.is_multiple_of(n)) {
+                println!(
+                    "frame {frame} fb_hash={:08x}",
+                    framebuffer_hash(gb.bus.ppu.framebuffer())
+                );
+            }
+            if hash_at {
+                break;
             }
         }
     }
 
     if args.verbose {
-        eprintln!("Done: frames={frames} cycles={cycles}");
+        eprintln!("Done: frames={} cycles={}", gb.frames(), gb.cycles());
+    }
+    if let Some(hist) = gb.cpu.take_opcode_histogram() {
+        print_opcode_profile(&hist);
+    }
+    if let (Some(path), Some(vgm)) = (&args.vgm_out, gb.bus.apu.export_vgm()) {
+        std::fs::write(path, vgm)
+            .map_err(|e| format!("failed to write VGM {}: {e}", path.display()))?;
     }
     if args.print_serial && !args.log_serial && !serial_out.is_empty() {
         print!("{}", String::from_utf8_lossy(&serial_out));
     }
 
+    if let Some(pc) = stuck_at {
+        println!(
+            "{} (frames={} cycles={})",
+            RomResult::Stuck { pc }.describe(),
+            gb.frames(),
+            gb.cycles()
+        );
+        return Ok(1);
+    }
+
     Ok(0)
 }
 
+/// CRC-32 of the framebuffer's pixels (little-endian 0xAARRGGBB), for golden-image checks.
+fn framebuffer_hash(fb: &Framebuffer) -> u32 {
+    let bytes: Vec<u8> = fb.iter().flat_map(|px| px.to_le_bytes()).collect();
+    gb_core::util::crc32(&bytes)
+}
+
 fn run_suite(args: SuiteArgs) -> Result<i32, String> {
     let mut roms: Vec<PathBuf> = if args.rom_paths.is_empty() {
         discover_roms(&args.rom_dir)?
@@ -892,6 +1364,7 @@ fn run_suite(args: SuiteArgs) -> Result<i32, String> {
     let mut pass = 0usize;
     let mut fail = 0usize;
     let mut timeout = 0usize;
+    let mut stuck = 0usize;
 
     for path in roms {
         let rom = match std::fs::read(&path) {
@@ -913,22 +1386,25 @@ fn run_suite(args: SuiteArgs) -> Result<i32, String> {
 
         let (res, serial, frames, cycles) = run_for_serial_result(
             cart,
+            args.mode,
             args.max_frames,
             args.max_cycles,
             &args.pass_text,
             &args.fail_text,
             args.print_vram,
+            args.stuck_cycles,
         );
 
         match res {
             RomResult::Pass => pass += 1,
             RomResult::Fail => fail += 1,
             RomResult::Timeout => timeout += 1,
+            RomResult::Stuck { .. } => stuck += 1,
         }
 
         println!(
             "{} {} (frames={frames} cycles={cycles})",
-            res.as_str(),
+            res.describe(),
             path.display()
         );
 
@@ -940,9 +1416,9 @@ fn run_suite(args: SuiteArgs) -> Result<i32, String> {
         }
     }
 
-    println!("Summary: {pass} passed, {fail} failed, {timeout} timed out");
+    println!("Summary: {pass} passed, {fail} failed, {timeout} timed out, {stuck} stuck");
 
-    if fail == 0 && timeout == 0 {
+    if fail == 0 && timeout == 0 && stuck == 0 {
         Ok(0)
     } else {
         Ok(1)
@@ -956,15 +1432,17 @@ fn run_self_test(args: SelfTestArgs) -> Result<i32, String> {
     let (res, serial, frames, cycles) = run_for_serial_result(
         cart,
         None,
+        None,
         args.max_cycles,
         &args.pass_text,
         &args.fail_text,
         args.print_vram,
+        args.stuck_cycles,
     );
 
     println!(
         "{} self-test (frames={frames} cycles={cycles})",
-        res.as_str()
+        res.describe()
     );
     if args.print_serial && !serial.is_empty() {
         print!("{}", String::from_utf8_lossy(&serial));
@@ -976,12 +1454,221 @@ fn run_self_test(args: SelfTestArgs) -> Result<i32, String> {
     Ok(if res == RomResult::Pass { 0 } else { 1 })
 }
 
+/// Steps `gb` unthrottled until `duration` of wall-clock time has passed.
+fn bench_gameboy(gb: &mut GameBoy, duration: Duration) -> BenchStats {
+    // Checking the clock every instruction would dominate the measurement.
+    const STEPS_PER_CLOCK_CHECK: u32 = 10_000;
+
+    let (start_frames, start_cycles) = (gb.frames(), gb.cycles());
+    let start = Instant::now();
+    loop {
+        for _ in 0..STEPS_PER_CLOCK_CHECK {
+            gb.step();
+            if gb.bus.ppu.frame_ready() {
+                gb.bus.ppu.clear_frame_ready();
+            }
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= duration {
+            return BenchStats {
+                frames: gb.frames() - start_frames,
+                cycles: gb.cycles() - start_cycles,
+                elapsed,
+            };
+        }
+    }
+}
+
+fn run_bench(args: BenchArgs) -> Result<i32, String> {
+    let rom = std::fs::read(&args.rom_path)
+        .map_err(|e| format!("failed to read ROM {}: {e}", args.rom_path.display()))?;
+    let cart = Cartridge::from_rom(rom).map_err(|e| format!("invalid ROM: {e:?}"))?;
+
+    let mut gb = new_gameboy(cart, args.mode);
+    gb.bus.ppu.set_rendering_enabled(!args.no_render);
+
+    let stats = bench_gameboy(&mut gb, Duration::from_secs_f64(args.seconds));
+    let secs = stats.elapsed.as_secs_f64();
+    println!(
+        "{}: {} frames, {} cycles in {secs:.2}s ({:.1} fps, {:.0} cycles/s{})",
+        args.rom_path.display(),
+        stats.frames,
+        stats.cycles,
+        stats.frames as f64 / secs,
+        stats.cycles as f64 / secs,
+        if args.no_render { ", no render" } else { "" }
+    );
+
+    Ok(0)
+}
+
+/// Steps until PC reaches `at_pc` (if given) or `max_cycles` have run. Returns whether `at_pc`
+/// was reached.
+fn run_to_pc(gb: &mut GameBoy, at_pc: Option<u16>, max_cycles: u64) -> bool {
+    loop {
+        if at_pc == Some(gb.cpu.pc) {
+            return true;
+        }
+        if gb.cycles() >= max_cycles {
+            return false;
+        }
+        gb.step();
+        if gb.bus.ppu.frame_ready() {
+            gb.bus.ppu.clear_frame_ready();
+        }
+    }
+}
+
+/// Formats `start..=end` like `xxd`: address, 16 bytes as 8 groups of 2, then ASCII.
+fn hex_dump(bus: &Bus, start: u16, end: u16) -> String {
+    const BYTES_PER_LINE: u32 = 16;
+
+    let mut out = String::new();
+    let (start, end) = (u32::from(start), u32::from(end));
+    let mut line_start = start;
+    while line_start <= end {
+        let line_end = (line_start + BYTES_PER_LINE - 1).min(end);
+        let bytes: Vec<u8> = (line_start..=line_end)
+            .map(|addr| bus.debug_read8(addr as u16))
+            .collect();
+
+        let mut hex = String::new();
+        for pair in bytes.chunks(2) {
+            for b in pair {
+                hex.push_str(&format!("{b:02x}"));
+            }
+            hex.push(' ');
+        }
+        let ascii: String = bytes
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        out.push_str(&format!("{line_start:08x}: {hex:<40} {ascii}\n"));
+        line_start += BYTES_PER_LINE;
+    }
+    out
+}
+
+fn run_dump(args: DumpArgs) -> Result<i32, String> {
+    let rom = std::fs::read(&args.rom_path)
+        .map_err(|e| format!("failed to read ROM {}: {e}", args.rom_path.display()))?;
+    let cart = Cartridge::from_rom(rom).map_err(|e| format!("invalid ROM: {e:?}"))?;
+
+    let mut gb = new_gameboy(cart, args.mode);
+    gb.bus.ppu.set_rendering_enabled(false);
+    let reached = run_to_pc(&mut gb, args.at_pc, args.max_cycles);
+    if let (Some(pc), false) = (args.at_pc, reached) {
+        eprintln!(
+            "warning: PC never reached {pc:04X} within {} cycles; dumping at PC={:04X}",
+            args.max_cycles, gb.cpu.pc
+        );
+    }
+
+    print!("{}", hex_dump(&gb.bus, args.start, args.end));
+    Ok(if args.at_pc.is_none() || reached {
+        0
+    } else {
+        1
+    })
+}
+
+/// The machine whose cartridge RAM `savedump` extracts: restored from `state`, or run from
+/// power-on for `frames` frames.
+fn savedump_gameboy(
+    rom: Vec<u8>,
+    state: Option<&[u8]>,
+    frames: u64,
+    mode: Option<EmulationMode>,
+) -> Result<GameBoy, String> {
+    if let Some(state) = state {
+        return GameBoy::from_state_bytes(state, rom)
+            .map_err(|e| format!("failed to load state: {e:?}"));
+    }
+    let cart = Cartridge::from_rom(rom).map_err(|e| format!("invalid ROM: {e:?}"))?;
+    let mut gb = new_gameboy(cart, mode);
+    gb.bus.ppu.set_rendering_enabled(false);
+    for _ in 0..frames {
+        gb.run_frame();
+    }
+    Ok(gb)
+}
+
+fn run_savedump(args: SaveDumpArgs) -> Result<i32, String> {
+    let rom = std::fs::read(&args.rom_path)
+        .map_err(|e| format!("failed to read ROM {}: {e}", args.rom_path.display()))?;
+    let state = match &args.state_path {
+        Some(path) => Some(
+            std::fs::read(path)
+                .map_err(|e| format!("failed to read state {}: {e}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    let gb = savedump_gameboy(rom, state.as_deref(), args.frames.unwrap_or(0), args.mode)?;
+    let ram = &gb.bus.cart.ram;
+    if ram.is_empty() {
+        return Err("cartridge has no RAM to dump".to_string());
+    }
+    std::fs::write(&args.out_path, ram)
+        .map_err(|e| format!("failed to write {}: {e}", args.out_path.display()))?;
+    println!(
+        "Wrote {} bytes of cartridge RAM to {}",
+        ram.len(),
+        args.out_path.display()
+    );
+    Ok(0)
+}
+
+/// Human-readable summary of a battery save's layout.
+fn describe_save(data: &[u8]) -> String {
+    use gb_core::cartridge::mbc3::{BGB_RTC_FOOTER_LEN, BGB_RTC_FOOTER_LEN_32BIT_TIME};
+
+    // Cartridge RAM sizes are multiples of 512 bytes (MBC2) or 2 KiB.
+    const RAM_GRANULE: usize = 512;
+
+    let mut out = format!("size: {} bytes\n", data.len());
+    if let Some((ram_len, payload_len)) = gb_core::cartridge::find_save_trailer(data) {
+        out.push_str(&format!(
+            "GBSV1 trailer: yes ({ram_len} bytes of RAM, {payload_len} bytes of MBC data)\n"
+        ));
+    } else {
+        out.push_str("GBSV1 trailer: no\n");
+        let footer = data.len() % RAM_GRANULE;
+        if data.len() > footer
+            && matches!(footer, BGB_RTC_FOOTER_LEN | BGB_RTC_FOOTER_LEN_32BIT_TIME)
+        {
+            out.push_str(&format!(
+                "BGB RTC footer: likely ({footer} bytes after {} bytes of RAM)\n",
+                data.len() - footer
+            ));
+        }
+    }
+    out
+}
+
+fn run_saveinfo(args: SaveInfoArgs) -> Result<i32, String> {
+    let data = std::fs::read(&args.sav_path)
+        .map_err(|e| format!("failed to read {}: {e}", args.sav_path.display()))?;
+    print!("{}: {}", args.sav_path.display(), describe_save(&data));
+    Ok(0)
+}
+
 fn run() -> Result<i32, String> {
     let cmd = parse_args()?;
     match cmd {
         Command::Run(a) => run_single(a),
         Command::Suite(a) => run_suite(a),
         Command::SelfTest(a) => run_self_test(a),
+        Command::Bench(a) => run_bench(a),
+        Command::Dump(a) => run_dump(a),
+        Command::SaveDump(a) => run_savedump(a),
+        Command::SaveInfo(a) => run_saveinfo(a),
     }
 }
 
@@ -999,6 +1686,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use gb_core::ppu::FRAMEBUFFER_LEN;
 
     #[test]
     fn vram_scrape_finds_passed_in_bg_map() {
@@ -1031,4 +1719,256 @@ mod tests {
         let t = scrape_bg_tilemap_text(&vram, 0x1800);
         assert!(t.contains("Passed"));
     }
+
+    #[test]
+    fn run_args_accept_patch_path() {
+        let args: Vec<String> = ["game.gb", "--patch", "hack.ips"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let run = parse_run_args(&args).unwrap();
+        assert_eq!(run.patch_path, Some(PathBuf::from("hack.ips")));
+
+        let args = vec!["game.gb".to_string(), "--patch".to_string()];
+        assert!(parse_run_args(&args).is_err());
+    }
+
+    #[test]
+    fn serial_out_appends_the_raw_serial_stream_to_a_file() {
+        let dir = std::env::temp_dir();
+        let id = std::process::id();
+        let rom_path = dir.join(format!("gb-cli-serial-out-{id}.gb"));
+        let out_path = dir.join(format!("gb-cli-serial-out-{id}.txt"));
+        std::fs::write(&rom_path, make_self_test_rom()).unwrap();
+        let _ = std::fs::remove_file(&out_path);
+
+        let args: Vec<String> = [
+            rom_path.to_str().unwrap(),
+            "--cycles",
+            "200000",
+            "--serial-out",
+            out_path.to_str().unwrap(),
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let run = parse_run_args(&args).unwrap();
+        assert_eq!(run_single(run).unwrap(), 0);
+        let written = std::fs::read(&out_path).unwrap();
+
+        let _ = std::fs::remove_file(&rom_path);
+        let _ = std::fs::remove_file(&out_path);
+        assert_eq!(written, b"Passed\n");
+    }
+
+    #[test]
+    fn framebuffer_hash_is_stable_across_runs() {
+        let hash_at_frame = |frames: u64| {
+            let cart = Cartridge::from_rom(make_self_test_rom()).unwrap();
+            let mut gb = new_gameboy(cart, None);
+            while gb.frames() < frames {
+                gb.run_frame();
+            }
+            framebuffer_hash(gb.bus.ppu.framebuffer())
+        };
+        assert_eq!(hash_at_frame(5), hash_at_frame(5));
+
+        let mut fb = [0u32; FRAMEBUFFER_LEN];
+        let blank = framebuffer_hash(&fb);
+        fb[FRAMEBUFFER_LEN - 1] = 1;
+        assert_ne!(framebuffer_hash(&fb), blank);
+
+        let args: Vec<String> = ["game.gb", "--fb-hash-at", "60", "--fb-hash-every", "10"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let run = parse_run_args(&args).unwrap();
+        assert_eq!((run.fb_hash_at, run.fb_hash_every), (Some(60), Some(10)));
+        for flag in ["--fb-hash-at", "--fb-hash-every"] {
+            let args = vec!["game.gb".to_string(), flag.to_string(), "0".to_string()];
+            assert!(parse_run_args(&args).is_err(), "{flag} 0");
+        }
+    }
+
+    #[test]
+    fn run_args_accept_gdb_port() {
+        let args: Vec<String> = ["game.gb", "--gdb", "2345"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let run = parse_run_args(&args).unwrap();
+        assert_eq!(run.gdb_port, Some(2345));
+
+        let args = vec!["game.gb".to_string(), "--gdb".to_string(), "x".to_string()];
+        assert!(parse_run_args(&args).is_err());
+    }
+
+    #[test]
+    fn self_loop_rom_reports_stuck_with_loop_address() {
+        let mut rom = vec![0u8; 0x8000];
+        rom[0x0100] = 0x00; // NOP
+        rom[0x0101] = 0x18; // JR -2
+        rom[0x0102] = 0xFE;
+        let cart = Cartridge::from_rom(rom).unwrap();
+
+        let (res, _, _, cycles) = run_for_serial_result(
+            cart,
+            None,
+            None,
+            Some(10_000_000),
+            &["passed".to_string()],
+            &["failed".to_string()],
+            false,
+            Some(100_000),
+        );
+        assert_eq!(res, RomResult::Stuck { pc: 0x0101 });
+        assert!(cycles < 200_000);
+        assert_eq!(res.describe(), "STUCK at PC=0101");
+    }
+
+    #[test]
+    fn loop_detector_ignores_code_that_moves_on() {
+        let mut detector = LoopDetector::new(100);
+        for i in 0..1000u64 {
+            // Walks forward through memory instead of looping.
+            assert_eq!(detector.observe((i * 4) as u16, i * 4), None);
+        }
+        assert_eq!(detector.observe(0x1000, 4000), None);
+        assert_eq!(detector.observe(0x1002, 4100), Some(0x1000));
+    }
+
+    #[test]
+    fn dump_shows_pattern_written_to_wram() {
+        let program = [
+            0x21, 0x00, 0xC0, // LD HL, $C000
+            0x3E, 0x41, // LD A, 'A'
+            0x22, // loop: LD (HL+), A
+            0x3C, // INC A
+            0xFE, 0x51, // CP 'Q'
+            0x20, 0xFA, // JR NZ, loop
+            0x18, 0xFE, // done: JR done
+        ];
+        let mut rom = vec![0u8; 0x8000];
+        rom[0x0100..0x0100 + program.len()].copy_from_slice(&program);
+        let mut gb = new_gameboy(Cartridge::from_rom(rom).unwrap(), None);
+
+        assert!(run_to_pc(&mut gb, Some(0x010B), 1_000_000));
+        assert_eq!(
+            hex_dump(&gb.bus, 0xC000, 0xC012),
+            "0000c000: 4142 4344 4546 4748 494a 4b4c 4d4e 4f50  ABCDEFGHIJKLMNOP\n\
+             0000c010: 0000 00                                  ...\n"
+        );
+    }
+
+    #[test]
+    fn dump_args_parse_hex_range_and_pc() {
+        let args: Vec<String> = ["game.gb", "--range", "C000:0xC0FF", "--at-pc", "$0150"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let dump = parse_dump_args(&args).unwrap();
+        assert_eq!((dump.start, dump.end), (0xC000, 0xC0FF));
+        assert_eq!(dump.at_pc, Some(0x0150));
+
+        let args = vec!["game.gb".to_string()];
+        assert!(parse_dump_args(&args).is_err());
+        let args: Vec<String> = ["game.gb", "--range", "C0FF:C000"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert!(parse_dump_args(&args).is_err());
+    }
+
+    fn make_mbc3_rom() -> Vec<u8> {
+        let mut rom = vec![0u8; 0x8000];
+        rom[0x0147] = 0x10; // MBC3 + Timer + RAM + Battery
+        rom[0x0148] = 0x00; // 32KB
+        rom[0x0149] = 0x02; // 8KB RAM
+        rom
+    }
+
+    #[test]
+    fn savedump_emits_raw_ram_without_trailer() {
+        let rom = make_mbc3_rom();
+        let mut gb = new_gameboy(Cartridge::from_rom(rom.clone()).unwrap(), None);
+        gb.bus.write8(0x0000, 0x0A); // enable RAM
+        gb.bus.write8(0xA000, 0x12);
+        gb.bus.write8(0xBFFF, 0x34);
+        let full_save = gb.bus.cart.save_data();
+        assert!(gb_core::cartridge::find_save_trailer(&full_save).is_some());
+
+        let state = gb.to_state_bytes();
+        let restored = savedump_gameboy(rom.clone(), Some(&state), 0, None).unwrap();
+        let ram = &restored.bus.cart.ram;
+        assert_eq!(ram.len(), 0x2000);
+        assert_eq!((ram[0], ram[0x1FFF]), (0x12, 0x34));
+        assert_eq!(ram[..], full_save[..0x2000]);
+        assert_eq!(gb_core::cartridge::find_save_trailer(ram), None);
+
+        // Without a state the ROM simply runs from power-on.
+        let fresh = savedump_gameboy(rom, None, 1, None).unwrap();
+        assert_eq!(fresh.bus.cart.ram.len(), 0x2000);
+    }
+
+    #[test]
+    fn saveinfo_reports_trailers() {
+        let cart = Cartridge::from_rom(make_mbc3_rom()).unwrap();
+        let info = describe_save(&cart.save_data());
+        assert!(
+            info.contains("GBSV1 trailer: yes (8192 bytes of RAM"),
+            "{info}"
+        );
+
+        let info = describe_save(&cart.ram);
+        assert_eq!(info, "size: 8192 bytes\nGBSV1 trailer: no\n");
+
+        let mut bgb = cart.ram.clone();
+        bgb.extend_from_slice(&[0; 48]);
+        assert!(describe_save(&bgb).contains("BGB RTC footer: likely (48 bytes after 8192"));
+    }
+
+    #[test]
+    fn savedump_args_require_a_source_and_output() {
+        let parse = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+            parse_savedump_args(&args)
+        };
+        let dump = parse(&["game.gb", "--state", "game.state", "--out", "game.sav"]).unwrap();
+        assert_eq!(dump.state_path, Some(PathBuf::from("game.state")));
+        assert_eq!(dump.out_path, PathBuf::from("game.sav"));
+        assert!(parse(&["game.gb", "--out", "game.sav"]).is_err());
+        assert!(parse(&["game.gb", "--state", "s", "--frames", "2", "--out", "o"]).is_err());
+        assert!(parse(&["game.gb", "--frames", "2"]).is_err());
+    }
+
+    #[test]
+    fn bench_runs_for_a_short_duration() {
+        let cart = Cartridge::from_rom(make_self_test_rom()).unwrap();
+        let mut gb = new_gameboy(cart, None);
+        gb.bus.ppu.set_rendering_enabled(false);
+
+        let stats = bench_gameboy(&mut gb, Duration::from_millis(50));
+        assert!(stats.elapsed >= Duration::from_millis(50));
+        assert!(stats.cycles > 0);
+        assert!(stats.frames > 0);
+    }
+
+    #[test]
+    fn bench_args_parse_seconds_and_no_render() {
+        let args: Vec<String> = ["game.gb", "--seconds", "0.5", "--no-render"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let bench = parse_bench_args(&args).unwrap();
+        assert_eq!(bench.rom_path, PathBuf::from("game.gb"));
+        assert_eq!(bench.seconds, 0.5);
+        assert!(bench.no_render);
+
+        let args = vec![
+            "game.gb".to_string(),
+            "--seconds".to_string(),
+            "0".to_string(),
+        ];
+        assert!(parse_bench_args(&args).is_err());
+    }
 }
@@ -1,12 +1,20 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
 use serde::{Deserialize, Serialize};
 
 use super::channels::noise::NoiseChannel;
 use super::channels::square::SquareChannel;
 use super::channels::wave::WaveChannel;
+use super::vgm::VgmCapture;
 
-const CPU_CLOCK_HZ: u64 = 4_194_304;
+pub(crate) const CPU_CLOCK_HZ: u64 = 4_194_304;
 const FRAME_SEQUENCER_PERIOD_CYCLES: u16 = 8_192;
 
+// Per-CPU-cycle charge factors of the output high-pass capacitor.
+const DMG_HIGHPASS_CHARGE: f32 = 0.999_958;
+const CGB_HIGHPASS_CHARGE: f32 = 0.998_943;
+
 const NR10: u16 = 0xFF10;
 const NR11: u16 = 0xFF11;
 const NR12: u16 = 0xFF12;
@@ -31,6 +39,10 @@ const NR52: u16 = 0xFF26;
 const WAVE_RAM_START: u16 = 0xFF30;
 const WAVE_RAM_END: u16 = 0xFF3F;
 
+fn default_sample_rate_hz() -> u32 {
+    Apu::DEFAULT_SAMPLE_RATE_HZ
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Apu {
     powered: bool,
@@ -49,15 +61,38 @@ pub struct Apu {
     frame_seq_counter: u16,
 
     sample_accum: u64,
-    samples: Vec<f32>,
+    // Output rate: one stereo frame is mixed every CPU_CLOCK_HZ / sample_rate_hz cycles.
+    #[serde(default = "default_sample_rate_hz")]
+    sample_rate_hz: u32,
+    // High-pass capacitor levels for the left/right outputs.
+    #[serde(default)]
+    highpass_cap: (f32, f32),
+    // Capacitor charge kept per output sample, from the model's per-cycle factor and the
+    // output rate (see `refresh_highpass_charge`).
+    #[serde(skip)]
+    highpass_charge: f32,
+    // Frontend setting, not machine state: bypasses the high-pass filter.
+    #[serde(skip)]
+    highpass_disabled: bool,
+    // Frontend setting, not machine state: outputs the average of both channels on each side.
+    #[serde(skip)]
+    mono: bool,
+    // Interleaved L/R output, capped at `sample_buffer_capacity()`; the oldest frames are
+    // dropped when a frontend stops draining it.
+    samples: VecDeque<f32>,
+    // Register writes logged for VGM export while capturing; tool state, not machine state.
+    #[serde(skip)]
+    vgm_capture: Option<VgmCapture>,
 }
 
 impl Apu {
     pub const DEFAULT_SAMPLE_RATE_HZ: u32 = 48_000;
     pub const DEFAULT_CHANNELS: u8 = 2;
+    /// Seconds of output kept queued before the oldest samples are dropped.
+    pub const SAMPLE_BUFFER_SECONDS: usize = 4;
 
     pub fn new() -> Self {
-        Self {
+        let mut apu = Self {
             powered: true,
             cgb_mode: false,
             ch1: SquareChannel::new(true),
@@ -69,15 +104,105 @@ impl Apu {
             frame_seq_step: 0,
             frame_seq_counter: 0,
             sample_accum: 0,
-            samples: Vec::new(),
-        }
+            sample_rate_hz: Self::DEFAULT_SAMPLE_RATE_HZ,
+            highpass_cap: (0.0, 0.0),
+            highpass_disabled: false,
+            mono: false,
+            samples: VecDeque::new(),
+            vgm_capture: None,
+            highpass_charge: 0.0,
+        };
+        apu.refresh_highpass_charge();
+        apu
+    }
+
+    /// Returns to power-on state, keeping the CGB mode and the host settings: the output
+    /// rate, mono and high-pass options, and any VGM capture. Queued samples are dropped.
+    pub fn reset(&mut self) {
+        let mut fresh = Self::new();
+        fresh.cgb_mode = self.cgb_mode;
+        fresh.take_settings(self);
+        *self = fresh;
+    }
+
+    /// Moves the host settings over from `old`: the output rate, mono and high-pass options,
+    /// and any VGM capture in progress.
+    pub(crate) fn take_settings(&mut self, old: &mut Self) {
+        self.set_sample_rate(old.sample_rate_hz);
+        self.highpass_disabled = old.highpass_disabled;
+        self.mono = old.mono;
+        self.vgm_capture = old.vgm_capture.take();
     }
 
     pub fn set_cgb_mode(&mut self, cgb_mode: bool) {
         self.cgb_mode = cgb_mode;
+        self.refresh_highpass_charge();
+    }
+
+    /// Sets the output sample rate (e.g. the audio device's native rate), clamped to
+    /// 1 Hz..=CPU clock.
+    pub fn set_sample_rate(&mut self, hz: u32) {
+        self.sample_rate_hz = hz.clamp(1, CPU_CLOCK_HZ as u32);
+        self.sample_accum = self.sample_accum.min(CPU_CLOCK_HZ - 1);
+        self.refresh_highpass_charge();
+        let excess = self
+            .samples
+            .len()
+            .saturating_sub(self.sample_buffer_capacity());
+        self.samples.drain(..excess);
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate_hz
+    }
+
+    /// Maximum number of queued samples: [`Self::SAMPLE_BUFFER_SECONDS`] of stereo output at
+    /// the current rate.
+    pub fn sample_buffer_capacity(&self) -> usize {
+        self.sample_rate_hz as usize * Self::DEFAULT_CHANNELS as usize * Self::SAMPLE_BUFFER_SECONDS
+    }
+
+    /// Enables the output high-pass filter (on by default), which removes DC offset the way
+    /// the console's output capacitor does.
+    pub fn set_highpass(&mut self, enabled: bool) {
+        self.highpass_disabled = !enabled;
+        self.highpass_cap = (0.0, 0.0);
+    }
+
+    pub fn highpass_enabled(&self) -> bool {
+        !self.highpass_disabled
+    }
+
+    /// Downmixes the output to mono: both samples of each stereo frame carry the average of
+    /// the left and right mixes. NR50/NR51 routing is still emulated as written; this only
+    /// changes what the frontend hears.
+    pub fn set_mono(&mut self, mono: bool) {
+        self.mono = mono;
+    }
+
+    pub fn mono(&self) -> bool {
+        self.mono
+    }
+
+    /// Starts (discarding any previous capture) or stops logging register writes for
+    /// [`Apu::export_vgm`].
+    pub fn set_vgm_capture(&mut self, enabled: bool) {
+        self.vgm_capture = enabled.then(VgmCapture::default);
+    }
+
+    pub fn vgm_capture(&self) -> Option<&VgmCapture> {
+        self.vgm_capture.as_ref()
+    }
+
+    /// The captured register writes as a VGM 1.71 file; `None` if capture is off.
+    pub fn export_vgm(&self) -> Option<Vec<u8>> {
+        self.vgm_capture.as_ref().map(VgmCapture::to_vgm)
     }
 
     pub fn tick(&mut self, cycles: u32) {
+        if let Some(capture) = self.vgm_capture.as_mut() {
+            capture.tick(cycles);
+        }
         for _ in 0..cycles {
             self.tick_cycle();
         }
@@ -104,12 +229,16 @@ impl Apu {
 
         self.sample_accum = self
             .sample_accum
-            .saturating_add(u64::from(Self::DEFAULT_SAMPLE_RATE_HZ));
+            .saturating_add(u64::from(self.sample_rate_hz));
         if self.sample_accum >= CPU_CLOCK_HZ {
             self.sample_accum -= CPU_CLOCK_HZ;
             let (left, right) = self.mix_stereo();
-            self.samples.push(left);
-            self.samples.push(right);
+            if self.samples.len() >= self.sample_buffer_capacity() {
+                // Drop the oldest stereo frame so L/R stay interleaved.
+                self.samples.drain(..2);
+            }
+            self.samples.push_back(left);
+            self.samples.push_back(right);
         }
     }
 
@@ -139,7 +268,7 @@ impl Apu {
         }
     }
 
-    fn mix_stereo(&self) -> (f32, f32) {
+    fn mix_stereo(&mut self) -> (f32, f32) {
         if !self.powered {
             return (0.0, 0.0);
         }
@@ -157,10 +286,48 @@ impl Apu {
 
         let left = (left_mix / 4.0) * ((left_vol + 1.0) / 8.0);
         let right = (right_mix / 4.0) * ((right_vol + 1.0) / 8.0);
+        let (left, right) = self.high_pass(left, right);
+        let (left, right) = if self.mono {
+            let mid = (left + right) / 2.0;
+            (mid, mid)
+        } else {
+            (left, right)
+        };
 
         (left.clamp(-1.0, 1.0), right.clamp(-1.0, 1.0))
     }
 
+    /// Recomputes the per-sample high-pass charge; needed whenever the CGB mode (which picks
+    /// the console's filter) or the output rate changes, including by loading a state.
+    pub(crate) fn refresh_highpass_charge(&mut self) {
+        let per_cycle = if self.cgb_mode {
+            CGB_HIGHPASS_CHARGE
+        } else {
+            DMG_HIGHPASS_CHARGE
+        };
+        self.highpass_charge =
+            libm::powf(per_cycle, CPU_CLOCK_HZ as f32 / self.sample_rate_hz as f32);
+    }
+
+    fn high_pass(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if self.highpass_disabled {
+            return (left, right);
+        }
+
+        let charge = self.highpass_charge;
+        let (cap_l, cap_r) = self.highpass_cap;
+        let out_l = left - cap_l;
+        let out_r = right - cap_r;
+        self.highpass_cap = (left - out_l * charge, right - out_r * charge);
+        (out_l, out_r)
+    }
+
+    /// Each channel's (left, right) output enable from NR51, CH1 first. Bits 0-3 route
+    /// CH1-CH4 to the right output, bits 4-7 to the left.
+    pub fn channel_routing(&self) -> [(bool, bool); 4] {
+        core::array::from_fn(|ch| (self.nr51 & (0x10 << ch) != 0, self.nr51 & (0x01 << ch) != 0))
+    }
+
     fn route_mix(&self, left: bool, c1: f32, c2: f32, c3: f32, c4: f32) -> f32 {
         let shift = if left { 4 } else { 0 };
         let route = self.nr51 >> shift;
@@ -244,6 +411,9 @@ impl Apu {
     }
 
     pub fn write_register(&mut self, addr: u16, value: u8, div_counter: u16) {
+        if let Some(capture) = self.vgm_capture.as_mut() {
+            capture.record(addr, value);
+        }
         if (WAVE_RAM_START..=WAVE_RAM_END).contains(&addr) {
             let index = (addr - WAVE_RAM_START) as usize;
             self.ch3.write_wave_ram(index, value, self.cgb_mode);
@@ -348,8 +518,25 @@ impl Apu {
         }
     }
 
+    /// Number of queued samples (interleaved, so twice the number of stereo frames).
+    pub fn samples_available(&self) -> usize {
+        self.samples.len()
+    }
+
     pub fn take_samples(&mut self) -> Vec<f32> {
-        std::mem::take(&mut self.samples)
+        core::mem::take(&mut self.samples).into()
+    }
+
+    /// Frame sequencer step (0..=7) that the next frame-sequencer tick will clock: lengths
+    /// on even steps, sweep on 2 and 6, envelopes on 7.
+    pub fn frame_sequencer_step(&self) -> u8 {
+        self.frame_seq_step
+    }
+
+    /// Cycles into the current frame-sequencer period (0..8192). Powering on re-phases it to
+    /// DIV on CGB and resets it on DMG.
+    pub fn frame_sequencer_counter(&self) -> u16 {
+        self.frame_seq_counter
     }
 
     #[cfg(test)]
@@ -1,5 +1,6 @@
 #[allow(clippy::module_inception)]
 pub mod apu;
 pub mod channels;
+pub mod vgm;
 
 pub use apu::Apu;
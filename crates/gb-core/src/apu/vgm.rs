@@ -0,0 +1,115 @@
+//! VGM 1.71 export of captured APU register writes (see [`Apu::set_vgm_capture`]).
+//!
+//! [`Apu::set_vgm_capture`]: super::Apu::set_vgm_capture
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::apu::CPU_CLOCK_HZ;
+
+/// VGM timestamps count samples at this fixed rate.
+pub const VGM_SAMPLE_RATE_HZ: u64 = 44_100;
+/// Size of the 1.71 header; command data starts right after it.
+pub const VGM_HEADER_LEN: usize = 0x100;
+
+/// `0xB3 aa dd`: write `dd` to GB DMG register `aa` (offset from 0xFF10).
+pub const CMD_GB_DMG_WRITE: u8 = 0xB3;
+/// `0x61 nnnn`: wait `nnnn` samples (little-endian).
+pub const CMD_WAIT: u8 = 0x61;
+/// Wait one NTSC frame (735 samples).
+pub const CMD_WAIT_735: u8 = 0x62;
+/// Wait one PAL frame (882 samples).
+pub const CMD_WAIT_882: u8 = 0x63;
+/// `0x7n`: wait `n + 1` samples.
+pub const CMD_WAIT_SHORT: u8 = 0x70;
+pub const CMD_END: u8 = 0x66;
+
+/// One APU register write and the APU cycles elapsed since the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterWrite {
+    pub addr: u16,
+    pub value: u8,
+    pub delta_cycles: u64,
+}
+
+/// APU register writes recorded while capturing.
+#[derive(Debug, Default, Clone)]
+pub struct VgmCapture {
+    writes: Vec<RegisterWrite>,
+    // Cycles since the last write (or the start of the capture).
+    pending_cycles: u64,
+}
+
+impl VgmCapture {
+    pub fn writes(&self) -> &[RegisterWrite] {
+        &self.writes
+    }
+
+    pub(crate) fn tick(&mut self, cycles: u32) {
+        self.pending_cycles += u64::from(cycles);
+    }
+
+    pub(crate) fn record(&mut self, addr: u16, value: u8) {
+        self.writes.push(RegisterWrite {
+            addr,
+            value,
+            delta_cycles: core::mem::take(&mut self.pending_cycles),
+        });
+    }
+
+    /// Encodes the capture as a VGM file. The time since the last write is kept as a final
+    /// wait, so the last notes play out for as long as they did in the emulator.
+    pub fn to_vgm(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut cycles = 0u64;
+        let mut samples = 0u64;
+        // Waits are derived from absolute time so rounding never accumulates.
+        let mut wait_until = |data: &mut Vec<u8>, cycles: u64| {
+            let target = cycles * VGM_SAMPLE_RATE_HZ / CPU_CLOCK_HZ;
+            push_wait(data, target - samples);
+            samples = target;
+        };
+        for write in &self.writes {
+            cycles += write.delta_cycles;
+            wait_until(&mut data, cycles);
+            data.extend_from_slice(&[
+                CMD_GB_DMG_WRITE,
+                write.addr.wrapping_sub(0xFF10) as u8,
+                write.value,
+            ]);
+        }
+        wait_until(&mut data, cycles + self.pending_cycles);
+        data.push(CMD_END);
+
+        let total_samples = (cycles + self.pending_cycles) * VGM_SAMPLE_RATE_HZ / CPU_CLOCK_HZ;
+        let mut vgm = vec![0u8; VGM_HEADER_LEN];
+        let file_len = VGM_HEADER_LEN + data.len();
+        let mut put =
+            |offset: usize, v: u32| vgm[offset..offset + 4].copy_from_slice(&v.to_le_bytes());
+        put(0x00, u32::from_le_bytes(*b"Vgm "));
+        // Offsets are relative to the field holding them.
+        put(0x04, (file_len - 0x04) as u32);
+        put(0x08, 0x0000_0171);
+        put(0x18, total_samples.min(u64::from(u32::MAX)) as u32);
+        put(0x34, (VGM_HEADER_LEN - 0x34) as u32);
+        put(0x80, CPU_CLOCK_HZ as u32);
+        vgm.extend_from_slice(&data);
+        vgm
+    }
+}
+
+fn push_wait(data: &mut Vec<u8>, mut samples: u64) {
+    while samples > 0 {
+        let n = samples.min(u64::from(u16::MAX));
+        match n {
+            735 => data.push(CMD_WAIT_735),
+            882 => data.push(CMD_WAIT_882),
+            1..=16 => data.push(CMD_WAIT_SHORT + (n - 1) as u8),
+            _ => {
+                data.push(CMD_WAIT);
+                data.extend_from_slice(&(n as u16).to_le_bytes());
+            }
+        }
+        samples -= n;
+    }
+}
@@ -0,0 +1,203 @@
+//! BPS ("beat") ROM patches.
+//!
+//! Layout: `BPS1`, varint source size, target size and metadata length (+ metadata), then
+//! actions until the 12-byte footer holding the source, target and patch CRC32s.
+
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+pub use crate::util::crc32;
+
+const HEADER: &[u8] = b"BPS1";
+const FOOTER_LEN: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BpsError {
+    InvalidHeader,
+    /// The patch ended in the middle of a field (byte offset into the patch).
+    Truncated(usize),
+    PatchCrcMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    SourceSizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    /// The loaded ROM is not the one the patch was made for.
+    SourceCrcMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    TargetCrcMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    /// An action read or wrote outside the source/target (byte offset into the patch).
+    OutOfBounds(usize),
+    /// A number in the patch doesn't fit in `usize` (byte offset into the patch).
+    Malformed(usize),
+}
+
+/// Applies a BPS `patch` to `source`, returning the patched ROM.
+pub fn apply_bps(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, BpsError> {
+    if !patch.starts_with(HEADER) {
+        return Err(BpsError::InvalidHeader);
+    }
+    if patch.len() < HEADER.len() + FOOTER_LEN {
+        return Err(BpsError::Truncated(patch.len()));
+    }
+
+    let footer_start = patch.len() - FOOTER_LEN;
+    let footer_crc = |i: usize| read_u32_le(&patch[footer_start + i * 4..]);
+    let (source_crc, target_crc, patch_crc) = (footer_crc(0), footer_crc(1), footer_crc(2));
+
+    let actual = crc32(&patch[..patch.len() - 4]);
+    if actual != patch_crc {
+        return Err(BpsError::PatchCrcMismatch {
+            expected: patch_crc,
+            actual,
+        });
+    }
+
+    let mut reader = Reader {
+        data: &patch[..footer_start],
+        pos: HEADER.len(),
+    };
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_len = reader.varint()?;
+    reader.take(metadata_len)?;
+
+    if source.len() != source_size {
+        return Err(BpsError::SourceSizeMismatch {
+            expected: source_size,
+            actual: source.len(),
+        });
+    }
+    let actual = crc32(source);
+    if actual != source_crc {
+        return Err(BpsError::SourceCrcMismatch {
+            expected: source_crc,
+            actual,
+        });
+    }
+
+    // The size comes from the patch, so don't let a tiny file reserve gigabytes up front.
+    let mut target = Vec::with_capacity(target_size.min(source.len() * 2 + patch.len()));
+    let mut source_rel = 0usize;
+    let mut target_rel = 0usize;
+
+    while reader.pos < reader.data.len() {
+        let action_pos = reader.pos;
+        let data = reader.varint()?;
+        let len = (data >> 2) + 1;
+        if target
+            .len()
+            .checked_add(len)
+            .is_none_or(|end| end > target_size)
+        {
+            return Err(BpsError::OutOfBounds(action_pos));
+        }
+
+        match data & 0x03 {
+            // SourceRead: copy from the source at the current output offset.
+            0 => {
+                let start = target.len();
+                let bytes = source
+                    .get(start..start.saturating_add(len))
+                    .ok_or(BpsError::OutOfBounds(action_pos))?;
+                target.extend_from_slice(bytes);
+            }
+            // TargetRead: literal bytes from the patch.
+            1 => {
+                let bytes = reader.take(len)?;
+                target.extend_from_slice(bytes);
+            }
+            // SourceCopy: copy from a relative source offset.
+            2 => {
+                source_rel = reader.relative(source_rel, action_pos)?;
+                let bytes = source
+                    .get(source_rel..source_rel.saturating_add(len))
+                    .ok_or(BpsError::OutOfBounds(action_pos))?;
+                target.extend_from_slice(bytes);
+                source_rel += len;
+            }
+            // TargetCopy: copy from already written output; ranges may overlap.
+            _ => {
+                target_rel = reader.relative(target_rel, action_pos)?;
+                if target_rel >= target.len() {
+                    return Err(BpsError::OutOfBounds(action_pos));
+                }
+                for _ in 0..len {
+                    target.push(target[target_rel]);
+                    target_rel += 1;
+                }
+            }
+        }
+    }
+
+    let actual = crc32(&target);
+    if target.len() != target_size || actual != target_crc {
+        return Err(BpsError::TargetCrcMismatch {
+            expected: target_crc,
+            actual,
+        });
+    }
+
+    Ok(target)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BpsError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos.saturating_add(len))
+            .ok_or(BpsError::Truncated(self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// Decodes BPS's variable-length number (7 bits per byte, high bit terminates).
+    fn varint(&mut self) -> Result<usize, BpsError> {
+        let start = self.pos;
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.take(1)?[0];
+            value = usize::from(byte & 0x7F)
+                .checked_mul(shift)
+                .and_then(|v| value.checked_add(v))
+                .ok_or(BpsError::Malformed(start))?;
+            if (byte & 0x80) != 0 {
+                return Ok(value);
+            }
+            shift = shift
+                .checked_mul(1 << 7)
+                .ok_or(BpsError::Malformed(start))?;
+            value = value.checked_add(shift).ok_or(BpsError::Malformed(start))?;
+        }
+    }
+
+    /// Applies a signed relative offset (bit 0 = sign) to `base`.
+    fn relative(&mut self, base: usize, action_pos: usize) -> Result<usize, BpsError> {
+        let data = self.varint()?;
+        let delta = data >> 1;
+        let out = if (data & 1) != 0 {
+            base.checked_sub(delta)
+        } else {
+            base.checked_add(delta)
+        };
+        out.ok_or(BpsError::OutOfBounds(action_pos))
+    }
+}
+
+fn read_u32_le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
@@ -3,19 +3,75 @@ use crate::cartridge::mbc::Mbc;
 use crate::cartridge::Cartridge;
 use crate::dma;
 use crate::input::Joypad;
+use crate::ppu::dmg_colorization;
 use crate::ppu::Ppu;
 use crate::serial::Serial;
 use crate::timer::Timer;
+use crate::util::boxed::boxed_array;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
 use serde::{Deserialize, Serialize};
 use serde_big_array::BigArray;
+#[cfg(feature = "std")]
 use std::path::Path;
 
+/// CPU cycles the CPU is stalled for while a CGB speed switch (STOP with KEY1 armed) completes.
+///
+/// Hardware takes ~2050 M-cycles; peripherals keep running during the stall.
+pub const CGB_SPEED_SWITCH_STALL_CYCLES: u32 = 2050 * 4;
+
+/// CPU cycles the CPU is stalled for per 16-byte block of a general-purpose DMA at normal
+/// speed; double speed takes twice as many CPU cycles (the same wall time).
+pub const GDMA_STALL_CYCLES_PER_BLOCK: u32 = 8 * 4;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EmulationMode {
     Dmg,
     Cgb,
 }
 
+impl EmulationMode {
+    /// Mode a real CGB would pick for this cartridge, based on the header CGB flag.
+    pub fn for_cartridge(cart: &Cartridge) -> Self {
+        match cart.header.cgb_support {
+            crate::cartridge::header::CgbSupport::DmgOnly => EmulationMode::Dmg,
+            crate::cartridge::header::CgbSupport::CgbCompatible
+            | crate::cartridge::header::CgbSupport::CgbOnly => EmulationMode::Cgb,
+        }
+    }
+
+    /// Parses a mode override as given on the command line, ignoring case: `dmg`, `cgb`, or
+    /// `auto` for `None` (the cartridge decides, see [`EmulationMode::for_cartridge`]).
+    pub fn parse_override(value: &str) -> Result<Option<Self>, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "auto" => Ok(None),
+            "dmg" => Ok(Some(EmulationMode::Dmg)),
+            "cgb" => Ok(Some(EmulationMode::Cgb)),
+            _ => Err(format!(
+                "invalid --mode value: {value} (expected auto, dmg, or cgb)"
+            )),
+        }
+    }
+}
+
+/// The LCD registers at one point in time, for debug overlays and PPU tracing (see
+/// [`Bus::ppu_status`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PpuStatus {
+    /// LY (0xFF44).
+    pub ly: u8,
+    /// STAT mode bits: 0 HBlank, 1 VBlank, 2 OAM scan, 3 drawing.
+    pub mode: u8,
+    /// LCDC (0xFF40).
+    pub lcdc: u8,
+    /// STAT (0xFF41).
+    pub stat: u8,
+}
+
+/// Callback observing a memory access: address and the value read or written.
+pub type MemoryHook = Box<dyn FnMut(u16, u8) + Send>;
+
 #[derive(Serialize, Deserialize)]
 pub struct Bus {
     pub cart: Cartridge,
@@ -25,10 +81,11 @@ pub struct Bus {
     pub timer: Timer,
     pub input: Joypad,
     pub serial: Serial,
-    #[serde(with = "BigArray")]
-    pub wram: [u8; 0x8000],
-    #[serde(with = "BigArray")]
-    pub vram: [u8; 0x4000],
+    // Boxed so `Bus` (and `GameBoy`) stay cheap to move; serialized like plain arrays.
+    #[serde(with = "crate::util::boxed::big_array")]
+    pub wram: Box<[u8; 0x8000]>,
+    #[serde(with = "crate::util::boxed::big_array")]
+    pub vram: Box<[u8; 0x4000]>,
     #[serde(with = "BigArray")]
     pub oam: [u8; 0xA0],
     #[serde(with = "BigArray")]
@@ -48,33 +105,69 @@ pub struct Bus {
     cgb_hdma_dst: u16,
     cgb_hdma_blocks_remaining: u8,
     cgb_hdma_active: bool,
-    cgb_hdma_last_hblank_ly: Option<u8>,
+    // `Ppu::hblank_count` when HDMA last caught up; HBlanks entered since each owe a block.
+    cgb_hdma_hblanks_seen: u32,
+    // CPU cycles of GDMA stall not yet handed to the CPU (see `take_dma_stall_cycles`). A CPU
+    // write drains it right away; a stall started by anyone else (a tool's `write8` or
+    // `debug_write8`) is dropped, at the latest when the next CPU step begins.
+    #[serde(skip)]
+    dma_stall_cycles: u32,
     oam_bug_read_idu_pending_addr: Option<u16>,
+    // DMG STOP low-power state: the system clock is halted until a joypad press.
+    #[serde(default)]
+    stopped: bool,
+
+    // Debugging knob, not machine state: CPU cycles per peripheral cycle (see
+    // `GameBoy::set_cpu_clock_multiplier`), with the cycles not yet passed on.
+    #[serde(skip, default = "default_cpu_clock_multiplier")]
+    cpu_clock_multiplier: u32,
+    #[serde(skip)]
+    cpu_clock_remainder: u32,
+
+    // Instrumentation for tracing tools (heatmaps, debuggers); called from `read8`/`write8`.
+    #[serde(skip)]
+    pub on_read: Option<MemoryHook>,
+    #[serde(skip)]
+    pub on_write: Option<MemoryHook>,
+}
+
+fn default_cpu_clock_multiplier() -> u32 {
+    1
 }
 
+// WRAM, VRAM and the framebuffer live on the heap; keep the rest of the machine small.
+const _: () = assert!(core::mem::size_of::<Bus>() <= 2048);
+
 impl Bus {
     const OAM_ROW_BYTES: usize = 8;
 
     pub fn new(cart: Cartridge) -> Self {
-        let mode = match cart.header.cgb_support {
-            crate::cartridge::header::CgbSupport::DmgOnly => EmulationMode::Dmg,
-            crate::cartridge::header::CgbSupport::CgbCompatible
-            | crate::cartridge::header::CgbSupport::CgbOnly => EmulationMode::Cgb,
-        };
+        let mode = EmulationMode::for_cartridge(&cart);
+        Self::new_with_mode(cart, mode)
+    }
 
+    /// Creates a bus running in `mode` regardless of the cartridge header.
+    ///
+    /// Useful for running CGB-compatible games in DMG mode.
+    pub fn new_with_mode(cart: Cartridge, mode: EmulationMode) -> Self {
         let mut apu = Apu::new();
         apu.set_cgb_mode(mode == EmulationMode::Cgb);
 
+        let mut ppu = Ppu::new();
+        if mode == EmulationMode::Cgb && Self::is_dmg_only(&cart) {
+            ppu.apply_dmg_colorization(dmg_colorization::palette_for_rom(&cart.rom));
+        }
+
         Self {
             cart,
             mode,
-            ppu: Ppu::new(),
+            ppu,
             apu,
             timer: Timer::new(),
             input: Joypad::new(),
             serial: Serial::new(),
-            wram: [0; 0x8000],
-            vram: [0; 0x4000],
+            wram: boxed_array(0),
+            vram: boxed_array(0),
             oam: [0; 0xA0],
             io: [0; 0x80],
             hram: [0; 0x7F],
@@ -89,16 +182,112 @@ impl Bus {
             cgb_hdma_dst: 0x8000,
             cgb_hdma_blocks_remaining: 0,
             cgb_hdma_active: false,
-            cgb_hdma_last_hblank_ly: None,
+            cgb_hdma_hblanks_seen: 0,
+            dma_stall_cycles: 0,
             oam_bug_read_idu_pending_addr: None,
+            stopped: false,
+            cpu_clock_multiplier: 1,
+            cpu_clock_remainder: 0,
+            on_read: None,
+            on_write: None,
         }
     }
 
+    /// Moves the cartridge ROM and the host/tool configuration that [`Bus::reset`] keeps over
+    /// from `old`, the bus this one replaces (as when a save state is loaded).
+    #[cfg(feature = "std")]
+    pub(crate) fn take_host_settings(&mut self, old: &mut Self) {
+        self.cart.rom = core::mem::take(&mut old.cart.rom);
+        self.cart.take_settings(&mut old.cart);
+        self.ppu.take_settings(&mut old.ppu);
+        self.apu.take_settings(&mut old.apu);
+        self.input.take_settings(&old.input);
+        self.serial.take_settings(&old.serial);
+        self.cpu_clock_multiplier = old.cpu_clock_multiplier;
+        self.on_read = old.on_read.take();
+        self.on_write = old.on_write.take();
+    }
+
+    /// Returns the machine to its power-on state in place (see [`Cartridge::reset`] for
+    /// `hard`). The emulation mode and host/tool configuration, such as memory hooks, the CPU
+    /// clock multiplier and each component's frontend settings, are kept.
+    pub fn reset(&mut self, hard: bool) {
+        // Exhaustive, so a new field has to be sorted into reset or kept.
+        let Self {
+            cart,
+            mode,
+            ppu,
+            apu,
+            timer,
+            input,
+            serial,
+            wram,
+            vram,
+            oam,
+            io,
+            hram,
+            ie,
+            iflag,
+            oam_dma,
+            cgb_double_speed,
+            cgb_speed_switch_prepare,
+            cgb_vram_bank,
+            cgb_wram_bank,
+            cgb_hdma_src,
+            cgb_hdma_dst,
+            cgb_hdma_blocks_remaining,
+            cgb_hdma_active,
+            cgb_hdma_hblanks_seen,
+            dma_stall_cycles,
+            oam_bug_read_idu_pending_addr,
+            stopped,
+            cpu_clock_multiplier: _,
+            cpu_clock_remainder,
+            on_read: _,
+            on_write: _,
+        } = self;
+
+        cart.reset(hard);
+        ppu.reset();
+        if *mode == EmulationMode::Cgb && Self::is_dmg_only(cart) {
+            ppu.apply_dmg_colorization(dmg_colorization::palette_for_rom(&cart.rom));
+        }
+        apu.reset();
+        *timer = Timer::new();
+        input.reset();
+        serial.reset();
+        wram.fill(0);
+        vram.fill(0);
+        *oam = [0; 0xA0];
+        *io = [0; 0x80];
+        *hram = [0; 0x7F];
+        *ie = 0;
+        *iflag = 0;
+        *oam_dma = dma::OamDma::default();
+        *cgb_double_speed = false;
+        *cgb_speed_switch_prepare = false;
+        *cgb_vram_bank = 0;
+        *cgb_wram_bank = 1;
+        *cgb_hdma_src = 0;
+        *cgb_hdma_dst = 0x8000;
+        *cgb_hdma_blocks_remaining = 0;
+        *cgb_hdma_active = false;
+        *cgb_hdma_hblanks_seen = 0;
+        *dma_stall_cycles = 0;
+        *oam_bug_read_idu_pending_addr = None;
+        *stopped = false;
+        *cpu_clock_remainder = 0;
+    }
+
     #[inline]
     fn is_cgb(&self) -> bool {
         self.mode == EmulationMode::Cgb
     }
 
+    fn is_dmg_only(cart: &Cartridge) -> bool {
+        cart.header.cgb_support == crate::cartridge::header::CgbSupport::DmgOnly
+    }
+
     fn read_key1(&self) -> u8 {
         if !self.is_cgb() {
             return 0xFF;
@@ -289,17 +478,22 @@ impl Bus {
         // Writing bit7=0 while HDMA is active terminates the in-progress HBlank transfer.
         if self.cgb_hdma_active && (control & 0x80) == 0 {
             self.cgb_hdma_active = false;
-            self.cgb_hdma_last_hblank_ly = None;
             return;
         }
 
         self.cgb_hdma_src = Self::sanitize_hdma_source(self.cgb_hdma_src);
         self.cgb_hdma_dst = 0x8000 | ((self.cgb_hdma_dst - 0x8000) & 0x1FF0);
         self.cgb_hdma_blocks_remaining = (control & 0x7F).wrapping_add(1);
-        self.cgb_hdma_last_hblank_ly = None;
+        // Starting during HBlank copies the first block right away.
+        let in_hblank = self.lcd_enabled() && self.ppu_mode() == 0 && self.io[0x44] < 144;
+        self.cgb_hdma_hblanks_seen = self.ppu.hblank_count().wrapping_sub(in_hblank as u32);
 
         if (control & 0x80) == 0 {
             self.cgb_hdma_active = false;
+            let speed_factor = if self.cgb_double_speed { 2 } else { 1 };
+            self.dma_stall_cycles += u32::from(self.cgb_hdma_blocks_remaining)
+                * GDMA_STALL_CYCLES_PER_BLOCK
+                * speed_factor;
             while self.cgb_hdma_blocks_remaining > 0 {
                 self.perform_hdma_block();
             }
@@ -328,7 +522,6 @@ impl Bus {
 
         if self.cgb_hdma_blocks_remaining == 0 {
             self.cgb_hdma_active = false;
-            self.cgb_hdma_last_hblank_ly = None;
         }
     }
 
@@ -345,16 +538,18 @@ impl Bus {
             return;
         }
 
-        let ly = self.io[0x44];
-        let mode = self.ppu_mode();
-
-        if mode == 0 && ly < 144 {
-            if self.cgb_hdma_last_hblank_ly != Some(ly) {
-                self.perform_hdma_block();
-                self.cgb_hdma_last_hblank_ly = Some(ly);
+        // Mode 0 starts later on lines where objects or SCX lengthen mode 3, so blocks follow
+        // the PPU's HBlank entries rather than a fixed dot. Counting entries (instead of
+        // watching for mode 0) also catches HBlanks that begin and end within one tick. The
+        // PPU runs on the base clock, so this is one block per HBlank at either CPU speed.
+        let hblank_count = self.ppu.hblank_count();
+        let entered = hblank_count.wrapping_sub(self.cgb_hdma_hblanks_seen);
+        self.cgb_hdma_hblanks_seen = hblank_count;
+        for _ in 0..entered {
+            if self.cgb_hdma_blocks_remaining == 0 {
+                break;
             }
-        } else {
-            self.cgb_hdma_last_hblank_ly = None;
+            self.perform_hdma_block();
         }
     }
 
@@ -527,6 +722,20 @@ impl Bus {
         self.oam_bug_read_idu_pending_addr = Some(idu_addr);
     }
 
+    /// Whether the cartridge's rumble motor has been on since the last call. Games vary
+    /// strength by pulsing the motor within a frame, so poll this once per frame.
+    pub fn take_rumble_state(&mut self) -> bool {
+        self.cart.mbc.take_rumble()
+    }
+
+    /// CPU cycles the CPU must stall for after a general-purpose DMA, clearing them.
+    ///
+    /// The copy itself happens at once; the CPU ticks the bus through the stall so timers and
+    /// the PPU advance as if the transfer took its hardware time.
+    pub fn take_dma_stall_cycles(&mut self) -> u32 {
+        core::mem::take(&mut self.dma_stall_cycles)
+    }
+
     /// Returns true if the CGB speed-switch handshake was performed.
     pub fn try_cgb_speed_switch(&mut self) -> bool {
         if !self.is_cgb() || !self.cgb_speed_switch_prepare {
@@ -538,7 +747,58 @@ impl Bus {
         true
     }
 
+    /// Enters the DMG `STOP` state: DIV resets, the system clock halts and the LCD blanks.
+    ///
+    /// Returns false on CGB, where `STOP` without a speed switch is treated as `HALT`.
+    pub fn try_enter_dmg_stop(&mut self) -> bool {
+        if self.is_cgb() {
+            return false;
+        }
+
+        self.timer.write_div();
+        self.ppu.enter_stop();
+        self.stopped = true;
+        true
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Leaves `STOP` if a button on a selected joypad line is pressed.
+    pub fn try_exit_stop(&mut self) -> bool {
+        if self.stopped && self.input.selected_button_pressed() {
+            self.stopped = false;
+        }
+        !self.stopped
+    }
+
     pub fn read8(&mut self, addr: u16) -> u8 {
+        let val = self.read8_cpu(addr);
+        if let Some(hook) = self.on_read.as_mut() {
+            hook(addr, val);
+        }
+        val
+    }
+
+    /// Reads memory for debuggers and tools: no side effects, no hooks, and no PPU/OAM-DMA
+    /// access blocking (VRAM/OAM read as their contents even in modes 2/3).
+    pub fn debug_read8(&self, addr: u16) -> u8 {
+        self.read8_direct(addr)
+    }
+
+    /// Writes memory for debuggers and tools: no hooks and no PPU/OAM-DMA access blocking.
+    /// ROM writes are dropped rather than reaching the MBC, so poking code can't switch banks;
+    /// I/O registers still react as they do to a CPU write.
+    pub fn debug_write8(&mut self, addr: u16, val: u8) {
+        if addr >= 0x8000 {
+            self.write8_direct(addr, val);
+            // A GDMA started from here copies at once; no CPU is waiting on it.
+            self.dma_stall_cycles = 0;
+        }
+    }
+
+    fn read8_cpu(&mut self, addr: u16) -> u8 {
         if self
             .oam_bug_read_idu_pending_addr
             .is_some_and(|pending| pending != addr)
@@ -555,7 +815,7 @@ impl Bus {
         self.read8_direct(addr)
     }
 
-    fn read8_direct(&mut self, addr: u16) -> u8 {
+    fn read8_direct(&self, addr: u16) -> u8 {
         match addr {
             // ROM: 0x0000..=0x7FFF
             0x0000..=0x7FFF => self.cart.mbc.read_rom(&self.cart.rom, addr),
@@ -628,6 +888,13 @@ impl Bus {
                         0xFF
                     }
                 }
+                0xFF6C => {
+                    if self.is_cgb() {
+                        self.ppu.read_opri()
+                    } else {
+                        0xFF
+                    }
+                }
                 0xFF4F => self.read_vbk(),
                 0xFF70 => self.read_svbk(),
                 0xFF4D => self.read_key1(),
@@ -643,6 +910,13 @@ impl Bus {
     }
 
     pub fn write8(&mut self, addr: u16, val: u8) {
+        self.write8_cpu(addr, val);
+        if let Some(hook) = self.on_write.as_mut() {
+            hook(addr, val);
+        }
+    }
+
+    fn write8_cpu(&mut self, addr: u16, val: u8) {
         if self.oam_dma.blocks_cpu_addr(addr) {
             return;
         }
@@ -689,10 +963,10 @@ impl Bus {
                 let idx = (addr - 0xFF00) as usize;
                 match addr {
                     0xFF00 => self.input.write_joyp(val),
-                    0xFF04 => self.timer.write_div(&mut self.iflag),
+                    0xFF04 => self.timer.write_div(),
                     0xFF05 => self.timer.write_tima(val),
                     0xFF06 => self.timer.write_tma(val),
-                    0xFF07 => self.timer.write_tac(val, &mut self.iflag),
+                    0xFF07 => self.timer.write_tac(val),
                     0xFF0F => self.iflag = val & 0x1F,
                     // APU register accesses take an M-cycle. Some APU behaviors (notably NR52
                     // power-up re-phasing on CGB) are sensitive to the global DIV phase.
@@ -726,17 +1000,30 @@ impl Bus {
                             self.ppu.write_obpd(val);
                         }
                     }
+                    0xFF6C => {
+                        if self.is_cgb() {
+                            self.ppu.write_opri(val);
+                        }
+                    }
                     0xFF02 => {
                         self.io[idx] = val;
                         // Common test ROM convention: write a byte to SB (0xFF01), then write 0x81
                         // to SC (0xFF02) to start a serial transfer.
                         if (val & 0x80) != 0 {
-                            self.serial.start_transfer(self.io[0x01], &mut self.io[idx]);
+                            let double_speed = self.is_cgb() && self.cgb_double_speed;
+                            self.serial.start_transfer(
+                                self.io[0x01],
+                                &mut self.io[idx],
+                                double_speed,
+                            );
                         } else {
                             self.serial.stop_transfer(&mut self.io[idx]);
                         }
                     }
-                    0xFF41 => self.io[idx] = (self.io[idx] & 0x07) | (val & 0x78),
+                    0xFF41 => {
+                        let dmg = !self.is_cgb();
+                        self.ppu.write_stat(val, &mut self.io, &mut self.iflag, dmg);
+                    }
                     0xFF44 => {
                         self.io[idx] = 0;
                         self.ppu.reset_ly();
@@ -761,6 +1048,79 @@ impl Bus {
         self.input.set_button(button, pressed, &mut self.iflag);
     }
 
+    /// Sets all eight buttons from a bitmask of [`Button::mask`](crate::input::Button::mask)
+    /// bits (1 = pressed), e.g. one movie frame. The joypad interrupt is raised once if any
+    /// selected line falls.
+    pub fn set_joypad_state(&mut self, mask: u8) {
+        self.input.set_state(mask, &mut self.iflag);
+    }
+
+    /// Selects how simultaneous opposite directions are reported (default: allowed, like
+    /// hardware).
+    pub fn set_socd_mode(&mut self, mode: crate::input::SocdMode) {
+        self.input.set_socd_mode(mode);
+    }
+
+    /// See [`Serial::set_loopback`].
+    pub fn set_serial_loopback(&mut self, enabled: bool) {
+        self.serial.set_loopback(enabled);
+    }
+
+    /// Clocks one bit of an external-clock serial transfer from a link partner, returning the
+    /// bit sent back (or `None` if the game isn't waiting on an external-clock transfer).
+    pub fn clock_serial_external(&mut self, bit_in: bool) -> Option<bool> {
+        let [sb, sc] = &mut self.io[0x01..=0x02] else {
+            unreachable!("SB and SC are adjacent");
+        };
+        self.serial
+            .clock_external_bit(bit_in, &mut self.iflag, sb, sc)
+    }
+
+    /// Runs the CPU `multiplier` times faster than the rest of the machine. Not accurate to
+    /// any hardware; see `GameBoy::set_cpu_clock_multiplier`.
+    pub fn set_cpu_clock_multiplier(&mut self, multiplier: u32) {
+        self.cpu_clock_multiplier = multiplier.max(1);
+        self.cpu_clock_remainder = 0;
+    }
+
+    pub fn cpu_clock_multiplier(&self) -> u32 {
+        self.cpu_clock_multiplier
+    }
+
+    /// IE (0xFFFF).
+    pub fn interrupt_enable(&self) -> u8 {
+        self.ie
+    }
+
+    /// IF (0xFF0F), without the unused upper bits that read back as 1.
+    pub fn interrupt_flags(&self) -> u8 {
+        self.iflag
+    }
+
+    /// LY (0xFF44) as the CPU reads it.
+    pub fn lcd_ly(&self) -> u8 {
+        self.io[0x44]
+    }
+
+    /// STAT (0xFF41) as the CPU reads it.
+    pub fn stat(&self) -> u8 {
+        self.io[0x41]
+    }
+
+    /// LCDC (0xFF40).
+    pub fn lcdc(&self) -> u8 {
+        self.io[0x40]
+    }
+
+    pub fn ppu_status(&self) -> PpuStatus {
+        PpuStatus {
+            ly: self.lcd_ly(),
+            mode: self.ppu_mode(),
+            lcdc: self.lcdc(),
+            stat: self.stat(),
+        }
+    }
+
     pub fn tick(&mut self, cycles: u32) {
         // The emulator's CPU executes in 4-cycle M-cycles. On CGB, the CPU can run in
         // double-speed mode (KEY1). In that mode, *only the CPU* runs at 2x frequency;
@@ -774,6 +1134,19 @@ impl Bus {
         } else {
             cycles
         };
+        let cycles = if self.cpu_clock_multiplier > 1 {
+            let total = cycles + self.cpu_clock_remainder;
+            self.cpu_clock_remainder = total % self.cpu_clock_multiplier;
+            total / self.cpu_clock_multiplier
+        } else {
+            cycles
+        };
+
+        if self.stopped {
+            // Only the blank screen keeps producing frames; everything else is frozen.
+            self.ppu.tick_stopped(cycles);
+            return;
+        }
 
         self.cart.mbc.tick(cycles);
         self.timer.tick(cycles, &mut self.iflag);
@@ -784,7 +1157,8 @@ impl Bus {
         let vram1: &[u8; 0x2000] = self.vram[0x2000..]
             .try_into()
             .expect("slice length for vram1 is fixed");
-        let cgb_mode = self.is_cgb();
+        // DMG cartridges on CGB hardware render through the DMG palette path (colorized).
+        let cgb_mode = self.is_cgb() && !Self::is_dmg_only(&self.cart);
         self.ppu.tick_with_vram_banks(
             cycles,
             vram0,
@@ -796,19 +1170,38 @@ impl Bus {
         );
         self.tick_hdma();
         self.apu.tick(cycles);
-        self.serial
-            .tick(cycles, &mut self.iflag, &mut self.io[0x02]);
+        if let [sb, sc] = &mut self.io[0x01..=0x02] {
+            self.serial.tick(cycles, &mut self.iflag, sb, sc);
+        }
+    }
+
+    /// Renders a whole frame from current VRAM/OAM/registers without advancing time; see
+    /// [`Ppu::force_render_frame`].
+    pub fn force_render_frame(&mut self) {
+        let vram0: &[u8; 0x2000] = self.vram[..0x2000]
+            .try_into()
+            .expect("slice length for vram0 is fixed");
+        let vram1: &[u8; 0x2000] = self.vram[0x2000..]
+            .try_into()
+            .expect("slice length for vram1 is fixed");
+        let cgb_mode = self.is_cgb() && !Self::is_dmg_only(&self.cart);
+        self.ppu
+            .force_render_frame(vram0, Some(vram1), &self.oam, &self.io, cgb_mode);
     }
 
+    #[cfg(feature = "std")]
     pub fn save_to_path(&self, path: &Path) -> Result<(), crate::cartridge::SaveError> {
         self.cart.save_to_path(path)
     }
 
+    #[cfg(feature = "std")]
     pub fn load_from_path(&mut self, path: &Path) -> Result<(), crate::cartridge::SaveError> {
         self.cart.load_from_path(path)
     }
 
     fn tick_oam_dma(&mut self, cycles: u32) {
+        // A restart (0xFF46 written mid-transfer) has already reset the source and counter,
+        // so any bytes still due are read from the new page.
         self.oam_dma.add_cycles(cycles);
         while let Some((src, dst)) = self.oam_dma.pop_transfer() {
             let v = self.read8_direct(src);
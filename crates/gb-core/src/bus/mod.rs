@@ -1,3 +1,5 @@
 #[allow(clippy::module_inception)]
 pub mod bus;
-pub use bus::{Bus, EmulationMode};
+pub use bus::{
+    Bus, EmulationMode, PpuStatus, CGB_SPEED_SWITCH_STALL_CYCLES, GDMA_STALL_CYCLES_PER_BLOCK,
+};
@@ -1,3 +1,5 @@
+use alloc::format;
+use alloc::string::String;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -156,8 +158,27 @@ impl CgbSupport {
     }
 }
 
+/// Market the cartridge was sold in (0x014A).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Destination {
+    Japan,
+    Overseas,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Header {
+    /// Game title, without the NUL padding. Up to 16 characters on DMG cartridges; CGB
+    /// cartridges give up the last byte to the CGB flag and often four more to the
+    /// manufacturer code.
+    pub title: String,
+    /// Four-character code (0x013F..=0x0142) found on some CGB cartridges.
+    pub manufacturer_code: Option<String>,
+    /// Raw CGB flag (0x0143); see `cgb_support` for its meaning.
+    pub cgb_flag: u8,
+    /// Licensee code: the two-character new code (0x0144..=0x0145) when the old code at
+    /// 0x014B is 0x33, otherwise the old code as two hex digits.
+    pub licensee: String,
+    pub destination: Destination,
     pub cartridge_type: CartridgeType,
     pub rom_size: RomSize,
     pub ram_size: RamSize,
@@ -174,16 +195,41 @@ pub enum HeaderError {
 
 impl Header {
     pub fn parse(rom: &[u8]) -> Result<Self, HeaderError> {
-        if rom.len() < 0x014A {
+        if rom.len() < 0x0150 {
             return Err(HeaderError::RomTooSmall);
         }
 
+        let cgb_flag = rom[0x0143];
+        let manufacturer_code = &rom[0x013F..=0x0142];
+        let has_manufacturer_code = cgb_flag & 0x80 != 0
+            && manufacturer_code
+                .iter()
+                .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit());
+        let title_end = match (cgb_flag & 0x80 != 0, has_manufacturer_code) {
+            (_, true) => 0x013F,
+            (true, false) => 0x0143,
+            (false, false) => 0x0144,
+        };
+        let licensee = match rom[0x014B] {
+            0x33 => ascii_field(&rom[0x0144..=0x0145]),
+            old => format!("{old:02X}"),
+        };
+
         let cartridge_type = CartridgeType::from_byte(rom[0x0147])?;
         let rom_size = RomSize::from_byte(rom[0x0148])?;
         let ram_size = RamSize::from_byte(rom[0x0149])?;
         let cgb_support = CgbSupport::from_byte(rom[0x0143]);
 
         Ok(Header {
+            title: ascii_field(&rom[0x0134..title_end]),
+            manufacturer_code: has_manufacturer_code.then(|| ascii_field(manufacturer_code)),
+            cgb_flag,
+            licensee,
+            destination: if rom[0x014A] == 0x00 {
+                Destination::Japan
+            } else {
+                Destination::Overseas
+            },
             cartridge_type,
             rom_size,
             ram_size,
@@ -191,3 +237,15 @@ impl Header {
         })
     }
 }
+
+/// A header text field up to its first NUL, with non-printable bytes dropped and trailing
+/// spaces trimmed.
+fn ascii_field(bytes: &[u8]) -> String {
+    let text: String = bytes
+        .iter()
+        .take_while(|&&b| b != 0)
+        .filter(|b| b.is_ascii_graphic() || **b == b' ')
+        .map(|&b| char::from(b))
+        .collect();
+    String::from(text.trim_end())
+}
@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -60,6 +62,26 @@ impl Mbc for MbcEnum {
         }
     }
 
+    fn current_banks(&self) -> BankInfo {
+        match self {
+            Self::Mbc0(m) => m.current_banks(),
+            Self::Mbc1(m) => m.current_banks(),
+            Self::Mbc2(m) => m.current_banks(),
+            Self::Mbc3(m) => m.current_banks(),
+            Self::Mbc5(m) => m.current_banks(),
+        }
+    }
+
+    fn take_rumble(&mut self) -> bool {
+        match self {
+            Self::Mbc0(m) => m.take_rumble(),
+            Self::Mbc1(m) => m.take_rumble(),
+            Self::Mbc2(m) => m.take_rumble(),
+            Self::Mbc3(m) => m.take_rumble(),
+            Self::Mbc5(m) => m.take_rumble(),
+        }
+    }
+
     fn save_extra(&self) -> Vec<u8> {
         match self {
             Self::Mbc0(m) => m.save_extra(),
@@ -81,6 +103,27 @@ impl Mbc for MbcEnum {
     }
 }
 
+/// A mapper's current bank selection, for debug views.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BankInfo {
+    /// The ROM bank mapped at 0x4000, before wrapping to the cartridge's size.
+    pub rom_bank: u16,
+    /// The RAM bank mapped at 0xA000; on MBC3 the RAM bank or RTC register select.
+    pub ram_bank: u8,
+    /// The banking mode register (MBC1); 0 on mappers without one.
+    pub mode: u8,
+}
+
+impl Default for BankInfo {
+    fn default() -> Self {
+        Self {
+            rom_bank: 1,
+            ram_bank: 0,
+            mode: 0,
+        }
+    }
+}
+
 pub trait Mbc {
     fn read_rom(&self, rom: &[u8], addr: u16) -> u8;
     fn write_rom(&mut self, addr: u16, val: u8);
@@ -89,6 +132,16 @@ pub trait Mbc {
 
     fn tick(&mut self, _cycles: u32) {}
 
+    /// The bank selection as the mapper's registers hold it (see [`BankInfo`]).
+    fn current_banks(&self) -> BankInfo {
+        BankInfo::default()
+    }
+
+    /// Whether the cartridge's rumble motor has been on since the last call.
+    fn take_rumble(&mut self) -> bool {
+        false
+    }
+
     fn save_extra(&self) -> Vec<u8> {
         Vec::new()
     }
@@ -1,4 +1,4 @@
-use crate::cartridge::mbc::Mbc;
+use crate::cartridge::mbc::{BankInfo, Mbc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -55,6 +55,8 @@ impl Mbc for Mbc1 {
                 self.ram_enabled = (val & 0x0F) == 0x0A;
             }
             0x2000..=0x3FFF => {
+                // The zero check only looks at the low 5 bits, so with the high bits set
+                // banks 0x20/0x40/0x60 map to 0x21/0x41/0x61 at 0x4000.
                 self.rom_bank_low5 = val & 0x1F;
                 if self.rom_bank_low5 == 0 {
                     self.rom_bank_low5 = 1;
@@ -70,6 +72,19 @@ impl Mbc for Mbc1 {
         }
     }
 
+    fn current_banks(&self) -> BankInfo {
+        let ram_bank = if self.banking_mode == 1 {
+            self.bank_high2
+        } else {
+            0
+        };
+        BankInfo {
+            rom_bank: (u16::from(self.bank_high2) << 5) | u16::from(self.rom_bank_low5),
+            ram_bank,
+            mode: self.banking_mode,
+        }
+    }
+
     fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
         if !self.ram_enabled || ram.is_empty() {
             return 0xFF;
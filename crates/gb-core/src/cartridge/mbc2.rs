@@ -1,4 +1,6 @@
-use crate::cartridge::mbc::Mbc;
+use crate::cartridge::mbc::{BankInfo, Mbc};
+use alloc::vec;
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
 const RAM_SIZE: usize = 0x200;
@@ -61,6 +63,13 @@ impl Mbc for Mbc2 {
         }
     }
 
+    fn current_banks(&self) -> BankInfo {
+        BankInfo {
+            rom_bank: u16::from(self.rom_bank.max(1)),
+            ..BankInfo::default()
+        }
+    }
+
     fn read_ram(&self, _ram: &[u8], addr: u16) -> u8 {
         if !self.ram_enabled || !(0xA000..=0xBFFF).contains(&addr) {
             return 0xFF;
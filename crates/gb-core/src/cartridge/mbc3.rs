@@ -1,7 +1,28 @@
-use crate::cartridge::mbc::Mbc;
+use crate::cartridge::mbc::{BankInfo, Mbc};
+use alloc::vec;
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
 const CYCLES_PER_SECOND: u32 = 4_194_304;
+const NATIVE_RTC_PAYLOAD_LEN: usize = 9;
+// Native payload followed by the u64 unix timestamp of the save.
+const NATIVE_RTC_PAYLOAD_WITH_TIME_LEN: usize = NATIVE_RTC_PAYLOAD_LEN + 8;
+
+/// Length of the BGB/VBA-M RTC footer: live + latched registers (5x u32 each) and a u64
+/// unix timestamp. Some emulators write a 32-bit timestamp instead (44 bytes).
+pub const BGB_RTC_FOOTER_LEN: usize = 48;
+pub const BGB_RTC_FOOTER_LEN_32BIT_TIME: usize = 44;
+
+/// How the RTC state is stored in battery saves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RtcSaveFormat {
+    /// Our compact payload inside the `GBSV1` trailer (live registers, sub-second cycles and
+    /// the save's unix timestamp).
+    #[default]
+    Native,
+    /// BGB-compatible 48-byte footer appended raw after cartridge RAM.
+    Bgb,
+}
 
 #[derive(Clone, Copy, Default, Serialize, Deserialize)]
 struct Rtc {
@@ -48,6 +69,26 @@ impl Rtc {
         self.day_high = (self.day_high & 0xFE) | (((day >> 8) & 0x01) as u8);
     }
 
+    /// Advances the clock by `secs` seconds at once (used for wall-clock catch-up).
+    fn advance_seconds(&mut self, secs: u64) {
+        if self.halted() {
+            return;
+        }
+
+        let total = u64::from(self.sec) + secs;
+        self.sec = (total % 60) as u8;
+        let total = u64::from(self.min) + total / 60;
+        self.min = (total % 60) as u8;
+        let total = u64::from(self.hour) + total / 60;
+        self.hour = (total % 24) as u8;
+        let mut day = u64::from(self.day_counter()) + total / 24;
+        if day > 0x01FF {
+            day &= 0x01FF;
+            self.day_high |= 0x80;
+        }
+        self.set_day_counter(day as u16);
+    }
+
     fn increment_second(&mut self) {
         if self.halted() {
             return;
@@ -89,6 +130,10 @@ pub struct Mbc3 {
     rtc_live: Rtc,
     rtc_latched: Option<Rtc>,
     rtc_cycle_accum: u32,
+    #[serde(default)]
+    rtc_save_format: RtcSaveFormat,
+    #[serde(default)]
+    wallclock_sync: bool,
 }
 
 impl Mbc3 {
@@ -101,8 +146,127 @@ impl Mbc3 {
             rtc_live: Rtc::default(),
             rtc_latched: None,
             rtc_cycle_accum: 0,
+            rtc_save_format: RtcSaveFormat::Native,
+            wallclock_sync: false,
         }
     }
+
+    pub fn rtc_save_format(&self) -> RtcSaveFormat {
+        self.rtc_save_format
+    }
+
+    /// Selects the RTC save payload written by `save_extra`; both formats are accepted on load.
+    pub fn set_rtc_save_format(&mut self, format: RtcSaveFormat) {
+        self.rtc_save_format = format;
+    }
+
+    /// When enabled, loading a save fast-forwards the RTC by the real time elapsed since the
+    /// save was written, like a cartridge that kept running while powered off. Saves with a
+    /// zero timestamp are loaded as is.
+    pub fn set_wallclock_sync(&mut self, enabled: bool) {
+        self.wallclock_sync = enabled;
+    }
+
+    pub fn wallclock_sync(&self) -> bool {
+        self.wallclock_sync
+    }
+
+    /// Takes the host settings (RTC save format and wall-clock sync) from `other`.
+    pub(crate) fn copy_settings_from(&mut self, other: &Mbc3) {
+        self.rtc_save_format = other.rtc_save_format;
+        self.wallclock_sync = other.wallclock_sync;
+    }
+
+    fn sync_to_wallclock(&mut self, saved_unix_time: u64) {
+        // Zero means no known time: BGB sometimes stores it, and so do builds without std.
+        let now = unix_now();
+        if !self.wallclock_sync || saved_unix_time == 0 || now == 0 {
+            return;
+        }
+        self.rtc_live
+            .advance_seconds(now.saturating_sub(saved_unix_time));
+    }
+
+    fn save_native(&self, unix_time: u64) -> Vec<u8> {
+        let mut out = vec![
+            self.rtc_live.sec,
+            self.rtc_live.min,
+            self.rtc_live.hour,
+            self.rtc_live.day_low,
+            self.rtc_live.day_high,
+            (self.rtc_cycle_accum & 0xFF) as u8,
+            ((self.rtc_cycle_accum >> 8) & 0xFF) as u8,
+            ((self.rtc_cycle_accum >> 16) & 0xFF) as u8,
+            ((self.rtc_cycle_accum >> 24) & 0xFF) as u8,
+        ];
+        out.extend_from_slice(&unix_time.to_le_bytes());
+        out
+    }
+
+    fn load_native(&mut self, data: &[u8]) {
+        self.rtc_live.sec = data[0] % 60;
+        self.rtc_live.min = data[1] % 60;
+        self.rtc_live.hour = data[2] % 24;
+        self.rtc_live.day_low = data[3];
+        self.rtc_live.day_high = data[4] & 0xC1;
+        self.rtc_cycle_accum = u32::from(data[5])
+            | (u32::from(data[6]) << 8)
+            | (u32::from(data[7]) << 16)
+            | (u32::from(data[8]) << 24);
+        self.rtc_cycle_accum %= CYCLES_PER_SECOND;
+        self.rtc_latched = None;
+
+        if let Some(time) = data.get(NATIVE_RTC_PAYLOAD_LEN..NATIVE_RTC_PAYLOAD_WITH_TIME_LEN) {
+            let time = u64::from_le_bytes(time.try_into().expect("timestamp is 8 bytes"));
+            self.sync_to_wallclock(time);
+        }
+    }
+
+    /// Builds the BGB RTC footer, stamped with `unix_time` (seconds).
+    pub fn save_bgb_footer(&self, unix_time: u64) -> Vec<u8> {
+        let latched = self.rtc_latched.unwrap_or(self.rtc_live);
+        let mut out = Vec::with_capacity(BGB_RTC_FOOTER_LEN);
+        for rtc in [self.rtc_live, latched] {
+            for reg in [rtc.sec, rtc.min, rtc.hour, rtc.day_low, rtc.day_high] {
+                out.extend_from_slice(&u32::from(reg).to_le_bytes());
+            }
+        }
+        out.extend_from_slice(&unix_time.to_le_bytes());
+        out
+    }
+
+    fn load_bgb_footer(&mut self, data: &[u8]) {
+        let reg = |i: usize| data[i * 4];
+        let read_rtc = |base: usize| Rtc {
+            sec: reg(base) % 60,
+            min: reg(base + 1) % 60,
+            hour: reg(base + 2) % 24,
+            day_low: reg(base + 3),
+            day_high: reg(base + 4) & 0xC1,
+        };
+
+        self.rtc_live = read_rtc(0);
+        self.rtc_latched = Some(read_rtc(5));
+        self.rtc_cycle_accum = 0;
+
+        let mut time = [0u8; 8];
+        let time_bytes = &data[40..];
+        time[..time_bytes.len()].copy_from_slice(time_bytes);
+        self.sync_to_wallclock(u64::from_le_bytes(time));
+    }
+}
+
+#[cfg(feature = "std")]
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+// Without std there is no wall clock: saves carry a zero timestamp and never fast-forward.
+#[cfg(not(feature = "std"))]
+fn unix_now() -> u64 {
+    0
 }
 
 impl Default for Mbc3 {
@@ -152,6 +316,14 @@ impl Mbc for Mbc3 {
         }
     }
 
+    fn current_banks(&self) -> BankInfo {
+        BankInfo {
+            rom_bank: u16::from(self.rom_bank.max(1)),
+            ram_bank: self.ram_rtc_select,
+            mode: 0,
+        }
+    }
+
     fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
         if !self.ram_enabled {
             return 0xFF;
@@ -219,38 +391,19 @@ impl Mbc for Mbc3 {
     }
 
     fn save_extra(&self) -> Vec<u8> {
-        vec![
-            self.rtc_live.sec,
-            self.rtc_live.min,
-            self.rtc_live.hour,
-            self.rtc_live.day_low,
-            self.rtc_live.day_high,
-            (self.rtc_cycle_accum & 0xFF) as u8,
-            ((self.rtc_cycle_accum >> 8) & 0xFF) as u8,
-            ((self.rtc_cycle_accum >> 16) & 0xFF) as u8,
-            ((self.rtc_cycle_accum >> 24) & 0xFF) as u8,
-        ]
+        match self.rtc_save_format {
+            RtcSaveFormat::Native => self.save_native(unix_now()),
+            RtcSaveFormat::Bgb => self.save_bgb_footer(unix_now()),
+        }
     }
 
     fn load_extra(&mut self, data: &[u8]) -> Result<(), &'static str> {
-        if data.is_empty() {
-            return Ok(());
+        match data.len() {
+            0 => {}
+            NATIVE_RTC_PAYLOAD_LEN | NATIVE_RTC_PAYLOAD_WITH_TIME_LEN => self.load_native(data),
+            BGB_RTC_FOOTER_LEN | BGB_RTC_FOOTER_LEN_32BIT_TIME => self.load_bgb_footer(data),
+            _ => return Err("invalid MBC3 RTC payload length"),
         }
-        if data.len() != 9 {
-            return Err("invalid MBC3 RTC payload length");
-        }
-
-        self.rtc_live.sec = data[0] % 60;
-        self.rtc_live.min = data[1] % 60;
-        self.rtc_live.hour = data[2] % 24;
-        self.rtc_live.day_low = data[3];
-        self.rtc_live.day_high = data[4] & 0xC1;
-        self.rtc_cycle_accum = u32::from(data[5])
-            | (u32::from(data[6]) << 8)
-            | (u32::from(data[7]) << 16)
-            | (u32::from(data[8]) << 24);
-        self.rtc_cycle_accum %= CYCLES_PER_SECOND;
-        self.rtc_latched = None;
         Ok(())
     }
 }
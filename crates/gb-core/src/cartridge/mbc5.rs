@@ -1,4 +1,4 @@
-use crate::cartridge::mbc::Mbc;
+use crate::cartridge::mbc::{BankInfo, Mbc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -6,6 +6,15 @@ pub struct Mbc5 {
     ram_enabled: bool,
     rom_bank: u16,
     ram_bank: u8,
+    // Rumble carts wire bit 3 of the RAM bank register to the motor instead of the RAM.
+    #[serde(default)]
+    rumble: bool,
+    #[serde(default)]
+    motor_on: bool,
+    // Whether the motor has run since the last `take_rumble`; games pulse it faster than a
+    // frontend polls, varying the duty cycle for strength.
+    #[serde(default)]
+    motor_pulsed: bool,
 }
 
 impl Mbc5 {
@@ -14,6 +23,17 @@ impl Mbc5 {
             ram_enabled: false,
             rom_bank: 1,
             ram_bank: 0,
+            rumble: false,
+            motor_on: false,
+            motor_pulsed: false,
+        }
+    }
+
+    /// An MBC5 with a rumble motor (cartridge types 0x1C-0x1E).
+    pub fn with_rumble() -> Self {
+        Self {
+            rumble: true,
+            ..Self::new()
         }
     }
 }
@@ -50,6 +70,11 @@ impl Mbc for Mbc5 {
             0x3000..=0x3FFF => {
                 self.rom_bank = (self.rom_bank & 0x00FF) | (((val & 0x01) as u16) << 8);
             }
+            0x4000..=0x5FFF if self.rumble => {
+                self.ram_bank = val & 0x07;
+                self.motor_on = (val & 0x08) != 0;
+                self.motor_pulsed |= self.motor_on;
+            }
             0x4000..=0x5FFF => {
                 self.ram_bank = val & 0x0F;
             }
@@ -57,6 +82,14 @@ impl Mbc for Mbc5 {
         }
     }
 
+    fn current_banks(&self) -> BankInfo {
+        BankInfo {
+            rom_bank: self.rom_bank,
+            ram_bank: self.ram_bank,
+            mode: 0,
+        }
+    }
+
     fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
         if !self.ram_enabled || ram.is_empty() {
             return 0xFF;
@@ -83,4 +116,9 @@ impl Mbc for Mbc5 {
             *entry = val;
         }
     }
+
+    fn take_rumble(&mut self) -> bool {
+        // A motor still running counts toward the next poll too.
+        core::mem::replace(&mut self.motor_pulsed, self.motor_on)
+    }
 }
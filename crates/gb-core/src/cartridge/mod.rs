@@ -7,8 +7,12 @@ pub mod mbc3;
 pub mod mbc5;
 
 use self::header::Header;
-use crate::cartridge::mbc::Mbc;
+use crate::cartridge::mbc::{BankInfo, Mbc};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
 use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,20 +28,132 @@ pub enum SaveError {
     NotBatteryBacked,
 }
 
+/// A battery save that loaded, but only after being adjusted (see
+/// [`Cartridge::take_save_warning`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveWarning {
+    /// The save's RAM image was a power-of-two size other than the header's RAM size (a
+    /// header that misreports it, or a save from another emulator), so it was zero-padded
+    /// or truncated to fit.
+    RamSizeMismatch { file_len: usize, header_len: usize },
+}
+
+impl core::fmt::Display for SaveWarning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::RamSizeMismatch {
+                file_len,
+                header_len,
+            } => write!(
+                f,
+                "save RAM is {file_len} bytes but the cartridge has {header_len}; {}",
+                if file_len < header_len {
+                    "padded with zeros"
+                } else {
+                    "extra bytes dropped"
+                }
+            ),
+        }
+    }
+}
+
+/// Something odd about a ROM that loaded anyway (see [`Cartridge::from_rom_checked`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CartridgeWarning {
+    /// The header's ROM size is more than the declared mapper can bank in, so the header is
+    /// probably wrong about one of them and the game may not boot.
+    MbcSizeMismatch {
+        cartridge_type: header::CartridgeType,
+        rom_size: header::RomSize,
+    },
+}
+
+impl core::fmt::Display for CartridgeWarning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MbcSizeMismatch {
+                cartridge_type,
+                rom_size,
+            } => write!(
+                f,
+                "header declares {cartridge_type:?} but a {} KiB ROM, which it can't address",
+                rom_size_kib(*rom_size)
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 impl From<std::io::Error> for SaveError {
     fn from(e: std::io::Error) -> Self {
         Self::Io(e.to_string())
     }
 }
 
+/// Starts the trailer that battery saves carry after cartridge RAM for MBC-specific data
+/// (magic, little-endian `u32` payload length, payload).
+pub const SAVE_TRAILER_MAGIC: &[u8; 5] = b"GBSV1";
+const SAVE_TRAILER_HEADER_LEN: usize = SAVE_TRAILER_MAGIC.len() + 4;
+
+/// Finds a `GBSV1` trailer in battery save contents without knowing the RAM size. Returns
+/// `(ram_len, payload_len)` when the trailer's payload ends exactly at the end of `data`.
+pub fn find_save_trailer(data: &[u8]) -> Option<(usize, usize)> {
+    (0..data.len().saturating_sub(SAVE_TRAILER_HEADER_LEN - 1))
+        .rev()
+        .find_map(|pos| {
+            let header = &data[pos..pos + SAVE_TRAILER_HEADER_LEN];
+            if &header[..SAVE_TRAILER_MAGIC.len()] != SAVE_TRAILER_MAGIC {
+                return None;
+            }
+            let len_bytes = [header[5], header[6], header[7], header[8]];
+            let payload_len = u32::from_le_bytes(len_bytes) as usize;
+            (pos + SAVE_TRAILER_HEADER_LEN + payload_len == data.len())
+                .then_some((pos, payload_len))
+        })
+}
+
+fn rom_size_kib(rom_size: header::RomSize) -> usize {
+    use header::RomSize::*;
+    match rom_size {
+        Kilobytes32 => 32,
+        Kilobytes64 => 64,
+        Kilobytes128 => 128,
+        Kilobytes256 => 256,
+        Kilobytes512 => 512,
+        Megabyte1 => 1024,
+        Megabyte2 => 2048,
+        Megabyte4 => 4096,
+        Megabyte8 => 8192,
+        Megabyte1_1 => 1152,
+        Megabyte1_2 => 1280,
+        Megabyte1_5 => 1536,
+    }
+}
+
+/// Identifies a ROM for save-state validation: a CRC of the header title in the high 16 bits
+/// and the header's global checksum (0x014E..=0x014F) in the low 16 bits.
+pub fn rom_fingerprint(rom: &[u8]) -> u32 {
+    let title = rom.get(0x0134..0x0144).unwrap_or(&[]);
+    let title_hash = crate::util::crc32(title) as u16;
+    let global_checksum = match rom.get(0x014E..0x0150) {
+        Some(&[hi, lo]) => u16::from_be_bytes([hi, lo]),
+        _ => 0,
+    };
+    (u32::from(title_hash) << 16) | u32::from(global_checksum)
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Cartridge {
-    #[serde(with = "serde_bytes")]
+    // Not part of save states; restored from the loaded ROM (matched by `rom_fingerprint`).
+    #[serde(skip)]
     pub rom: Vec<u8>,
     #[serde(with = "serde_bytes")]
     pub ram: Vec<u8>,
     pub header: Header,
     pub mbc: mbc::MbcEnum,
+    // Set by the last save load; not machine state.
+    #[serde(skip)]
+    save_warning: Option<SaveWarning>,
 }
 
 impl Cartridge {
@@ -60,10 +176,12 @@ impl Cartridge {
             | header::CartridgeType::Mbc3RamBattery => mbc::MbcEnum::Mbc3(mbc3::Mbc3::new()),
             header::CartridgeType::Mbc5
             | header::CartridgeType::Mbc5Ram
-            | header::CartridgeType::Mbc5RamBattery
-            | header::CartridgeType::Mbc5Rumble
+            | header::CartridgeType::Mbc5RamBattery => mbc::MbcEnum::Mbc5(mbc5::Mbc5::new()),
+            header::CartridgeType::Mbc5Rumble
             | header::CartridgeType::Mbc5RumbleRam
-            | header::CartridgeType::Mbc5RumbleRamBattery => mbc::MbcEnum::Mbc5(mbc5::Mbc5::new()),
+            | header::CartridgeType::Mbc5RumbleRamBattery => {
+                mbc::MbcEnum::Mbc5(mbc5::Mbc5::with_rumble())
+            }
         };
 
         Ok(Self {
@@ -71,9 +189,100 @@ impl Cartridge {
             ram,
             header,
             mbc,
+            save_warning: None,
         })
     }
 
+    /// Returns the mapper to its power-on state. Cartridge RAM, MBC2 RAM and the RTC survive
+    /// unless `hard`, which is like inserting a fresh copy of the cartridge. Host settings
+    /// (the MBC3 RTC save format and wall-clock sync) and the last save warning are kept.
+    pub fn reset(&mut self, hard: bool) {
+        let save = (!hard).then(|| self.save_data());
+        let mut fresh = Self::from_rom(core::mem::take(&mut self.rom))
+            .expect("ROM header was already accepted");
+        if let Some(save) = save {
+            // The trailer came from this same mapper, so it always loads.
+            let _ = fresh.load_save_data(&save);
+        }
+        // Taken after the load, so wall-clock sync doesn't move the RTC.
+        fresh.take_settings(self);
+        *self = fresh;
+    }
+
+    /// Moves the host settings (the MBC3 RTC save format and wall-clock sync) and the last
+    /// save warning over from `old`, a cartridge with the same mapper.
+    pub(crate) fn take_settings(&mut self, old: &mut Self) {
+        if let (mbc::MbcEnum::Mbc3(mbc3), mbc::MbcEnum::Mbc3(old_mbc3)) = (&mut self.mbc, &old.mbc)
+        {
+            mbc3.copy_settings_from(old_mbc3);
+        }
+        self.save_warning = old.save_warning.take();
+    }
+
+    /// Like [`Cartridge::from_rom`], but also reports header inconsistencies that commonly
+    /// explain a game failing to boot.
+    pub fn from_rom_checked(
+        rom: Vec<u8>,
+    ) -> Result<(Self, Option<CartridgeWarning>), CartridgeError> {
+        let cart = Self::from_rom(rom)?;
+        let warning = cart.mbc_size_mismatch();
+        Ok((cart, warning))
+    }
+
+    fn mbc_size_mismatch(&self) -> Option<CartridgeWarning> {
+        use header::CartridgeType::*;
+        let cartridge_type = self.header.cartridge_type;
+        let rom_size = self.header.rom_size;
+        // Most ROM each mapper can bank in. MBC1 reaches past 512 KiB only by giving its
+        // secondary register to ROM banking, so a bigger MBC1 header is more often a
+        // mislabeled MBC5 image.
+        let max_kib = match cartridge_type {
+            RomOnly => 32,
+            Mbc1 | Mbc1Ram | Mbc1RamBattery => 512,
+            Mbc2 | Mbc2Battery => 256,
+            Mbc3TimerBattery | Mbc3TimerRamBattery | Mbc3 | Mbc3Ram | Mbc3RamBattery => 2048,
+            _ => 8192,
+        };
+        (rom_size_kib(rom_size) > max_kib).then_some(CartridgeWarning::MbcSizeMismatch {
+            cartridge_type,
+            rom_size,
+        })
+    }
+
+    /// The game title from the header; may be empty.
+    pub fn title(&self) -> &str {
+        &self.header.title
+    }
+
+    /// A stable 8-byte ID for this ROM image, independent of where it was loaded from:
+    /// the title hash and global checksum from [`rom_fingerprint`], the header checksum, and
+    /// 24 bits of a CRC over the whole image so patched or corrupted dumps differ too.
+    pub fn fingerprint(&self) -> [u8; 8] {
+        let [title_hi, title_lo, global_hi, global_lo] = rom_fingerprint(&self.rom).to_be_bytes();
+        let header_checksum = self.rom.get(0x014D).copied().unwrap_or(0);
+        let [_, crc_hi, crc_mid, crc_lo] = crate::util::crc32(&self.rom).to_be_bytes();
+        [
+            title_hi,
+            title_lo,
+            header_checksum,
+            global_hi,
+            global_lo,
+            crc_hi,
+            crc_mid,
+            crc_lo,
+        ]
+    }
+
+    /// Identifies the ROM for save-state validation (see [`rom_fingerprint`]).
+    pub fn rom_fingerprint(&self) -> u32 {
+        rom_fingerprint(&self.rom)
+    }
+
+    /// Which ROM bank is mapped at 0x4000 and which RAM bank at 0xA000.
+    pub fn bank_info(&self) -> BankInfo {
+        self.mbc.current_banks()
+    }
+
     pub fn has_battery(&self) -> bool {
         matches!(
             self.header.cartridge_type,
@@ -87,22 +296,20 @@ impl Cartridge {
         )
     }
 
+    fn uses_bgb_rtc_footer(&self) -> bool {
+        matches!(&self.mbc, mbc::MbcEnum::Mbc3(m) if m.rtc_save_format() == mbc3::RtcSaveFormat::Bgb)
+    }
+
+    #[cfg(feature = "std")]
     pub fn save_to_path(&self, path: &Path) -> Result<(), SaveError> {
         if !self.has_battery() {
             return Ok(());
         }
 
-        let mut data = self.ram.clone();
-        let extra = self.mbc.save_extra();
-        if !extra.is_empty() {
-            data.extend_from_slice(b"GBSV1");
-            data.extend_from_slice(&(extra.len() as u32).to_le_bytes());
-            data.extend_from_slice(&extra);
-        }
-
-        std::fs::write(path, data).map_err(|e| SaveError::Io(e.to_string()))
+        std::fs::write(path, self.save_data()).map_err(|e| SaveError::Io(e.to_string()))
     }
 
+    #[cfg(feature = "std")]
     pub fn load_from_path(&mut self, path: &Path) -> Result<(), SaveError> {
         if !self.has_battery() {
             return Ok(());
@@ -112,7 +319,53 @@ impl Cartridge {
         }
 
         let data = std::fs::read(path).map_err(|e| SaveError::Io(e.to_string()))?;
+        self.load_save_data(&data)
+    }
+
+    /// Battery save contents as written by `save_to_path`: cartridge RAM followed by any
+    /// MBC-specific trailer (e.g. the MBC3 RTC).
+    pub fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        let extra = self.mbc.save_extra();
+        if self.uses_bgb_rtc_footer() {
+            // Other emulators expect the RTC footer directly after RAM.
+            data.extend_from_slice(&extra);
+        } else if !extra.is_empty() {
+            data.extend_from_slice(SAVE_TRAILER_MAGIC);
+            data.extend_from_slice(&(extra.len() as u32).to_le_bytes());
+            data.extend_from_slice(&extra);
+        }
+        data
+    }
 
+    /// Why the last loaded save had to be adjusted, if it did; cleared by the call.
+    pub fn take_save_warning(&mut self) -> Option<SaveWarning> {
+        self.save_warning.take()
+    }
+
+    /// Restores cartridge RAM (and any MBC trailer) from battery save contents.
+    ///
+    /// A RAM image whose size is a power of two other than the header's RAM size is padded or
+    /// truncated to fit and reported through [`Cartridge::take_save_warning`].
+    pub fn load_save_data(&mut self, data: &[u8]) -> Result<(), SaveError> {
+        self.save_warning = None;
+        let ram_len = self.ram.len();
+        let file_ram_len = find_save_trailer(data).map_or(data.len(), |(pos, _)| pos);
+        if ram_len == 0 || file_ram_len == ram_len || !file_ram_len.is_power_of_two() {
+            return self.load_exact_save_data(data);
+        }
+
+        let mut repaired = data[..file_ram_len.min(ram_len)].to_vec();
+        repaired.resize(ram_len, 0);
+        repaired.extend_from_slice(&data[file_ram_len..]);
+        self.save_warning = Some(SaveWarning::RamSizeMismatch {
+            file_len: file_ram_len,
+            header_len: ram_len,
+        });
+        self.load_exact_save_data(&repaired)
+    }
+
+    fn load_exact_save_data(&mut self, data: &[u8]) -> Result<(), SaveError> {
         // Basic verification: data must be at least as large as RAM
         let ram_len = self.ram.len();
         if data.len() < ram_len {
@@ -135,12 +388,24 @@ impl Cartridge {
             return self.mbc.load_extra(&[]).map_err(SaveError::InvalidFormat);
         }
 
-        if trailer.len() < 9 {
+        if trailer.len() < SAVE_TRAILER_HEADER_LEN {
             // Too short for header, ignore
             return Ok(());
         }
 
-        if &trailer[..5] != b"GBSV1" {
+        if &trailer[..SAVE_TRAILER_MAGIC.len()] != SAVE_TRAILER_MAGIC {
+            // BGB-style RTC footer written directly after RAM.
+            if matches!(self.mbc, mbc::MbcEnum::Mbc3(_))
+                && matches!(
+                    trailer.len(),
+                    mbc3::BGB_RTC_FOOTER_LEN_32BIT_TIME | mbc3::BGB_RTC_FOOTER_LEN
+                )
+            {
+                return self
+                    .mbc
+                    .load_extra(trailer)
+                    .map_err(SaveError::InvalidFormat);
+            }
             // Not our format, maybe raw RAM dump.
             return Ok(());
         }
@@ -148,12 +413,12 @@ impl Cartridge {
         let len_bytes = [trailer[5], trailer[6], trailer[7], trailer[8]];
         let extra_len = u32::from_le_bytes(len_bytes) as usize;
 
-        if trailer.len() < 9 + extra_len {
+        if trailer.len() < SAVE_TRAILER_HEADER_LEN + extra_len {
             return Err(SaveError::InvalidFormat("save trailer truncated"));
         }
 
         self.mbc
-            .load_extra(&trailer[9..9 + extra_len])
+            .load_extra(&trailer[SAVE_TRAILER_HEADER_LEN..SAVE_TRAILER_HEADER_LEN + extra_len])
             .map_err(SaveError::InvalidFormat)
     }
 }
@@ -1,5 +1,7 @@
 use crate::bus::Bus;
 use crate::interrupt::{pending_mask, Interrupt};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
 use super::{cb_ops, ops};
@@ -37,6 +39,77 @@ impl Flag {
     }
 }
 
+/// Execution counts per opcode, collected while profiling is enabled on [`Cpu`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpcodeHistogram {
+    /// Unprefixed opcodes; `base[0xCB]` counts every CB-prefixed instruction.
+    pub base: [u64; 256],
+    /// CB-prefixed opcodes, indexed by the byte after 0xCB.
+    pub cb: [u64; 256],
+}
+
+impl OpcodeHistogram {
+    pub fn new() -> Self {
+        Self {
+            base: [0; 256],
+            cb: [0; 256],
+        }
+    }
+
+    /// The `n` most executed instructions, most frequent first. CB-prefixed opcodes are
+    /// reported as `0xCBxx`; the 0xCB prefix itself is left out.
+    pub fn top(&self, n: usize) -> Vec<(u16, u64)> {
+        let base = (0u16..256)
+            .filter(|&op| op != 0xCB)
+            .map(|op| (op, self.base[op as usize]));
+        let cb = (0u16..256).map(|op| (0xCB00 | op, self.cb[op as usize]));
+        let mut counts: Vec<(u16, u64)> = base.chain(cb).filter(|&(_, c)| c > 0).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+}
+
+impl Default for OpcodeHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the CPU does when it executes one of the unused opcodes (0xD3, 0xDB, 0xDD, 0xE3,
+/// 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum IllegalOpcodeMode {
+    /// Treat them as NOP (lenient; keeps buggy ROMs running).
+    #[default]
+    Nop,
+    /// Hang like hardware: nothing but idle cycles until reset, interrupts included.
+    Lockup,
+    /// Panic, so test tools notice accidental execution.
+    Panic,
+}
+
+/// What the CPU does when it executes STOP (0x10) outside a CGB speed switch.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum StopMode {
+    /// Stop like hardware: the CPU sleeps (DMG: until a selected button is pressed).
+    #[default]
+    Hardware,
+    /// Skip it like a two-byte NOP, for ROMs that hang on an accidental STOP.
+    TreatAsNop,
+}
+
+/// One entry of the shadow call stack (see [`Cpu::call_stack`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackFrame {
+    /// Address of the CALL/RST, or the interrupted PC for an interrupt dispatch.
+    pub caller_pc: u16,
+    /// The pushed address the matching RET/RETI resumes at.
+    pub return_addr: u16,
+    /// SP after the push, i.e. where the return address is stored.
+    pub sp: u16,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Cpu {
     // 8-bit registers
@@ -61,6 +134,23 @@ pub struct Cpu {
     /// HALT bug latch: next opcode fetch reads at PC without incrementing it.
     pub halt_bug: bool,
     pub step_cycles: u32,
+    // Cycles of the current step spent stalled by DMA, on top of the instruction's own length.
+    #[serde(skip)]
+    stall_cycles: u32,
+    /// Set after an illegal opcode in `IllegalOpcodeMode::Lockup`; the CPU never runs again.
+    pub locked_up: bool,
+    // Tool setting, not machine state.
+    #[serde(skip)]
+    pub illegal_opcode_mode: IllegalOpcodeMode,
+    // Tool setting, not machine state.
+    #[serde(skip)]
+    pub stop_mode: StopMode,
+    // Opcode counts while profiling; `None` keeps the default path to a single branch.
+    #[serde(skip)]
+    opcode_histogram: Option<Box<OpcodeHistogram>>,
+    // Shadow call stack while tracking; innermost frame last.
+    #[serde(skip)]
+    call_stack: Option<Vec<StackFrame>>,
 }
 
 impl Cpu {
@@ -81,6 +171,110 @@ impl Cpu {
             ei_pending: false,
             halt_bug: false,
             step_cycles: 0,
+            stall_cycles: 0,
+            locked_up: false,
+            illegal_opcode_mode: IllegalOpcodeMode::Nop,
+            stop_mode: StopMode::Hardware,
+            opcode_histogram: None,
+            call_stack: None,
+        }
+    }
+
+    /// Returns to power-on state, keeping the tool settings: illegal opcode and STOP modes,
+    /// opcode profiling and call-stack tracking.
+    pub fn reset(&mut self) {
+        let mut fresh = Self::new();
+        fresh.take_settings(self);
+        *self = fresh;
+    }
+
+    /// Moves the tool settings over from `old`: illegal opcode and STOP modes, opcode
+    /// profiling, and call-stack tracking with an emptied stack.
+    pub(crate) fn take_settings(&mut self, old: &mut Self) {
+        self.illegal_opcode_mode = old.illegal_opcode_mode;
+        self.stop_mode = old.stop_mode;
+        self.opcode_histogram = old.opcode_histogram.take();
+        self.call_stack = old.call_stack.take().map(|mut stack| {
+            stack.clear();
+            stack
+        });
+    }
+
+    /// Handles an unused opcode according to `illegal_opcode_mode`, returning its cycles.
+    pub(crate) fn illegal_opcode(&mut self, opcode: u8) -> u32 {
+        match self.illegal_opcode_mode {
+            IllegalOpcodeMode::Nop => {}
+            IllegalOpcodeMode::Lockup => self.locked_up = true,
+            IllegalOpcodeMode::Panic => panic!(
+                "illegal opcode {opcode:#04X} at {:#06X}",
+                self.pc.wrapping_sub(1)
+            ),
+        }
+        4
+    }
+
+    /// Starts (with zeroed counts) or stops counting executed opcodes.
+    pub fn set_opcode_profiling(&mut self, enabled: bool) {
+        self.opcode_histogram = enabled.then(|| Box::new(OpcodeHistogram::new()));
+    }
+
+    /// Returns the counts gathered so far and resets them; `None` if profiling is off.
+    pub fn take_opcode_histogram(&mut self) -> Option<OpcodeHistogram> {
+        self.opcode_histogram
+            .as_mut()
+            .map(|h| core::mem::take(h.as_mut()))
+    }
+
+    /// Starts (with an empty stack) or stops tracking CALL/RST/interrupt frames.
+    pub fn set_call_stack_tracking(&mut self, enabled: bool) {
+        self.call_stack = enabled.then(Vec::new);
+    }
+
+    pub fn call_stack_tracking(&self) -> bool {
+        self.call_stack.is_some()
+    }
+
+    /// The shadow call stack, outermost frame first; empty unless tracking is on.
+    ///
+    /// Frames follow the real stack by SP: a RET pops the frame whose return address it reads,
+    /// and frames the program abandoned by moving SP itself are dropped at the next call or
+    /// return that passes them.
+    pub fn call_stack(&self) -> &[StackFrame] {
+        self.call_stack.as_deref().unwrap_or(&[])
+    }
+
+    /// Records a call whose return address was just pushed at SP.
+    #[inline]
+    pub(crate) fn record_call(&mut self, caller_pc: u16, return_addr: u16) {
+        let Some(stack) = self.call_stack.as_mut() else {
+            return;
+        };
+        let sp = self.sp;
+        // The push overwrote (or skipped above) the slots of these frames.
+        while stack.last().is_some_and(|f| f.sp <= sp) {
+            stack.pop();
+        }
+        stack.push(StackFrame {
+            caller_pc,
+            return_addr,
+            sp,
+        });
+    }
+
+    /// Records a return about to pop its address from SP.
+    #[inline]
+    pub(crate) fn record_return(&mut self) {
+        let Some(stack) = self.call_stack.as_mut() else {
+            return;
+        };
+        let sp = self.sp;
+        // Frames below SP were unwound without a RET.
+        while stack.last().is_some_and(|f| f.sp < sp) {
+            stack.pop();
+        }
+        // Otherwise this returns through an address pushed by hand (PUSH + RET); keep the frame.
+        if stack.last().is_some_and(|f| f.sp == sp) {
+            stack.pop();
         }
     }
 
@@ -92,9 +286,16 @@ impl Cpu {
         bus.iflag &= !intr.bit();
         self.ime = false;
         self.halted = false;
+        // EI ; HALT with an interrupt already pending: the halt bug hits the return address
+        // instead of the handler's first fetch, so the handler returns to the HALT itself.
+        if self.halt_bug {
+            self.halt_bug = false;
+            self.pc = self.pc.wrapping_sub(1);
+        }
 
         let pc = self.pc;
         self.push16(bus, pc);
+        self.record_call(pc, pc);
         self.pc = intr.vector();
 
         self.finish_step(bus, 20)
@@ -121,6 +322,7 @@ impl Cpu {
     #[inline]
     fn finish_step(&mut self, bus: &mut Bus, target_cycles: u32) -> u32 {
         debug_assert_eq!(target_cycles % 4, 0);
+        let target_cycles = target_cycles + self.stall_cycles;
         if self.step_cycles < target_cycles {
             self.tick_idle(bus, target_cycles - self.step_cycles);
         }
@@ -139,6 +341,12 @@ impl Cpu {
     pub fn write8(&mut self, bus: &mut Bus, addr: u16, val: u8) {
         bus.write8(addr, val);
         self.tick_mcycle(bus);
+        // Starting a general-purpose DMA halts the CPU until the copy would have finished.
+        let stall = bus.take_dma_stall_cycles();
+        if stall > 0 {
+            self.tick_idle(bus, stall);
+            self.stall_cycles += stall;
+        }
     }
 
     #[inline]
@@ -283,6 +491,20 @@ impl Cpu {
 
     pub fn step(&mut self, bus: &mut Bus) -> u32 {
         self.step_cycles = 0;
+        self.stall_cycles = 0;
+        // Only a GDMA this CPU starts stalls it (see `Cpu::write8`).
+        bus.take_dma_stall_cycles();
+
+        if self.locked_up {
+            self.tick_idle(bus, 4);
+            return 4;
+        }
+
+        // DMG STOP: nothing runs until a joypad press wakes the CPU.
+        if !bus.try_exit_stop() {
+            self.tick_idle(bus, 4);
+            return 4;
+        }
 
         let pending = pending_mask(bus.ie, bus.iflag);
         let requested = bus.iflag & 0x1F;
@@ -308,8 +530,15 @@ impl Cpu {
         let opcode = self.fetch8(bus);
         let cycles = if opcode == 0xCB {
             let cb = self.fetch8(bus);
+            if let Some(h) = self.opcode_histogram.as_mut() {
+                h.base[0xCB] += 1;
+                h.cb[cb as usize] += 1;
+            }
             cb_ops::exec(self, bus, cb)
         } else {
+            if let Some(h) = self.opcode_histogram.as_mut() {
+                h.base[opcode as usize] += 1;
+            }
             ops::exec(self, bus, opcode)
         };
 
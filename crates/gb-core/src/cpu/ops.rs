@@ -1,7 +1,7 @@
-use crate::bus::Bus;
+use crate::bus::{Bus, CGB_SPEED_SWITCH_STALL_CYCLES};
 use crate::interrupt::pending_mask;
 
-use super::cpu::{Flag, R8};
+use super::cpu::{Flag, StopMode, R8};
 use super::Cpu;
 
 fn r8_from_code(code: u8) -> R8 {
@@ -91,10 +91,20 @@ pub fn exec(cpu: &mut Cpu, bus: &mut Bus, opcode: u8) -> u32 {
             // STOP; consume the following padding byte.
             let _ = cpu.fetch8(bus);
 
-            // On CGB, STOP is also used for the KEY1 speed-switch handshake.
-            cpu.halted = !bus.try_cgb_speed_switch();
-
-            8
+            // On CGB, STOP is also used for the KEY1 speed-switch handshake. The switch stalls
+            // the CPU; `finish_step` ticks the bus (at the new speed) for the whole stall.
+            if bus.try_cgb_speed_switch() {
+                cpu.halted = false;
+                8 + CGB_SPEED_SWITCH_STALL_CYCLES
+            } else if cpu.stop_mode == StopMode::TreatAsNop {
+                8
+            } else if bus.try_enter_dmg_stop() {
+                // DMG STOP: the CPU sleeps until a selected joypad button is pressed.
+                8
+            } else {
+                cpu.halted = true;
+                8
+            }
         }
 
         // 16-bit loads
@@ -489,6 +499,7 @@ pub fn exec(cpu: &mut Cpu, bus: &mut Bus, opcode: u8) -> u32 {
         0xCD => {
             let addr = cpu.fetch16(bus);
             cpu.push16(bus, cpu.pc);
+            cpu.record_call(cpu.pc.wrapping_sub(3), cpu.pc);
             cpu.pc = addr;
             24
         }
@@ -496,6 +507,7 @@ pub fn exec(cpu: &mut Cpu, bus: &mut Bus, opcode: u8) -> u32 {
             let addr = cpu.fetch16(bus);
             if cond(cpu, opcode) {
                 cpu.push16(bus, cpu.pc);
+                cpu.record_call(cpu.pc.wrapping_sub(3), cpu.pc);
                 cpu.pc = addr;
                 24
             } else {
@@ -505,11 +517,13 @@ pub fn exec(cpu: &mut Cpu, bus: &mut Bus, opcode: u8) -> u32 {
 
         // RET
         0xC9 => {
+            cpu.record_return();
             cpu.pc = cpu.pop16(bus);
             16
         }
         0xC0 | 0xC8 | 0xD0 | 0xD8 => {
             if cond(cpu, opcode) {
+                cpu.record_return();
                 cpu.pc = cpu.pop16(bus);
                 20
             } else {
@@ -517,6 +531,7 @@ pub fn exec(cpu: &mut Cpu, bus: &mut Bus, opcode: u8) -> u32 {
             }
         }
         0xD9 => {
+            cpu.record_return();
             cpu.pc = cpu.pop16(bus);
             cpu.ime = true;
             16
@@ -526,6 +541,7 @@ pub fn exec(cpu: &mut Cpu, bus: &mut Bus, opcode: u8) -> u32 {
         0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => {
             let vec = (opcode & 0x38) as u16;
             cpu.push16(bus, cpu.pc);
+            cpu.record_call(cpu.pc.wrapping_sub(1), cpu.pc);
             cpu.pc = vec;
             16
         }
@@ -669,7 +685,7 @@ pub fn exec(cpu: &mut Cpu, bus: &mut Bus, opcode: u8) -> u32 {
             8
         }
 
-        // Undocumented/unused opcodes: treat as NOP.
-        _ => 4,
+        // Unused opcodes (0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD).
+        _ => cpu.illegal_opcode(opcode),
     }
 }
@@ -0,0 +1,369 @@
+//! A GDB remote serial protocol (RSP) server, so gdb or another RSP client can debug a machine
+//! over TCP.
+//!
+//! The SM83 has no gdb architecture of its own: the target description claims `z80` and lists
+//! AF, BC, DE, HL, SP and PC as six 16-bit little-endian registers, in that order. Memory goes
+//! through [`Bus::debug_read8`]/[`Bus::debug_write8`], so inspecting it never disturbs the
+//! machine. Breakpoints (`Z0`/`Z1`) are PC addresses checked before each instruction.
+//!
+//! [`Bus::debug_read8`]: crate::bus::Bus::debug_read8
+//! [`Bus::debug_write8`]: crate::bus::Bus::debug_write8
+
+use std::collections::BTreeSet;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+use crate::gb::GameBoy;
+
+/// Served to `qXfer:features:read:target.xml`.
+pub const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <architecture>z80</architecture>
+  <feature name="org.gnu.gdb.z80.cpu">
+    <reg name="af" bitsize="16" type="int"/>
+    <reg name="bc" bitsize="16" type="int"/>
+    <reg name="de" bitsize="16" type="int"/>
+    <reg name="hl" bitsize="16" type="int"/>
+    <reg name="sp" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+const REGISTER_COUNT: usize = 6;
+/// Largest packet accepted, as advertised in the `qSupported` reply (`PacketSize=1000`).
+const MAX_PACKET_LEN: usize = 0x1000;
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+/// Why [`GdbStub::serve`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEnd {
+    /// The client detached (`D`) or closed the connection; the machine may keep running.
+    Detached,
+    /// The client asked to kill the target (`k`).
+    Killed,
+}
+
+enum Action {
+    Reply(String),
+    End(SessionEnd),
+}
+
+/// One client connection. The machine only runs while the client has it stepping or
+/// continuing.
+pub struct GdbStub {
+    stream: TcpStream,
+    breakpoints: BTreeSet<u16>,
+}
+
+impl GdbStub {
+    /// Instructions run between checks for a Ctrl-C from the client while continuing.
+    const INTERRUPT_POLL_STEPS: u32 = 4096;
+
+    pub fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    /// Answers packets until the client detaches, disconnects or kills the target.
+    pub fn serve(&mut self, gb: &mut GameBoy) -> io::Result<SessionEnd> {
+        loop {
+            let Some(packet) = self.read_packet()? else {
+                return Ok(SessionEnd::Detached);
+            };
+            let packet = String::from_utf8_lossy(&packet).into_owned();
+            match self.handle(gb, &packet)? {
+                Action::Reply(reply) => self.write_packet(&reply)?,
+                Action::End(end) => return Ok(end),
+            }
+        }
+    }
+
+    fn handle(&mut self, gb: &mut GameBoy, packet: &str) -> io::Result<Action> {
+        let reply = match packet.as_bytes().first().copied() {
+            Some(b'?') => stop_reply(SIGTRAP),
+            Some(b'q') => query(packet),
+            Some(b'g') => (0..REGISTER_COUNT)
+                .filter_map(|n| register(gb, n))
+                .map(hex_u16_le)
+                .collect(),
+            Some(b'G') => write_registers(gb, &packet[1..]).unwrap_or_else(error_reply),
+            Some(b'p') => parse_hex(&packet[1..])
+                .and_then(|n| register(gb, n as usize))
+                .map(hex_u16_le)
+                .unwrap_or_else(error_reply),
+            Some(b'P') => write_register(gb, &packet[1..]).unwrap_or_else(error_reply),
+            Some(b'm') => read_memory(gb, &packet[1..]).unwrap_or_else(error_reply),
+            Some(b'M') => write_memory(gb, &packet[1..]).unwrap_or_else(error_reply),
+            Some(b's') => {
+                step(gb);
+                stop_reply(SIGTRAP)
+            }
+            Some(b'c') => self.resume(gb)?,
+            Some(b'Z') => self.set_breakpoint(&packet[1..], true),
+            Some(b'z') => self.set_breakpoint(&packet[1..], false),
+            // A single machine has a single thread.
+            Some(b'H') => "OK".to_string(),
+            Some(b'D') => {
+                self.write_packet("OK")?;
+                return Ok(Action::End(SessionEnd::Detached));
+            }
+            Some(b'k') => return Ok(Action::End(SessionEnd::Killed)),
+            // An empty reply tells the client the packet isn't supported.
+            _ => String::new(),
+        };
+        Ok(Action::Reply(reply))
+    }
+
+    /// Runs until a breakpoint or a Ctrl-C from the client.
+    fn resume(&mut self, gb: &mut GameBoy) -> io::Result<String> {
+        // Leave a breakpoint at the current PC before watching for the next one.
+        step(gb);
+        let mut steps = 0u32;
+        loop {
+            if self.breakpoints.contains(&gb.cpu.pc) {
+                return Ok(stop_reply(SIGTRAP));
+            }
+            steps = steps.wrapping_add(1);
+            if steps.is_multiple_of(Self::INTERRUPT_POLL_STEPS) && self.interrupt_requested()? {
+                return Ok(stop_reply(SIGINT));
+            }
+            step(gb);
+        }
+    }
+
+    /// `Z`/`z` payload `type,addr,kind`; software (0) and hardware (1) breakpoints behave alike.
+    fn set_breakpoint(&mut self, args: &str, insert: bool) -> String {
+        let mut fields = args.split(',');
+        let (Some("0" | "1"), Some(addr)) = (fields.next(), fields.next()) else {
+            return String::new();
+        };
+        let Some(addr) = parse_hex(addr).and_then(|a| u16::try_from(a).ok()) else {
+            return error_reply();
+        };
+        if insert {
+            self.breakpoints.insert(addr);
+        } else {
+            self.breakpoints.remove(&addr);
+        }
+        "OK".to_string()
+    }
+
+    /// Checks for the 0x03 byte a client sends to interrupt a continue, without blocking.
+    /// A closed connection also stops the machine; the next read then ends the session.
+    fn interrupt_requested(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let mut byte = [0u8; 1];
+        let read = self.stream.read(&mut byte);
+        self.stream.set_nonblocking(false)?;
+        match read {
+            Ok(0) => Ok(true),
+            Ok(_) => Ok(byte[0] == 0x03),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0u8; 1];
+        match self.stream.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    /// Next packet payload, or `None` once the client disconnects. Acks and stray bytes
+    /// (including a Ctrl-C while stopped) are skipped; packets with a bad checksum or longer
+    /// than [`MAX_PACKET_LEN`] are nak'ed.
+    fn read_packet(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'$') => break,
+                    Some(_) => {}
+                }
+            }
+            let mut payload = Vec::new();
+            let mut oversized = false;
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(b) if payload.len() < MAX_PACKET_LEN => payload.push(b),
+                    Some(_) => oversized = true,
+                }
+            }
+            let mut sum = [0u8; 2];
+            self.stream.read_exact(&mut sum)?;
+            let sum = core::str::from_utf8(&sum).ok().and_then(parse_hex);
+            if !oversized && sum == Some(u32::from(checksum(&payload))) {
+                self.stream.write_all(b"+")?;
+                return Ok(Some(payload));
+            }
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    fn write_packet(&mut self, payload: &str) -> io::Result<()> {
+        let packet = format!("${payload}#{:02x}", checksum(payload.as_bytes()));
+        self.stream.write_all(packet.as_bytes())
+    }
+}
+
+/// Executes one instruction. Nothing presents frames while the client drives the machine, so
+/// the frame flag is cleared here to keep [`GameBoy::frames`] counting.
+fn step(gb: &mut GameBoy) {
+    gb.step();
+    if gb.bus.ppu.frame_ready() {
+        gb.bus.ppu.clear_frame_ready();
+    }
+}
+
+fn query(packet: &str) -> String {
+    if packet.starts_with("qSupported") {
+        "PacketSize=1000;qXfer:features:read+".to_string()
+    } else if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+        read_target_xml(range).unwrap_or_else(error_reply)
+    } else if packet == "qAttached" {
+        "1".to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// `offset,length` of the target description; `l` marks the last chunk.
+fn read_target_xml(range: &str) -> Option<String> {
+    let (offset, length) = range.split_once(',')?;
+    let xml = TARGET_XML.as_bytes();
+    let start = (parse_hex(offset)? as usize).min(xml.len());
+    let end = start
+        .saturating_add(parse_hex(length)? as usize)
+        .min(xml.len());
+    let marker = if end == xml.len() { 'l' } else { 'm' };
+    let chunk = core::str::from_utf8(&xml[start..end]).ok()?;
+    Some(format!("{marker}{chunk}"))
+}
+
+fn register(gb: &GameBoy, n: usize) -> Option<u16> {
+    let cpu = &gb.cpu;
+    Some(match n {
+        0 => cpu.af(),
+        1 => cpu.bc(),
+        2 => cpu.de(),
+        3 => cpu.hl(),
+        4 => cpu.sp,
+        5 => cpu.pc,
+        _ => return None,
+    })
+}
+
+fn set_register(gb: &mut GameBoy, n: usize, v: u16) -> Option<()> {
+    let cpu = &mut gb.cpu;
+    match n {
+        0 => cpu.set_af(v),
+        1 => cpu.set_bc(v),
+        2 => cpu.set_de(v),
+        3 => cpu.set_hl(v),
+        4 => cpu.sp = v,
+        5 => cpu.pc = v,
+        _ => return None,
+    }
+    Some(())
+}
+
+/// `G` payload: every register, in target-description order.
+fn write_registers(gb: &mut GameBoy, hex: &str) -> Option<String> {
+    let bytes = decode_hex(hex)?;
+    if bytes.len() != REGISTER_COUNT * 2 {
+        return None;
+    }
+    for (n, v) in bytes.chunks_exact(2).enumerate() {
+        set_register(gb, n, u16::from_le_bytes([v[0], v[1]]))?;
+    }
+    Some("OK".to_string())
+}
+
+/// `P` payload: `n=value`.
+fn write_register(gb: &mut GameBoy, args: &str) -> Option<String> {
+    let (n, value) = args.split_once('=')?;
+    let bytes = decode_hex(value)?;
+    let [lo, hi] = bytes[..] else {
+        return None;
+    };
+    set_register(gb, parse_hex(n)? as usize, u16::from_le_bytes([lo, hi]))?;
+    Some("OK".to_string())
+}
+
+/// `addr,length`, checked to stay within the 16-bit address space.
+fn parse_range(args: &str) -> Option<(u16, usize)> {
+    let (addr, length) = args.split_once(',')?;
+    let addr = parse_hex(addr)?;
+    let length = parse_hex(length)?;
+    if addr.checked_add(length)? > 0x1_0000 {
+        return None;
+    }
+    Some((addr as u16, length as usize))
+}
+
+fn read_memory(gb: &GameBoy, args: &str) -> Option<String> {
+    let (addr, length) = parse_range(args)?;
+    Some(
+        (0..length)
+            .map(|i| format!("{:02x}", gb.bus.debug_read8(addr.wrapping_add(i as u16))))
+            .collect(),
+    )
+}
+
+/// `M` payload: `addr,length:data`.
+fn write_memory(gb: &mut GameBoy, args: &str) -> Option<String> {
+    let (range, data) = args.split_once(':')?;
+    let (addr, length) = parse_range(range)?;
+    let data = decode_hex(data)?;
+    if data.len() != length {
+        return None;
+    }
+    for (i, &b) in data.iter().enumerate() {
+        gb.bus.debug_write8(addr.wrapping_add(i as u16), b);
+    }
+    Some("OK".to_string())
+}
+
+fn stop_reply(signal: u8) -> String {
+    format!("S{signal:02x}")
+}
+
+fn error_reply() -> String {
+    "E01".to_string()
+}
+
+fn hex_u16_le(v: u16) -> String {
+    let [lo, hi] = v.to_le_bytes();
+    format!("{lo:02x}{hi:02x}")
+}
+
+fn parse_hex(s: &str) -> Option<u32> {
+    u32::from_str_radix(s, 16).ok()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn checksum(payload: &[u8]) -> u8 {
+    payload.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
@@ -1,2 +1,4 @@
+#[cfg(feature = "gdb")]
+pub mod gdb;
 pub mod trace;
 pub mod views;
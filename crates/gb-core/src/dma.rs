@@ -15,7 +15,13 @@ pub struct OamDma {
     cycle_budget: u32,
 }
 
+/// The DMA source register; writes to it always reach the bus so a transfer can be restarted.
+pub const OAM_DMA_REG: u16 = 0xFF46;
+
 impl OamDma {
+    /// Starts a transfer from `page << 8`. Called mid-transfer, this restarts from the new
+    /// source: the byte counter goes back to 0 and the startup delay is re-armed. The
+    /// transfer stays active throughout, so the CPU bus remains blocked across the restart.
     pub fn start(&mut self, page: u8) {
         self.active = true;
         self.source_base = (page as u16) << 8;
@@ -30,7 +36,7 @@ impl OamDma {
     }
 
     pub fn blocks_cpu_addr(&self, addr: u16) -> bool {
-        self.active && !(0xFF80..=0xFFFE).contains(&addr)
+        self.active && addr != OAM_DMA_REG && !(0xFF80..=0xFFFE).contains(&addr)
     }
 
     pub fn add_cycles(&mut self, cycles: u32) {
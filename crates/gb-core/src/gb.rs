@@ -1,22 +1,450 @@
-use crate::bus::Bus;
+use crate::bus::{Bus, EmulationMode};
+use crate::cartridge::header::CgbSupport;
 use crate::cpu::Cpu;
+use crate::interrupt::{decode_mask, pending_mask, Interrupt};
+use crate::ppu::{dmg_colorization, Framebuffer};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
+/// Magic bytes at the start of a save state produced by [`GameBoy::to_state_bytes`].
+pub const STATE_MAGIC: &[u8; 4] = b"GBST";
+/// Save state format version; bump whenever the serialized layout changes.
+pub const STATE_VERSION: u32 = 10;
+const DOTS_PER_SCANLINE: u32 = 456;
+// Frame after power-on at which a held button combo picks a DMG colorization palette, standing
+// in for the end of the CGB boot animation, where the boot ROM reads the buttons once.
+const BOOT_PALETTE_FRAMES: u64 = 60;
+// 16-bit words from SP upward shown by `GameBoy::debug_dump`.
+const STACK_DUMP_WORDS: u16 = 8;
+// Fast deflate level: states are mostly zero-filled RAM, so this already shrinks them a lot.
+#[cfg(feature = "std")]
+const STATE_COMPRESSION_LEVEL: u8 = 1;
+// Magic, version, ROM fingerprint.
+#[cfg(feature = "std")]
+const STATE_HEADER_LEN: usize = STATE_MAGIC.len() + 4 + 4;
+
+/// Receives each completed frame; see [`GameBoy::on_vblank`].
+pub type VBlankCallback = Box<dyn FnMut(&Framebuffer) + Send>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    /// The state was saved from a different ROM (see `Cartridge::rom_fingerprint`).
+    RomMismatch {
+        expected: u32,
+        found: u32,
+    },
+    Decode(String),
+}
+
+#[cfg(feature = "std")]
+struct StateHeader {
+    rom_fingerprint: u32,
+}
+
+#[cfg(feature = "std")]
+fn parse_state_header(bytes: &[u8]) -> Result<(StateHeader, &[u8]), StateError> {
+    if bytes.len() < STATE_HEADER_LEN || &bytes[..STATE_MAGIC.len()] != STATE_MAGIC {
+        return Err(StateError::InvalidMagic);
+    }
+    let field = |i: usize| {
+        let start = STATE_MAGIC.len() + i * 4;
+        u32::from_le_bytes(
+            bytes[start..start + 4]
+                .try_into()
+                .expect("field is 4 bytes"),
+        )
+    };
+    let version = field(0);
+    if version != STATE_VERSION {
+        return Err(StateError::UnsupportedVersion(version));
+    }
+    let header = StateHeader {
+        rom_fingerprint: field(1),
+    };
+    Ok((header, &bytes[STATE_HEADER_LEN..]))
+}
+
+#[cfg(feature = "std")]
+fn decode_state_body(body: &[u8]) -> Result<GameBoy, StateError> {
+    let raw = miniz_oxide::inflate::decompress_to_vec(body)
+        .map_err(|e| StateError::Decode(format!("decompression failed: {e:?}")))?;
+    let mut gb: GameBoy =
+        bincode::deserialize(&raw).map_err(|e| StateError::Decode(e.to_string()))?;
+    gb.bus.apu.refresh_highpass_charge();
+    Ok(gb)
+}
+
+/// A complete emulated machine.
+///
+/// `GameBoy` owns all of its state and is `Send`, so instances can be moved to worker threads
+/// (e.g. running several ROMs in parallel). Keep it that way: no `Rc`/`RefCell` in core state.
 #[derive(Serialize, Deserialize)]
 pub struct GameBoy {
     pub cpu: Cpu,
     pub bus: Bus,
+    // T-cycles executed and frames completed since power-on.
+    #[serde(default)]
+    total_cycles: u64,
+    #[serde(default)]
+    total_frames: u64,
+    // Embedder hook, not machine state.
+    #[serde(skip)]
+    on_vblank: Option<VBlankCallback>,
 }
 
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<GameBoy>();
+};
+
 impl GameBoy {
+    pub fn new(cpu: Cpu, bus: Bus) -> Self {
+        Self {
+            cpu,
+            bus,
+            total_cycles: 0,
+            total_frames: 0,
+            on_vblank: None,
+        }
+    }
+
+    /// Sets the CPU and I/O registers to the values the DMG or CGB boot ROM (picked by the
+    /// emulation mode) leaves behind, with PC at the cartridge entry point 0x0100. Used
+    /// instead of running a boot ROM.
+    pub fn init_post_boot(&mut self) {
+        let cgb = self.bus.mode == EmulationMode::Cgb;
+        let [a, f, b, c, d, e, h, l] = if cgb {
+            [0x11, 0x80, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D]
+        } else {
+            [0x01, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D]
+        };
+        let cpu = &mut self.cpu;
+        (cpu.a, cpu.f, cpu.b, cpu.c, cpu.d, cpu.e, cpu.h, cpu.l) = (a, f, b, c, d, e, h, l);
+        cpu.sp = 0xFFFE;
+        cpu.pc = 0x0100;
+        self.bus.ie = 0x00;
+        self.bus.iflag = 0x01;
+
+        const IO_INITS: [(u16, u8); 31] = [
+            (0xFF00, 0xCF),
+            (0xFF05, 0x00),
+            (0xFF06, 0x00),
+            (0xFF07, 0x00),
+            (0xFF10, 0x80),
+            (0xFF11, 0xBF),
+            (0xFF12, 0xF3),
+            (0xFF14, 0xBF),
+            (0xFF16, 0x3F),
+            (0xFF17, 0x00),
+            (0xFF19, 0xBF),
+            (0xFF1A, 0x7F),
+            (0xFF1B, 0xFF),
+            (0xFF1C, 0x9F),
+            (0xFF1E, 0xBF),
+            (0xFF20, 0xFF),
+            (0xFF21, 0x00),
+            (0xFF22, 0x00),
+            (0xFF23, 0xBF),
+            (0xFF24, 0x77),
+            (0xFF25, 0xF3),
+            (0xFF26, 0xF1),
+            (0xFF40, 0x91),
+            (0xFF42, 0x00),
+            (0xFF43, 0x00),
+            (0xFF45, 0x00),
+            (0xFF47, 0xFC),
+            (0xFF48, 0xFF),
+            (0xFF49, 0xFF),
+            (0xFF4A, 0x00),
+            (0xFF4B, 0x00),
+        ];
+        for (addr, val) in IO_INITS {
+            self.bus.write8(addr, val);
+        }
+
+        if cgb {
+            // KEY1, VBK, SVBK.
+            for (addr, val) in [(0xFF4D, 0x00), (0xFF4F, 0x00), (0xFF70, 0x01)] {
+                self.bus.write8(addr, val);
+            }
+            // The CGB boot ROM leaves BG palette 0 color 0 white (0x7FFF); palette RAM is
+            // otherwise zero, which would start games on a black screen.
+            self.bus.ppu.write_bgpi(0x80);
+            self.bus.ppu.write_bgpd(0xFF);
+            self.bus.ppu.write_bgpd(0x7F);
+        }
+    }
+
+    /// Returns the machine to its power-on state with the same ROM and emulation mode.
+    ///
+    /// A soft reset (`hard == false`) is the console's power cycle with the cartridge left in:
+    /// cartridge RAM, MBC2 RAM and the RTC survive. A hard reset also clears them, as if a
+    /// fresh cartridge were inserted. As with [`GameBoy::new`], post-boot register values are
+    /// left to the caller (see [`GameBoy::init_post_boot`]).
+    ///
+    /// The machine is reset in place, so host and tool configuration (hooks, callbacks,
+    /// frontend settings, tracing and profiling) carries over.
+    pub fn reset(&mut self, hard: bool) {
+        self.cpu.reset();
+        self.bus.reset(hard);
+        self.total_cycles = 0;
+        self.total_frames = 0;
+    }
+
+    /// Executes one instruction (or interrupt dispatch / halted tick), returning its T-cycles.
+    ///
+    /// A frame is counted when the PPU raises `frame_ready`; callers stepping manually must
+    /// clear it (as [`GameBoy::run_frame`] does) for the next frame to be counted.
     pub fn step(&mut self) -> u32 {
-        self.cpu.step(&mut self.bus)
+        let was_ready = self.bus.ppu.frame_ready();
+        let cycles = self.cpu.step(&mut self.bus);
+        self.total_cycles += u64::from(cycles);
+        if !was_ready && self.bus.ppu.frame_ready() {
+            self.total_frames += 1;
+            if self.total_frames == BOOT_PALETTE_FRAMES {
+                self.select_boot_palette(self.bus.input.state());
+            }
+            if let Some(callback) = self.on_vblank.as_mut() {
+                callback(self.bus.ppu.present_framebuffer());
+            }
+        }
+        cycles
+    }
+
+    /// Applies the DMG colorization palette the CGB boot ROM picks for `combo` (a
+    /// [`Button::mask`](crate::input::Button::mask) bitmask; see
+    /// [`palette_for_combo`](crate::ppu::dmg_colorization::palette_for_combo)), replacing the
+    /// one chosen from the title. Returns whether it applied: only DMG-only cartridges in CGB
+    /// mode are colorized, and only recognized combos select a palette.
+    ///
+    /// A combo held when the first second after power-on ends is applied automatically; later
+    /// presses are left to the game.
+    pub fn select_boot_palette(&mut self, combo: u8) -> bool {
+        if self.bus.mode != EmulationMode::Cgb
+            || self.bus.cart.header.cgb_support != CgbSupport::DmgOnly
+        {
+            return false;
+        }
+        let Some(palette) = dmg_colorization::palette_for_combo(combo) else {
+            return false;
+        };
+        self.bus.ppu.apply_dmg_colorization(palette);
+        true
+    }
+
+    /// Registers `callback` to receive the presented frame (see
+    /// [`Ppu::present_framebuffer`](crate::ppu::Ppu::present_framebuffer)) each time the PPU
+    /// enters VBlank, i.e. once per frame [`GameBoy::frames`] counts. Replaces any earlier
+    /// callback. It survives [`GameBoy::reset`] but not loading a save state, which builds a
+    /// new `GameBoy`.
+    pub fn on_vblank(&mut self, callback: VBlankCallback) {
+        self.on_vblank = Some(callback);
+    }
+
+    /// Removes the callback set by [`GameBoy::on_vblank`], going back to polling.
+    pub fn clear_vblank_callback(&mut self) {
+        self.on_vblank = None;
     }
 
     pub fn run_frame(&mut self) {
+        self.run_to_vblank(|_, _| {});
+    }
+
+    /// Like [`GameBoy::run_frame`], but calls `on_scanline(bus, ly)` each time LY changes, so
+    /// a frontend can push fresh input (e.g. with [`Bus::set_joypad_button`]) partway through
+    /// the frame rather than once before it.
+    pub fn run_to_vblank(&mut self, mut on_scanline: impl FnMut(&mut Bus, u8)) {
+        let mut ly = self.bus.ppu.current_ly();
         while !self.bus.ppu.frame_ready() {
             self.step();
+            let now = self.bus.ppu.current_ly();
+            if now != ly {
+                ly = now;
+                on_scanline(&mut self.bus, ly);
+            }
         }
         self.bus.ppu.clear_frame_ready();
     }
+
+    /// Runs until the PPU moves to another scanline (LY changes), e.g. for single-stepping
+    /// while paused. Like [`GameBoy::run_frame`], clears `frame_ready` if the line started
+    /// VBlank. With the LCD off LY never moves, so this stops after one scanline's worth of
+    /// cycles instead.
+    pub fn run_to_next_scanline(&mut self) {
+        let start_ly = self.bus.ppu.current_ly();
+        let mut elapsed = 0;
+        loop {
+            elapsed += self.step();
+            if self.bus.ppu.current_ly() != start_ly {
+                break;
+            }
+            if self.bus.debug_read8(0xFF40) & 0x80 == 0 && elapsed >= DOTS_PER_SCANLINE {
+                break;
+            }
+        }
+        self.bus.ppu.clear_frame_ready();
+    }
+
+    /// Overclocks the CPU relative to the PPU, APU, timer and serial: each CPU cycle advances
+    /// them by `1 / multiplier` cycles (on top of CGB double speed). Default 1; 0 is treated
+    /// as 1.
+    ///
+    /// This is a debugging aid for surfacing timing races in test ROMs and does not match any
+    /// real hardware. [`GameBoy::cycles`] keeps counting CPU cycles.
+    pub fn set_cpu_clock_multiplier(&mut self, multiplier: u32) {
+        self.bus.set_cpu_clock_multiplier(multiplier);
+    }
+
+    pub fn cpu_clock_multiplier(&self) -> u32 {
+        self.bus.cpu_clock_multiplier()
+    }
+
+    /// IME, the CPU's interrupt master enable.
+    pub fn interrupt_master_enable(&self) -> bool {
+        self.cpu.ime
+    }
+
+    /// Interrupts both requested (IF) and enabled (IE), as a bit mask. They are serviced when
+    /// IME is set; otherwise they only wake the CPU from HALT.
+    pub fn pending_interrupts(&self) -> u8 {
+        pending_mask(self.bus.interrupt_enable(), self.bus.interrupt_flags())
+    }
+
+    /// [`GameBoy::pending_interrupts`] decoded, highest priority first.
+    pub fn pending_interrupt_list(&self) -> Vec<Interrupt> {
+        decode_mask(self.pending_interrupts())
+    }
+
+    /// CPU registers, IME, HALT and IE/IF on one line, as printed by `gb-cli --trace-cpu`:
+    /// `AF=01B0 BC=0013 DE=00D8 HL=014D SP=FFFE IME=false HALT=false IE=00 IF=E1`.
+    pub fn register_summary(&self) -> String {
+        let cpu = &self.cpu;
+        format!(
+            "AF={:02X}{:02X} BC={:02X}{:02X} DE={:02X}{:02X} HL={:02X}{:02X} SP={:04X} IME={} HALT={} IE={:02X} IF={:02X}",
+            cpu.a,
+            cpu.f,
+            cpu.b,
+            cpu.c,
+            cpu.d,
+            cpu.e,
+            cpu.h,
+            cpu.l,
+            cpu.sp,
+            cpu.ime,
+            cpu.halted,
+            self.bus.interrupt_enable(),
+            self.bus.interrupt_flags()
+        )
+    }
+
+    /// A human-readable snapshot for crash reports and debug views: CPU registers, IE/IF,
+    /// the LCD registers, the MBC's bank selection and the top of the stack. Memory is read
+    /// without side effects.
+    pub fn debug_dump(&self) -> String {
+        use core::fmt::Write;
+
+        let ppu = self.bus.ppu_status();
+        let banks = self.bus.cart.bank_info();
+        let mut out = format!("PC={:04X} {}\n", self.cpu.pc, self.register_summary());
+        // Writing to a String can't fail.
+        let _ = writeln!(
+            out,
+            "LCDC={:02X} STAT={:02X} LY={} mode={}",
+            ppu.lcdc, ppu.stat, ppu.ly, ppu.mode
+        );
+        let _ = writeln!(
+            out,
+            "ROM bank={:02X} RAM bank={:02X} mode={}",
+            banks.rom_bank, banks.ram_bank, banks.mode
+        );
+        let _ = write!(out, "Stack at {:04X}:", self.cpu.sp);
+        for i in 0..STACK_DUMP_WORDS {
+            let addr = self.cpu.sp.wrapping_add(i * 2);
+            let word = u16::from_le_bytes([
+                self.bus.debug_read8(addr),
+                self.bus.debug_read8(addr.wrapping_add(1)),
+            ]);
+            let _ = write!(out, " {word:04X}");
+        }
+        let _ = writeln!(out, "\nframes={} cycles={}", self.frames(), self.cycles());
+        out
+    }
+
+    /// Total T-cycles executed by [`GameBoy::step`].
+    pub fn cycles(&self) -> u64 {
+        self.total_cycles
+    }
+
+    /// Total frames completed.
+    pub fn frames(&self) -> u64 {
+        self.total_frames
+    }
+
+    /// A copy of the most recently rendered frame, e.g. for save-state thumbnails.
+    pub fn snapshot_framebuffer(&self) -> Framebuffer {
+        *self.bus.ppu.framebuffer()
+    }
+
+    /// Serializes the machine as a save state: magic, version and ROM fingerprint header, then
+    /// deflate-compressed bincode. The ROM itself is not included.
+    #[cfg(feature = "std")]
+    pub fn to_state_bytes(&self) -> Vec<u8> {
+        // Serializing into a Vec only fails for unsupported serde types, which we don't use.
+        let raw = bincode::serialize(self).expect("machine state is always serializable");
+        let body = miniz_oxide::deflate::compress_to_vec(&raw, STATE_COMPRESSION_LEVEL);
+        let mut out = Vec::with_capacity(STATE_HEADER_LEN + body.len());
+        out.extend_from_slice(STATE_MAGIC);
+        out.extend_from_slice(&STATE_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.bus.cart.rom_fingerprint().to_le_bytes());
+        out.extend_from_slice(&body);
+        out
+    }
+
+    /// Restores a machine from bytes produced by [`GameBoy::to_state_bytes`], using `rom` for
+    /// the cartridge ROM (which must be the ROM the state was saved from).
+    #[cfg(feature = "std")]
+    pub fn from_state_bytes(bytes: &[u8], rom: Vec<u8>) -> Result<GameBoy, StateError> {
+        let (header, body) = parse_state_header(bytes)?;
+        let expected = crate::cartridge::rom_fingerprint(&rom);
+        if header.rom_fingerprint != expected {
+            return Err(StateError::RomMismatch {
+                expected,
+                found: header.rom_fingerprint,
+            });
+        }
+        let mut gb = decode_state_body(body)?;
+        gb.bus.cart.rom = rom;
+        Ok(gb)
+    }
+
+    /// Replaces this machine with a save state, refusing states made from another ROM.
+    ///
+    /// The current cartridge ROM and, as with [`GameBoy::reset`], host and tool configuration
+    /// (hooks, callbacks, the output sample rate, frontend settings, tracing and profiling)
+    /// carry over into the restored machine.
+    #[cfg(feature = "std")]
+    pub fn load_state_bytes_for(
+        &mut self,
+        bytes: &[u8],
+        expected_rom_fingerprint: u32,
+    ) -> Result<(), StateError> {
+        let (header, body) = parse_state_header(bytes)?;
+        if header.rom_fingerprint != expected_rom_fingerprint {
+            return Err(StateError::RomMismatch {
+                expected: expected_rom_fingerprint,
+                found: header.rom_fingerprint,
+            });
+        }
+        let mut gb = decode_state_body(body)?;
+        gb.cpu.take_settings(&mut self.cpu);
+        gb.bus.take_host_settings(&mut self.bus);
+        gb.on_vblank = self.on_vblank.take();
+        *self = gb;
+        Ok(())
+    }
 }
@@ -0,0 +1,78 @@
+//! A small, filesystem-free facade over [`GameBoy`] for embedding frontends such as a
+//! `wasm-bindgen` wrapper: load a ROM from bytes, feed one button bitmask per frame, and read
+//! back RGBA pixels and audio samples. Battery RAM goes in and out as byte slices.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::bus::Bus;
+use crate::cartridge::{Cartridge, CartridgeError, SaveError};
+use crate::cpu::Cpu;
+use crate::gb::GameBoy;
+use crate::ppu::{framebuffer_to_rgba8888, FRAMEBUFFER_LEN};
+
+/// One emulated machine with the ROM already past the boot ROM.
+pub struct GbHandle {
+    gb: GameBoy,
+    // The last presented frame as RGBA8888, refreshed by `run_frame`.
+    rgba: Vec<u8>,
+}
+
+impl GbHandle {
+    /// Loads `rom`, picking DMG or CGB mode from its header, and starts at 0x0100 with the
+    /// registers the boot ROM would leave behind.
+    pub fn new(rom: &[u8]) -> Result<Self, CartridgeError> {
+        let cart = Cartridge::from_rom(rom.to_vec())?;
+        let mut gb = GameBoy::new(Cpu::new(), Bus::new(cart));
+        gb.init_post_boot();
+        Ok(Self {
+            gb,
+            rgba: vec![0; FRAMEBUFFER_LEN * 4],
+        })
+    }
+
+    /// Sets all eight buttons from a [`Button::mask`](crate::input::Button::mask) bitmask.
+    pub fn set_buttons(&mut self, mask: u8) {
+        self.gb.bus.set_joypad_state(mask);
+    }
+
+    /// Runs until the next frame is complete and refreshes [`GbHandle::framebuffer_rgba`].
+    pub fn run_frame(&mut self) {
+        self.gb.run_frame();
+        framebuffer_to_rgba8888(self.gb.bus.ppu.present_framebuffer(), &mut self.rgba);
+    }
+
+    /// The last frame as 160x144 RGBA8888 pixels, row by row.
+    pub fn framebuffer_rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Interleaved stereo samples produced since the last call.
+    pub fn audio_samples(&mut self) -> Vec<f32> {
+        self.gb.bus.apu.take_samples()
+    }
+
+    /// Sets the audio output rate, e.g. to the browser's `AudioContext.sampleRate`.
+    pub fn set_sample_rate(&mut self, hz: u32) {
+        self.gb.bus.apu.set_sample_rate(hz);
+    }
+
+    /// Battery save contents to persist (see [`Cartridge::save_data`]); empty for carts
+    /// without RAM.
+    pub fn save_data(&self) -> Vec<u8> {
+        self.gb.bus.cart.save_data()
+    }
+
+    /// Restores battery save contents previously returned by [`GbHandle::save_data`].
+    pub fn load_save_data(&mut self, data: &[u8]) -> Result<(), SaveError> {
+        self.gb.bus.cart.load_save_data(data)
+    }
+
+    pub fn gameboy(&self) -> &GameBoy {
+        &self.gb
+    }
+
+    pub fn gameboy_mut(&mut self) -> &mut GameBoy {
+        &mut self.gb
+    }
+}
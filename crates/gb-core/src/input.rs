@@ -14,7 +14,19 @@ pub enum Button {
 }
 
 impl Button {
-    const fn mask(self) -> u8 {
+    pub const ALL: [Self; 8] = [
+        Self::Right,
+        Self::Left,
+        Self::Up,
+        Self::Down,
+        Self::A,
+        Self::B,
+        Self::Select,
+        Self::Start,
+    ];
+
+    /// This button's bit in a joypad state bitmask (see [`Joypad::set_state`]).
+    pub const fn mask(self) -> u8 {
         match self {
             Self::Right => 1 << 0,
             Self::Left => 1 << 1,
@@ -28,6 +40,20 @@ impl Button {
     }
 }
 
+/// How simultaneous opposite directions (Left+Right, Up+Down) are presented to the game.
+///
+/// Real hardware reports both as pressed, which some games mishandle.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum SocdMode {
+    /// Report both directions, like hardware.
+    #[default]
+    Allow,
+    /// Report neither direction.
+    Neutral,
+    /// Report only the most recently pressed direction.
+    LastWins,
+}
+
 /// Joypad (JOYP/P1) register + button state.
 ///
 /// - 0xFF00 bits 4-5 are selection lines (active low)
@@ -39,29 +65,88 @@ pub struct Joypad {
     select: u8,
     /// Button state bitmask; 1 = pressed.
     state: u8,
+    // Frontend setting, not machine state.
+    #[serde(skip)]
+    socd_mode: SocdMode,
+    // Most recently pressed direction on each axis, for `SocdMode::LastWins`.
+    #[serde(skip)]
+    last_horizontal: u8,
+    #[serde(skip)]
+    last_vertical: u8,
 }
 
 impl Joypad {
+    const HORIZONTAL: u8 = Button::Left.mask() | Button::Right.mask();
+    const VERTICAL: u8 = Button::Up.mask() | Button::Down.mask();
+
     pub fn new() -> Self {
         Self {
             select: 0x30,
             state: 0,
+            socd_mode: SocdMode::Allow,
+            last_horizontal: 0,
+            last_vertical: 0,
         }
     }
 
+    /// Releases all buttons and deselects both lines, keeping the SOCD mode.
+    pub fn reset(&mut self) {
+        let mut fresh = Self::new();
+        fresh.take_settings(self);
+        *self = fresh;
+    }
+
+    /// Takes the SOCD mode, a frontend setting, from `old`.
+    pub(crate) fn take_settings(&mut self, old: &Self) {
+        self.socd_mode = old.socd_mode;
+    }
+
+    pub fn set_socd_mode(&mut self, mode: SocdMode) {
+        self.socd_mode = mode;
+    }
+
+    pub fn socd_mode(&self) -> SocdMode {
+        self.socd_mode
+    }
+
+    /// Held buttons as a [`Button::mask`] bitmask, before SOCD resolution.
+    pub fn state(&self) -> u8 {
+        self.state
+    }
+
+    /// Pressed buttons as seen by the game, after SOCD resolution.
+    fn effective_state(&self) -> u8 {
+        let mut state = self.state;
+        for (axis, last) in [
+            (Self::HORIZONTAL, self.last_horizontal),
+            (Self::VERTICAL, self.last_vertical),
+        ] {
+            if state & axis != axis {
+                continue;
+            }
+            match self.socd_mode {
+                SocdMode::Allow => {}
+                SocdMode::Neutral => state &= !axis,
+                SocdMode::LastWins => state &= !(axis & !last),
+            }
+        }
+        state
+    }
+
     #[inline]
     pub fn read_joyp(&self) -> u8 {
         let select_buttons = (self.select & 0x20) == 0;
         let select_directions = (self.select & 0x10) == 0;
+        let state = self.effective_state();
 
         let dir_nibble = if select_directions {
-            (!(self.state & 0x0F)) & 0x0F
+            (!(state & 0x0F)) & 0x0F
         } else {
             0x0F
         };
 
         let btn_nibble = if select_buttons {
-            (!((self.state >> 4) & 0x0F)) & 0x0F
+            (!((state >> 4) & 0x0F)) & 0x0F
         } else {
             0x0F
         };
@@ -70,23 +155,52 @@ impl Joypad {
         0xC0 | (self.select & 0x30) | nibble
     }
 
+    /// Returns true if a pressed button is visible on a selected line (wakes DMG `STOP`).
+    #[inline]
+    pub fn selected_button_pressed(&self) -> bool {
+        (self.read_joyp() & 0x0F) != 0x0F
+    }
+
     #[inline]
     pub fn write_joyp(&mut self, val: u8) {
         self.select = val & 0x30;
     }
 
+    /// Updates a button and requests the joypad interrupt if that pulls a selected line low.
     #[inline]
     pub fn set_button(&mut self, button: Button, pressed: bool, iflag: &mut u8) {
-        let mask = button.mask();
-        let was_pressed = (self.state & mask) != 0;
+        let state = if pressed {
+            self.state | button.mask()
+        } else {
+            self.state & !button.mask()
+        };
+        self.set_state(state, iflag);
+    }
 
-        if pressed {
-            self.state |= mask;
-            if !was_pressed {
-                *iflag |= Interrupt::Joypad.bit();
+    /// Sets all eight buttons at once from a bitmask of [`Button::mask`] bits (1 = pressed),
+    /// requesting the joypad interrupt if that pulls a selected line low.
+    pub fn set_state(&mut self, state: u8, iflag: &mut u8) {
+        let lines_before = self.read_joyp() & 0x0F;
+
+        // Directions pressed together count as pressed in bit order, so the higher bit is
+        // the most recent.
+        let newly_pressed = state & !self.state;
+        for (axis, last) in [
+            (Self::HORIZONTAL, &mut self.last_horizontal),
+            (Self::VERTICAL, &mut self.last_vertical),
+        ] {
+            let pressed = newly_pressed & axis;
+            if pressed != 0 {
+                *last = 0x80 >> pressed.leading_zeros();
             }
-        } else {
-            self.state &= !mask;
+        }
+        self.state = state;
+
+        // The interrupt fires on a high-to-low transition of a P10-P13 input line, so only
+        // buttons in a selected group can raise it.
+        let lines_after = self.read_joyp() & 0x0F;
+        if lines_before & !lines_after != 0 {
+            *iflag |= Interrupt::Joypad.bit();
         }
     }
 }
@@ -138,4 +252,122 @@ mod tests {
         // Directions are unselected => low nibble stays 0x0F.
         assert_eq!(jp.read_joyp() & 0x0F, 0x0F);
     }
+
+    fn directions_with_left_and_right(mode: SocdMode) -> u8 {
+        let mut jp = Joypad::new();
+        let mut iflag = 0u8;
+        jp.set_socd_mode(mode);
+        jp.write_joyp(0x20); // select directions
+        jp.set_button(Button::Left, true, &mut iflag);
+        jp.set_button(Button::Right, true, &mut iflag);
+        jp.read_joyp() & 0x0F
+    }
+
+    #[test]
+    fn socd_allow_reports_both_directions() {
+        assert_eq!(directions_with_left_and_right(SocdMode::Allow), 0x0C);
+    }
+
+    #[test]
+    fn socd_neutral_reports_neither_direction() {
+        assert_eq!(directions_with_left_and_right(SocdMode::Neutral), 0x0F);
+    }
+
+    #[test]
+    fn socd_last_wins_reports_latest_direction() {
+        assert_eq!(directions_with_left_and_right(SocdMode::LastWins), 0x0E);
+
+        // Releasing the newer direction falls back to the one still held.
+        let mut jp = Joypad::new();
+        let mut iflag = 0u8;
+        jp.set_socd_mode(SocdMode::LastWins);
+        jp.write_joyp(0x20);
+        jp.set_button(Button::Right, true, &mut iflag);
+        jp.set_button(Button::Left, true, &mut iflag);
+        assert_eq!(jp.read_joyp() & 0x0F, 0x0D);
+        jp.set_button(Button::Left, false, &mut iflag);
+        assert_eq!(jp.read_joyp() & 0x0F, 0x0E);
+    }
+
+    #[test]
+    fn socd_modes_leave_a_single_axis_alone() {
+        let mut jp = Joypad::new();
+        let mut iflag = 0u8;
+        jp.set_socd_mode(SocdMode::Neutral);
+        jp.write_joyp(0x20);
+        jp.set_button(Button::Left, true, &mut iflag);
+        jp.set_button(Button::Up, true, &mut iflag);
+        assert_eq!(jp.read_joyp() & 0x0F, 0x09);
+    }
+
+    #[test]
+    fn joypad_interrupt_requires_a_selected_line_to_fall() {
+        let mut jp = Joypad::new();
+        let mut iflag = 0u8;
+
+        // Buttons selected: a direction press changes no visible line.
+        jp.write_joyp(0x10);
+        jp.set_button(Button::Down, true, &mut iflag);
+        assert_eq!(iflag & Interrupt::Joypad.bit(), 0);
+        jp.set_button(Button::Down, false, &mut iflag);
+
+        // Directions selected: the same press pulls P13 low.
+        jp.write_joyp(0x20);
+        jp.set_button(Button::Down, true, &mut iflag);
+        assert_eq!(iflag & Interrupt::Joypad.bit(), Interrupt::Joypad.bit());
+
+        // No group selected: nothing can fall.
+        iflag = 0;
+        jp.write_joyp(0x30);
+        jp.set_button(Button::Up, true, &mut iflag);
+        assert_eq!(iflag, 0);
+    }
+
+    #[test]
+    fn set_state_matches_individual_button_presses() {
+        for socd_mode in [SocdMode::Allow, SocdMode::Neutral, SocdMode::LastWins] {
+            for select in [0x00, 0x10, 0x20, 0x30] {
+                for state in [0x00, 0x03, 0x0C, 0x5A, 0xA5, 0xFF] {
+                    let (mut bulk, mut single) = (Joypad::new(), Joypad::new());
+                    let (mut bulk_iflag, mut single_iflag) = (0u8, 0u8);
+                    for jp in [&mut bulk, &mut single] {
+                        jp.set_socd_mode(socd_mode);
+                        jp.write_joyp(select);
+                    }
+
+                    bulk.set_state(state, &mut bulk_iflag);
+                    for button in Button::ALL {
+                        let pressed = state & button.mask() != 0;
+                        single.set_button(button, pressed, &mut single_iflag);
+                    }
+
+                    assert_eq!(
+                        bulk.read_joyp(),
+                        single.read_joyp(),
+                        "{socd_mode:?} select={select:02X} state={state:02X}"
+                    );
+                    // SOCD resolution can hide an intermediate press that fired the
+                    // interrupt when buttons go down one at a time.
+                    if socd_mode == SocdMode::Allow {
+                        assert_eq!(bulk_iflag, single_iflag);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn joypad_interrupt_not_requested_when_line_already_low() {
+        let mut jp = Joypad::new();
+        let mut iflag = 0u8;
+
+        // Both groups selected: A and Right share P10.
+        jp.write_joyp(0x00);
+        jp.set_button(Button::A, true, &mut iflag);
+        assert_ne!(iflag & Interrupt::Joypad.bit(), 0);
+
+        iflag = 0;
+        jp.set_button(Button::Right, true, &mut iflag);
+        assert_eq!(iflag, 0);
+    }
 }
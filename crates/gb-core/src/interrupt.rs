@@ -1,5 +1,6 @@
 //! Interrupt helper types.
 
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
 /// Interrupt bits and vectors, in CPU priority order.
@@ -13,6 +14,15 @@ pub enum Interrupt {
 }
 
 impl Interrupt {
+    /// All interrupts, highest priority first.
+    pub const ALL: [Self; 5] = [
+        Self::VBlank,
+        Self::LcdStat,
+        Self::Timer,
+        Self::Serial,
+        Self::Joypad,
+    ];
+
     #[inline]
     pub const fn bit(self) -> u8 {
         1 << (self as u8)
@@ -49,3 +59,11 @@ impl Interrupt {
 pub const fn pending_mask(ie: u8, iflag: u8) -> u8 {
     ie & iflag & 0x1F
 }
+
+/// Interrupts whose bits are set in `mask`, highest priority first.
+pub fn decode_mask(mask: u8) -> Vec<Interrupt> {
+    Interrupt::ALL
+        .into_iter()
+        .filter(|i| mask & i.bit() != 0)
+        .collect()
+}
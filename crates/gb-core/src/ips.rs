@@ -0,0 +1,77 @@
+//! IPS ("International Patching System") ROM patches.
+//!
+//! Layout: `PATCH`, then records of a 3-byte big-endian offset and a 2-byte size followed by
+//! `size` data bytes. A size of 0 marks an RLE record (2-byte run length + 1 fill byte).
+//! The patch ends with `EOF`, optionally followed by a 3-byte truncation length.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+const HEADER: &[u8] = b"PATCH";
+const EOF_MARKER: [u8; 3] = *b"EOF";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IpsError {
+    InvalidHeader,
+    /// The patch ended in the middle of a record (byte offset into the patch).
+    Truncated(usize),
+}
+
+/// Applies an IPS `patch` to `rom`, growing it when records write past the end.
+pub fn apply_ips(rom: &mut Vec<u8>, patch: &[u8]) -> Result<(), IpsError> {
+    if !patch.starts_with(HEADER) {
+        return Err(IpsError::InvalidHeader);
+    }
+
+    let mut pos = HEADER.len();
+    let take = |pos: &mut usize, len: usize| -> Result<&[u8], IpsError> {
+        let bytes = patch
+            .get(*pos..*pos + len)
+            .ok_or(IpsError::Truncated(*pos))?;
+        *pos += len;
+        Ok(bytes)
+    };
+
+    loop {
+        let offset = take(&mut pos, 3)?;
+        if offset == EOF_MARKER {
+            break;
+        }
+        let offset = be_u24(offset);
+        let size = be_u16(take(&mut pos, 2)?);
+
+        if size == 0 {
+            let run_len = be_u16(take(&mut pos, 2)?);
+            let fill = take(&mut pos, 1)?[0];
+            write_at(rom, offset, &vec![fill; run_len]);
+        } else {
+            let data = take(&mut pos, size)?;
+            write_at(rom, offset, data);
+        }
+    }
+
+    // Truncation extension: a 3-byte ROM length after EOF.
+    if let Some(len) = patch.get(pos..pos + 3) {
+        rom.truncate(be_u24(len));
+    }
+
+    Ok(())
+}
+
+fn write_at(rom: &mut Vec<u8>, offset: usize, data: &[u8]) {
+    let end = offset + data.len();
+    if rom.len() < end {
+        rom.resize(end, 0);
+    }
+    rom[offset..end].copy_from_slice(data);
+}
+
+fn be_u24(bytes: &[u8]) -> usize {
+    ((bytes[0] as usize) << 16) | ((bytes[1] as usize) << 8) | bytes[2] as usize
+}
+
+fn be_u16(bytes: &[u8]) -> usize {
+    ((bytes[0] as usize) << 8) | bytes[1] as usize
+}
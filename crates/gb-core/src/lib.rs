@@ -1,12 +1,20 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod apu;
+pub mod bps;
 pub mod bus;
 pub mod cartridge;
 pub mod cpu;
 pub mod debug;
 pub mod dma;
 pub mod gb;
+pub mod handle;
 pub mod input;
 pub mod interrupt;
+pub mod ips;
+pub mod patch;
 pub mod ppu;
 pub mod serial;
 pub mod timer;
@@ -0,0 +1,28 @@
+//! ROM patch format detection (IPS / BPS).
+
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bps::{self, BpsError};
+use crate::ips::{self, IpsError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatchError {
+    /// Neither a `PATCH` (IPS) nor a `BPS1` (BPS) header.
+    UnknownFormat,
+    Ips(IpsError),
+    Bps(BpsError),
+}
+
+/// Applies an IPS or BPS `patch` to `rom`, picking the format from its magic bytes.
+pub fn apply_patch(rom: &mut Vec<u8>, patch: &[u8]) -> Result<(), PatchError> {
+    if patch.starts_with(b"PATCH") {
+        ips::apply_ips(rom, patch).map_err(PatchError::Ips)
+    } else if patch.starts_with(b"BPS1") {
+        *rom = bps::apply_bps(rom, patch).map_err(PatchError::Bps)?;
+        Ok(())
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
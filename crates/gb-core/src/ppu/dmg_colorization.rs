@@ -0,0 +1,279 @@
+//! CGB boot-ROM style colorization for DMG-only cartridges.
+//!
+//! When a CGB runs a DMG cartridge it picks BG/OBJ palettes from a built-in table keyed by
+//! the checksum of the header title (0x0134..=0x0143). Checksum collisions are resolved with
+//! the fourth title character. Only licensed (Nintendo) titles are looked up.
+//!
+//! Holding a direction (optionally with A or B) during the boot animation overrides the
+//! title's set with one of twelve alternates (see [`palette_for_combo`]).
+
+use crate::input::Button;
+
+/// One set of compatibility palettes, stored as BGR555 colors (color 0 first).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaletteSet {
+    pub bg: [u16; 4],
+    pub obj0: [u16; 4],
+    pub obj1: [u16; 4],
+}
+
+const fn rgb(rgb: u32) -> u16 {
+    let r = ((rgb >> 16) & 0xFF) as u16 >> 3;
+    let g = ((rgb >> 8) & 0xFF) as u16 >> 3;
+    let b = (rgb & 0xFF) as u16 >> 3;
+    r | (g << 5) | (b << 10)
+}
+
+const fn pal(c0: u32, c1: u32, c2: u32, c3: u32) -> [u16; 4] {
+    [rgb(c0), rgb(c1), rgb(c2), rgb(c3)]
+}
+
+pub const GRAYSCALE: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFFF, 0xA5A5A5, 0x525252, 0x000000),
+    obj0: pal(0xFFFFFF, 0xA5A5A5, 0x525252, 0x000000),
+    obj1: pal(0xFFFFFF, 0xA5A5A5, 0x525252, 0x000000),
+};
+
+pub const RED: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFFF, 0xFF8584, 0x943A3A, 0x000000),
+    obj0: pal(0xFFFFFF, 0x7BFF31, 0x008400, 0x000000),
+    obj1: pal(0xFFFFFF, 0x63A5FF, 0x0000FF, 0x000000),
+};
+
+pub const BLUE: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFFF, 0x63A5FF, 0x0000FF, 0x000000),
+    obj0: pal(0xFFFFFF, 0xFF8484, 0x943A3A, 0x000000),
+    obj1: pal(0xFFFFFF, 0xFF8484, 0x943A3A, 0x000000),
+};
+
+pub const GREEN: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFFF, 0x7BFF31, 0x0063C5, 0x000000),
+    obj0: pal(0xFFFFFF, 0xFF8484, 0x943A3A, 0x000000),
+    obj1: pal(0xFFFFFF, 0xFF8484, 0x943A3A, 0x000000),
+};
+
+pub const BROWN: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFFF, 0xFFAD63, 0x843100, 0x000000),
+    obj0: pal(0xFFFFFF, 0xFFAD63, 0x843100, 0x000000),
+    obj1: pal(0xFFFFFF, 0xFFAD63, 0x843100, 0x000000),
+};
+
+pub const YELLOW_RED: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFFF, 0xFFFF00, 0xFF0000, 0x000000),
+    obj0: pal(0xFFFFFF, 0xFFFF00, 0xFF0000, 0x000000),
+    obj1: pal(0xFFFFFF, 0xFFFF00, 0xFF0000, 0x000000),
+};
+
+pub const DARK_BLUE: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFFF, 0x8C8CDE, 0x52528C, 0x000000),
+    obj0: pal(0xFFFFFF, 0xFF8484, 0x943A3A, 0x000000),
+    obj1: pal(0xFFFFFF, 0xFFAD63, 0x843100, 0x000000),
+};
+
+/// Up + B at boot.
+pub const DARK_BROWN: PaletteSet = PaletteSet {
+    bg: pal(0xFFE6C5, 0xCE9C84, 0x846B29, 0x5A3108),
+    obj0: pal(0xFFFFFF, 0xFFAD63, 0x843100, 0x000000),
+    obj1: pal(0xFFFFFF, 0xFFAD63, 0x843100, 0x000000),
+};
+
+/// Left at boot: [`BLUE`] with green OBJ1 sprites.
+pub const BLUE_ALT: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFFF, 0x63A5FF, 0x0000FF, 0x000000),
+    obj0: pal(0xFFFFFF, 0xFF8484, 0x943A3A, 0x000000),
+    obj1: pal(0xFFFFFF, 0x7BFF31, 0x008400, 0x000000),
+};
+
+/// Down at boot.
+pub const PALE_YELLOW: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFA5, 0xFF9494, 0x9494FF, 0x000000),
+    obj0: pal(0xFFFFA5, 0xFF9494, 0x9494FF, 0x000000),
+    obj1: pal(0xFFFFA5, 0xFF9494, 0x9494FF, 0x000000),
+};
+
+/// Down + B at boot.
+pub const YELLOW: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFFF, 0xFFFF00, 0x7B4A00, 0x000000),
+    obj0: pal(0xFFFFFF, 0x63A5FF, 0x0000FF, 0x000000),
+    obj1: pal(0xFFFFFF, 0x7BFF31, 0x008400, 0x000000),
+};
+
+/// Right at boot.
+pub const GREEN_RED: PaletteSet = PaletteSet {
+    bg: pal(0xFFFFFF, 0x52FF00, 0xFF4200, 0x000000),
+    obj0: pal(0xFFFFFF, 0x52FF00, 0xFF4200, 0x000000),
+    obj1: pal(0xFFFFFF, 0x52FF00, 0xFF4200, 0x000000),
+};
+
+/// Right + B at boot.
+pub const INVERTED: PaletteSet = PaletteSet {
+    bg: pal(0x000000, 0x008484, 0xFFDE00, 0xFFFFFF),
+    obj0: pal(0x000000, 0x008484, 0xFFDE00, 0xFFFFFF),
+    obj1: pal(0x000000, 0x008484, 0xFFDE00, 0xFFFFFF),
+};
+
+const UP: u8 = Button::Up.mask();
+const DOWN: u8 = Button::Down.mask();
+const LEFT: u8 = Button::Left.mask();
+const RIGHT: u8 = Button::Right.mask();
+const A: u8 = Button::A.mask();
+const B: u8 = Button::B.mask();
+
+// Button combos the boot ROM recognizes and the set each selects.
+const COMBO_TABLE: &[(u8, &PaletteSet)] = &[
+    (UP, &BROWN),
+    (UP | A, &RED),
+    (UP | B, &DARK_BROWN),
+    (LEFT, &BLUE_ALT),
+    (LEFT | A, &DARK_BLUE),
+    (LEFT | B, &GRAYSCALE),
+    (DOWN, &PALE_YELLOW),
+    (DOWN | A, &YELLOW_RED),
+    (DOWN | B, &YELLOW),
+    (RIGHT, &GREEN_RED),
+    (RIGHT | A, &GREEN),
+    (RIGHT | B, &INVERTED),
+];
+
+struct TitleEntry {
+    checksum: u8,
+    /// Fourth title character, used when several titles share a checksum.
+    fourth_char: Option<u8>,
+    palette: &'static PaletteSet,
+}
+
+// Subset of the boot ROM table.
+const TITLE_TABLE: &[TitleEntry] = &[
+    TitleEntry {
+        checksum: 0x14, // POKEMON RED
+        fourth_char: None,
+        palette: &RED,
+    },
+    TitleEntry {
+        checksum: 0x61, // POKEMON BLUE
+        fourth_char: None,
+        palette: &BLUE,
+    },
+    TitleEntry {
+        checksum: 0xAA, // POKEMON GREEN
+        fourth_char: None,
+        palette: &GREEN,
+    },
+    TitleEntry {
+        checksum: 0xDB, // TETRIS
+        fourth_char: None,
+        palette: &YELLOW_RED,
+    },
+    TitleEntry {
+        checksum: 0x46, // SUPER MARIOLAND
+        fourth_char: Some(b'E'),
+        palette: &BROWN,
+    },
+    TitleEntry {
+        checksum: 0x46, // METROID2
+        fourth_char: Some(b'R'),
+        palette: &DARK_BLUE,
+    },
+];
+
+/// Sum of the title bytes, as computed by the CGB boot ROM.
+pub fn title_checksum(title: &[u8]) -> u8 {
+    title.iter().fold(0u8, |acc, &b| acc.wrapping_add(b))
+}
+
+/// Looks up the palette set for a 16-byte header title (0x0134..=0x0143).
+///
+/// Falls back to [`GRAYSCALE`] when the title is not in the table.
+pub fn palette_for_title(title: &[u8]) -> &'static PaletteSet {
+    let checksum = title_checksum(title);
+    let fourth = title.get(3).copied();
+    TITLE_TABLE
+        .iter()
+        .find(|e| e.checksum == checksum && e.fourth_char.is_none_or(|c| Some(c) == fourth))
+        .map_or(&GRAYSCALE, |e| e.palette)
+}
+
+/// Palette set selected by holding `buttons` (a [`Button::mask`] bitmask) at boot: one
+/// direction, alone or with A or B. Select and Start are ignored; anything else is `None`.
+pub fn palette_for_combo(buttons: u8) -> Option<&'static PaletteSet> {
+    let buttons = buttons & !(Button::Select.mask() | Button::Start.mask());
+    COMBO_TABLE
+        .iter()
+        .find(|&&(combo, _)| combo == buttons)
+        .map(|&(_, palette)| palette)
+}
+
+fn is_nintendo_licensee(rom: &[u8]) -> bool {
+    match rom.get(0x014B) {
+        Some(0x01) => true,
+        Some(0x33) => rom.get(0x0144..0x0146) == Some(b"01".as_slice()),
+        _ => false,
+    }
+}
+
+/// Palette set the CGB boot ROM would pick for a DMG cartridge image.
+pub fn palette_for_rom(rom: &[u8]) -> &'static PaletteSet {
+    if !is_nintendo_licensee(rom) {
+        return &GRAYSCALE;
+    }
+    match rom.get(0x0134..0x0144) {
+        Some(title) => palette_for_title(title),
+        None => &GRAYSCALE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn title(s: &str) -> [u8; 16] {
+        let mut out = [0u8; 16];
+        out[..s.len()].copy_from_slice(s.as_bytes());
+        out
+    }
+
+    #[test]
+    fn known_title_maps_to_its_palette_set() {
+        assert_eq!(title_checksum(&title("POKEMON RED")), 0x14);
+        assert_eq!(palette_for_title(&title("POKEMON RED")), &RED);
+        assert_eq!(palette_for_title(&title("POKEMON BLUE")), &BLUE);
+    }
+
+    #[test]
+    fn checksum_collisions_use_fourth_title_char() {
+        let sml = title("SUPER MARIOLAND");
+        let metroid = title("METROID2");
+        assert_eq!(title_checksum(&sml), title_checksum(&metroid));
+        assert_eq!(palette_for_title(&sml), &BROWN);
+        assert_eq!(palette_for_title(&metroid), &DARK_BLUE);
+    }
+
+    #[test]
+    fn unknown_title_or_licensee_falls_back_to_grayscale() {
+        assert_eq!(palette_for_title(&title("HOMEBREW")), &GRAYSCALE);
+
+        let mut rom = vec![0u8; 0x8000];
+        rom[0x0134..0x0144].copy_from_slice(&title("POKEMON RED"));
+        assert_eq!(palette_for_rom(&rom), &GRAYSCALE);
+
+        rom[0x014B] = 0x01;
+        assert_eq!(palette_for_rom(&rom), &RED);
+    }
+
+    #[test]
+    fn boot_combos_select_alternate_palette_sets() {
+        assert_eq!(palette_for_combo(UP), Some(&BROWN));
+        assert_eq!(palette_for_combo(LEFT | A), Some(&DARK_BLUE));
+        assert_eq!(palette_for_combo(RIGHT | B), Some(&INVERTED));
+        // Start doesn't change the combo.
+        assert_eq!(
+            palette_for_combo(DOWN | B | Button::Start.mask()),
+            Some(&YELLOW)
+        );
+
+        assert_eq!(palette_for_combo(0), None);
+        assert_eq!(palette_for_combo(A), None);
+        assert_eq!(palette_for_combo(UP | LEFT), None);
+        assert_eq!(palette_for_combo(UP | A | B), None);
+    }
+}
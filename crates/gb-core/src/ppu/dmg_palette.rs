@@ -0,0 +1,84 @@
+//! Named four-shade palettes for DMG-mode rendering.
+//!
+//! These replace the grayscale shades for BG/window and both OBJ palettes alike; see
+//! [`Ppu::set_dmg_palette`](super::Ppu::set_dmg_palette).
+
+/// Four ARGB shades, indexed by the 2-bit shade from BGP/OBP0/OBP1 (color 0 first).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DmgPalette {
+    pub name: &'static str,
+    pub shades: [u32; 4],
+}
+
+impl DmgPalette {
+    pub const GRAYSCALE: Self = Self {
+        name: "Grayscale",
+        shades: super::render::DMG_SHADES,
+    };
+
+    /// White, yellow, blue, black: neighbouring shades differ in hue as well as brightness.
+    pub const HIGH_CONTRAST: Self = Self {
+        name: "High contrast",
+        shades: [0xFFFF_FFFF, 0xFFFF_E000, 0xFF00_40FF, 0xFF00_0000],
+    };
+
+    /// Grayscale with light and dark swapped, so games with dark screens render light.
+    pub const INVERTED: Self = Self {
+        name: "Inverted",
+        shades: [0xFF00_0000, 0xFF55_5555, 0xFFAA_AAAA, 0xFFFF_FFFF],
+    };
+
+    /// Samples of the viridis ramp, which stays ordered under the common color deficiencies.
+    pub const VIRIDIS: Self = Self {
+        name: "Viridis (colorblind-safe)",
+        shades: [0xFFFD_E725, 0xFF35_B779, 0xFF31_688E, 0xFF44_0154],
+    };
+
+    /// Samples of the cividis ramp (blue to yellow), designed for red-green deficiencies.
+    pub const CIVIDIS: Self = Self {
+        name: "Cividis (colorblind-safe)",
+        shades: [0xFFFF_EA46, 0xFFA5_9C74, 0xFF57_5C6D, 0xFF00_204D],
+    };
+
+    pub const PRESETS: [Self; 5] = [
+        Self::GRAYSCALE,
+        Self::HIGH_CONTRAST,
+        Self::INVERTED,
+        Self::VIRIDIS,
+        Self::CIVIDIS,
+    ];
+
+    /// Looks up a preset by its [`DmgPalette::name`].
+    pub fn by_name(name: &str) -> Option<Self> {
+        Self::PRESETS.into_iter().find(|p| p.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DmgPalette;
+
+    // Rec. 709 luma weights, scaled to integers.
+    fn luminance(argb: u32) -> u32 {
+        let [_, r, g, b] = argb.to_be_bytes();
+        2126 * u32::from(r) + 7152 * u32::from(g) + 722 * u32::from(b)
+    }
+
+    #[test]
+    fn presets_have_four_distinct_ordered_luminances() {
+        for palette in DmgPalette::PRESETS {
+            let lum = palette.shades.map(luminance);
+            let falling = lum.windows(2).all(|w| w[0] > w[1]);
+            let rising = lum.windows(2).all(|w| w[0] < w[1]);
+            assert!(falling || rising, "{}: {lum:?}", palette.name);
+        }
+    }
+
+    #[test]
+    fn presets_are_found_by_name() {
+        for palette in DmgPalette::PRESETS {
+            assert_eq!(DmgPalette::by_name(palette.name), Some(palette));
+        }
+        assert_eq!(DmgPalette::by_name("Sepia"), None);
+    }
+}
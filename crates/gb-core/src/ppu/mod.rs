@@ -4,9 +4,20 @@ pub const FRAMEBUFFER_LEN: usize = LCD_WIDTH * LCD_HEIGHT;
 
 pub type Framebuffer = [u32; FRAMEBUFFER_LEN];
 
+pub mod dmg_colorization;
+pub mod dmg_palette;
 pub mod oam;
 #[allow(clippy::module_inception)]
 pub mod ppu;
 pub mod render;
 
 pub use ppu::Ppu;
+
+/// Converts an ARGB8888 framebuffer into RGBA8888 bytes (`out` holds 4 bytes per pixel).
+pub fn framebuffer_to_rgba8888(fb: &Framebuffer, out: &mut [u8]) {
+    assert_eq!(out.len(), fb.len() * 4);
+    for (px, chunk) in fb.iter().zip(out.chunks_exact_mut(4)) {
+        // 0xAARRGGBB rotated left by a byte is 0xRRGGBBAA; big-endian bytes are R, G, B, A.
+        chunk.copy_from_slice(&px.rotate_left(8).to_be_bytes());
+    }
+}
@@ -1,12 +1,17 @@
+use alloc::boxed::Box;
 use serde::{Deserialize, Serialize};
 use serde_big_array::BigArray;
 
-use super::{Framebuffer, LCD_HEIGHT, LCD_WIDTH};
+use super::dmg_colorization::PaletteSet;
+use super::dmg_palette::DmgPalette;
+use super::render::{DmgPalettes, Scanline, DMG_GRAYSCALE_PALETTES};
+use super::{Framebuffer, FRAMEBUFFER_LEN, LCD_WIDTH};
+use crate::util::boxed::boxed_array;
 
 #[derive(Serialize, Deserialize)]
 pub struct Ppu {
     #[serde(skip, default = "default_framebuffer")]
-    framebuffer: Framebuffer,
+    framebuffer: Box<Framebuffer>,
     frame_ready: bool,
 
     // Phase 6: timing state (rendering comes later)
@@ -14,7 +19,9 @@ pub struct Ppu {
     ly: u8,
     mode: u8,
     lcd_enabled: bool,
-    prev_coincidence: bool,
+    // The STAT interrupt line: the OR of all enabled STAT conditions. IF is only requested
+    // on its rising edge, so overlapping conditions block each other.
+    stat_line: bool,
 
     // CGB BG palette registers/RAM (FF68/FF69).
     cgb_bgpi: u8,
@@ -24,10 +31,79 @@ pub struct Ppu {
     cgb_obpi: u8,
     #[serde(with = "BigArray")]
     cgb_obj_palette_ram: [u8; 0x40],
+    // CGB object priority mode (FF6C): bit 0 set = X-coordinate priority.
+    #[serde(default)]
+    cgb_opri: u8,
+    // Internal window line counter (WLY), reset at the start of each frame.
+    #[serde(default)]
+    window_line: u8,
+    // Dot at which mode 3 of the current line ends (see `mode3_end_dot`).
+    #[serde(default = "default_mode3_end")]
+    mode3_end: u32,
+    // Dots elapsed on the blank screen shown during DMG STOP.
+    #[serde(default)]
+    stop_dots: u32,
+    // Wrapping count of mode 3 -> 0 transitions (see `hblank_count`).
+    #[serde(default)]
+    hblank_count: u32,
+    // Colors used by the DMG rendering path (grayscale unless a DMG game is colorized).
+    #[serde(default = "default_dmg_palettes")]
+    dmg_palettes: DmgPalettes,
+    // Frontend setting, not machine state: skips scanline rendering (timing is unaffected).
+    #[serde(skip)]
+    rendering_disabled: bool,
+    // Frontend setting: LCD ghosting applied by `present_framebuffer`. `completed_frames`
+    // lets it blend once per frame however often it is called.
+    #[serde(skip)]
+    frame_blend: Option<Box<FrameBlend>>,
+    #[serde(skip)]
+    completed_frames: u32,
+    // The LCD doesn't show the first frame after it is switched on: `present_framebuffer`
+    // returns a blank screen while `completed_frames` equals this.
+    #[serde(skip)]
+    hidden_frame: Option<u32>,
+    // Frontend setting: renders mode 3 pixel by pixel (see `set_fifo_rendering`).
+    #[serde(skip)]
+    fifo: Option<Box<FifoLine>>,
 }
 
-fn default_framebuffer() -> Framebuffer {
-    [super::render::DMG_SHADES[0]; LCD_WIDTH * LCD_HEIGHT]
+/// Retained state for frame blending (see [`Ppu::set_frame_blend`]).
+struct FrameBlend {
+    // Weight of the previous frame, as a fraction of 256.
+    previous_weight: u32,
+    previous: Framebuffer,
+    output: Framebuffer,
+    // `completed_frames` when `output` was last blended.
+    blended_frame: Option<u32>,
+}
+
+/// Progress through the current line in FIFO rendering mode (see [`Ppu::set_fifo_rendering`]).
+struct FifoLine {
+    // Whether mode 3 of the current line is being drawn; false until the next line starts
+    // after the mode is turned on.
+    active: bool,
+    // Next pixel to output.
+    x: usize,
+    // The whole line as rendered with `lcd_regs` (LCDC..=WX), re-rendered only when one of
+    // them changes.
+    line: Scanline,
+    lcd_regs: Option<[u8; 12]>,
+    // Whether any pixels drawn so far came from the window.
+    window_drawn: bool,
+}
+
+fn default_mode3_end() -> u32 {
+    Ppu::MODE3_MIN_END
+}
+
+fn default_dmg_palettes() -> DmgPalettes {
+    DMG_GRAYSCALE_PALETTES
+}
+
+static BLANK_FRAME: Framebuffer = [super::render::DMG_SHADES[0]; FRAMEBUFFER_LEN];
+
+fn default_framebuffer() -> Box<Framebuffer> {
+    boxed_array(super::render::DMG_SHADES[0])
 }
 
 impl Ppu {
@@ -35,31 +111,177 @@ impl Ppu {
     const STAT: usize = 0x41;
     const LY: usize = 0x44;
     const LYC: usize = 0x45;
+    const SCX: usize = 0x43;
+
+    const DOTS_PER_FRAME: u32 = 456 * 154;
+    // LY reads 153 only for the first few dots of line 153, then 0 for the rest of VBlank.
+    const LINE_153_LY_DOTS: u32 = 4;
+    // Mode 3 is at least 172 dots, so HBlank starts at dot 252 at the earliest.
+    const MODE3_MIN_END: u32 = 80 + 172;
+    // Mode 3 spends its first 12 dots fetching before pixel 0 is output, one pixel per dot
+    // after that.
+    const FIRST_PIXEL_DOT: u32 = 80 + 12;
 
     const IF_VBLANK: u8 = 0x01;
     const IF_STAT: u8 = 0x02;
 
     pub fn new() -> Self {
         Self {
-            framebuffer: [super::render::DMG_SHADES[0]; LCD_WIDTH * LCD_HEIGHT],
+            framebuffer: default_framebuffer(),
             frame_ready: false,
             dots: 0,
             ly: 0,
             mode: 0,
             lcd_enabled: false,
-            prev_coincidence: false,
+            stat_line: false,
             cgb_bgpi: 0,
             cgb_bg_palette_ram: [0; 0x40],
             cgb_obpi: 0,
             cgb_obj_palette_ram: [0; 0x40],
+            cgb_opri: 0,
+            window_line: 0,
+            mode3_end: Self::MODE3_MIN_END,
+            stop_dots: 0,
+            hblank_count: 0,
+            dmg_palettes: DMG_GRAYSCALE_PALETTES,
+            rendering_disabled: false,
+            frame_blend: None,
+            completed_frames: 0,
+            hidden_frame: None,
+            fifo: None,
+        }
+    }
+
+    /// Returns to power-on state, keeping the frontend settings: rendering on/off, FIFO mode
+    /// and frame blending.
+    pub fn reset(&mut self) {
+        let mut fresh = Self::new();
+        fresh.take_settings(self);
+        *self = fresh;
+    }
+
+    /// Moves the frontend settings over from `old`: rendering on/off, FIFO mode and frame
+    /// blending.
+    pub(crate) fn take_settings(&mut self, old: &mut Self) {
+        self.rendering_disabled = old.rendering_disabled;
+        self.frame_blend = old.frame_blend.take().map(|mut blend| {
+            blend.blended_frame = None;
+            blend
+        });
+        self.set_fifo_rendering(old.fifo_rendering());
+    }
+
+    /// Turns scanline rendering on or off. With rendering off the framebuffer is left
+    /// untouched, but modes, interrupts and frame completion are emulated as usual.
+    pub fn set_rendering_enabled(&mut self, enabled: bool) {
+        self.rendering_disabled = !enabled;
+    }
+
+    pub fn rendering_enabled(&self) -> bool {
+        !self.rendering_disabled
+    }
+
+    /// Draws each line pixel by pixel across mode 3 instead of all at once when mode 3 starts,
+    /// so SCX, palette and other register writes made partway through mode 3 (raster
+    /// effects) change the rest of the line. Off by default; slower, since the line is
+    /// re-rendered whenever the registers change mid-line. Takes effect from the next line.
+    pub fn set_fifo_rendering(&mut self, enabled: bool) {
+        if !enabled {
+            self.fifo = None;
+        } else if self.fifo.is_none() {
+            self.fifo = Some(Box::new(FifoLine {
+                active: false,
+                x: 0,
+                line: [0; LCD_WIDTH],
+                lcd_regs: None,
+                window_drawn: false,
+            }));
+        }
+    }
+
+    pub fn fifo_rendering(&self) -> bool {
+        self.fifo.is_some()
+    }
+
+    /// Emulates the slow response of the DMG LCD: [`Ppu::present_framebuffer`] mixes each
+    /// frame with the previous one, `factor` being the previous frame's weight (0.5 turns
+    /// 30 Hz flicker into transparency). 0 turns blending off; values are clamped to 0..=1.
+    ///
+    /// Only the presented image is affected; [`Ppu::framebuffer`] stays the raw frame.
+    pub fn set_frame_blend(&mut self, factor: f32) {
+        let factor = if factor.is_nan() {
+            0.0
+        } else {
+            factor.clamp(0.0, 1.0)
+        };
+        let previous_weight = (factor * 256.0) as u32;
+        if previous_weight == 0 {
+            self.frame_blend = None;
+            return;
+        }
+        match self.frame_blend.as_mut() {
+            Some(blend) => blend.previous_weight = previous_weight,
+            None => {
+                self.frame_blend = Some(Box::new(FrameBlend {
+                    previous_weight,
+                    previous: *self.framebuffer,
+                    output: *self.framebuffer,
+                    blended_frame: None,
+                }));
+            }
+        }
+    }
+
+    /// Previous frame's weight in frame blending; 0 when off.
+    pub fn frame_blend(&self) -> f32 {
+        self.frame_blend
+            .as_ref()
+            .map_or(0.0, |b| b.previous_weight as f32 / 256.0)
+    }
+
+    /// The image to display: the raw framebuffer, or with frame blending on, the latest frame
+    /// mixed with the one before it. Blends once per completed frame, so calling this more
+    /// often (e.g. while paused) returns the same image.
+    ///
+    /// The first frame after the LCD is switched on is presented blank, as real hardware
+    /// doesn't display it. Only the presented image changes: that frame is still rendered
+    /// into [`framebuffer`](Self::framebuffer) and still raises `frame_ready`.
+    pub fn present_framebuffer(&mut self) -> &Framebuffer {
+        if self.hidden_frame == Some(self.completed_frames) {
+            return &BLANK_FRAME;
+        }
+        let Some(blend) = self.frame_blend.as_deref_mut() else {
+            return &self.framebuffer;
+        };
+        if blend.blended_frame != Some(self.completed_frames) {
+            blend.blended_frame = Some(self.completed_frames);
+            let prev_w = blend.previous_weight;
+            let cur_w = 256 - prev_w;
+            for ((out, prev), &cur) in blend
+                .output
+                .iter_mut()
+                .zip(blend.previous.iter_mut())
+                .zip(self.framebuffer.iter())
+            {
+                // Per 8-bit channel; the red/blue and alpha/green pairs are mixed together.
+                let mix = |mask: u32| {
+                    let p = u64::from(*prev & mask);
+                    let c = u64::from(cur & mask);
+                    (((p * u64::from(prev_w) + c * u64::from(cur_w)) >> 8) as u32) & mask
+                };
+                *out = mix(0x00FF_00FF) | mix(0xFF00_FF00);
+                *prev = cur;
+            }
         }
+        &blend.output
     }
 
     pub fn reset_ly(&mut self) {
         self.dots = 0;
         self.ly = 0;
+        self.window_line = 0;
         self.mode = if self.lcd_enabled { 2 } else { 0 };
-        self.prev_coincidence = false;
+        self.stat_line = false;
         self.frame_ready = false;
     }
 
@@ -67,6 +289,22 @@ impl Ppu {
         self.framebuffer.fill(super::render::DMG_SHADES[0]);
     }
 
+    /// Blanks the screen for DMG `STOP`; PPU timing state is left frozen.
+    pub fn enter_stop(&mut self) {
+        self.clear_framebuffer();
+        self.stop_dots = 0;
+    }
+
+    /// Advances the blank screen shown during `STOP` so frames keep completing.
+    pub fn tick_stopped(&mut self, cycles: u32) {
+        self.stop_dots += cycles;
+        while self.stop_dots >= Self::DOTS_PER_FRAME {
+            self.stop_dots -= Self::DOTS_PER_FRAME;
+            self.frame_ready = true;
+            self.completed_frames = self.completed_frames.wrapping_add(1);
+        }
+    }
+
     pub fn tick(
         &mut self,
         cycles: u32,
@@ -97,8 +335,9 @@ impl Ppu {
             self.lcd_enabled = false;
             self.dots = 0;
             self.ly = 0;
+            self.window_line = 0;
             self.mode = 0;
-            self.prev_coincidence = false;
+            self.stat_line = false;
             self.frame_ready = false;
             self.sync_registers(io, iflag);
             return;
@@ -108,8 +347,10 @@ impl Ppu {
             self.lcd_enabled = true;
             self.dots = 0;
             self.ly = 0;
+            self.window_line = 0;
             self.mode = 2;
-            self.prev_coincidence = false;
+            self.stat_line = false;
+            self.hidden_frame = Some(self.completed_frames.wrapping_add(1));
         }
 
         while cycles > 0 {
@@ -118,26 +359,54 @@ impl Ppu {
             self.dots += step;
             cycles -= step;
 
+            if self.ly < 144 && self.mode == 3 {
+                self.render_fifo_pixels(vram0, vram1, oam, io, cgb_mode);
+            }
+
             // Mode transitions during visible lines.
             if self.ly < 144 {
                 if self.mode == 2 && self.dots == 80 {
-                    super::render::render_scanline_with_cgb(
-                        &mut self.framebuffer,
-                        self.ly,
-                        vram0,
-                        vram1,
-                        oam,
-                        io,
-                        cgb_mode,
-                        &self.cgb_bg_palette_ram,
-                        &self.cgb_obj_palette_ram,
-                    );
+                    self.mode3_end = Self::mode3_end_dot(oam, io, self.ly);
+                    if let Some(fifo) = self.fifo.as_deref_mut() {
+                        fifo.active = !self.rendering_disabled;
+                        fifo.x = 0;
+                        fifo.lcd_regs = None;
+                        fifo.window_drawn = false;
+                    } else if !self.rendering_disabled {
+                        super::render::render_scanline_with_cgb(
+                            &mut self.framebuffer,
+                            self.ly,
+                            vram0,
+                            vram1,
+                            oam,
+                            io,
+                            cgb_mode,
+                            &self.cgb_bg_palette_ram,
+                            &self.cgb_obj_palette_ram,
+                            &self.dmg_palettes,
+                            (self.cgb_opri & 0x01) != 0,
+                            &mut self.window_line,
+                        );
+                    }
                     self.set_mode(3, io, iflag);
-                } else if self.mode == 3 && self.dots == 252 {
+                } else if self.mode == 3 && self.dots == self.mode3_end {
+                    if let Some(fifo) = self.fifo.as_deref_mut() {
+                        // The window line counter only advances on lines where the window
+                        // was drawn.
+                        if fifo.active && fifo.window_drawn {
+                            self.window_line = self.window_line.wrapping_add(1);
+                        }
+                        fifo.active = false;
+                    }
+                    self.hblank_count = self.hblank_count.wrapping_add(1);
                     self.set_mode(0, io, iflag);
                 }
             }
 
+            if self.ly == 153 && self.dots == Self::LINE_153_LY_DOTS {
+                self.sync_registers(io, iflag);
+            }
+
             // End-of-line.
             if self.dots == 456 {
                 self.dots = 0;
@@ -145,10 +414,12 @@ impl Ppu {
 
                 if self.ly == 144 {
                     self.frame_ready = true;
+                    self.completed_frames = self.completed_frames.wrapping_add(1);
                     *iflag |= Self::IF_VBLANK;
                     self.set_mode(1, io, iflag);
                 } else if self.ly > 153 {
                     self.ly = 0;
+                    self.window_line = 0;
                     self.set_mode(2, io, iflag);
                 } else if self.ly >= 144 {
                     self.set_mode(1, io, iflag);
@@ -163,6 +434,108 @@ impl Ppu {
         self.sync_registers(io, iflag);
     }
 
+    /// Dot at which mode 3 ends on line `ly`, following the Pan Docs timing model: 172 dots,
+    /// plus `SCX % 8` for the discarded first pixels, plus 6 dots per object fetched and up to
+    /// 5 more when an object lands early in a BG tile the fetcher hasn't paused on yet. The
+    /// window's extra fetch is not modeled.
+    fn mode3_end_dot(oam: &[u8; 0xA0], io: &[u8; 0x80], ly: u8) -> u32 {
+        let scx_fine = u32::from(io[Self::SCX] & 0x07);
+        let mut end = Self::MODE3_MIN_END + scx_fine;
+
+        let lcdc = io[Self::LCDC];
+        if lcdc & 0x02 == 0 {
+            return end;
+        }
+        let height: i16 = if lcdc & 0x04 != 0 { 16 } else { 8 };
+
+        // OAM scan: the first 10 objects covering this line, by OAM X.
+        let mut xs = [0u8; 10];
+        let mut count = 0;
+        for entry in oam.chunks_exact(4) {
+            let top = i16::from(entry[0]) - 16;
+            if (top..top + height).contains(&i16::from(ly)) {
+                xs[count] = entry[1];
+                count += 1;
+                if count == xs.len() {
+                    break;
+                }
+            }
+        }
+        let xs = &mut xs[..count];
+        xs.sort_unstable();
+
+        let mut penalized_tile = None;
+        for &x in xs.iter() {
+            // Objects past the right edge are never fetched.
+            if x >= 168 {
+                continue;
+            }
+            end += 6;
+            if x == 0 {
+                // Entirely off the left edge: always the full extra wait, whatever SCX is.
+                end += 5;
+                continue;
+            }
+            // Offset by 8 like OAM X, which doesn't change where tile boundaries fall.
+            let pos = u32::from(x) + scx_fine;
+            let tile = pos / 8;
+            if penalized_tile != Some(tile) {
+                end += 5u32.saturating_sub(pos % 8);
+                penalized_tile = Some(tile);
+            }
+        }
+        end
+    }
+
+    /// In FIFO rendering mode, outputs the pixels of the current line up to the current dot,
+    /// using the registers as they are now.
+    fn render_fifo_pixels(
+        &mut self,
+        vram0: &[u8; 0x2000],
+        vram1: Option<&[u8; 0x2000]>,
+        oam: &[u8; 0xA0],
+        io: &[u8; 0x80],
+        cgb_mode: bool,
+    ) {
+        let Some(fifo) = self.fifo.as_deref_mut() else {
+            return;
+        };
+        // Object fetches and SCX stall the pixel output, so with a longer mode 3 the first
+        // pixel comes later and the last one still lands on the end of mode 3.
+        let first_pixel_dot = Self::FIRST_PIXEL_DOT + (self.mode3_end - Self::MODE3_MIN_END);
+        let end = (self.dots.saturating_sub(first_pixel_dot) as usize).min(LCD_WIDTH);
+        if !fifo.active || end <= fifo.x {
+            return;
+        }
+
+        let lcd_regs: [u8; 12] = io[Self::LCDC..Self::LCDC + 12]
+            .try_into()
+            .expect("LCDC..=WX is 12 registers");
+        if fifo.lcd_regs != Some(lcd_regs) {
+            // VRAM, OAM and palette RAM are locked during mode 3, so only registers can
+            // change the rest of the line.
+            fifo.window_drawn |= super::render::render_line(
+                &mut fifo.line,
+                self.ly,
+                vram0,
+                vram1,
+                oam,
+                io,
+                cgb_mode,
+                &self.cgb_bg_palette_ram,
+                &self.cgb_obj_palette_ram,
+                &self.dmg_palettes,
+                (self.cgb_opri & 0x01) != 0,
+                self.window_line,
+            );
+            fifo.lcd_regs = Some(lcd_regs);
+        }
+
+        let row = self.ly as usize * LCD_WIDTH;
+        self.framebuffer[row + fifo.x..row + end].copy_from_slice(&fifo.line[fifo.x..end]);
+        fifo.x = end;
+    }
+
     pub fn read_bgpi(&self) -> u8 {
         0x40 | (self.cgb_bgpi & 0xBF)
     }
@@ -207,13 +580,54 @@ impl Ppu {
         }
     }
 
+    pub fn read_opri(&self) -> u8 {
+        0xFE | (self.cgb_opri & 0x01)
+    }
+
+    pub fn write_opri(&mut self, val: u8) {
+        self.cgb_opri = val & 0x01;
+    }
+
+    /// Loads a DMG-game colorization palette set, as the CGB boot ROM does.
+    ///
+    /// The colors are written to BG palette 0 and OBJ palettes 0/1 of CGB palette RAM and
+    /// are used for DMG-path rendering, where BGP/OBP0/OBP1 shades index into them.
+    pub fn apply_dmg_colorization(&mut self, palette_set: &PaletteSet) {
+        Self::write_palette_colors(&mut self.cgb_bg_palette_ram, 0, &palette_set.bg);
+        Self::write_palette_colors(&mut self.cgb_obj_palette_ram, 0, &palette_set.obj0);
+        Self::write_palette_colors(&mut self.cgb_obj_palette_ram, 1, &palette_set.obj1);
+
+        let layers = [&palette_set.bg, &palette_set.obj0, &palette_set.obj1];
+        for (dst, colors) in self.dmg_palettes.iter_mut().zip(layers) {
+            for (out, &color) in dst.iter_mut().zip(colors) {
+                *out = super::render::cgb_bgr15_to_argb(color);
+            }
+        }
+    }
+
+    /// Renders DMG-path shades with `palette` for BG/window and both OBJ palettes, replacing
+    /// grayscale or any CGB colorization. Part of machine state, so save states keep it.
+    pub fn set_dmg_palette(&mut self, palette: &DmgPalette) {
+        self.dmg_palettes = [palette.shades; 3];
+    }
+
+    fn write_palette_colors(ram: &mut [u8; 0x40], palette: usize, colors: &[u16; 4]) {
+        for (i, &color) in colors.iter().enumerate() {
+            let [lo, hi] = color.to_le_bytes();
+            ram[palette * 8 + i * 2] = lo;
+            ram[palette * 8 + i * 2 + 1] = hi;
+        }
+    }
+
     fn cycles_to_next_event(&self) -> u32 {
-        if self.ly >= 144 {
+        if self.ly == 153 && self.dots < Self::LINE_153_LY_DOTS {
+            Self::LINE_153_LY_DOTS - self.dots
+        } else if self.ly >= 144 {
             456 - self.dots
         } else {
             match self.mode {
                 2 => 80 - self.dots,
-                3 => 252 - self.dots,
+                3 => self.mode3_end - self.dots,
                 0 => 456 - self.dots,
                 _ => 456 - self.dots,
             }
@@ -225,24 +639,60 @@ impl Ppu {
             return;
         }
         self.mode = mode;
+        self.update_stat_line(io, iflag);
+    }
+
+    /// Whether any STAT condition selected by `enables` (STAT bits 3-6) currently holds.
+    fn stat_conditions(&self, enables: u8, io: &[u8; 0x80]) -> bool {
+        let mode_enable = match self.mode {
+            0 => 0x08,
+            1 => 0x10,
+            2 => 0x20,
+            _ => 0x00,
+        };
+        let coincidence = self.register_ly() == io[Self::LYC];
+        (enables & mode_enable) != 0 || (coincidence && (enables & 0x40) != 0)
+    }
 
-        match self.mode {
-            0 if (io[Self::STAT] & 0x08) != 0 => *iflag |= Self::IF_STAT,
-            1 if (io[Self::STAT] & 0x10) != 0 => *iflag |= Self::IF_STAT,
-            2 if (io[Self::STAT] & 0x20) != 0 => *iflag |= Self::IF_STAT,
-            _ => {}
+    fn update_stat_line(&mut self, io: &[u8; 0x80], iflag: &mut u8) {
+        let line = self.lcd_enabled && self.stat_conditions(io[Self::STAT], io);
+        if line && !self.stat_line {
+            *iflag |= Self::IF_STAT;
         }
+        self.stat_line = line;
     }
 
-    fn sync_registers(&mut self, io: &mut [u8; 0x80], iflag: &mut u8) {
-        io[Self::LY] = self.ly;
+    /// CPU write to STAT (0xFF41); only the interrupt enables (bits 3-6) are writable.
+    ///
+    /// On DMG the write briefly acts as if every enable were set, so writing STAT during
+    /// HBlank, VBlank or while LY == LYC requests a STAT interrupt if the line was low (the
+    /// STAT-write bug some games rely on). CGB doesn't have this bug.
+    pub fn write_stat(&mut self, val: u8, io: &mut [u8; 0x80], iflag: &mut u8, dmg: bool) {
+        if dmg && self.lcd_enabled && self.stat_conditions(0x58, io) {
+            if !self.stat_line {
+                *iflag |= Self::IF_STAT;
+            }
+            self.stat_line = true;
+        }
+        io[Self::STAT] = (io[Self::STAT] & 0x07) | (val & 0x78);
+        self.update_stat_line(io, iflag);
+    }
 
-        let coincidence = self.ly == io[Self::LYC];
-        if coincidence && !self.prev_coincidence && (io[Self::STAT] & 0x40) != 0 {
-            *iflag |= Self::IF_STAT;
+    /// LY as the CPU reads it (and LYC compares against it): the current line, except that
+    /// line 153 reads as 0 after its first few dots, so LYC=0 matches before line 0 starts.
+    fn register_ly(&self) -> u8 {
+        if self.ly == 153 && self.dots >= Self::LINE_153_LY_DOTS {
+            0
+        } else {
+            self.ly
         }
-        self.prev_coincidence = coincidence;
+    }
 
+    fn sync_registers(&mut self, io: &mut [u8; 0x80], iflag: &mut u8) {
+        io[Self::LY] = self.register_ly();
+        self.update_stat_line(io, iflag);
+
+        let coincidence = self.register_ly() == io[Self::LYC];
         let mut stat = io[Self::STAT] & 0x78; // keep interrupt enables
         stat |= self.mode & 0x03;
         if coincidence {
@@ -251,10 +701,41 @@ impl Ppu {
         io[Self::STAT] = stat;
     }
 
+    /// The last rendered frame, without frame blending or the blank first frame after the
+    /// LCD is switched on (see [`present_framebuffer`](Self::present_framebuffer)).
     pub fn framebuffer(&self) -> &Framebuffer {
         &self.framebuffer
     }
 
+    /// Renders all 144 lines into the framebuffer from the current VRAM/OAM/register state,
+    /// without stepping timing (see [`render_full_frame`](super::render::render_full_frame)).
+    /// Mid-frame register changes are not reproduced.
+    pub fn force_render_frame(
+        &mut self,
+        vram0: &[u8; 0x2000],
+        vram1: Option<&[u8; 0x2000]>,
+        oam: &[u8; 0xA0],
+        io: &[u8; 0x80],
+        cgb_mode: bool,
+    ) {
+        if (io[Self::LCDC] & 0x80) == 0 {
+            self.clear_framebuffer();
+            return;
+        }
+        super::render::render_full_frame(
+            &mut self.framebuffer,
+            vram0,
+            vram1,
+            oam,
+            io,
+            cgb_mode,
+            &self.cgb_bg_palette_ram,
+            &self.cgb_obj_palette_ram,
+            &self.dmg_palettes,
+            (self.cgb_opri & 0x01) != 0,
+        );
+    }
+
     pub fn frame_ready(&self) -> bool {
         self.frame_ready
     }
@@ -274,6 +755,12 @@ impl Ppu {
     pub fn current_dots(&self) -> u32 {
         self.dots
     }
+
+    /// Wrapping count of HBlank periods entered. Comparing two readings tells how many
+    /// HBlanks started in between, even when a single tick spans a whole HBlank.
+    pub fn hblank_count(&self) -> u32 {
+        self.hblank_count
+    }
 }
 
 impl Default for Ppu {
@@ -285,6 +772,7 @@ impl Default for Ppu {
 #[cfg(test)]
 mod tests {
     use super::Ppu;
+    use crate::ppu::render::DMG_SHADES;
 
     const LCDC: usize = 0x40;
     const STAT: usize = 0x41;
@@ -434,6 +922,64 @@ mod tests {
         assert_eq!(ppu.framebuffer()[8], 0xFFFFFFFF);
     }
 
+    #[test]
+    fn ppu_with_rendering_disabled_still_completes_frames() {
+        let mut ppu = Ppu::new();
+        ppu.set_rendering_enabled(false);
+        let mut vram = [0u8; 0x2000];
+        let mut io = [0u8; 0x80];
+        let mut iflag = 0u8;
+
+        // Tile 1 (all color 3) at the top-left of the BG map.
+        for b in &mut vram[16..32] {
+            *b = 0xFF;
+        }
+        vram[0x1800] = 1;
+        io[0x47] = 0xE4;
+        io[0x40] = 0x91;
+
+        let oam = [0u8; 0xA0];
+        ppu.tick(0, &vram, &oam, &mut io, &mut iflag);
+        ppu.tick(456 * 144, &vram, &oam, &mut io, &mut iflag);
+
+        assert!(ppu.frame_ready());
+        assert_ne!(iflag & 0x01, 0);
+        assert_eq!(ppu.framebuffer()[0], 0xFFFFFFFF);
+    }
+
+    #[test]
+    fn ppu_timing_is_identical_with_rendering_disabled() {
+        let mut rendered = Ppu::new();
+        let mut skipped = Ppu::new();
+        skipped.set_rendering_enabled(false);
+
+        let mut vram = [0u8; 0x2000];
+        vram[0x1800] = 1;
+        let oam = [0u8; 0xA0];
+        let mut io_a = [0u8; 0x80];
+        io_a[0x40] = 0xB3; // LCD, BG, OBJ and window on
+        io_a[0x41] = 0x78; // all STAT interrupt sources
+        io_a[0x45] = 42;
+        io_a[0x4A] = 20;
+        let mut io_b = io_a;
+        let (mut if_a, mut if_b) = (0u8, 0u8);
+
+        // Two frames in uneven steps so mode boundaries fall mid-tick.
+        for step in 0..(2 * 154 * 456 / 7) {
+            let cycles = [4, 8, 12, 4, 20, 8, 4][step % 7];
+            rendered.tick(cycles, &vram, &oam, &mut io_a, &mut if_a);
+            skipped.tick(cycles, &vram, &oam, &mut io_b, &mut if_b);
+
+            assert_eq!(skipped.current_mode(), rendered.current_mode());
+            assert_eq!(skipped.current_ly(), rendered.current_ly());
+            assert_eq!(skipped.current_dots(), rendered.current_dots());
+            assert_eq!(skipped.frame_ready(), rendered.frame_ready());
+            assert_eq!(io_b, io_a);
+            assert_eq!(if_b, if_a);
+        }
+        assert_ne!(if_a & 0x01, 0);
+    }
+
     #[test]
     fn ppu_framebuffer_updates_when_vram_changes() {
         use crate::ppu::LCD_WIDTH;
@@ -578,4 +1124,112 @@ mod tests {
         assert_eq!(ppu.framebuffer()[LCD_WIDTH + 7], 0xFFFFFFFF);
         assert_eq!(ppu.framebuffer()[LCD_WIDTH + 8], 0xFF000000);
     }
+
+    #[test]
+    fn objects_and_scx_delay_the_mode0_transition() {
+        // Dots from the start of line 0 until mode 0 and its STAT interrupt.
+        let hblank_start = |oam: &[u8; 0xA0], scx: u8| {
+            let mut ppu = Ppu::new();
+            let mut io = [0u8; 0x80];
+            let mut iflag = 0u8;
+            let vram = [0u8; 0x2000];
+            io[LCDC] = 0x82; // LCD and objects on
+            io[STAT] = 0x08; // mode 0 interrupt
+            io[0x43] = scx;
+
+            ppu.tick(80, &vram, oam, &mut io, &mut iflag);
+            let mut dots = 80;
+            while mode(io[STAT]) == 3 {
+                assert_eq!(iflag & 0x02, 0);
+                ppu.tick(1, &vram, oam, &mut io, &mut iflag);
+                dots += 1;
+            }
+            assert_eq!(iflag & 0x02, 0x02);
+            dots
+        };
+
+        let mut oam = [0u8; 0xA0];
+        assert_eq!(hblank_start(&oam, 0), 252);
+        assert_eq!(hblank_start(&oam, 3), 255);
+
+        // Objects on line 0, each at the start of its own BG tile: 6 + 5 dots apiece.
+        oam[0] = 16;
+        oam[1] = 8;
+        assert_eq!(hblank_start(&oam, 0), 252 + 11);
+        oam[4] = 16;
+        oam[5] = 40;
+        assert_eq!(hblank_start(&oam, 0), 252 + 22);
+        // A third object sharing the second one's tile only pays the fetch.
+        oam[8] = 16;
+        oam[9] = 44;
+        assert_eq!(hblank_start(&oam, 0), 252 + 28);
+
+        // Objects on other lines don't count.
+        oam[12] = 40;
+        oam[13] = 80;
+        assert_eq!(hblank_start(&oam, 0), 252 + 28);
+    }
+
+    #[test]
+    fn fifo_rendering_applies_mid_mode3_scx_write_partway_across_line() {
+        let split_line = |fifo: bool| {
+            let mut ppu = Ppu::new();
+            ppu.set_fifo_rendering(fifo);
+            let mut io = [0u8; 0x80];
+            let mut iflag = 0u8;
+            let mut vram = [0u8; 0x2000];
+            let oam = [0u8; 0xA0];
+
+            // Tile 1 is solid black; the BG map has it in column 1 only.
+            for row in 0..8 {
+                vram[16 + row * 2] = 0xFF;
+                vram[16 + row * 2 + 1] = 0xFF;
+            }
+            vram[0x1801] = 1;
+            io[0x47] = 0xE4;
+            io[LCDC] = 0x91;
+
+            // Into mode 3, up to the dot that outputs pixel 4.
+            ppu.tick(80 + 12 + 4, &vram, &oam, &mut io, &mut iflag);
+            io[0x43] = 8; // SCX
+            ppu.tick(456 - 96, &vram, &oam, &mut io, &mut iflag);
+            ppu.framebuffer()[..16].to_vec()
+        };
+
+        let white = 0xFFFFFFFF;
+        let black = 0xFF000000;
+        let line = split_line(true);
+        assert_eq!(line[..4], [white; 4]);
+        assert_eq!(line[4..8], [black; 4]);
+        assert_eq!(line[8..], [white; 8]);
+
+        // The scanline renderer draws the whole line with the SCX seen when mode 3 starts.
+        let line = split_line(false);
+        assert_eq!(line[..8], [white; 8]);
+        assert_eq!(line[8..], [black; 8]);
+    }
+
+    #[test]
+    fn frame_blend_mixes_alternating_frames_to_gray() {
+        let mut ppu = Ppu::new();
+        let white = DMG_SHADES[0];
+        let black = DMG_SHADES[3];
+        ppu.framebuffer.fill(white);
+        ppu.set_frame_blend(0.5);
+        assert_eq!(ppu.frame_blend(), 0.5);
+
+        for (frame, shade) in [black, white, black].into_iter().enumerate() {
+            ppu.framebuffer.fill(shade);
+            ppu.completed_frames += 1;
+            let presented = ppu.present_framebuffer()[0];
+            assert_eq!(presented, 0xFF7F_7F7F, "frame {frame}");
+            // Presenting again without a new frame doesn't blend again.
+            assert_eq!(ppu.present_framebuffer()[0], 0xFF7F_7F7F);
+        }
+        // The raw framebuffer is left alone.
+        assert!(ppu.framebuffer().iter().all(|&px| px == black));
+
+        ppu.set_frame_blend(0.0);
+        assert_eq!(ppu.present_framebuffer()[0], black);
+    }
 }
@@ -4,6 +4,13 @@ use super::{Framebuffer, LCD_HEIGHT, LCD_WIDTH};
 
 pub const DMG_SHADES: [u32; 4] = [0xFFFF_FFFF, 0xFFAA_AAAA, 0xFF55_5555, 0xFF00_0000];
 
+/// Final ARGB colors for DMG-path shades: BG/window, OBP0 and OBP1.
+///
+/// Grayscale on DMG; CGB colorization of DMG games replaces these with palette RAM colors.
+pub type DmgPalettes = [[u32; 4]; 3];
+
+pub const DMG_GRAYSCALE_PALETTES: DmgPalettes = [DMG_SHADES; 3];
+
 const LCDC: usize = 0x40;
 const SCY: usize = 0x42;
 const SCX: usize = 0x43;
@@ -23,7 +30,7 @@ fn scale_5bit_to_8bit(v: u8) -> u8 {
     (v << 3) | (v >> 2)
 }
 
-fn cgb_bgr15_to_argb(color: u16) -> u32 {
+pub(crate) fn cgb_bgr15_to_argb(color: u16) -> u32 {
     let r = (color & 0x1F) as u8;
     let g = ((color >> 5) & 0x1F) as u8;
     let b = ((color >> 10) & 0x1F) as u8;
@@ -51,27 +58,108 @@ fn cgb_obj_color(obj_palette_ram: &[u8; 0x40], palette: u8, color_num: u8) -> u3
     cgb_bgr15_to_argb(color)
 }
 
+/// One row of the framebuffer.
+pub(crate) type Scanline = [u32; LCD_WIDTH];
+
+/// The framebuffer row for `ly`, or `None` outside the visible area.
+fn scanline_mut(framebuffer: &mut Framebuffer, ly: u8) -> Option<&mut Scanline> {
+    let start = (ly as usize).checked_mul(LCD_WIDTH)?;
+    framebuffer
+        .get_mut(start..start + LCD_WIDTH)
+        .map(|row| row.try_into().expect("row is LCD_WIDTH long"))
+}
+
+/// Whether the window covers part of line `ly` (and so advances the window line counter).
+fn window_visible_on_line(io: &[u8; 0x80], ly: u8, cgb_mode: bool) -> bool {
+    let lcdc = io[LCDC];
+    let window_enabled = ((lcdc & 0x01) != 0 || cgb_mode) && (lcdc & 0x20) != 0;
+    let win_x_start = (io[WX] as i16) - 7;
+    window_enabled && ly >= io[WY] && win_x_start < LCD_WIDTH as i16
+}
+
+/// Decoded color numbers of the most recently used tile row, so the 8 pixels of a tile
+/// share one VRAM fetch and decode.
+#[derive(Default)]
+struct TileRowCache {
+    // (row address, VRAM bank, x flip)
+    key: Option<(u16, u8, bool)>,
+    colors: [u8; 8],
+    // Rows decoded so far, for the tests.
+    #[cfg(test)]
+    decodes: u32,
+}
+
+impl TileRowCache {
+    /// Color numbers of the tile row at `row_addr`, in screen order (x flip applied).
+    #[inline]
+    fn row(&mut self, tile_vram: &[u8; 0x2000], row_addr: u16, bank: u8, x_flip: bool) -> [u8; 8] {
+        let key = (row_addr, bank, x_flip);
+        if self.key != Some(key) {
+            let off = (row_addr - 0x8000) as usize;
+            let (lo, hi) = (tile_vram[off], tile_vram[off + 1]);
+            for (i, color) in self.colors.iter_mut().enumerate() {
+                let bit = if x_flip { i } else { 7 - i };
+                *color = (((hi >> bit) & 1) << 1) | ((lo >> bit) & 1);
+            }
+            self.key = Some(key);
+            #[cfg(test)]
+            {
+                self.decodes += 1;
+            }
+        }
+        self.colors
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn render_bg_window_scanline(
-    framebuffer: &mut Framebuffer,
+    line: &mut Scanline,
     ly: u8,
     vram0: &[u8; 0x2000],
     vram1: Option<&[u8; 0x2000]>,
     io: &[u8; 0x80],
     cgb_mode: bool,
     bg_palette_ram: &[u8; 0x40],
-    mut bg_pixels: Option<&mut [BgPixelInfo; LCD_WIDTH]>,
-) {
-    if ly as usize >= LCD_HEIGHT {
-        return;
-    }
+    dmg_palettes: &DmgPalettes,
+    window_line: u8,
+    bg_pixels: Option<&mut [BgPixelInfo; LCD_WIDTH]>,
+) -> bool {
+    render_bg_window_scanline_cached(
+        line,
+        ly,
+        vram0,
+        vram1,
+        io,
+        cgb_mode,
+        bg_palette_ram,
+        dmg_palettes,
+        window_line,
+        bg_pixels,
+        &mut TileRowCache::default(),
+        &mut TileRowCache::default(),
+    )
+}
 
+#[allow(clippy::too_many_arguments)]
+fn render_bg_window_scanline_cached(
+    line: &mut Scanline,
+    ly: u8,
+    vram0: &[u8; 0x2000],
+    vram1: Option<&[u8; 0x2000]>,
+    io: &[u8; 0x80],
+    cgb_mode: bool,
+    bg_palette_ram: &[u8; 0x40],
+    dmg_palettes: &DmgPalettes,
+    window_line: u8,
+    mut bg_pixels: Option<&mut [BgPixelInfo; LCD_WIDTH]>,
+    bg_cache: &mut TileRowCache,
+    win_cache: &mut TileRowCache,
+) -> bool {
     let lcdc = io[LCDC];
     // On DMG, bit 0 controls both BG and window rendering.
     // In CGB mode, BG and window are always enabled, but bit 0
     // acts as a "master priority" flag.
     let bg_enabled = if cgb_mode { true } else { (lcdc & 0x01) != 0 };
-    let window_enabled = ((lcdc & 0x01) != 0 || cgb_mode) && (lcdc & 0x20) != 0;
 
     let scy = io[SCY];
     let scx = io[SCX];
@@ -87,13 +175,12 @@ fn render_bg_window_scanline(
     let bg_pixel_row = y as u16 % 8;
 
     // Window coordinates (no scroll); visible when LY >= WY and X >= WX-7.
-    let wy = io[WY];
-    let wx = io[WX];
-    let window_active_line = window_enabled && ly >= wy;
-    let window_y = ly.wrapping_sub(wy) as u16;
+    // Rows come from the internal window line counter, not LY - WY.
+    let win_x_start = (io[WX] as i16) - 7;
+    let window_active_line = window_visible_on_line(io, ly, cgb_mode);
+    let window_y = window_line as u16;
     let win_tile_row = window_y / 8;
     let win_pixel_row = window_y % 8;
-    let win_x_start = (wx as i16) - 7;
 
     for x in 0..(LCD_WIDTH as u16) {
         let mut color_num = 0u8;
@@ -112,7 +199,7 @@ fn render_bg_window_scanline(
             } else {
                 0
             };
-            let tile_bank = if (attrs & 0x08) != 0 { 1 } else { 0 };
+            let tile_bank = u8::from(cgb_mode && (attrs & 0x08) != 0);
             let palette_num = attrs & 0x07;
             let y_flip = (attrs & 0x40) != 0;
             let x_flip = (attrs & 0x20) != 0;
@@ -123,11 +210,6 @@ fn render_bg_window_scanline(
                 pixel_row = 7 - pixel_row;
             }
 
-            let mut pixel_col = bg_pixel_col as u8;
-            if x_flip {
-                pixel_col = 7 - pixel_col;
-            }
-
             let tile_addr = if tiledata_unsigned {
                 0x8000u16 + (tile_id as u16) * 16
             } else {
@@ -142,12 +224,7 @@ fn render_bg_window_scanline(
             } else {
                 vram0
             };
-            let lo = tile_vram[(row_addr - 0x8000) as usize];
-            let hi = tile_vram[(row_addr - 0x8000 + 1) as usize];
-            let bit = 7 - pixel_col;
-            let lsb = (lo >> bit) & 1;
-            let msb = (hi >> bit) & 1;
-            color_num = (msb << 1) | lsb;
+            color_num = bg_cache.row(tile_vram, row_addr, tile_bank, x_flip)[bg_pixel_col as usize];
 
             if let Some(ref mut px) = bg_pixels {
                 px[x as usize].bg_to_oam_priority = bg_to_oam_priority;
@@ -155,8 +232,7 @@ fn render_bg_window_scanline(
             }
 
             if cgb_mode {
-                framebuffer[(ly as usize) * LCD_WIDTH + (x as usize)] =
-                    cgb_bg_color(bg_palette_ram, palette_num, color_num);
+                line[x as usize] = cgb_bg_color(bg_palette_ram, palette_num, color_num);
                 cgb_pixel_written = true;
             }
         }
@@ -174,7 +250,7 @@ fn render_bg_window_scanline(
             } else {
                 0
             };
-            let tile_bank = if (attrs & 0x08) != 0 { 1 } else { 0 };
+            let tile_bank = u8::from(cgb_mode && (attrs & 0x08) != 0);
             let palette_num = attrs & 0x07;
             let y_flip = (attrs & 0x40) != 0;
             let x_flip = (attrs & 0x20) != 0;
@@ -185,11 +261,6 @@ fn render_bg_window_scanline(
                 pixel_row = 7 - pixel_row;
             }
 
-            let mut pixel_col = win_pixel_col as u8;
-            if x_flip {
-                pixel_col = 7 - pixel_col;
-            }
-
             let tile_addr = if tiledata_unsigned {
                 0x8000u16 + (tile_id as u16) * 16
             } else {
@@ -203,12 +274,8 @@ fn render_bg_window_scanline(
             } else {
                 vram0
             };
-            let lo = tile_vram[(row_addr - 0x8000) as usize];
-            let hi = tile_vram[(row_addr - 0x8000 + 1) as usize];
-            let bit = 7 - pixel_col;
-            let lsb = (lo >> bit) & 1;
-            let msb = (hi >> bit) & 1;
-            color_num = (msb << 1) | lsb;
+            color_num =
+                win_cache.row(tile_vram, row_addr, tile_bank, x_flip)[win_pixel_col as usize];
 
             if let Some(ref mut px) = bg_pixels {
                 px[x as usize].bg_to_oam_priority = bg_to_oam_priority;
@@ -216,8 +283,7 @@ fn render_bg_window_scanline(
             }
 
             if cgb_mode {
-                framebuffer[(ly as usize) * LCD_WIDTH + (x as usize)] =
-                    cgb_bg_color(bg_palette_ram, palette_num, color_num);
+                line[x as usize] = cgb_bg_color(bg_palette_ram, palette_num, color_num);
                 cgb_pixel_written = true;
             }
         }
@@ -231,8 +297,10 @@ fn render_bg_window_scanline(
         }
 
         let shade = (bgp >> (color_num * 2)) & 0x03;
-        framebuffer[(ly as usize) * LCD_WIDTH + (x as usize)] = DMG_SHADES[shade as usize];
+        line[x as usize] = dmg_palettes[0][shade as usize];
     }
+
+    window_active_line
 }
 
 pub fn render_bg_scanline(
@@ -241,7 +309,21 @@ pub fn render_bg_scanline(
     vram: &[u8; 0x2000],
     io: &[u8; 0x80],
 ) {
-    render_bg_window_scanline(framebuffer, ly, vram, None, io, false, &[0; 0x40], None);
+    let Some(line) = scanline_mut(framebuffer, ly) else {
+        return;
+    };
+    render_bg_window_scanline(
+        line,
+        ly,
+        vram,
+        None,
+        io,
+        false,
+        &[0; 0x40],
+        &DMG_GRAYSCALE_PALETTES,
+        ly.wrapping_sub(io[WY]),
+        None,
+    );
 }
 
 #[derive(Copy, Clone)]
@@ -255,7 +337,7 @@ struct SpriteLine {
 
 #[allow(clippy::too_many_arguments)]
 fn render_obj_scanline(
-    framebuffer: &mut Framebuffer,
+    line: &mut Scanline,
     ly: u8,
     vram0: &[u8; 0x2000],
     vram1: Option<&[u8; 0x2000]>,
@@ -264,11 +346,9 @@ fn render_obj_scanline(
     cgb_mode: bool,
     bg_pixels: &[BgPixelInfo; LCD_WIDTH],
     obj_palette_ram: &[u8; 0x40],
+    dmg_palettes: &DmgPalettes,
+    x_priority: bool,
 ) {
-    if ly as usize >= LCD_HEIGHT {
-        return;
-    }
-
     let lcdc = io[LCDC];
     let sprites_enabled = (lcdc & 0x02) != 0;
     if !sprites_enabled {
@@ -363,7 +443,9 @@ fn render_obj_scanline(
                 continue;
             }
 
-            if cgb_mode {
+            // CGB OAM-order priority: the first opaque sprite in OAM wins. DMG (and CGB with
+            // OPRI bit 0 set) prefers the smallest X, then OAM index.
+            if cgb_mode && !x_priority {
                 best = Some((sprite.x, sprite.oam_index, sprite.attrs, color_num));
                 break;
             }
@@ -397,13 +479,16 @@ fn render_obj_scanline(
 
         if cgb_mode {
             let palette_num = attrs & 0x07;
-            framebuffer[(ly as usize) * LCD_WIDTH + x] =
-                cgb_obj_color(obj_palette_ram, palette_num, color_num);
+            line[x] = cgb_obj_color(obj_palette_ram, palette_num, color_num);
         } else {
             let use_obp1 = (attrs & 0x10) != 0;
-            let pal = if use_obp1 { obp1 } else { obp0 };
+            let (pal, colors) = if use_obp1 {
+                (obp1, &dmg_palettes[2])
+            } else {
+                (obp0, &dmg_palettes[1])
+            };
             let shade = (pal >> (color_num * 2)) & 0x03;
-            framebuffer[(ly as usize) * LCD_WIDTH + x] = DMG_SHADES[shade as usize];
+            line[x] = colors[shade as usize];
         }
     }
 }
@@ -415,19 +500,24 @@ pub fn render_scanline(
     oam: &[u8; 0xA0],
     io: &[u8; 0x80],
 ) {
+    let Some(line) = scanline_mut(framebuffer, ly) else {
+        return;
+    };
     let mut bg_pixels = [BgPixelInfo::default(); LCD_WIDTH];
     render_bg_window_scanline(
-        framebuffer,
+        line,
         ly,
         vram,
         None,
         io,
         false,
         &[0; 0x40],
+        &DMG_GRAYSCALE_PALETTES,
+        ly.wrapping_sub(io[WY]),
         Some(&mut bg_pixels),
     );
     render_obj_scanline(
-        framebuffer,
+        line,
         ly,
         vram,
         None,
@@ -436,6 +526,8 @@ pub fn render_scanline(
         false,
         &bg_pixels,
         &[0; 0x40],
+        &DMG_GRAYSCALE_PALETTES,
+        true,
     );
 }
 
@@ -450,20 +542,64 @@ pub fn render_scanline_with_cgb(
     cgb_mode: bool,
     bg_palette_ram: &[u8; 0x40],
     obj_palette_ram: &[u8; 0x40],
+    dmg_palettes: &DmgPalettes,
+    obj_x_priority: bool,
+    window_line: &mut u8,
 ) {
+    let Some(line) = scanline_mut(framebuffer, ly) else {
+        return;
+    };
+    let window_rendered = render_line(
+        line,
+        ly,
+        vram0,
+        vram1,
+        oam,
+        io,
+        cgb_mode,
+        bg_palette_ram,
+        obj_palette_ram,
+        dmg_palettes,
+        obj_x_priority,
+        *window_line,
+    );
+    // The window line counter only advances on lines where the window was drawn.
+    if window_rendered {
+        *window_line = window_line.wrapping_add(1);
+    }
+}
+
+/// Renders BG/window and objects for one line; returns whether the window was drawn.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn render_line(
+    line: &mut Scanline,
+    ly: u8,
+    vram0: &[u8; 0x2000],
+    vram1: Option<&[u8; 0x2000]>,
+    oam: &[u8; 0xA0],
+    io: &[u8; 0x80],
+    cgb_mode: bool,
+    bg_palette_ram: &[u8; 0x40],
+    obj_palette_ram: &[u8; 0x40],
+    dmg_palettes: &DmgPalettes,
+    obj_x_priority: bool,
+    window_line: u8,
+) -> bool {
     let mut bg_pixels = [BgPixelInfo::default(); LCD_WIDTH];
-    render_bg_window_scanline(
-        framebuffer,
+    let window_rendered = render_bg_window_scanline(
+        line,
         ly,
         vram0,
         vram1,
         io,
         cgb_mode,
         bg_palette_ram,
+        dmg_palettes,
+        window_line,
         Some(&mut bg_pixels),
     );
     render_obj_scanline(
-        framebuffer,
+        line,
         ly,
         vram0,
         vram1,
@@ -472,7 +608,71 @@ pub fn render_scanline_with_cgb(
         cgb_mode,
         &bg_pixels,
         obj_palette_ram,
+        dmg_palettes,
+        obj_x_priority,
     );
+    window_rendered
+}
+
+/// Renders a whole frame from the current VRAM/OAM/register state, as if the registers were
+/// held constant for all 144 lines (e.g. for thumbnails and viewers, not mid-frame effects).
+///
+/// Lines are independent once each line's window row is known, so with the `parallel`
+/// feature they are rendered on the rayon thread pool.
+#[allow(clippy::too_many_arguments)]
+pub fn render_full_frame(
+    framebuffer: &mut Framebuffer,
+    vram0: &[u8; 0x2000],
+    vram1: Option<&[u8; 0x2000]>,
+    oam: &[u8; 0xA0],
+    io: &[u8; 0x80],
+    cgb_mode: bool,
+    bg_palette_ram: &[u8; 0x40],
+    obj_palette_ram: &[u8; 0x40],
+    dmg_palettes: &DmgPalettes,
+    obj_x_priority: bool,
+) {
+    // Window line counter value at the start of each line.
+    let mut window_lines = [0u8; LCD_HEIGHT];
+    let mut window_line = 0u8;
+    for (ly, start) in window_lines.iter_mut().enumerate() {
+        *start = window_line;
+        if window_visible_on_line(io, ly as u8, cgb_mode) {
+            window_line = window_line.wrapping_add(1);
+        }
+    }
+
+    let render = |(ly, row): (usize, &mut [u32])| {
+        let line: &mut Scanline = row.try_into().expect("row is LCD_WIDTH long");
+        render_line(
+            line,
+            ly as u8,
+            vram0,
+            vram1,
+            oam,
+            io,
+            cgb_mode,
+            bg_palette_ram,
+            obj_palette_ram,
+            dmg_palettes,
+            obj_x_priority,
+            window_lines[ly],
+        );
+    };
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        framebuffer
+            .par_chunks_exact_mut(LCD_WIDTH)
+            .enumerate()
+            .for_each(render);
+    }
+    #[cfg(not(feature = "parallel"))]
+    framebuffer
+        .chunks_exact_mut(LCD_WIDTH)
+        .enumerate()
+        .for_each(render);
 }
 
 #[cfg(test)]
@@ -482,6 +682,7 @@ mod tests {
     const LCDC: usize = 0x40;
     const BGP: usize = 0x47;
     const OBP0: usize = 0x48;
+    const WX: usize = 0x4B;
 
     fn write_tile(vram: &mut [u8; 0x2000], tile: usize, rows: &[(u8, u8); 8]) {
         let base = tile * 16;
@@ -650,4 +851,169 @@ mod tests {
         render_scanline(&mut fb, 0, &vram, &oam, &io);
         assert_eq!(fb[0], DMG_SHADES[0]);
     }
+
+    #[test]
+    fn full_frame_matches_serial_scanline_loop() {
+        use super::{render_full_frame, render_scanline_with_cgb, DMG_GRAYSCALE_PALETTES};
+        use crate::ppu::FRAMEBUFFER_LEN;
+
+        // Pseudo-random VRAM, OAM and palettes so every line differs.
+        let mut seed = 0x2468_ACE1u32;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as u8
+        };
+        let mut vram0 = [0u8; 0x2000];
+        let mut vram1 = [0u8; 0x2000];
+        let mut oam = [0u8; 0xA0];
+        let mut bg_pal = [0u8; 0x40];
+        let mut obj_pal = [0u8; 0x40];
+        vram0.iter_mut().for_each(|b| *b = next());
+        vram1.iter_mut().for_each(|b| *b = next());
+        oam.iter_mut().for_each(|b| *b = next());
+        bg_pal.iter_mut().for_each(|b| *b = next());
+        obj_pal.iter_mut().for_each(|b| *b = next());
+
+        let mut io = [0u8; 0x80];
+        io[LCDC] = 0xF7; // window on (0x9C00 map), 8x16 objects
+        io[0x42] = 37; // SCY
+        io[0x43] = 91; // SCX
+        io[BGP] = 0xE4;
+        io[OBP0] = 0xD2;
+        io[0x49] = 0x1B; // OBP1
+        io[0x4A] = 40; // WY
+        io[0x4B] = 60; // WX
+
+        for cgb_mode in [false, true] {
+            let vram1 = cgb_mode.then_some(&vram1);
+
+            let mut serial = [0u32; FRAMEBUFFER_LEN];
+            let mut window_line = 0u8;
+            for ly in 0..144 {
+                render_scanline_with_cgb(
+                    &mut serial,
+                    ly,
+                    &vram0,
+                    vram1,
+                    &oam,
+                    &io,
+                    cgb_mode,
+                    &bg_pal,
+                    &obj_pal,
+                    &DMG_GRAYSCALE_PALETTES,
+                    !cgb_mode,
+                    &mut window_line,
+                );
+            }
+
+            let mut full = [0u32; FRAMEBUFFER_LEN];
+            render_full_frame(
+                &mut full,
+                &vram0,
+                vram1,
+                &oam,
+                &io,
+                cgb_mode,
+                &bg_pal,
+                &obj_pal,
+                &DMG_GRAYSCALE_PALETTES,
+                !cgb_mode,
+            );
+
+            assert!(full[..] == serial[..], "cgb_mode={cgb_mode}");
+        }
+    }
+
+    #[test]
+    fn window_is_clipped_at_the_left_edge_and_offscreen_past_wx_166() {
+        let mut vram = [0u8; 0x2000];
+        // BG (0x9800 map, all tile 0): color 1. Window (0x9C00 map): tile 3 then tile 2,
+        // color 2 except tile 3's last pixel, which is color 3.
+        write_tile(&mut vram, 0, &[(0xFF, 0x00); 8]);
+        write_tile(&mut vram, 2, &[(0x00, 0xFF); 8]);
+        write_tile(&mut vram, 3, &[(0x01, 0xFF); 8]);
+        vram[0x1C00..0x1C20].fill(2);
+        vram[0x1C00] = 3;
+
+        let mut io = [0u8; 0x80];
+        io[LCDC] = 0xF1; // LCD, window (0x9C00 map), unsigned tile data, BG
+        io[BGP] = 0xE4;
+        let render = |wx: u8| {
+            let mut io = io;
+            io[WX] = wx;
+            let mut fb = [0u32; 160 * 144];
+            render_scanline(&mut fb, 0, &vram, &[0; 0xA0], &io);
+            fb[..LCD_WIDTH].to_vec()
+        };
+        let [bg, win, win_edge] = [DMG_SHADES[1], DMG_SHADES[2], DMG_SHADES[3]];
+
+        // WX < 7 shifts the window left, dropping its first 7 - WX columns.
+        let line = render(0);
+        assert_eq!(&line[..2], &[win_edge, win]);
+        assert!(line[1..].iter().all(|&px| px == win));
+        let line = render(6);
+        assert_eq!(&line[..8], &[win, win, win, win, win, win, win_edge, win]);
+        let line = render(7);
+        assert_eq!(line[7], win_edge);
+        assert!(line[..7].iter().all(|&px| px == win));
+
+        // WX = 166 shows only the window's first column, at x = 159; WX >= 167 hides it.
+        let line = render(166);
+        assert!(line[..159].iter().all(|&px| px == bg));
+        assert_eq!(line[159], win);
+        assert!(render(167).iter().all(|&px| px == bg));
+        assert!(render(255).iter().all(|&px| px == bg));
+    }
+
+    #[test]
+    fn tile_row_cache_decodes_once_per_tile() {
+        use super::{render_bg_window_scanline_cached, TileRowCache, DMG_GRAYSCALE_PALETTES};
+
+        let mut vram = [0u8; 0x2000];
+        for (i, b) in vram.iter_mut().enumerate() {
+            *b = (i as u8).wrapping_mul(37) ^ 0x5A;
+        }
+
+        // Cached rows match a per-pixel bit extraction, with and without x flip.
+        let mut cache = TileRowCache::default();
+        for x_flip in [false, true] {
+            let colors = cache.row(&vram, 0x8010, 0, x_flip);
+            let (lo, hi) = (vram[0x10], vram[0x11]);
+            for (x, &color) in colors.iter().enumerate() {
+                let bit = if x_flip { x } else { 7 - x };
+                assert_eq!(color, (((hi >> bit) & 1) << 1) | ((lo >> bit) & 1));
+            }
+        }
+        assert_eq!(cache.decodes, 2);
+
+        let mut io = [0u8; 0x80];
+        io[LCDC] = 0x91;
+        io[BGP] = 0xE4;
+        for scx in [0u8, 3] {
+            io[0x43] = scx;
+            let mut line = [0u32; LCD_WIDTH];
+            let mut bg_cache = TileRowCache::default();
+            let mut win_cache = TileRowCache::default();
+            render_bg_window_scanline_cached(
+                &mut line,
+                0,
+                &vram,
+                None,
+                &io,
+                false,
+                &[0; 0x40],
+                &DMG_GRAYSCALE_PALETTES,
+                0,
+                None,
+                &mut bg_cache,
+                &mut win_cache,
+            );
+            // 20 tiles per line, plus one when SCX splits the edge tiles (vs 160 per-pixel fetches).
+            let expected = if scx % 8 == 0 { 20 } else { 21 };
+            assert_eq!(bg_cache.decodes, expected, "scx={scx}");
+            assert_eq!(win_cache.decodes, 0);
+        }
+    }
 }
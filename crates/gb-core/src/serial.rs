@@ -1,13 +1,24 @@
+use alloc::vec::Vec;
+
 use serde::{Deserialize, Serialize};
 
-const SERIAL_INTERNAL_TRANSFER_CYCLES: u32 = 4096;
+/// System cycles per bit with the internal 8192 Hz clock (8 bits = 4096 cycles).
+const SERIAL_INTERNAL_BIT_CYCLES: u32 = 512;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Serial {
     output: Vec<u8>,
     in_progress: bool,
-    cycles_remaining: u32,
+    internal_clock: bool,
+    // Bits still to shift in the current transfer.
+    bits_remaining: u8,
+    // Internal clock: system cycles per bit, and until the next bit is shifted.
+    bit_cycles: u32,
+    cycles_until_bit: u32,
     pending_byte: u8,
+    // Test setting, not machine state.
+    #[serde(skip)]
+    loopback: bool,
 }
 
 impl Serial {
@@ -15,49 +26,112 @@ impl Serial {
         Self::default()
     }
 
+    /// Returns to power-on state, keeping the loopback setting.
+    pub fn reset(&mut self) {
+        let mut fresh = Self::new();
+        fresh.take_settings(self);
+        *self = fresh;
+    }
+
+    /// Takes the loopback setting from `old`.
+    pub(crate) fn take_settings(&mut self, old: &Self) {
+        self.loopback = old.loopback;
+    }
+
+    /// With loopback on, internal-clock transfers receive each bit they send, as if SO were
+    /// wired to SI: SB holds the same byte once the transfer completes.
+    pub fn set_loopback(&mut self, enabled: bool) {
+        self.loopback = enabled;
+    }
+
+    pub fn loopback(&self) -> bool {
+        self.loopback
+    }
+
     pub fn on_transfer(&mut self, byte: u8) {
         self.output.push(byte);
     }
 
-    pub fn start_transfer(&mut self, byte: u8, sc: &mut u8) {
+    /// Starts a transfer of `byte` (the current SB) after SC is written with bit 7 set.
+    ///
+    /// With the internal clock (SC bit 0) the 8 bits are shifted at 8192 Hz, twice as fast in
+    /// CGB double-speed mode. With the external clock the transfer waits for a peer to clock
+    /// each bit via [`Serial::clock_external_bit`].
+    pub fn start_transfer(&mut self, byte: u8, sc: &mut u8, double_speed: bool) {
         self.pending_byte = byte;
         self.in_progress = (*sc & 0x80) != 0;
-        let internal_clock = (*sc & 0x01) != 0;
-        self.cycles_remaining = if self.in_progress && internal_clock {
-            SERIAL_INTERNAL_TRANSFER_CYCLES
+        self.internal_clock = (*sc & 0x01) != 0;
+        self.bits_remaining = 8;
+        self.bit_cycles = if double_speed {
+            SERIAL_INTERNAL_BIT_CYCLES / 2
         } else {
-            0
+            SERIAL_INTERNAL_BIT_CYCLES
         };
+        self.cycles_until_bit = self.bit_cycles;
         *sc |= 0x80;
     }
 
     pub fn stop_transfer(&mut self, sc: &mut u8) {
         self.in_progress = false;
-        self.cycles_remaining = 0;
+        self.bits_remaining = 0;
+        self.cycles_until_bit = 0;
         *sc &= 0x7F;
     }
 
-    pub fn tick(&mut self, cycles: u32, iflag: &mut u8, sc: &mut u8) {
-        if !self.in_progress {
-            return;
-        }
-
-        if self.cycles_remaining == 0 || cycles >= self.cycles_remaining {
+    /// Shifts one bit: SB's top bit goes out and `bit_in` comes in at the bottom. After the
+    /// 8th bit the transfer completes and the serial interrupt is requested.
+    fn shift_bit(&mut self, bit_in: bool, iflag: &mut u8, sb: &mut u8, sc: &mut u8) -> bool {
+        let bit_out = (*sb & 0x80) != 0;
+        *sb = (*sb << 1) | u8::from(bit_in);
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
             self.in_progress = false;
-            self.cycles_remaining = 0;
             *sc &= 0x7F;
             self.on_transfer(self.pending_byte);
             *iflag |= crate::interrupt::Interrupt::Serial.bit();
-        } else {
-            self.cycles_remaining -= cycles;
         }
+        bit_out
+    }
+
+    /// Advances an internal-clock transfer by `cycles` system cycles. Without a link partner
+    /// the incoming bits read as 1, so SB ends up 0xFF (unless in loopback).
+    pub fn tick(&mut self, cycles: u32, iflag: &mut u8, sb: &mut u8, sc: &mut u8) {
+        if !self.in_progress || !self.internal_clock {
+            return;
+        }
+
+        let mut cycles = cycles;
+        while self.in_progress && cycles >= self.cycles_until_bit {
+            cycles -= self.cycles_until_bit;
+            self.cycles_until_bit = self.bit_cycles;
+            let bit_in = !self.loopback || (*sb & 0x80) != 0;
+            self.shift_bit(bit_in, iflag, sb, sc);
+        }
+        if self.in_progress {
+            self.cycles_until_bit -= cycles;
+        }
+    }
+
+    /// Clock pulse from a link partner driving an external-clock transfer. Returns the bit
+    /// shifted out, or `None` if no external-clock transfer is waiting.
+    pub fn clock_external_bit(
+        &mut self,
+        bit_in: bool,
+        iflag: &mut u8,
+        sb: &mut u8,
+        sc: &mut u8,
+    ) -> Option<bool> {
+        if !self.in_progress || self.internal_clock {
+            return None;
+        }
+        Some(self.shift_bit(bit_in, iflag, sb, sc))
     }
 
-    pub fn drain_output(&mut self) -> std::vec::Drain<'_, u8> {
+    pub fn drain_output(&mut self) -> alloc::vec::Drain<'_, u8> {
         self.output.drain(..)
     }
 
     pub fn take_output(&mut self) -> Vec<u8> {
-        std::mem::take(&mut self.output)
+        core::mem::take(&mut self.output)
     }
 }
@@ -13,15 +13,26 @@ pub struct Timer {
     tima: u8,
     tma: u8,
     tac: u8,
+    // Cycles left before a pending TIMA overflow reloads TMA and requests the interrupt.
+    #[serde(default)]
+    reload_delay: u8,
+    // Cycles left in the M-cycle during which TMA is being copied into TIMA.
+    #[serde(default)]
+    reloading: u8,
 }
 
 impl Timer {
+    /// TIMA overflow is delayed by one M-cycle before TMA is loaded and the interrupt fires.
+    const RELOAD_DELAY: u8 = 4;
+
     pub fn new() -> Self {
         Self {
             counter: 0,
             tima: 0,
             tma: 0,
             tac: 0,
+            reload_delay: 0,
+            reloading: 0,
         }
     }
 
@@ -35,31 +46,49 @@ impl Timer {
         self.counter
     }
 
+    /// The counter bit whose falling edges increment TIMA, or `None` with the timer off.
     #[inline]
-    fn input_bit(counter: u16, tac: u8) -> bool {
+    fn input_bit_index(tac: u8) -> Option<u32> {
         if (tac & 0x04) == 0 {
-            return false;
+            return None;
         }
 
-        let bit = match tac & 0x03 {
+        Some(match tac & 0x03 {
             0x00 => 9, // 4096 Hz   => 1024 cycles
             0x01 => 3, // 262144 Hz => 16 cycles
             0x02 => 5, // 65536 Hz  => 64 cycles
             0x03 => 7, // 16384 Hz  => 256 cycles
             _ => unreachable!(),
-        };
+        })
+    }
 
-        (counter & (1 << bit)) != 0
+    #[inline]
+    fn input_bit(counter: u16, tac: u8) -> bool {
+        Self::input_bit_index(tac).is_some_and(|bit| (counter & (1 << bit)) != 0)
     }
 
     #[inline]
-    fn inc_tima(&mut self, iflag: &mut u8) {
+    fn inc_tima(&mut self) {
         let (v, overflow) = self.tima.overflowing_add(1);
+        self.tima = v;
         if overflow {
-            self.tima = self.tma;
-            *iflag |= Interrupt::Timer.bit();
-        } else {
-            self.tima = v;
+            // TIMA reads 0 for one M-cycle; the reload happens in `tick`.
+            self.reload_delay = Self::RELOAD_DELAY;
+        }
+    }
+
+    #[inline]
+    fn tick_reload(&mut self, iflag: &mut u8) {
+        if self.reloading > 0 {
+            self.reloading -= 1;
+        }
+        if self.reload_delay > 0 {
+            self.reload_delay -= 1;
+            if self.reload_delay == 0 {
+                self.tima = self.tma;
+                self.reloading = Self::RELOAD_DELAY;
+                *iflag |= Interrupt::Timer.bit();
+            }
         }
     }
 
@@ -69,13 +98,13 @@ impl Timer {
     }
 
     #[inline]
-    pub fn write_div(&mut self, iflag: &mut u8) {
+    pub fn write_div(&mut self) {
         // DIV reset can create a falling edge on the timer input.
         let old = Self::input_bit(self.counter, self.tac);
         self.counter = 0;
         let new = Self::input_bit(self.counter, self.tac);
         if old && !new {
-            self.inc_tima(iflag);
+            self.inc_tima();
         }
     }
 
@@ -86,6 +115,12 @@ impl Timer {
 
     #[inline]
     pub fn write_tima(&mut self, val: u8) {
+        // Writes during the reload M-cycle are overridden by TMA.
+        if self.reloading > 0 {
+            return;
+        }
+        // Writing during the overflow delay cancels the pending reload and interrupt.
+        self.reload_delay = 0;
         self.tima = val;
     }
 
@@ -97,6 +132,10 @@ impl Timer {
     #[inline]
     pub fn write_tma(&mut self, val: u8) {
         self.tma = val;
+        // While TMA is being copied into TIMA, the new value goes through as well.
+        if self.reloading > 0 {
+            self.tima = val;
+        }
     }
 
     #[inline]
@@ -105,24 +144,55 @@ impl Timer {
     }
 
     #[inline]
-    pub fn write_tac(&mut self, val: u8, iflag: &mut u8) {
+    pub fn write_tac(&mut self, val: u8) {
         // TAC change can create a falling edge on the timer input.
         let old = Self::input_bit(self.counter, self.tac);
         self.tac = val & 0x07;
         let new = Self::input_bit(self.counter, self.tac);
         if old && !new {
-            self.inc_tima(iflag);
+            self.inc_tima();
         }
     }
 
-    pub fn tick(&mut self, cycles: u32, iflag: &mut u8) {
-        for _ in 0..(cycles as usize) {
-            let old = Self::input_bit(self.counter, self.tac);
-            self.counter = self.counter.wrapping_add(1);
-            let new = Self::input_bit(self.counter, self.tac);
-            if old && !new {
-                self.inc_tima(iflag);
+    /// Advances the timer by `cycles`. Long stretches (HALT, GDMA) cost one step per TIMA
+    /// overflow rather than per cycle: between overflows, all the falling edges of the input
+    /// bit are counted at once.
+    pub fn tick(&mut self, mut cycles: u32, iflag: &mut u8) {
+        while cycles > 0 {
+            if self.reload_delay > 0 || self.reloading > 0 {
+                self.tick_cycle(iflag);
+                cycles -= 1;
+                continue;
             }
+            // The counter wraps at 16 bits, so truncating the advance is exact.
+            let Some(bit) = Self::input_bit_index(self.tac) else {
+                self.counter = self.counter.wrapping_add(cycles as u16);
+                return;
+            };
+            // The input bit falls each time the counter reaches a multiple of `period`.
+            let period = 2u32 << bit;
+            let to_edge = period - (u32::from(self.counter) & (period - 1));
+            if cycles < to_edge {
+                self.counter = self.counter.wrapping_add(cycles as u16);
+                return;
+            }
+            // Stop at the edge that overflows TIMA so the reload is stepped cycle by cycle.
+            let edges = (1 + (cycles - to_edge) / period).min(256 - u32::from(self.tima));
+            let advance = to_edge + (edges - 1) * period;
+            self.counter = self.counter.wrapping_add(advance as u16);
+            cycles -= advance;
+            self.tima += (edges - 1) as u8;
+            self.inc_tima();
+        }
+    }
+
+    fn tick_cycle(&mut self, iflag: &mut u8) {
+        self.tick_reload(iflag);
+        let old = Self::input_bit(self.counter, self.tac);
+        self.counter = self.counter.wrapping_add(1);
+        let new = Self::input_bit(self.counter, self.tac);
+        if old && !new {
+            self.inc_tima();
         }
     }
 }
@@ -0,0 +1,60 @@
+//! Heap-allocated fixed-size arrays, for keeping large buffers out of `Bus`/`Ppu` so the
+//! machine is cheap to move.
+
+use alloc::boxed::Box;
+use alloc::vec;
+
+/// Allocates `[value; N]` directly on the heap (no large temporary on the stack).
+pub fn boxed_array<T: Clone, const N: usize>(value: T) -> Box<[T; N]> {
+    match vec![value; N].into_boxed_slice().try_into() {
+        Ok(array) => array,
+        Err(_) => unreachable!("vec has exactly N elements"),
+    }
+}
+
+/// `#[serde(with = "...")]` adapter for `Box<[u8; N]>`; encodes exactly like the unboxed
+/// array with `BigArray`, so boxing a field does not change the save-state layout.
+pub mod big_array {
+    use super::boxed_array;
+    use alloc::boxed::Box;
+    use core::fmt;
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+    use serde_big_array::BigArray;
+
+    #[allow(clippy::borrowed_box)]
+    pub fn serialize<S: Serializer, const N: usize>(
+        array: &Box<[u8; N]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        <[u8; N] as BigArray<u8>>::serialize(array, serializer)
+    }
+
+    /// Reads the elements straight into a heap array; going through `[u8; N]` would put the
+    /// whole array (and its copies, in debug builds) on the stack.
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<Box<[u8; N]>, D::Error> {
+        deserializer.deserialize_tuple(N, BoxedArrayVisitor::<N>)
+    }
+
+    struct BoxedArrayVisitor<const N: usize>;
+
+    impl<'de, const N: usize> Visitor<'de> for BoxedArrayVisitor<N> {
+        type Value = Box<[u8; N]>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "an array of {N} bytes")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut array = boxed_array(0u8);
+            for (i, byte) in array.iter_mut().enumerate() {
+                *byte = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+            }
+            Ok(array)
+        }
+    }
+}
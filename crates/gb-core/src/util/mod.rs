@@ -1 +1,5 @@
 pub mod bits;
+pub mod boxed;
+mod crc32;
+
+pub use crc32::crc32;
@@ -186,3 +186,47 @@ fn inc_dec_r8_sets_znh_and_preserves_c() {
     assert_eq!(cpu.b, 0x00);
     assert_flags(&cpu, true, true, false, false);
 }
+
+/// DAA as the GB CPU manual's nibble table describes it: after an addition the correction
+/// depends on C, H and both nibbles of A; after a subtraction only on H and C.
+fn reference_daa(a: u8, n: bool, h: bool, c: bool) -> (u8, bool) {
+    let (hi, lo) = (a >> 4, a & 0x0F);
+    if n {
+        let diff = if h { 0x06 } else { 0x00 } | if c { 0x60 } else { 0x00 };
+        return (a.wrapping_sub(diff), c);
+    }
+    let (diff, carry) = match (c, hi, h, lo) {
+        (false, 0x0..=0x9, false, 0x0..=0x9) => (0x00, false),
+        (false, 0x0..=0x9, true, 0x0..=0x9) => (0x06, false),
+        (false, 0x0..=0x8, _, 0xA..=0xF) => (0x06, false),
+        (false, 0xA..=0xF, false, 0x0..=0x9) => (0x60, true),
+        (false, 0x9..=0xF, _, 0xA..=0xF) => (0x66, true),
+        (false, 0xA..=0xF, true, 0x0..=0x9) => (0x66, true),
+        (true, _, false, 0x0..=0x9) => (0x60, true),
+        (true, _, _, _) => (0x66, true),
+        _ => unreachable!(),
+    };
+    (a.wrapping_add(diff), carry)
+}
+
+#[test]
+fn daa_matches_reference_for_every_input() {
+    let (mut cpu, mut bus) = setup(&[0x27]); // DAA
+    for a in 0..=0xFFu8 {
+        for flags in 0..8u8 {
+            let (n, h, c) = (flags & 4 != 0, flags & 2 != 0, flags & 1 != 0);
+            cpu.pc = 0;
+            cpu.a = a;
+            cpu.set_flag(Flag::Z, false);
+            cpu.set_flag(Flag::N, n);
+            cpu.set_flag(Flag::H, h);
+            cpu.set_flag(Flag::C, c);
+            cpu.step(&mut bus);
+
+            let (expected, carry) = reference_daa(a, n, h, c);
+            let case = format!("A={a:02X} N={n} H={h} C={c}");
+            assert_eq!(cpu.a, expected, "{case}");
+            assert_flags(&cpu, expected == 0, n, false, carry);
+        }
+    }
+}
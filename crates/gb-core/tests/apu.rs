@@ -1,4 +1,4 @@
-use gb_core::bus::Bus;
+use gb_core::bus::{Bus, EmulationMode};
 use gb_core::cartridge::Cartridge;
 
 const NR10: u16 = 0xFF10;
@@ -14,6 +14,13 @@ fn make_bus() -> Bus {
     Bus::new(cart)
 }
 
+fn make_cgb_bus() -> Bus {
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x0143] = 0x80;
+    let cart = Cartridge::from_rom(rom).expect("valid ROM");
+    Bus::new_with_mode(cart, EmulationMode::Cgb)
+}
+
 fn read_mask(addr: u16) -> u8 {
     match addr {
         0xFF10 => 0x80,
@@ -181,3 +188,242 @@ fn apu_long_run_sample_rate_stays_stable() {
     assert_eq!(samples.len(), 192_000);
     assert!(samples.iter().all(|s| s.is_finite()));
 }
+
+/// Wave channel playing a constant full-scale level on both outputs.
+fn start_dc_wave(bus: &mut Bus) {
+    for addr in WAVE_START..WAVE_START + 16 {
+        bus.write8(addr, 0xFF);
+    }
+    bus.write8(0xFF24, 0x77);
+    bus.write8(0xFF25, 0x44);
+    bus.write8(0xFF1A, 0x80);
+    bus.write8(0xFF1C, 0x20);
+    bus.write8(0xFF1E, 0x80);
+}
+
+#[test]
+fn apu_highpass_removes_dc_offset() {
+    let mut bus = make_bus();
+    start_dc_wave(&mut bus);
+
+    // 0.25 seconds.
+    bus.tick(1_048_576);
+
+    let left: Vec<f32> = bus.apu.take_samples().into_iter().step_by(2).collect();
+    // The wave channel emits its empty sample buffer briefly before the first fetch, so
+    // look at the decay from the peak onward.
+    let peak = (0..left.len())
+        .max_by(|&a, &b| left[a].total_cmp(&left[b]))
+        .unwrap();
+    assert!(left[peak] > 0.2, "peak {}", left[peak]);
+    assert!(left[peak..].windows(2).all(|w| w[1] <= w[0]));
+    assert!(left.last().unwrap().abs() < 0.01);
+}
+
+#[test]
+fn apu_highpass_can_be_disabled() {
+    let mut bus = make_bus();
+    bus.apu.set_highpass(false);
+    start_dc_wave(&mut bus);
+
+    bus.tick(1_048_576);
+
+    let samples = bus.apu.take_samples();
+    assert!((samples.last().unwrap() - 0.25).abs() < 1e-6);
+}
+
+#[test]
+fn apu_mono_downmix_centers_hard_panned_channel() {
+    let mut bus = make_bus();
+    bus.apu.set_highpass(false);
+    bus.apu.set_mono(true);
+    start_dc_wave(&mut bus);
+    // Wave channel to the left output only.
+    bus.write8(0xFF25, 0x40);
+
+    bus.tick(1_048_576);
+
+    let samples = bus.apu.take_samples();
+    let (left, right) = (samples[samples.len() - 2], samples[samples.len() - 1]);
+    // Half of the 0.25 the left output alone would carry.
+    assert!((left - 0.125).abs() < 1e-6, "left {left}");
+    assert_eq!(left, right);
+    assert!(samples.chunks_exact(2).all(|f| f[0] == f[1]));
+}
+
+#[test]
+fn nr51_pans_channels_to_the_left_and_right_outputs() {
+    let mut bus = make_bus();
+    bus.apu.set_highpass(false);
+    bus.write8(NR52, 0x80);
+    bus.write8(0xFF24, 0x77);
+    // CH1 to the left only (bit 4), CH3 to the right only (bit 2).
+    bus.write8(0xFF25, 0x14);
+    assert_eq!(
+        bus.apu.channel_routing(),
+        [(true, false), (false, false), (false, true), (false, false)]
+    );
+
+    // CH1: 50% duty square at full volume.
+    bus.write8(0xFF11, 0x80);
+    bus.write8(0xFF12, 0xF0);
+    bus.write8(0xFF13, 0x00);
+    bus.write8(0xFF14, 0x87);
+    // CH3: constant lowest level, a full-scale negative output.
+    for addr in WAVE_START..WAVE_START + 16 {
+        bus.write8(addr, 0x00);
+    }
+    bus.write8(0xFF1A, 0x80);
+    bus.write8(0xFF1C, 0x20);
+    bus.write8(0xFF1E, 0x80);
+
+    bus.tick(1_048_576);
+
+    let samples = bus.apu.take_samples();
+    let frames = &samples[samples.len() / 2..];
+    // Only the square reaches the left: it swings both ways.
+    assert!(frames.chunks_exact(2).any(|f| f[0] > 0.0));
+    assert!(frames.chunks_exact(2).any(|f| f[0] < 0.0));
+    // Only the wave reaches the right: it stays at its negative level.
+    assert!(frames.chunks_exact(2).all(|f| (f[1] + 0.25).abs() < 1e-6));
+}
+
+#[test]
+fn apu_sample_rate_can_be_changed() {
+    let mut bus = make_bus();
+    bus.apu.set_sample_rate(44_100);
+    assert_eq!(bus.apu.sample_rate(), 44_100);
+
+    // 1.0 second at DMG CPU clock.
+    bus.tick(4_194_304);
+
+    let frames = bus.apu.take_samples().len() / 2;
+    assert!((44_099..=44_101).contains(&frames), "{frames} frames");
+}
+
+#[test]
+fn apu_sample_buffer_drops_oldest_samples_on_overflow() {
+    let mut bus = make_bus();
+
+    bus.write8(0xFF24, 0x77);
+    bus.write8(0xFF25, 0x11);
+    bus.write8(0xFF11, 0x80);
+    bus.write8(0xFF12, 0xF0);
+    bus.write8(0xFF13, 0x00);
+    bus.write8(0xFF14, 0x80);
+
+    // One second of tone, then power off so everything after it is silence.
+    bus.tick(4_194_304);
+    assert!(bus.apu.samples_available() > 0);
+    bus.write8(NR52, 0x00);
+
+    // Well over the capacity's worth of silence, without draining.
+    bus.tick(4_194_304 * 9 / 2);
+
+    assert_eq!(
+        bus.apu.samples_available(),
+        bus.apu.sample_buffer_capacity()
+    );
+    let samples = bus.apu.take_samples();
+    assert_eq!(samples.len(), bus.apu.sample_buffer_capacity());
+    assert!(samples.iter().all(|&s| s == 0.0));
+    assert_eq!(bus.apu.samples_available(), 0);
+}
+
+#[test]
+fn apu_sample_buffer_capacity_follows_the_sample_rate() {
+    let mut bus = make_bus();
+    bus.apu.set_sample_rate(96_000);
+    assert_eq!(bus.apu.sample_buffer_capacity(), 96_000 * 2 * 4);
+
+    // One second queued, then a rate whose capacity holds less than that.
+    bus.tick(4_194_304);
+    assert_eq!(bus.apu.samples_available(), 96_000 * 2);
+    bus.apu.set_sample_rate(8_000);
+    assert_eq!(bus.apu.samples_available(), 8_000 * 2 * 4);
+
+    bus.tick(4_194_304);
+    assert_eq!(
+        bus.apu.samples_available(),
+        bus.apu.sample_buffer_capacity()
+    );
+}
+
+#[test]
+fn apu_frame_sequencer_steps_every_8192_cycles() {
+    let mut bus = make_bus();
+    assert_eq!(bus.apu.frame_sequencer_step(), 0);
+    assert_eq!(bus.apu.frame_sequencer_counter(), 0);
+
+    bus.tick(8_192);
+    assert_eq!(bus.apu.frame_sequencer_step(), 1);
+    assert_eq!(bus.apu.frame_sequencer_counter(), 0);
+
+    bus.tick(8_192 * 7 + 100);
+    assert_eq!(bus.apu.frame_sequencer_step(), 0);
+    assert_eq!(bus.apu.frame_sequencer_counter(), 100);
+}
+
+#[test]
+fn apu_power_cycle_rephases_frame_sequencer() {
+    // DMG: power cycling resets the sequencer.
+    let mut bus = make_bus();
+    bus.tick(8_192 * 3 + 500);
+    bus.write8(NR52, 0x00);
+    assert_eq!(bus.apu.frame_sequencer_step(), 0);
+    assert_eq!(bus.apu.frame_sequencer_counter(), 0);
+    bus.tick(1_000);
+    bus.write8(NR52, 0x80);
+    assert_eq!(bus.apu.frame_sequencer_step(), 0);
+    assert_eq!(bus.apu.frame_sequencer_counter(), 0);
+
+    // CGB: the sequencer halts while off, then powering on aligns it to DIV.
+    let mut bus = make_cgb_bus();
+    bus.tick(8_192 * 3 + 500);
+    bus.write8(NR52, 0x00);
+    assert_eq!(bus.apu.frame_sequencer_step(), 3);
+    assert_eq!(bus.apu.frame_sequencer_counter(), 500);
+    bus.write8(0xFF04, 0x00);
+    bus.tick(1_000);
+    assert_eq!(bus.apu.frame_sequencer_counter(), 500);
+    bus.write8(NR52, 0x80);
+    assert_eq!(bus.apu.frame_sequencer_step(), 0);
+    assert_eq!(bus.apu.frame_sequencer_counter(), 1_000);
+}
+
+#[test]
+fn vgm_export_logs_register_writes_with_waits() {
+    let mut bus = make_bus();
+    assert_eq!(bus.apu.export_vgm(), None);
+    bus.apu.set_vgm_capture(true);
+
+    bus.write8(NR52, 0x80);
+    bus.tick(400); // 4.2 samples
+    bus.write8(0xFF12, 0xF3);
+    bus.tick(70_224); // one frame: sample 742 overall
+    bus.write8(0xFF14, 0x87);
+
+    let writes = bus.apu.vgm_capture().unwrap().writes();
+    assert_eq!(writes.len(), 3);
+    assert_eq!((writes[1].addr, writes[1].delta_cycles), (0xFF12, 400));
+
+    let vgm = bus.apu.export_vgm().unwrap();
+    let u32_at = |offset: usize| u32::from_le_bytes(vgm[offset..offset + 4].try_into().unwrap());
+    assert_eq!(&vgm[..4], b"Vgm ");
+    assert_eq!(u32_at(0x04) as usize, vgm.len() - 4);
+    assert_eq!(u32_at(0x08), 0x171);
+    assert_eq!(u32_at(0x18), 742);
+    assert_eq!(u32_at(0x34), 0xCC);
+    assert_eq!(u32_at(0x80), 4_194_304);
+    assert_eq!(
+        &vgm[0x100..],
+        &[
+            0xB3, 0x16, 0x80, // NR52
+            0x73, // wait 4
+            0xB3, 0x02, 0xF3, // NR12
+            0x61, 0xE2, 0x02, // wait 738
+            0xB3, 0x04, 0x87, // NR14
+            0x66,
+        ]
+    );
+}
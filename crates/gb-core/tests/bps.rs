@@ -0,0 +1,157 @@
+use gb_core::bps::{apply_bps, BpsError};
+use gb_core::patch::{apply_patch, PatchError};
+use gb_core::util::crc32;
+
+fn varint(mut value: usize, out: &mut Vec<u8>) {
+    loop {
+        let x = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(0x80 | x);
+            return;
+        }
+        out.push(x);
+        value -= 1;
+    }
+}
+
+fn action(kind: usize, len: usize, out: &mut Vec<u8>) {
+    varint(((len - 1) << 2) | kind, out);
+}
+
+fn relative(delta: isize, out: &mut Vec<u8>) {
+    let sign = usize::from(delta < 0);
+    varint((delta.unsigned_abs() << 1) | sign, out);
+}
+
+fn source_rom() -> Vec<u8> {
+    (0u8..16).collect()
+}
+
+/// SourceRead 4, TargetRead "XY", SourceCopy 4 from offset 8, TargetCopy 3 from offset 4.
+fn build_patch(source: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let target = vec![0, 1, 2, 3, b'X', b'Y', 8, 9, 10, 11, b'X', b'Y', 8];
+
+    let mut patch = b"BPS1".to_vec();
+    varint(source.len(), &mut patch);
+    varint(target.len(), &mut patch);
+    varint(0, &mut patch);
+
+    action(0, 4, &mut patch);
+    action(1, 2, &mut patch);
+    patch.extend_from_slice(b"XY");
+    action(2, 4, &mut patch);
+    relative(8, &mut patch);
+    action(3, 3, &mut patch);
+    relative(4, &mut patch);
+
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(&target).to_le_bytes());
+    let patch_crc = crc32(&patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+
+    (patch, target)
+}
+
+#[test]
+fn crc32_matches_reference_check_value() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+}
+
+#[test]
+fn bps_applies_read_and_copy_actions() {
+    let source = source_rom();
+    let (patch, target) = build_patch(&source);
+
+    assert_eq!(apply_bps(&source, &patch).unwrap(), target);
+}
+
+#[test]
+fn bps_rejects_source_crc_mismatch() {
+    let source = source_rom();
+    let (patch, _) = build_patch(&source);
+
+    let mut other = source.clone();
+    other[0] = 0xFF;
+    assert_eq!(
+        apply_bps(&other, &patch),
+        Err(BpsError::SourceCrcMismatch {
+            expected: crc32(&source),
+            actual: crc32(&other),
+        })
+    );
+}
+
+#[test]
+fn bps_rejects_corrupted_patch() {
+    let source = source_rom();
+    let (mut patch, _) = build_patch(&source);
+    patch[8] ^= 0x01;
+
+    assert!(matches!(
+        apply_bps(&source, &patch),
+        Err(BpsError::PatchCrcMismatch { .. })
+    ));
+}
+
+/// A patch with the given header fields and actions, and valid CRCs for `source`.
+fn patch_with_body(source: &[u8], body: &[u8]) -> Vec<u8> {
+    let mut patch = b"BPS1".to_vec();
+    patch.extend_from_slice(body);
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&0u32.to_le_bytes());
+    let patch_crc = crc32(&patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+    patch
+}
+
+#[test]
+fn bps_rejects_oversized_numbers_without_panicking() {
+    let source = source_rom();
+
+    // A source size with more 7-bit groups than fit in a usize.
+    let mut body = vec![0x7F; 12];
+    body.push(0x80);
+    assert_eq!(
+        apply_bps(&source, &patch_with_body(&source, &body)),
+        Err(BpsError::Malformed(4))
+    );
+
+    // A huge target size is not reserved up front; the patch just runs out of actions.
+    let mut body = Vec::new();
+    varint(source.len(), &mut body);
+    varint(1 << 40, &mut body);
+    varint(0, &mut body);
+    assert!(matches!(
+        apply_bps(&source, &patch_with_body(&source, &body)),
+        Err(BpsError::TargetCrcMismatch { .. })
+    ));
+
+    // An action length that overflows the output offset.
+    let mut body = Vec::new();
+    varint(source.len(), &mut body);
+    varint(source.len(), &mut body);
+    varint(0, &mut body);
+    action(0, 1, &mut body);
+    varint(usize::MAX >> 1 << 2, &mut body);
+    assert!(apply_bps(&source, &patch_with_body(&source, &body)).is_err());
+}
+
+#[test]
+fn apply_patch_detects_format_by_magic() {
+    let source = source_rom();
+    let (patch, target) = build_patch(&source);
+
+    let mut rom = source.clone();
+    apply_patch(&mut rom, &patch).unwrap();
+    assert_eq!(rom, target);
+
+    let mut rom = source.clone();
+    apply_patch(&mut rom, b"PATCH\x00\x00\x01\x00\x01\xAAEOF").unwrap();
+    assert_eq!(rom[1], 0xAA);
+
+    assert_eq!(
+        apply_patch(&mut rom, b"UPS1"),
+        Err(PatchError::UnknownFormat)
+    );
+}
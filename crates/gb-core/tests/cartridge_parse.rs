@@ -53,3 +53,100 @@ fn accepts_mbc2_and_mbc5_cartridge_types() {
     mbc5_rom[0x0149] = 0x03; // 32KB RAM
     assert!(Cartridge::from_rom(mbc5_rom).is_ok());
 }
+
+fn rom_with_title_area(title_area: &[u8], cgb_flag: u8) -> Vec<u8> {
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x0143] = cgb_flag;
+    // A 16-byte DMG title runs into the CGB flag byte.
+    rom[0x0134..0x0134 + title_area.len()].copy_from_slice(title_area);
+    rom
+}
+
+#[test]
+fn title_is_trimmed_at_nul_padding() {
+    let cart =
+        Cartridge::from_rom(rom_with_title_area(b"TETRIS\0\0\0\0\0\0\0\0\0\0", 0x00)).unwrap();
+    assert_eq!(cart.title(), "TETRIS");
+    assert_eq!(cart.header.manufacturer_code, None);
+
+    // DMG titles may use all 16 bytes.
+    let cart = Cartridge::from_rom(rom_with_title_area(b"SIXTEEN CHAR NAM", 0x00)).unwrap();
+    assert_eq!(cart.title(), "SIXTEEN CHAR NAM");
+
+    // On CGB carts the last byte is the CGB flag, not part of the title.
+    let cart = Cartridge::from_rom(rom_with_title_area(b"FIFTEEN CHARS  ", 0x80)).unwrap();
+    assert_eq!(cart.title(), "FIFTEEN CHARS");
+    assert_eq!(cart.header.cgb_flag, 0x80);
+}
+
+#[test]
+fn cgb_manufacturer_code_is_split_from_the_title() {
+    let cart = Cartridge::from_rom(rom_with_title_area(b"POKEMON GLDAAUE", 0xC0)).unwrap();
+    assert_eq!(cart.title(), "POKEMON GLD");
+    assert_eq!(cart.header.manufacturer_code.as_deref(), Some("AAUE"));
+
+    // Lowercase or padded bytes there are still title text.
+    let cart =
+        Cartridge::from_rom(rom_with_title_area(b"ZELDA\0\0\0\0\0\0\0\0\0\0", 0x80)).unwrap();
+    assert_eq!(cart.title(), "ZELDA");
+    assert_eq!(cart.header.manufacturer_code, None);
+}
+
+#[test]
+fn licensee_and_destination_are_parsed() {
+    use gb_core::cartridge::header::Destination;
+
+    let mut rom = rom_with_title_area(b"GAME", 0x00);
+    rom[0x014B] = 0x01;
+    let cart = Cartridge::from_rom(rom.clone()).unwrap();
+    assert_eq!(cart.header.licensee, "01");
+    assert_eq!(cart.header.destination, Destination::Japan);
+
+    rom[0x014B] = 0x33;
+    rom[0x0144..=0x0145].copy_from_slice(b"01");
+    rom[0x014A] = 0x01;
+    let cart = Cartridge::from_rom(rom).unwrap();
+    assert_eq!(cart.header.licensee, "01");
+    assert_eq!(cart.header.destination, Destination::Overseas);
+}
+
+#[test]
+fn mbc1_header_with_a_1mb_rom_warns_about_the_size() {
+    use gb_core::cartridge::header::{CartridgeType, RomSize};
+    use gb_core::cartridge::CartridgeWarning;
+
+    let mut rom = vec![0u8; 0x10_0000];
+    rom[0x0147] = 0x01; // MBC1
+    rom[0x0148] = 0x05; // 1MB
+    let (_, warning) = Cartridge::from_rom_checked(rom.clone()).unwrap();
+    assert_eq!(
+        warning,
+        Some(CartridgeWarning::MbcSizeMismatch {
+            cartridge_type: CartridgeType::Mbc1,
+            rom_size: RomSize::Megabyte1,
+        })
+    );
+
+    rom[0x0147] = 0x19; // MBC5
+    let (_, warning) = Cartridge::from_rom_checked(rom).unwrap();
+    assert_eq!(warning, None);
+}
+
+#[test]
+fn fingerprint_matches_for_identical_roms_and_changes_with_any_byte() {
+    let mut rom = rom_with_title_area(b"POKEMON RED", 0x00);
+    rom[0x4000] = 0x12;
+    let a = Cartridge::from_rom(rom.clone()).unwrap();
+    let b = Cartridge::from_rom(rom.clone()).unwrap();
+    assert_eq!(a.fingerprint(), b.fingerprint());
+
+    // A byte outside the header (e.g. from a patch) still changes the ID.
+    rom[0x4000] = 0x13;
+    let patched = Cartridge::from_rom(rom.clone()).unwrap();
+    assert_ne!(patched.fingerprint(), a.fingerprint());
+
+    rom[0x4000] = 0x12;
+    rom[0x0134] = b'Q';
+    let retitled = Cartridge::from_rom(rom).unwrap();
+    assert_ne!(retitled.fingerprint()[..2], a.fingerprint()[..2]);
+}
@@ -1,5 +1,9 @@
 use gb_core::bus::Bus;
-use gb_core::cartridge::Cartridge;
+use gb_core::cartridge::mbc::{Mbc, MbcEnum};
+use gb_core::cartridge::mbc3::{RtcSaveFormat, BGB_RTC_FOOTER_LEN};
+use gb_core::cartridge::{find_save_trailer, Cartridge, SAVE_TRAILER_MAGIC};
+use gb_core::cpu::Cpu;
+use gb_core::gb::GameBoy;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 
@@ -89,6 +93,186 @@ fn mbc3_battery_save_roundtrip_persists_ram_and_rtc() {
     remove_if_exists(&sav);
 }
 
+#[test]
+fn save_trailer_is_found_without_knowing_the_ram_size() {
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x10; // MBC3 + Timer + RAM + Battery
+    rom[0x0149] = 0x02; // 8KB RAM
+    let mut cart = Cartridge::from_rom(rom).unwrap();
+    // RAM that happens to contain the magic must not confuse the search.
+    cart.ram[0x100..0x105].copy_from_slice(SAVE_TRAILER_MAGIC);
+
+    let data = cart.save_data();
+    let (ram_len, payload_len) = find_save_trailer(&data).unwrap();
+    assert_eq!(ram_len, 0x2000);
+    assert_eq!(
+        ram_len + SAVE_TRAILER_MAGIC.len() + 4 + payload_len,
+        data.len()
+    );
+
+    assert_eq!(find_save_trailer(&cart.ram), None);
+    assert_eq!(find_save_trailer(&data[..data.len() - 1]), None);
+}
+
+#[test]
+fn mbc3_bgb_rtc_footer_roundtrip_preserves_day_carry() {
+    let sav = temp_sav_path("mbc3-bgb");
+    remove_if_exists(&sav);
+
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x10; // MBC3 + Timer + RAM + Battery
+    rom[0x0149] = 0x03; // 32KB RAM
+
+    let cart = Cartridge::from_rom(rom.clone()).unwrap();
+    let mut bus = Bus::new(cart);
+    let MbcEnum::Mbc3(mbc3) = &mut bus.cart.mbc else {
+        panic!("expected MBC3");
+    };
+    mbc3.set_rtc_save_format(RtcSaveFormat::Bgb);
+
+    bus.write8(0x0000, 0x0A);
+    bus.write8(0x4000, 0x00);
+    bus.write8(0xA000, 0x33);
+    for (reg, val) in [
+        (0x08, 59),
+        (0x09, 30),
+        (0x0A, 23),
+        (0x0B, 0x10),
+        (0x0C, 0x81),
+    ] {
+        bus.write8(0x4000, reg);
+        bus.write8(0xA000, val);
+    }
+
+    bus.save_to_path(&sav).unwrap();
+
+    // Footer layout: live sec/min/hour/day_low/day_high as u32 LE, then latched, then time.
+    let data = std::fs::read(&sav).unwrap();
+    assert_eq!(data.len(), 0x8000 + BGB_RTC_FOOTER_LEN);
+    let footer = &data[0x8000..];
+    assert_eq!(&footer[0..4], &59u32.to_le_bytes());
+    assert_eq!(&footer[16..20], &0x81u32.to_le_bytes());
+    assert_eq!(&footer[36..40], &0x81u32.to_le_bytes());
+
+    // A default (native-format) cartridge still loads the BGB footer.
+    let cart2 = Cartridge::from_rom(rom).unwrap();
+    let mut bus2 = Bus::new(cart2);
+    bus2.load_from_path(&sav).unwrap();
+    bus2.write8(0x0000, 0x0A);
+
+    bus2.write8(0x4000, 0x00);
+    assert_eq!(bus2.read8(0xA000), 0x33);
+    for (reg, val) in [(0x08, 59), (0x09, 30), (0x0A, 23), (0x0B, 0x10)] {
+        bus2.write8(0x4000, reg);
+        assert_eq!(bus2.read8(0xA000), val);
+    }
+    bus2.write8(0x4000, 0x0C);
+    assert_eq!(bus2.read8(0xA000) & 0xC1, 0x81);
+
+    remove_if_exists(&sav);
+}
+
+fn read_rtc(bus: &mut Bus) -> [u8; 5] {
+    let mut regs = [0u8; 5];
+    for (i, reg) in (0x08..=0x0C).enumerate() {
+        bus.write8(0x4000, reg);
+        regs[i] = bus.read8(0xA000);
+    }
+    regs
+}
+
+#[test]
+fn mbc3_wallclock_sync_advances_rtc_by_elapsed_time_since_save() {
+    let sav = temp_sav_path("mbc3-wallclock");
+    remove_if_exists(&sav);
+
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x10; // MBC3 + Timer + RAM + Battery
+    rom[0x0149] = 0x03; // 32KB RAM
+
+    // Save written 2d 3h 4m 5s ago with the clock at day 0x1FE, 20:50:10.
+    let elapsed = 2 * 86_400 + 3 * 3_600 + 4 * 60 + 5;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut cart = Cartridge::from_rom(rom.clone()).unwrap();
+    cart.mbc.write_rom(0x0000, 0x0A);
+    for (reg, val) in [
+        (0x08, 10),
+        (0x09, 50),
+        (0x0A, 20),
+        (0x0B, 0xFE),
+        (0x0C, 0x01),
+    ] {
+        cart.mbc.write_rom(0x4000, reg);
+        cart.mbc.write_ram(&mut [], 0xA000, val);
+    }
+    let MbcEnum::Mbc3(mbc3) = &cart.mbc else {
+        panic!("expected MBC3");
+    };
+    let mut data = vec![0u8; 0x8000];
+    data.extend_from_slice(&mbc3.save_bgb_footer(now - elapsed));
+    std::fs::write(&sav, data).unwrap();
+
+    // Without sync the clock resumes where it was saved.
+    let mut bus = Bus::new(Cartridge::from_rom(rom.clone()).unwrap());
+    bus.load_from_path(&sav).unwrap();
+    bus.write8(0x0000, 0x0A);
+    bus.write8(0x6000, 0x00);
+    bus.write8(0x6000, 0x01);
+    assert_eq!(read_rtc(&mut bus), [10, 50, 20, 0xFE, 0x01 | 0x3E]);
+
+    let mut cart = Cartridge::from_rom(rom).unwrap();
+    let MbcEnum::Mbc3(mbc3) = &mut cart.mbc else {
+        panic!("expected MBC3");
+    };
+    mbc3.set_wallclock_sync(true);
+    let mut bus = Bus::new(cart);
+    bus.load_from_path(&sav).unwrap();
+    bus.write8(0x0000, 0x0A);
+    bus.write8(0x6000, 0x00);
+    bus.write8(0x6000, 0x01);
+
+    // 20:50:10 + 3:04:05 = 23:54:15 (allowing one second of test runtime), day 0x1FE + 2
+    // wraps to 0x000 and sets the carry flag.
+    let [sec, min, hour, day_low, day_high] = read_rtc(&mut bus);
+    assert!((15..=16).contains(&sec), "sec = {sec}");
+    assert_eq!([min, hour, day_low], [54, 23, 0x00]);
+    assert_eq!(day_high & 0xC1, 0x80);
+
+    remove_if_exists(&sav);
+}
+
+#[test]
+fn mbc3_wallclock_sync_ignores_a_zero_timestamp() {
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x10; // MBC3 + Timer + RAM + Battery
+    rom[0x0149] = 0x03; // 32KB RAM
+
+    let mut cart = Cartridge::from_rom(rom.clone()).unwrap();
+    cart.mbc.write_rom(0x0000, 0x0A);
+    cart.mbc.write_rom(0x4000, 0x0A); // hours
+    cart.mbc.write_ram(&mut [], 0xA000, 7);
+    let MbcEnum::Mbc3(mbc3) = &cart.mbc else {
+        panic!("expected MBC3");
+    };
+    let mut data = vec![0u8; 0x8000];
+    data.extend_from_slice(&mbc3.save_bgb_footer(0));
+
+    let mut cart = Cartridge::from_rom(rom).unwrap();
+    let MbcEnum::Mbc3(mbc3) = &mut cart.mbc else {
+        panic!("expected MBC3");
+    };
+    mbc3.set_wallclock_sync(true);
+    let mut bus = Bus::new(cart);
+    bus.cart.load_save_data(&data).unwrap();
+    bus.write8(0x0000, 0x0A);
+    bus.write8(0x6000, 0x00);
+    bus.write8(0x6000, 0x01);
+    assert_eq!(read_rtc(&mut bus), [0, 0, 7, 0, 0x3E]);
+}
+
 #[test]
 fn mbc2_battery_save_roundtrip_persists_internal_ram() {
     let sav = temp_sav_path("mbc2");
@@ -114,3 +298,187 @@ fn mbc2_battery_save_roundtrip_persists_internal_ram() {
 
     remove_if_exists(&sav);
 }
+
+#[test]
+fn soft_reset_preserves_cart_ram_and_hard_reset_clears_it() {
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x1B; // MBC5 + RAM + Battery
+    rom[0x0149] = 0x03; // 32KB RAM
+
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut gb = GameBoy::new(Cpu::new(), Bus::new(cart));
+    gb.bus.write8(0x0000, 0x0A);
+    gb.bus.write8(0x4000, 0x02);
+    gb.bus.write8(0xA123, 0x5A);
+    gb.bus.write8(0xC000, 0x77);
+    gb.cpu.pc = 0x1234;
+    gb.step();
+
+    gb.reset(false);
+    assert_eq!(gb.cpu.pc, Cpu::new().pc);
+    assert_eq!(gb.cycles(), 0);
+    // Mapper registers are back at power-on values: RAM disabled, bank 0.
+    assert_eq!(gb.bus.read8(0xA123), 0xFF);
+    gb.bus.write8(0x0000, 0x0A);
+    gb.bus.write8(0x4000, 0x02);
+    assert_eq!(gb.bus.read8(0xA123), 0x5A);
+    assert_eq!(gb.bus.cart.ram[0x4123], 0x5A);
+
+    gb.reset(true);
+    assert!(gb.bus.cart.ram.iter().all(|&b| b == 0));
+    gb.bus.write8(0x0000, 0x0A);
+    gb.bus.write8(0x4000, 0x02);
+    assert_eq!(gb.bus.read8(0xA123), 0x00);
+}
+
+#[test]
+fn reset_keeps_hooks_and_settings() {
+    use gb_core::cpu::cpu::StopMode;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let cart = Cartridge::from_rom(make_banked_rom(4)).unwrap();
+    let mut gb = GameBoy::new(Cpu::new(), Bus::new(cart));
+    let writes = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&writes);
+    gb.bus.on_write = Some(Box::new(move |_, _| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }));
+    gb.set_cpu_clock_multiplier(2);
+    gb.cpu.stop_mode = StopMode::TreatAsNop;
+    gb.bus.ppu.set_fifo_rendering(true);
+    gb.bus.write8(0xC000, 0x77);
+
+    gb.reset(true);
+    assert_eq!(gb.bus.read8(0xC000), 0x00);
+    assert_eq!(gb.cpu_clock_multiplier(), 2);
+    assert_eq!(gb.cpu.stop_mode, StopMode::TreatAsNop);
+    assert!(gb.bus.ppu.fifo_rendering());
+    gb.bus.write8(0xC000, 0x11);
+    assert_eq!(writes.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn soft_reset_keeps_bgb_rtc_format_and_clock() {
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x10; // MBC3 + Timer + RAM + Battery
+    rom[0x0149] = 0x03; // 32KB RAM
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut gb = GameBoy::new(Cpu::new(), Bus::new(cart));
+    let MbcEnum::Mbc3(mbc3) = &mut gb.bus.cart.mbc else {
+        panic!("expected MBC3");
+    };
+    mbc3.set_rtc_save_format(RtcSaveFormat::Bgb);
+    mbc3.set_wallclock_sync(true);
+    gb.bus.write8(0x0000, 0x0A);
+    gb.bus.write8(0x4000, 0x0A); // hours
+    gb.bus.write8(0xA000, 13);
+
+    gb.reset(false);
+    let MbcEnum::Mbc3(mbc3) = &gb.bus.cart.mbc else {
+        panic!("expected MBC3");
+    };
+    assert_eq!(mbc3.rtc_save_format(), RtcSaveFormat::Bgb);
+    assert!(mbc3.wallclock_sync());
+    assert_eq!(gb.bus.cart.save_data().len(), 0x8000 + BGB_RTC_FOOTER_LEN);
+    gb.bus.write8(0x0000, 0x0A);
+    gb.bus.write8(0x4000, 0x0A);
+    assert_eq!(gb.bus.read8(0xA000), 13);
+
+    gb.reset(true);
+    let MbcEnum::Mbc3(mbc3) = &gb.bus.cart.mbc else {
+        panic!("expected MBC3");
+    };
+    assert_eq!(mbc3.rtc_save_format(), RtcSaveFormat::Bgb);
+}
+
+fn mbc1_battery_cart(ram_size: u8) -> Cartridge {
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x03; // MBC1 + RAM + Battery
+    rom[0x0149] = ram_size;
+    Cartridge::from_rom(rom).unwrap()
+}
+
+#[test]
+fn undersized_power_of_two_sav_is_padded_to_header_ram_size() {
+    use gb_core::cartridge::SaveWarning;
+
+    let sav = temp_sav_path("undersized");
+    let mut data = vec![0u8; 0x2000];
+    data[0] = 0x11;
+    data[0x1FFF] = 0x22;
+    std::fs::write(&sav, &data).unwrap();
+
+    let mut cart = mbc1_battery_cart(0x03); // header says 32KB
+    cart.ram.fill(0xEE);
+    cart.load_from_path(&sav).unwrap();
+    remove_if_exists(&sav);
+
+    assert_eq!(cart.ram.len(), 0x8000);
+    assert_eq!((cart.ram[0], cart.ram[0x1FFF]), (0x11, 0x22));
+    assert!(cart.ram[0x2000..].iter().all(|&b| b == 0));
+    assert_eq!(
+        cart.take_save_warning(),
+        Some(SaveWarning::RamSizeMismatch {
+            file_len: 0x2000,
+            header_len: 0x8000,
+        })
+    );
+    assert_eq!(cart.take_save_warning(), None);
+}
+
+#[test]
+fn oversized_power_of_two_sav_is_truncated_and_keeps_its_trailer() {
+    use gb_core::cartridge::SaveWarning;
+
+    let sav = temp_sav_path("oversized");
+    let mut data = vec![0u8; 0x8000];
+    data[0] = 0x33;
+    data[0x1FFF] = 0x44;
+    data[0x2000] = 0x55;
+    std::fs::write(&sav, &data).unwrap();
+
+    let mut cart = mbc1_battery_cart(0x02); // header says 8KB
+    cart.load_from_path(&sav).unwrap();
+    remove_if_exists(&sav);
+    assert_eq!(cart.ram.len(), 0x2000);
+    assert_eq!((cart.ram[0], cart.ram[0x1FFF]), (0x33, 0x44));
+    assert_eq!(
+        cart.take_save_warning(),
+        Some(SaveWarning::RamSizeMismatch {
+            file_len: 0x8000,
+            header_len: 0x2000,
+        })
+    );
+
+    // A matching save loads without a warning.
+    let saved = cart.save_data();
+    let mut reloaded = mbc1_battery_cart(0x02);
+    reloaded.load_save_data(&saved).unwrap();
+    assert_eq!(reloaded.ram, cart.ram);
+    assert_eq!(reloaded.take_save_warning(), None);
+
+    // A GBSV1 trailer after a mismatched RAM image still reaches the MBC.
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x10; // MBC3 + Timer + RAM + Battery
+    rom[0x0149] = 0x03; // 32KB
+    let mut mbc3 = Cartridge::from_rom(rom.clone()).unwrap();
+    mbc3.mbc.write_rom(0x0000, 0x0A); // enable RAM/RTC
+    mbc3.mbc.write_rom(0x4000, 0x0C);
+    mbc3.mbc.write_ram(&mut mbc3.ram, 0xA000, 0x40); // halt the RTC so it can't tick
+    mbc3.mbc.write_rom(0x4000, 0x08);
+    mbc3.mbc.write_ram(&mut mbc3.ram, 0xA000, 42); // RTC seconds
+    let full = mbc3.save_data();
+    let (ram_len, _) = find_save_trailer(&full).unwrap();
+    let mut short = full[..0x2000].to_vec();
+    short.extend_from_slice(&full[ram_len..]);
+
+    let mut restored = Cartridge::from_rom(rom).unwrap();
+    restored.load_save_data(&short).unwrap();
+    assert!(restored.take_save_warning().is_some());
+    restored.mbc.write_rom(0x0000, 0x0A);
+    restored.mbc.write_rom(0x6000, 0x00);
+    restored.mbc.write_rom(0x6000, 0x01); // latch
+    restored.mbc.write_rom(0x4000, 0x08);
+    assert_eq!(restored.mbc.read_ram(&restored.ram, 0xA000), 42);
+}
@@ -1,4 +1,4 @@
-use gb_core::bus::Bus;
+use gb_core::bus::{Bus, CGB_SPEED_SWITCH_STALL_CYCLES};
 use gb_core::cartridge::Cartridge;
 use gb_core::cpu::Cpu;
 
@@ -40,7 +40,7 @@ fn cgb_double_speed_halves_bus_cycles_for_timer_div() {
 
         bus.write8(0xFF4D, 0x01); // KEY1 prepare
         let cycles = cpu.step(&mut bus);
-        assert_eq!(cycles, 8);
+        assert_eq!(cycles, 8 + CGB_SPEED_SWITCH_STALL_CYCLES);
         assert_eq!(bus.read8(0xFF4D) & 0x80, 0x80);
 
         bus.write8(0xFF04, 0x00); // reset DIV after switching speed
@@ -60,6 +60,32 @@ fn cgb_double_speed_halves_bus_cycles_for_timer_div() {
     }
 }
 
+#[test]
+fn cgb_speed_switch_stalls_cpu_while_div_keeps_running() {
+    // STOP 00; STOP 00
+    let cart = Cartridge::from_rom(make_rom(0x80, &[0x10, 0x00, 0x10, 0x00])).unwrap();
+    let mut bus = Bus::new(cart);
+    let mut cpu = Cpu::new();
+
+    // Normal -> double speed: STOP's two fetches run at normal speed, the stall at double speed.
+    bus.write8(0xFF04, 0x00);
+    bus.write8(0xFF4D, 0x01);
+    let cycles = cpu.step(&mut bus);
+    assert_eq!(cycles, 8 + CGB_SPEED_SWITCH_STALL_CYCLES);
+    assert!(!cpu.halted);
+    let base_cycles = 8 + CGB_SPEED_SWITCH_STALL_CYCLES / 2;
+    assert_eq!(bus.read8(0xFF04) as u32, base_cycles / 256);
+
+    // Double -> normal speed: fetches at double speed, the stall at normal speed.
+    bus.write8(0xFF04, 0x00);
+    bus.write8(0xFF4D, 0x01);
+    let cycles = cpu.step(&mut bus);
+    assert_eq!(cycles, 8 + CGB_SPEED_SWITCH_STALL_CYCLES);
+    assert_eq!(bus.read8(0xFF4D) & 0x80, 0x00);
+    let base_cycles = 4 + CGB_SPEED_SWITCH_STALL_CYCLES;
+    assert_eq!(bus.read8(0xFF04) as u32, base_cycles / 256);
+}
+
 #[test]
 fn cgb_double_speed_does_not_speed_up_apu_frame_sequencer() {
     // We observe APU length clocking via NR52 channel enable.
@@ -116,6 +142,8 @@ fn cgb_double_speed_does_not_speed_up_apu_frame_sequencer() {
         cpu.step(&mut bus);
         assert_eq!(bus.read8(0xFF4D) & 0x80, 0x80);
 
+        // The speed-switch stall advances DIV; realign before timing the frame sequencer.
+        bus.write8(0xFF04, 0x00);
         setup_ch1_length_1(&mut bus);
 
         for _ in 0..2048 {
@@ -1,5 +1,6 @@
 use gb_core::bus::Bus;
 use gb_core::cartridge::Cartridge;
+use gb_core::cpu::Cpu;
 
 fn make_rom(cgb_flag: u8) -> Vec<u8> {
     let mut rom = vec![0u8; 0x8000];
@@ -144,6 +145,48 @@ fn gdma_copies_requested_blocks_and_completes() {
     assert_eq!(bus.read8(0xFF55), 0xFF);
 }
 
+#[test]
+fn gdma_stalls_the_cpu_while_timers_keep_running() {
+    let mut rom = make_rom(0x80);
+    rom[0x0100..0x0104].copy_from_slice(&[
+        0x3E, 0x0F, // LD A,$0F
+        0xE0, 0x55, // LDH ($55),A: GDMA, 16 blocks
+    ]);
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut bus = Bus::new(cart);
+    let mut cpu = Cpu::new();
+    cpu.pc = 0x0100;
+
+    bus.write8(0xFF51, 0xC0);
+    bus.write8(0xFF52, 0x00);
+    bus.write8(0xFF53, 0x00);
+    bus.write8(0xFF54, 0x00);
+    bus.write8(0xFF04, 0x00);
+
+    assert_eq!(cpu.step(&mut bus), 8);
+    // 16 blocks at 8 M-cycles each, on top of LDH's 12 cycles.
+    assert_eq!(cpu.step(&mut bus), 12 + 16 * 32);
+    assert_eq!(bus.read8(0xFF55), 0xFF);
+    // DIV advances once per 256 cycles: 8 + 12 + 512 = 532.
+    assert_eq!(bus.read8(0xFF04), 2);
+}
+
+#[test]
+fn gdma_started_outside_the_cpu_does_not_stall_the_next_step() {
+    let cart = Cartridge::from_rom(make_rom(0x80)).unwrap();
+    let mut bus = Bus::new(cart);
+    let mut cpu = Cpu::new();
+
+    for write in [Bus::debug_write8, Bus::write8] {
+        write(&mut bus, 0xFF51, 0xC0);
+        write(&mut bus, 0xFF53, 0x00);
+        write(&mut bus, 0xFF55, 0x0F); // GDMA, 16 blocks
+        assert_eq!(bus.read8(0xFF55), 0xFF);
+        // NOP.
+        assert_eq!(cpu.step(&mut bus), 4);
+    }
+}
+
 #[test]
 fn hdma_transfers_one_block_per_hblank() {
     let cart = Cartridge::from_rom(make_rom(0x80)).unwrap();
@@ -184,6 +227,63 @@ fn hdma_transfers_one_block_per_hblank() {
     assert_eq!(bus.read8(0xFF55), 0xFF);
 }
 
+#[test]
+fn hdma_transfers_one_block_per_hblank_at_double_speed() {
+    let cart = Cartridge::from_rom(make_rom(0x80)).unwrap();
+    let mut bus = Bus::new(cart);
+
+    for i in 0..0x30u16 {
+        bus.write8(0xC400 + i, i as u8 + 1);
+    }
+
+    bus.write8(0xFF4D, 0x01);
+    assert!(bus.try_cgb_speed_switch());
+
+    bus.write8(0xFF51, 0xC4);
+    bus.write8(0xFF52, 0x00);
+    bus.write8(0xFF53, 0x04);
+    bus.write8(0xFF54, 0x00);
+    bus.write8(0xFF55, 0x82); // HDMA, 3 blocks
+
+    // CPU cycles are twice the PPU dots at double speed. HDMA5 counts the remaining blocks
+    // (minus one) and stays readable while VRAM is locked in mode 3.
+    bus.write8(0xFF40, 0x80);
+    bus.tick(2 * (80 + 172));
+    assert_eq!(bus.read8(0xFF55), 0x01);
+
+    // The rest of this HBlank, in small steps, must not copy another block.
+    for _ in 0..(2 * 200 / 4) {
+        bus.tick(4);
+    }
+    assert_eq!(bus.read8(0xFF55), 0x01);
+
+    // From mode 3 of the next line, a single tick spanning its whole HBlank still copies
+    // that HBlank's block.
+    bus.tick(2 * (4 + 88));
+    assert_eq!(bus.read8(0xFF41) & 0x03, 3);
+    assert_eq!(bus.read8(0xFF55), 0x01);
+    bus.tick(2 * 456);
+    assert_eq!(bus.read8(0xFF41) & 0x03, 3);
+    assert_eq!(bus.read8(0xFF55), 0x00);
+
+    // Terminate, switch back to normal speed, and resume the remaining block.
+    bus.write8(0xFF55, 0x00);
+    assert_eq!(bus.read8(0xFF55), 0x80);
+    bus.write8(0xFF4D, 0x01);
+    assert!(bus.try_cgb_speed_switch());
+    bus.tick(456);
+    assert_eq!(bus.read8(0xFF55), 0x80);
+
+    bus.write8(0xFF55, 0x80);
+    bus.tick(456);
+    assert_eq!(bus.read8(0xFF55), 0xFF);
+
+    bus.write8(0xFF40, 0x00);
+    for i in 0..0x30u16 {
+        assert_eq!(bus.read8(0x8400 + i), i as u8 + 1);
+    }
+}
+
 #[test]
 fn hdma_can_be_terminated_by_writing_bit7_clear() {
     let cart = Cartridge::from_rom(make_rom(0x80)).unwrap();
@@ -1,4 +1,4 @@
-use gb_core::bus::Bus;
+use gb_core::bus::{Bus, EmulationMode, CGB_SPEED_SWITCH_STALL_CYCLES};
 use gb_core::cartridge::header::CgbSupport;
 use gb_core::cartridge::Cartridge;
 use gb_core::cpu::Cpu;
@@ -62,7 +62,7 @@ fn stop_switches_cpu_speed_only_when_key1_prepare_is_set() {
     bus.write8(0xFF4D, 0x01);
 
     let cycles = cpu.step(&mut bus);
-    assert_eq!(cycles, 8);
+    assert_eq!(cycles, 8 + CGB_SPEED_SWITCH_STALL_CYCLES);
     assert_eq!(cpu.pc, 2);
     assert!(!cpu.halted);
 
@@ -97,6 +97,39 @@ fn dmg_rom_does_not_expose_cgb_speed_switch_side_effects() {
     bus.write8(0xFF4D, 0x01);
 
     cpu.step(&mut bus);
-    assert!(cpu.halted);
+    assert!(bus.is_stopped());
     assert_eq!(bus.read8(0xFF4D), 0xFF);
 }
+
+#[test]
+fn cgb_compatible_rom_can_be_forced_to_dmg_mode() {
+    let cart = Cartridge::from_rom(make_rom(0x80, &[])).unwrap();
+    let mut bus = Bus::new_with_mode(cart, EmulationMode::Dmg);
+
+    assert_eq!(bus.mode, EmulationMode::Dmg);
+    bus.write8(0xFF4F, 0x01);
+    assert_eq!(bus.read8(0xFF4F), 0xFF);
+}
+
+#[test]
+fn header_mode_is_used_by_default() {
+    let cart = Cartridge::from_rom(make_rom(0x80, &[])).unwrap();
+    assert_eq!(EmulationMode::for_cartridge(&cart), EmulationMode::Cgb);
+
+    let bus = Bus::new(cart);
+    assert_eq!(bus.mode, EmulationMode::Cgb);
+}
+
+#[test]
+fn mode_override_parses_auto_dmg_and_cgb() {
+    assert_eq!(EmulationMode::parse_override("auto").unwrap(), None);
+    assert_eq!(
+        EmulationMode::parse_override("dmg").unwrap(),
+        Some(EmulationMode::Dmg)
+    );
+    assert_eq!(
+        EmulationMode::parse_override("CGB").unwrap(),
+        Some(EmulationMode::Cgb)
+    );
+    assert!(EmulationMode::parse_override("gba").is_err());
+}
@@ -1,4 +1,4 @@
-use gb_core::bus::Bus;
+use gb_core::bus::{Bus, EmulationMode};
 use gb_core::cartridge::Cartridge;
 
 fn make_rom(cgb_flag: u8) -> Vec<u8> {
@@ -363,3 +363,121 @@ fn cgb_lcdc_bit0_zero_ignores_priorities_but_bg_remains_visible() {
     // x=8: BG color 1 (green)
     assert_eq!(bus.ppu.framebuffer()[8], 0xFF00_FF00);
 }
+
+#[test]
+fn dmg_rom_in_cgb_mode_renders_with_title_colorization_palette() {
+    let mut rom = make_rom(0x00);
+    rom[0x0134..0x013F].copy_from_slice(b"POKEMON RED");
+    rom[0x014B] = 0x01; // Nintendo licensee
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut bus = Bus::new_with_mode(cart, EmulationMode::Cgb);
+
+    // BG palette 0 color 1 comes from the colorization table (0xFF8584 -> BGR15 0x4A1F).
+    bus.write8(0xFF68, 0x02);
+    assert_eq!(bus.read8(0xFF69), 0x1F);
+
+    // Tile 1 row 0 => color 1, mapped through BGP as on DMG.
+    bus.vram[0x1800] = 1;
+    bus.vram[16] = 0xFF;
+    bus.vram[17] = 0x00;
+    bus.write8(0xFF47, 0xE4);
+    bus.write8(0xFF40, 0x91);
+
+    bus.tick(0);
+    bus.tick(252);
+
+    assert_eq!(bus.ppu.framebuffer()[0], 0xFFFF_8484);
+}
+
+#[test]
+fn boot_button_combo_selects_alternate_colorization_palette() {
+    use gb_core::cpu::Cpu;
+    use gb_core::gb::GameBoy;
+    use gb_core::input::Button;
+
+    let bg_color1_lo = |gb: &mut GameBoy| {
+        gb.bus.write8(0xFF68, 0x02);
+        gb.bus.read8(0xFF69)
+    };
+    let cart = Cartridge::from_rom(make_rom(0x00)).unwrap();
+    let mut gb = GameBoy::new(Cpu::new(), Bus::new_with_mode(cart, EmulationMode::Cgb));
+
+    // Left + A: dark blue (0x8C8CDE -> BGR15 0x6E31).
+    assert!(gb.select_boot_palette(Button::Left.mask() | Button::A.mask()));
+    assert_eq!(bg_color1_lo(&mut gb), 0x31);
+    assert!(!gb.select_boot_palette(Button::A.mask()));
+    assert_eq!(bg_color1_lo(&mut gb), 0x31);
+
+    // Held as the first second ends: Up + B, dark brown (0xCE9C84 -> BGR15 0x4279).
+    gb.bus.write8(0xFF40, 0x91);
+    gb.bus
+        .set_joypad_state(Button::Up.mask() | Button::B.mask());
+    while gb.frames() < 59 {
+        gb.run_frame();
+    }
+    assert_eq!(bg_color1_lo(&mut gb), 0x31);
+    gb.run_frame();
+    assert_eq!(bg_color1_lo(&mut gb), 0x79);
+
+    // Presses after that are the game's.
+    gb.bus
+        .set_joypad_state(Button::Left.mask() | Button::A.mask());
+    gb.run_frame();
+    assert_eq!(bg_color1_lo(&mut gb), 0x79);
+
+    // CGB games set their own palettes.
+    let cart = Cartridge::from_rom(make_rom(0x80)).unwrap();
+    let mut gb = GameBoy::new(Cpu::new(), Bus::new(cart));
+    assert!(!gb.select_boot_palette(Button::Up.mask()));
+}
+
+#[test]
+fn opri_register_is_gated_in_dmg_mode() {
+    let mut cgb = setup_cgb_bus();
+    assert_eq!(cgb.read8(0xFF6C), 0xFE);
+    cgb.write8(0xFF6C, 0xFF);
+    assert_eq!(cgb.read8(0xFF6C), 0xFF);
+
+    let mut dmg = setup_dmg_bus();
+    dmg.write8(0xFF6C, 0x00);
+    assert_eq!(dmg.read8(0xFF6C), 0xFF);
+}
+
+#[test]
+fn opri_selects_between_oam_order_and_x_coordinate_priority() {
+    fn render_overlap(opri: u8) -> u32 {
+        let mut bus = setup_cgb_bus();
+
+        // Tile 1 row 0 => color 1 across row.
+        bus.vram[16] = 0xFF;
+        bus.vram[17] = 0x00;
+
+        // Sprite 0 at screen x=4, palette 1 => green.
+        bus.oam[0] = 16;
+        bus.oam[1] = 12;
+        bus.oam[2] = 1;
+        bus.oam[3] = 0x01;
+
+        // Sprite 1 at screen x=0 (smaller X, later in OAM), palette 2 => blue.
+        bus.oam[4] = 16;
+        bus.oam[5] = 8;
+        bus.oam[6] = 1;
+        bus.oam[7] = 0x02;
+
+        write_obj_palette_color(&mut bus, 1, 1, 0x03E0);
+        write_obj_palette_color(&mut bus, 2, 1, 0x7C00);
+        bus.write8(0xFF6C, opri);
+
+        bus.write8(0xFF40, 0x93);
+
+        bus.tick(0);
+        bus.tick(252);
+
+        bus.ppu.framebuffer()[4]
+    }
+
+    // OPRI=0: OAM order, sprite 0 wins.
+    assert_eq!(render_overlap(0x00), 0xFF00_FF00);
+    // OPRI=1: X-coordinate priority, sprite 1 wins.
+    assert_eq!(render_overlap(0x01), 0xFF00_00FF);
+}
@@ -1,7 +1,8 @@
 use gb_core::bus::Bus;
 use gb_core::cartridge::Cartridge;
-use gb_core::cpu::cpu::Flag;
+use gb_core::cpu::cpu::{Flag, IllegalOpcodeMode, StopMode};
 use gb_core::cpu::Cpu;
+use gb_core::input::Button;
 
 fn make_rom(program: &[u8]) -> Vec<u8> {
     let mut rom = vec![0u8; 0x8000];
@@ -82,6 +83,76 @@ fn pending_interrupt_after_ei_is_serviced_on_third_step() {
     assert_eq!(bus.read8(0xFFFD), 0x00);
 }
 
+#[test]
+fn reti_enables_ime_without_the_ei_delay() {
+    // RETI ; with 0x0100 (NOPs) as the return address.
+    let (mut cpu, mut bus) = setup(&[0xD9]);
+    cpu.sp = 0xFFFC;
+    bus.write8(0xFFFC, 0x00);
+    bus.write8(0xFFFD, 0x01);
+    bus.ie = 0x01;
+    bus.iflag = 0x01;
+
+    let cycles = cpu.step(&mut bus);
+    assert_eq!(cycles, 16);
+    assert_eq!(cpu.pc, 0x0100);
+    assert!(cpu.ime);
+
+    // Unlike EI, no instruction runs at the return address before the interrupt is taken.
+    let cycles = cpu.step(&mut bus);
+    assert_eq!(cycles, 20);
+    assert_eq!(cpu.pc, 0x0040);
+    assert_eq!(bus.read8(0xFFFC), 0x00);
+    assert_eq!(bus.read8(0xFFFD), 0x01);
+}
+
+#[test]
+fn ei_then_ret_services_pending_interrupt_at_the_return_address() {
+    // EI ; RET ; with 0x0200 as the return address.
+    let (mut cpu, mut bus) = setup(&[0xFB, 0xC9]);
+    cpu.sp = 0xFFFC;
+    bus.write8(0xFFFC, 0x00);
+    bus.write8(0xFFFD, 0x02);
+    bus.ie = 0x01;
+    bus.iflag = 0x01;
+
+    cpu.step(&mut bus);
+    assert!(!cpu.ime);
+    // RET still runs with interrupts disabled; IME turns on once it completes.
+    assert_eq!(cpu.step(&mut bus), 16);
+    assert_eq!(cpu.pc, 0x0200);
+    assert!(cpu.ime);
+
+    assert_eq!(cpu.step(&mut bus), 20);
+    assert_eq!(cpu.pc, 0x0040);
+    assert_eq!(bus.read8(0xFFFC), 0x00);
+    assert_eq!(bus.read8(0xFFFD), 0x02);
+}
+
+#[test]
+fn ei_then_halt_with_pending_interrupt_returns_to_the_halt() {
+    let (mut cpu, mut bus) = setup(&[0xFB, 0x76]); // EI ; HALT
+    cpu.sp = 0xFFFE;
+    bus.ie = 0x01;
+    bus.iflag = 0x01;
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    assert!(!cpu.halted);
+    assert!(cpu.ime);
+
+    assert_eq!(cpu.step(&mut bus), 20);
+    assert_eq!(cpu.pc, 0x0040);
+    // The pushed return address is the HALT, which runs again after the handler.
+    assert_eq!(bus.read8(0xFFFC), 0x01);
+    assert_eq!(bus.read8(0xFFFD), 0x00);
+    assert!(!cpu.halt_bug);
+
+    // The handler's first fetch is not affected by the bug.
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x0041);
+}
+
 #[test]
 fn halt_without_pending_interrupt_stays_halted() {
     let (mut cpu, mut bus) = setup(&[]);
@@ -246,6 +317,38 @@ fn cb_rlc_and_bit_hl_update_flags_and_cycles() {
     assert_flags(&cpu, true, false, true, true);
 }
 
+#[test]
+fn cb_set_and_swap_hl_are_read_modify_write() {
+    // SET 0,(HL)
+    let (mut cpu, mut bus) = setup(&[0xCB, 0xC6]);
+    cpu.set_hl(0xC000);
+    bus.write8(0xC000, 0xF0);
+    assert_eq!(cpu.step(&mut bus), 16);
+    assert_eq!(bus.read8(0xC000), 0xF1);
+
+    // SWAP (HL)
+    let (mut cpu, mut bus) = setup(&[0xCB, 0x36]);
+    cpu.set_hl(0xC000);
+    bus.write8(0xC000, 0x12);
+    assert_eq!(cpu.step(&mut bus), 16);
+    assert_eq!(bus.read8(0xC000), 0x21);
+    assert_flags(&cpu, false, false, false, false);
+}
+
+#[test]
+fn cb_opcode_cycles_match_operand_kind() {
+    for op in 0..=0xFFu8 {
+        let (mut cpu, mut bus) = setup(&[0xCB, op]);
+        cpu.set_hl(0xC000);
+        let expected = match (op & 0x07, op) {
+            (6, 0x40..=0x7F) => 12, // BIT b,(HL) only reads
+            (6, _) => 16,           // rotates/shifts/SWAP/RES/SET (HL) read and write back
+            _ => 8,
+        };
+        assert_eq!(cpu.step(&mut bus), expected, "CB {op:02X}");
+    }
+}
+
 #[test]
 fn halt_bug_duplicates_next_opcode_fetch_when_ime_off_and_interrupt_pending() {
     // HALT ; NOP ; NOP
@@ -324,8 +427,28 @@ fn stop_consumes_padding_byte_and_accounts_full_cycles() {
 
     let cycles = cpu.step(&mut bus);
     assert_eq!(cycles, 8);
-    assert!(cpu.halted);
+    assert!(bus.is_stopped());
+    assert_eq!(cpu.pc, 2);
+}
+
+#[test]
+fn stop_mode_hardware_stops_and_treat_as_nop_continues() {
+    // STOP 0 ; INC A
+    let (mut cpu, mut bus) = setup(&[0x10, 0x00, 0x3C]);
+    assert_eq!(cpu.stop_mode, StopMode::Hardware);
+    cpu.step(&mut bus);
+    assert!(bus.is_stopped());
+    cpu.step(&mut bus);
+    assert_eq!(cpu.a, 0);
+
+    let (mut cpu, mut bus) = setup(&[0x10, 0x00, 0x3C]);
+    cpu.stop_mode = StopMode::TreatAsNop;
+    assert_eq!(cpu.step(&mut bus), 8);
+    assert!(!bus.is_stopped());
+    assert!(!cpu.halted);
     assert_eq!(cpu.pc, 2);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.a, 1);
 }
 
 #[test]
@@ -342,3 +465,295 @@ fn cpu_step_advances_timer_without_external_bus_tick() {
 
     assert_eq!(bus.read8(0xFF05), 0x01);
 }
+
+#[test]
+fn dmg_stop_blanks_lcd_and_resumes_on_selected_button_press() {
+    let (mut cpu, mut bus) = setup(&[0x10, 0x00, 0x00]); // STOP 00 ; NOP
+
+    // Render a black BG line before entering STOP.
+    bus.vram[0] = 0xFF;
+    bus.vram[1] = 0xFF;
+    bus.write8(0xFF47, 0xE4);
+    bus.write8(0xFF40, 0x91);
+    bus.tick(0);
+    bus.tick(252);
+    assert_eq!(bus.ppu.framebuffer()[0], 0xFF00_0000);
+
+    let cycles = cpu.step(&mut bus);
+    assert_eq!(cycles, 8);
+    assert_eq!(cpu.pc, 2);
+    assert!(bus.is_stopped());
+    assert_eq!(bus.ppu.framebuffer()[0], 0xFFFF_FFFF);
+
+    // Stopped: the CPU stays put and the system clock (DIV) is frozen.
+    bus.write8(0xFF00, 0x10); // select action buttons
+    for _ in 0..100 {
+        assert_eq!(cpu.step(&mut bus), 4);
+    }
+    assert_eq!(cpu.pc, 2);
+    assert_eq!(bus.read8(0xFF04), 0x00);
+
+    // A direction press is not on the selected line.
+    bus.set_joypad_button(Button::Right, true);
+    cpu.step(&mut bus);
+    assert!(bus.is_stopped());
+
+    bus.set_joypad_button(Button::A, true);
+    let cycles = cpu.step(&mut bus);
+    assert_eq!(cycles, 4);
+    assert_eq!(cpu.pc, 3);
+    assert!(!bus.is_stopped());
+}
+
+#[test]
+fn gameboy_counts_cycles_and_frames() {
+    use gb_core::gb::GameBoy;
+
+    // NOP ; LD A, d8 ; INC A ; JP a16 (back to 0)
+    let (cpu, bus) = setup(&[0x00, 0x3E, 0x01, 0x3C, 0xC3, 0x00, 0x00]);
+    let mut gb = GameBoy::new(cpu, bus);
+    assert_eq!((gb.cycles(), gb.frames()), (0, 0));
+
+    let per_loop = 4 + 8 + 4 + 16;
+    for _ in 0..4 * 10 {
+        gb.step();
+    }
+    assert_eq!(gb.cycles(), 10 * per_loop);
+    assert_eq!(gb.frames(), 0);
+
+    gb.bus.write8(0xFF40, 0x91);
+    gb.run_frame();
+    gb.run_frame();
+    assert_eq!(gb.frames(), 2);
+    assert!(gb.cycles() > 10 * per_loop);
+}
+
+#[test]
+fn run_to_vblank_lets_input_change_partway_through_the_frame() {
+    use gb_core::gb::GameBoy;
+
+    // LD A, 0x10 ; LDH (00), A (select the action buttons)
+    // loop: LDH A, (00) ; LD (C000), A ; JP loop
+    let program = [
+        0x3E, 0x10, 0xE0, 0x00, 0xF0, 0x00, 0xEA, 0x00, 0xC0, 0xC3, 0x04, 0x00,
+    ];
+    let (cpu, bus) = setup(&program);
+    let mut gb = GameBoy::new(cpu, bus);
+    gb.bus.write8(0xFF40, 0x91);
+
+    let mut lines = Vec::new();
+    let mut seen = Vec::new();
+    gb.run_to_vblank(|bus, ly| {
+        lines.push(ly);
+        match ly {
+            72 => bus.set_joypad_button(Button::A, true),
+            10 | 100 => seen.push(bus.read8(0xC000) & 0x0F),
+            _ => {}
+        }
+    });
+    assert_eq!(lines, (1..=144).collect::<Vec<u8>>());
+    // The game read A as pressed (bit 0 low) well before VBlank.
+    assert_eq!(seen, [0x0F, 0x0E]);
+    assert_eq!(gb.frames(), 1);
+    assert!(!gb.bus.ppu.frame_ready());
+}
+
+#[test]
+fn vblank_callback_fires_once_per_frame_with_the_finished_frame() {
+    use gb_core::gb::GameBoy;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    // Zero-filled ROM: a run of NOPs.
+    let (cpu, bus) = setup(&[]);
+    let mut gb = GameBoy::new(cpu, bus);
+    gb.bus.write8(0xFF40, 0x91);
+
+    let calls = Arc::new(AtomicU64::new(0));
+    let counter = Arc::clone(&calls);
+    gb.on_vblank(Box::new(move |fb| {
+        assert_eq!(fb.len(), 160 * 144);
+        counter.fetch_add(1, Ordering::Relaxed);
+    }));
+    for _ in 0..5 {
+        gb.run_frame();
+        // Fired at the LY 144 transition, before `run_frame` returned.
+        assert_eq!(gb.bus.ppu.current_ly(), 144);
+        assert_eq!(calls.load(Ordering::Relaxed), gb.frames());
+    }
+    assert_eq!(calls.load(Ordering::Relaxed), 5);
+
+    gb.clear_vblank_callback();
+    gb.run_frame();
+    assert_eq!(calls.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn run_to_next_scanline_advances_exactly_one_ly() {
+    use gb_core::gb::GameBoy;
+
+    // Zero-filled ROM: a run of NOPs.
+    let (cpu, bus) = setup(&[]);
+    let mut gb = GameBoy::new(cpu, bus);
+    gb.bus.write8(0xFF40, 0x91);
+    gb.step();
+
+    for expected in 1..=153 {
+        gb.run_to_next_scanline();
+        assert_eq!(gb.bus.ppu.current_ly(), expected);
+        // Stops on the first instruction of the new line.
+        assert!(gb.bus.ppu.current_dots() < 4, "ly {expected}");
+    }
+    assert_eq!(gb.frames(), 1);
+    assert!(!gb.bus.ppu.frame_ready());
+
+    gb.run_to_next_scanline();
+    assert_eq!(gb.bus.ppu.current_ly(), 0);
+
+    // LCD off: LY stays put, but the call still returns.
+    gb.bus.write8(0xFF40, 0x11);
+    let cycles = gb.cycles();
+    gb.run_to_next_scanline();
+    assert_eq!(gb.bus.ppu.current_ly(), 0);
+    assert_eq!(gb.cycles() - cycles, 456);
+}
+
+#[test]
+fn cpu_clock_multiplier_slows_peripherals_per_instruction() {
+    use gb_core::gb::GameBoy;
+
+    let div_advance = |multiplier: u32| {
+        // Zero-filled ROM: a run of NOPs.
+        let (cpu, bus) = setup(&[]);
+        let mut gb = GameBoy::new(cpu, bus);
+        gb.set_cpu_clock_multiplier(multiplier);
+        let start = gb.bus.read8(0xFF04);
+        for _ in 0..1024 {
+            gb.step();
+        }
+        assert_eq!(gb.cycles(), 4096);
+        gb.bus.read8(0xFF04).wrapping_sub(start)
+    };
+
+    // DIV ticks every 256 system cycles.
+    assert_eq!(div_advance(1), 16);
+    assert_eq!(div_advance(2), 8);
+    assert_eq!(div_advance(0), 16);
+}
+
+#[test]
+fn illegal_opcode_nop_mode_continues() {
+    let (mut cpu, mut bus) = setup(&[0xD3, 0x3C]); // illegal ; INC A
+    assert_eq!(cpu.illegal_opcode_mode, IllegalOpcodeMode::Nop);
+
+    assert_eq!(cpu.step(&mut bus), 4);
+    assert_eq!(cpu.pc, 1);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.a, 1);
+}
+
+#[test]
+fn illegal_opcode_lockup_mode_hangs_forever() {
+    let (mut cpu, mut bus) = setup(&[0xD3, 0x3C]);
+    cpu.illegal_opcode_mode = IllegalOpcodeMode::Lockup;
+
+    assert_eq!(cpu.step(&mut bus), 4);
+    assert!(cpu.locked_up);
+
+    // Not even an enabled, pending interrupt gets it going again.
+    cpu.ime = true;
+    bus.ie = 0x01;
+    bus.iflag = 0x01;
+    for _ in 0..10 {
+        assert_eq!(cpu.step(&mut bus), 4);
+    }
+    assert_eq!(cpu.pc, 1);
+    assert_eq!(cpu.a, 0);
+}
+
+#[test]
+#[should_panic(expected = "illegal opcode 0xD3 at 0x0000")]
+fn illegal_opcode_panic_mode_panics() {
+    let (mut cpu, mut bus) = setup(&[0xD3]);
+    cpu.illegal_opcode_mode = IllegalOpcodeMode::Panic;
+    cpu.step(&mut bus);
+}
+
+#[test]
+fn opcode_profiling_counts_executed_instructions() {
+    // NOP ; INC A ; SWAP A ; NOP ; JR -7
+    let (mut cpu, mut bus) = setup(&[0x00, 0x3C, 0xCB, 0x37, 0x00, 0x18, 0xF9]);
+    assert_eq!(cpu.take_opcode_histogram(), None);
+
+    cpu.set_opcode_profiling(true);
+    for _ in 0..5 * 3 {
+        cpu.step(&mut bus);
+    }
+
+    let hist = cpu.take_opcode_histogram().unwrap();
+    assert_eq!(hist.base[0x00], 6);
+    assert_eq!(hist.base[0x3C], 3);
+    assert_eq!(hist.base[0xCB], 3);
+    assert_eq!(hist.cb[0x37], 3);
+    assert_eq!(hist.base[0x18], 3);
+    assert_eq!(hist.base.iter().sum::<u64>(), 15);
+    assert_eq!(hist.top(2), vec![(0x00, 6), (0x18, 3)]);
+    assert!(hist.top(10).contains(&(0xCB37, 3)));
+
+    // Taking resets the counts but keeps profiling on.
+    cpu.step(&mut bus);
+    assert_eq!(cpu.take_opcode_histogram().unwrap().base[0x00], 1);
+
+    cpu.set_opcode_profiling(false);
+    assert_eq!(cpu.take_opcode_histogram(), None);
+}
+
+#[test]
+fn call_stack_tracks_nested_calls_and_resyncs_after_sp_reload() {
+    use gb_core::cpu::cpu::StackFrame;
+
+    let mut program = vec![0u8; 0x40];
+    program[0x00..0x06].copy_from_slice(&[0x31, 0xFE, 0xFF, 0xCD, 0x10, 0x00]); // LD SP,FFFE ; CALL 0010
+    program[0x10..0x14].copy_from_slice(&[0xCD, 0x20, 0x00, 0xC9]); // CALL 0020 ; RET
+    program[0x20..0x22].copy_from_slice(&[0x00, 0xC9]); // NOP ; RET
+    program[0x30..0x36].copy_from_slice(&[0x31, 0xFE, 0xFF, 0xCD, 0x20, 0x00]); // LD SP,FFFE ; CALL 0020
+    let (mut cpu, mut bus) = setup(&program);
+
+    cpu.step(&mut bus);
+    assert!(cpu.call_stack().is_empty(), "tracking is off by default");
+    cpu.set_call_stack_tracking(true);
+
+    cpu.step(&mut bus); // CALL 0010
+    cpu.step(&mut bus); // CALL 0020
+    assert_eq!(cpu.pc, 0x0020);
+    assert_eq!(cpu.call_stack().len(), 2);
+    assert_eq!(
+        cpu.call_stack().last(),
+        Some(&StackFrame {
+            caller_pc: 0x0010,
+            return_addr: 0x0013,
+            sp: 0xFFFA,
+        })
+    );
+    assert_eq!(cpu.call_stack()[0].caller_pc, 0x0003);
+
+    cpu.step(&mut bus); // NOP
+    cpu.step(&mut bus); // RET
+    assert_eq!(cpu.pc, 0x0013);
+    assert_eq!(cpu.call_stack().len(), 1);
+    assert_eq!(cpu.call_stack()[0].return_addr, 0x0006);
+    cpu.step(&mut bus); // RET
+    assert_eq!(cpu.pc, 0x0006);
+    assert!(cpu.call_stack().is_empty());
+
+    // Reloading SP abandons the open frames; the next call replaces them.
+    cpu.pc = 0x0003;
+    cpu.step(&mut bus); // CALL 0010
+    cpu.step(&mut bus); // CALL 0020
+    assert_eq!(cpu.call_stack().len(), 2);
+    cpu.pc = 0x0030;
+    cpu.step(&mut bus); // LD SP,FFFE
+    cpu.step(&mut bus); // CALL 0020
+    assert_eq!(cpu.call_stack().len(), 1);
+    assert_eq!(cpu.call_stack()[0].caller_pc, 0x0033);
+}
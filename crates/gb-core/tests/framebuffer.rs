@@ -0,0 +1,45 @@
+use gb_core::ppu::{framebuffer_to_rgba8888, FRAMEBUFFER_LEN};
+
+/// Straightforward per-channel shuffle the optimized conversion must match.
+fn reference_rgba8888(fb: &[u32], out: &mut [u8]) {
+    for (px, chunk) in fb.iter().zip(out.chunks_exact_mut(4)) {
+        chunk[0] = (px >> 16) as u8;
+        chunk[1] = (px >> 8) as u8;
+        chunk[2] = *px as u8;
+        chunk[3] = (px >> 24) as u8;
+    }
+}
+
+#[test]
+fn framebuffer_argb_to_rgba_conversion_is_stable() {
+    let mut fb = [0u32; FRAMEBUFFER_LEN];
+    fb[0] = 0xFF00_0000; // opaque black
+    fb[1] = 0x1122_3344; // A,R,G,B
+
+    let mut bytes = vec![0u8; FRAMEBUFFER_LEN * 4];
+    framebuffer_to_rgba8888(&fb, &mut bytes);
+
+    assert_eq!(&bytes[0..4], &[0x00, 0x00, 0x00, 0xFF]);
+    assert_eq!(&bytes[4..8], &[0x22, 0x33, 0x44, 0x11]);
+}
+
+#[test]
+fn framebuffer_conversion_matches_reference_loop() {
+    let mut fb = [0u32; FRAMEBUFFER_LEN];
+    let mut x = 0x1234_5678u32;
+    for px in fb.iter_mut() {
+        // xorshift32 for varied pixel values.
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        *px = x;
+    }
+    fb[1] = 0x1122_3344;
+
+    let mut expected = vec![0u8; FRAMEBUFFER_LEN * 4];
+    reference_rgba8888(&fb, &mut expected);
+    let mut actual = vec![0u8; FRAMEBUFFER_LEN * 4];
+    framebuffer_to_rgba8888(&fb, &mut actual);
+
+    assert_eq!(actual, expected);
+}
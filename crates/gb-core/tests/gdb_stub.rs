@@ -0,0 +1,129 @@
+#![cfg(feature = "gdb")]
+
+use gb_core::bus::Bus;
+use gb_core::cartridge::Cartridge;
+use gb_core::cpu::Cpu;
+use gb_core::debug::gdb::{GdbStub, SessionEnd};
+use gb_core::gb::GameBoy;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+fn make_gameboy(program: &[u8]) -> GameBoy {
+    let mut rom = vec![0u8; 0x8000];
+    rom[..program.len()].copy_from_slice(program);
+    let cart = Cartridge::from_rom(rom).unwrap();
+    GameBoy::new(Cpu::new(), Bus::new(cart))
+}
+
+/// Sends one packet and returns the stub's reply payload, acking it like gdb does.
+fn request(client: &mut TcpStream, payload: &str) -> String {
+    let sum = payload.bytes().fold(0u8, |s, b| s.wrapping_add(b));
+    write!(client, "${payload}#{sum:02x}").unwrap();
+
+    let mut byte = [0u8; 1];
+    client.read_exact(&mut byte).unwrap();
+    assert_eq!(byte[0], b'+', "packet {payload:?} was not acked");
+    client.read_exact(&mut byte).unwrap();
+    assert_eq!(byte[0], b'$');
+
+    let mut reply = Vec::new();
+    loop {
+        client.read_exact(&mut byte).unwrap();
+        if byte[0] == b'#' {
+            break;
+        }
+        reply.push(byte[0]);
+    }
+    let mut sum = [0u8; 2];
+    client.read_exact(&mut sum).unwrap();
+    let expected = reply.iter().fold(0u8, |s, &b| s.wrapping_add(b));
+    assert_eq!(
+        std::str::from_utf8(&sum).unwrap(),
+        format!("{expected:02x}")
+    );
+    client.write_all(b"+").unwrap();
+    String::from_utf8(reply).unwrap()
+}
+
+/// The PC field of a `g` reply: the sixth little-endian 16-bit register.
+fn pc_of(registers: &str) -> u16 {
+    let bytes = u32::from_str_radix(&registers[20..24], 16).unwrap() as u16;
+    bytes.swap_bytes()
+}
+
+#[test]
+fn gdb_stub_reads_registers_and_memory_and_stops_at_breakpoints() {
+    // NOP ; LD A, d8 ; INC A ; JP a16 (back to 0)
+    let gb = make_gameboy(&[0x00, 0x3E, 0x01, 0x3C, 0xC3, 0x00, 0x00]);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let mut gb = gb;
+        let (stream, _) = listener.accept().unwrap();
+        let mut stub = GdbStub::new(stream);
+        let end = stub.serve(&mut gb).unwrap();
+        (end, gb)
+    });
+
+    let mut client = TcpStream::connect(addr).unwrap();
+    assert!(request(&mut client, "qSupported:xmlRegisters=z80").contains("qXfer:features:read+"));
+    assert!(request(&mut client, "qXfer:features:read:target.xml:0,fff").starts_with('l'));
+    assert_eq!(request(&mut client, "?"), "S05");
+
+    let registers = request(&mut client, "g");
+    assert_eq!(registers.len(), 6 * 4);
+    assert_eq!(pc_of(&registers), 0x0000);
+
+    assert_eq!(request(&mut client, "Z0,3,1"), "OK");
+    assert_eq!(request(&mut client, "c"), "S05");
+    let registers = request(&mut client, "g");
+    assert_eq!(pc_of(&registers), 0x0003);
+    // AF is sent F first, then A (loaded with 1 before the breakpoint).
+    assert_eq!(&registers[2..4], "01");
+
+    // Continuing from the breakpoint runs the loop once more and stops there again.
+    assert_eq!(request(&mut client, "c"), "S05");
+    assert_eq!(pc_of(&request(&mut client, "g")), 0x0003);
+
+    assert_eq!(request(&mut client, "z0,3,1"), "OK");
+    assert_eq!(request(&mut client, "s"), "S05");
+    assert_eq!(request(&mut client, "p5"), "0400");
+
+    assert_eq!(request(&mut client, "Mc000,2:abcd"), "OK");
+    assert_eq!(request(&mut client, "mc000,2"), "abcd");
+    assert_eq!(request(&mut client, "m0,2"), "003e");
+    assert_eq!(request(&mut client, "mffff,2"), "E01");
+    assert_eq!(request(&mut client, "vMustReplyEmpty"), "");
+
+    write!(client, "$k#6b").unwrap();
+    let (end, mut gb) = server.join().unwrap();
+    assert_eq!(end, SessionEnd::Killed);
+    assert_eq!(gb.cpu.pc, 0x0004);
+    assert_eq!(gb.bus.read8(0xC000), 0xAB);
+}
+
+#[test]
+fn gdb_stub_naks_packets_longer_than_it_advertises() {
+    let gb = make_gameboy(&[0x00]);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let mut gb = gb;
+        let (stream, _) = listener.accept().unwrap();
+        GdbStub::new(stream).serve(&mut gb).unwrap()
+    });
+
+    let mut client = TcpStream::connect(addr).unwrap();
+    // One byte over `PacketSize=1000`, with a valid checksum.
+    let payload = "m".repeat(0x1001);
+    let sum = payload.bytes().fold(0u8, |s, b| s.wrapping_add(b));
+    write!(client, "${payload}#{sum:02x}").unwrap();
+    let mut byte = [0u8; 1];
+    client.read_exact(&mut byte).unwrap();
+    assert_eq!(byte[0], b'-');
+
+    // The stub is back in sync for the next packet.
+    assert_eq!(request(&mut client, "?"), "S05");
+    write!(client, "$k#6b").unwrap();
+    assert_eq!(server.join().unwrap(), SessionEnd::Killed);
+}
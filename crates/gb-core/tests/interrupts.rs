@@ -53,3 +53,29 @@ fn services_highest_priority_interrupt() {
     assert_eq!(bus.iflag & (1 << 0), 0);
     assert_ne!(bus.iflag & (1 << 2), 0);
 }
+
+#[test]
+fn pending_interrupts_decode_enabled_requests() {
+    use gb_core::gb::GameBoy;
+    use gb_core::interrupt::Interrupt;
+
+    let cart = Cartridge::from_rom(make_rom()).unwrap();
+    let mut gb = GameBoy::new(Cpu::new(), Bus::new(cart));
+    gb.bus.ie = 0x01;
+    gb.bus.iflag = 0x01;
+
+    assert!(!gb.interrupt_master_enable());
+    assert_eq!(gb.bus.interrupt_enable(), 0x01);
+    assert_eq!(gb.bus.interrupt_flags(), 0x01);
+    assert_eq!(gb.pending_interrupts(), 0x01);
+    assert_eq!(gb.pending_interrupt_list(), vec![Interrupt::VBlank]);
+
+    // Requested but not enabled: not pending.
+    gb.bus.iflag = 0x05;
+    assert_eq!(gb.pending_interrupt_list(), vec![Interrupt::VBlank]);
+    gb.bus.ie = 0x1F;
+    assert_eq!(
+        gb.pending_interrupt_list(),
+        vec![Interrupt::VBlank, Interrupt::Timer]
+    );
+}
@@ -0,0 +1,75 @@
+use gb_core::ips::{apply_ips, IpsError};
+
+fn record(offset: u32, data: &[u8]) -> Vec<u8> {
+    let mut out = offset.to_be_bytes()[1..].to_vec();
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+fn rle_record(offset: u32, len: u16, fill: u8) -> Vec<u8> {
+    let mut out = offset.to_be_bytes()[1..].to_vec();
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&len.to_be_bytes());
+    out.push(fill);
+    out
+}
+
+fn patch(records: &[Vec<u8>], trailer: &[u8]) -> Vec<u8> {
+    let mut out = b"PATCH".to_vec();
+    for r in records {
+        out.extend_from_slice(r);
+    }
+    out.extend_from_slice(b"EOF");
+    out.extend_from_slice(trailer);
+    out
+}
+
+#[test]
+fn ips_patches_bytes_at_offset() {
+    let mut rom = vec![0u8; 0x200];
+    apply_ips(&mut rom, &patch(&[record(0x0134, b"HACK")], &[])).unwrap();
+
+    assert_eq!(&rom[0x0134..0x0138], b"HACK");
+    assert_eq!(rom[0x0133], 0);
+    assert_eq!(rom[0x0138], 0);
+    assert_eq!(rom.len(), 0x200);
+}
+
+#[test]
+fn ips_rle_record_fills_run() {
+    let mut rom = vec![0u8; 0x100];
+    apply_ips(&mut rom, &patch(&[rle_record(0x10, 4, 0xAB)], &[])).unwrap();
+
+    assert_eq!(&rom[0x0F..0x15], &[0, 0xAB, 0xAB, 0xAB, 0xAB, 0]);
+}
+
+#[test]
+fn ips_records_past_end_grow_rom() {
+    let mut rom = vec![0u8; 0x10];
+    apply_ips(&mut rom, &patch(&[record(0x12, &[1, 2])], &[])).unwrap();
+
+    assert_eq!(rom.len(), 0x14);
+    assert_eq!(&rom[0x10..], &[0, 0, 1, 2]);
+}
+
+#[test]
+fn ips_truncation_extension_shrinks_rom() {
+    let mut rom = vec![0xFFu8; 0x100];
+    apply_ips(&mut rom, &patch(&[], &[0x00, 0x00, 0x80])).unwrap();
+
+    assert_eq!(rom.len(), 0x80);
+}
+
+#[test]
+fn ips_rejects_bad_header_and_truncated_records() {
+    let mut rom = vec![0u8; 0x10];
+    assert_eq!(
+        apply_ips(&mut rom, b"PATCX\0\0\0\0\x01\xFFEOF"),
+        Err(IpsError::InvalidHeader)
+    );
+    assert_eq!(
+        apply_ips(&mut rom, b"PATCH\0\0\x01\0\x04\xAA"),
+        Err(IpsError::Truncated(10))
+    );
+}
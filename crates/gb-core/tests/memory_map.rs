@@ -1,4 +1,5 @@
 use gb_core::bus::Bus;
+use gb_core::cartridge::mbc::BankInfo;
 use gb_core::cartridge::Cartridge;
 
 // Helper to create a banked ROM with each bank marked
@@ -139,6 +140,25 @@ fn mbc1_rom_bank_switch_low5() {
     assert_eq!(bus.read8(0x4000), 0x03);
 }
 
+#[test]
+fn mbc1_bank_info_reports_the_bank_mapped_at_0x4000() {
+    let mut rom = make_banked_rom(8);
+    rom[0x0147] = 0x01; // MBC1
+    rom[0x0148] = 0x03; // 256KB ROM = 8 banks
+
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut bus = Bus::new(cart);
+    assert_eq!(bus.cart.bank_info(), BankInfo::default());
+
+    bus.write8(0x2000, 0x03);
+    let banks = bus.cart.bank_info();
+    assert_eq!(banks.rom_bank, 3);
+    assert_eq!(bus.read8(0x4000), 0x03);
+
+    bus.write8(0x6000, 0x01);
+    assert_eq!(bus.cart.bank_info().mode, 1);
+}
+
 #[test]
 fn mbc1_rom_bank_switch_uses_high_bits() {
     let mut rom = make_banked_rom(128);
@@ -157,6 +177,33 @@ fn mbc1_rom_bank_switch_uses_high_bits() {
     assert_eq!(bus.read8(0x4000), 33_u8);
 }
 
+#[test]
+fn mbc1_low5_zero_maps_to_next_bank_with_high_bits_set() {
+    let mut rom = make_banked_rom(128);
+    rom[0x0147] = 0x01; // MBC1
+    rom[0x0148] = 0x07; // 4MB ROM = 128 banks
+    rom[0x0149] = 0x00;
+
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut bus = Bus::new(cart);
+
+    // 0x4000 never maps a multiple of 0x20: 0x20/0x40/0x60 become 0x21/0x41/0x61.
+    bus.write8(0x2000, 0x00);
+    for (high2, expected) in [(1u8, 0x21u8), (2, 0x41), (3, 0x61)] {
+        bus.write8(0x4000, high2);
+        assert_eq!(bus.read8(0x4000), expected);
+    }
+
+    // Mode 1: the high bits also bank the 0x0000 region (bank 0x60 here).
+    bus.write8(0x6000, 0x01);
+    assert_eq!(bus.read8(0x0000), 0x60);
+    assert_eq!(bus.read8(0x4000), 0x61);
+
+    // Mode 0: 0x0000 is back to bank 0.
+    bus.write8(0x6000, 0x00);
+    assert_eq!(bus.read8(0x0000), 0x00);
+}
+
 #[test]
 fn mbc1_ram_enable_disable() {
     let mut rom = vec![0x00; 0x4000];
@@ -212,6 +259,51 @@ fn mbc1_ram_bank_switch_mode1() {
     assert_eq!(bus.read8(0xA000), 0x22);
 }
 
+#[test]
+fn mbc2_ram_stores_low_nibble_and_reads_high_nibble_as_ones() {
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x06; // MBC2 + Battery
+    rom[0x0148] = 0x02;
+    rom[0x0149] = 0x00;
+
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut bus = Bus::new(cart);
+
+    bus.write8(0x0000, 0x0A);
+    bus.write8(0xA000, 0xA5);
+    assert_eq!(bus.read8(0xA000), 0xF5);
+
+    bus.write8(0xA1FF, 0x3C);
+    assert_eq!(bus.read8(0xA1FF), 0xFC);
+
+    // 512 nibbles are mirrored through 0xA000..=0xBFFF.
+    assert_eq!(bus.read8(0xA200), 0xF5);
+    assert_eq!(bus.read8(0xBFFF), 0xFC);
+}
+
+#[test]
+fn mbc2_ram_enable_requires_address_bit8_clear() {
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x06; // MBC2 + Battery
+    rom[0x0148] = 0x02;
+    rom[0x0149] = 0x00;
+
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut bus = Bus::new(cart);
+
+    // Bit 8 set: this selects a ROM bank instead of enabling RAM.
+    bus.write8(0x0100, 0x0A);
+    assert_eq!(bus.read8(0xA000), 0xFF);
+    assert_eq!(bus.read8(0x4000), 0x02); // bank 0x0A wraps to 2 of 4
+
+    bus.write8(0x0000, 0x0A);
+    bus.write8(0xA000, 0x07);
+    assert_eq!(bus.read8(0xA000), 0xF7);
+
+    bus.write8(0x0000, 0x00);
+    assert_eq!(bus.read8(0xA000), 0xFF);
+}
+
 #[test]
 fn mbc3_ram_banking_and_rtc_select() {
     let mut rom = make_banked_rom(2);
@@ -347,6 +439,91 @@ fn mbc5_rom_and_ram_bank_switching() {
     assert_eq!(bus.read8(0xA000), 0x22);
 }
 
+#[test]
+fn mbc5_reaches_rom_bank_0x1ff_bank_0_and_ram_bank_0xf() {
+    let mut rom = make_banked_rom(512);
+    rom[0x0147] = 0x1B; // MBC5 + RAM + Battery
+    rom[0x0148] = 0x08; // 8MB ROM
+    rom[0x0149] = 0x04; // 128KB RAM
+
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut bus = Bus::new(cart);
+
+    // Bit 8 and the low byte are separate registers, written in either order.
+    bus.write8(0x3000, 0x01);
+    bus.write8(0x2000, 0xFF);
+    assert_eq!((bus.read8(0x4000), bus.read8(0x4001)), (0xFF, 0x01));
+    bus.write8(0x2000, 0x00);
+    assert_eq!((bus.read8(0x4000), bus.read8(0x4001)), (0x00, 0x01));
+    // Unlike MBC1/MBC3, bank 0 can be mapped at 0x4000.
+    bus.write8(0x3000, 0x00);
+    assert_eq!((bus.read8(0x4000), bus.read8(0x4001)), (0x00, 0x00));
+    assert_eq!(bus.read8(0x4002), bus.read8(0x0002));
+
+    // All 16 RAM banks are distinct; the upper nibble of the bank register is ignored.
+    bus.write8(0x0000, 0x0A);
+    for bank in 0..16u8 {
+        bus.write8(0x4000, bank);
+        bus.write8(0xBFFF, 0xA0 | bank);
+    }
+    bus.write8(0x4000, 0x0F);
+    assert_eq!(bus.read8(0xBFFF), 0xAF);
+    bus.write8(0x4000, 0x00);
+    assert_eq!(bus.read8(0xBFFF), 0xA0);
+    bus.write8(0x4000, 0x1F);
+    assert_eq!(bus.read8(0xBFFF), 0xAF);
+}
+
+#[test]
+fn mbc5_rumble_bit_drives_motor_without_selecting_ram_bank() {
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x1E; // MBC5 + Rumble + RAM + Battery
+    rom[0x0149] = 0x03; // 32KB RAM
+
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut bus = Bus::new(cart);
+    assert!(!bus.take_rumble_state());
+
+    bus.write8(0x0000, 0x0A);
+    bus.write8(0x4000, 0x01);
+    bus.write8(0xA000, 0x11);
+
+    // Motor on, same RAM bank.
+    bus.write8(0x4000, 0x09);
+    assert_eq!(bus.read8(0xA000), 0x11);
+    assert!(bus.take_rumble_state());
+    assert!(bus.take_rumble_state());
+
+    // A pulse between polls is still reported once.
+    bus.write8(0x4000, 0x01);
+    assert!(bus.take_rumble_state());
+    assert!(!bus.take_rumble_state());
+    bus.write8(0x4000, 0x09);
+    bus.write8(0x4000, 0x01);
+    assert!(bus.take_rumble_state());
+    assert!(!bus.take_rumble_state());
+    assert_eq!(bus.read8(0xA000), 0x11);
+}
+
+#[test]
+fn mbc5_without_rumble_uses_bit3_for_ram_bank() {
+    let mut rom = make_banked_rom(4);
+    rom[0x0147] = 0x1B; // MBC5 + RAM + Battery
+    rom[0x0149] = 0x04; // 128KB RAM
+
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut bus = Bus::new(cart);
+
+    bus.write8(0x0000, 0x0A);
+    bus.write8(0x4000, 0x01);
+    bus.write8(0xA000, 0x11);
+    bus.write8(0x4000, 0x09);
+    bus.write8(0xA000, 0x22);
+    bus.write8(0x4000, 0x01);
+    assert_eq!(bus.read8(0xA000), 0x11);
+    assert!(!bus.take_rumble_state());
+}
+
 #[test]
 fn vram_read_write() {
     let rom = vec![0x00; 0x4000];
@@ -385,3 +562,64 @@ fn unusable_region_reads_ff_ignores_writes() {
     bus.write8(0xFEA0, 0x55);
     assert_eq!(bus.read8(0xFEA0), 0xFF);
 }
+
+#[test]
+fn bus_hooks_observe_reads_and_writes() {
+    use std::sync::{Arc, Mutex};
+
+    let cart = Cartridge::from_rom(make_banked_rom(2)).unwrap();
+    let mut bus = Bus::new(cart);
+
+    let writes = Arc::new(Mutex::new(Vec::new()));
+    let reads = Arc::new(Mutex::new(Vec::new()));
+    let w = Arc::clone(&writes);
+    bus.on_write = Some(Box::new(move |addr, val| {
+        w.lock().unwrap().push((addr, val))
+    }));
+    let r = Arc::clone(&reads);
+    bus.on_read = Some(Box::new(move |addr, val| {
+        r.lock().unwrap().push((addr, val))
+    }));
+
+    bus.write8(0xC123, 0x5A);
+    assert_eq!(bus.read8(0xC123), 0x5A);
+    // Echo RAM resolves to the same byte.
+    assert_eq!(bus.read8(0xE123), 0x5A);
+
+    assert_eq!(*writes.lock().unwrap(), vec![(0xC123, 0x5A)]);
+    assert_eq!(*reads.lock().unwrap(), vec![(0xC123, 0x5A), (0xE123, 0x5A)]);
+
+    bus.on_write = None;
+    bus.write8(0xC124, 0x01);
+    assert_eq!(writes.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn debug_dump_reports_registers_lcd_banks_and_stack() {
+    use gb_core::cpu::Cpu;
+    use gb_core::gb::GameBoy;
+
+    let mut rom = make_banked_rom(64);
+    rom[0x0147] = 0x01; // MBC1
+    let mut gb = GameBoy::new(Cpu::new(), Bus::new(Cartridge::from_rom(rom).unwrap()));
+    gb.bus.write8(0x2000, 0x05);
+    gb.bus.write8(0x4000, 0x01);
+    gb.bus.write8(0x6000, 0x01);
+    gb.bus.write8(0xFF40, 0x91);
+    gb.bus.write8(0xFFFF, 0x05);
+
+    let cpu = &mut gb.cpu;
+    (cpu.a, cpu.f, cpu.b, cpu.c) = (0x12, 0xB0, 0x34, 0x56);
+    (cpu.d, cpu.e, cpu.h, cpu.l) = (0x78, 0x9A, 0xBC, 0xDE);
+    (cpu.pc, cpu.sp) = (0x4321, 0xFFF0);
+    for (i, b) in [0x34, 0x12, 0x78, 0x56].into_iter().enumerate() {
+        gb.bus.write8(0xFFF0 + i as u16, b);
+    }
+
+    let dump = gb.debug_dump();
+    assert!(dump
+        .starts_with("PC=4321 AF=12B0 BC=3456 DE=789A HL=BCDE SP=FFF0 IME=false HALT=false IE=05"));
+    assert!(dump.contains("LCDC=91 "), "{dump}");
+    assert!(dump.contains("ROM bank=25 RAM bank=01"), "{dump}");
+    assert!(dump.contains("Stack at FFF0: 1234 5678 "), "{dump}");
+}
@@ -0,0 +1,73 @@
+//! Exercises only the API that is available with `--no-default-features` (no `std`):
+//! no file I/O, wall clock or save states. Build the core itself without std with
+//! `cargo build -p gb-core --no-default-features`.
+
+use gb_core::bus::Bus;
+use gb_core::cartridge::Cartridge;
+use gb_core::cpu::Cpu;
+use gb_core::gb::GameBoy;
+use gb_core::handle::GbHandle;
+use gb_core::input::Button;
+
+fn make_rom(cart_type: u8, ram_size: u8) -> Vec<u8> {
+    // LD A, $42 ; LD ($A000), A ; JR -2
+    let program = [0x3E, 0x42, 0xEA, 0x00, 0xA0, 0x18, 0xFE];
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x0100..0x0100 + program.len()].copy_from_slice(&program);
+    rom[0x0147] = cart_type;
+    rom[0x0148] = 0x00;
+    rom[0x0149] = ram_size;
+    rom
+}
+
+#[test]
+fn core_runs_frames_and_produces_output_without_std_apis() {
+    let cart = Cartridge::from_rom(make_rom(0x00, 0x00)).unwrap();
+    let mut gb = GameBoy::new(Cpu::new(), Bus::new(cart));
+    gb.cpu.pc = 0x0100;
+    gb.bus.write8(0xFF40, 0x91);
+
+    gb.run_frame();
+    assert_eq!(gb.cpu.a, 0x42);
+    assert!(!gb.bus.ppu.framebuffer().is_empty());
+    assert!(!gb.bus.apu.take_samples().is_empty());
+}
+
+#[test]
+fn battery_saves_round_trip_through_memory() {
+    // MBC1+RAM+BATTERY, 8 KiB RAM.
+    let mut cart = Cartridge::from_rom(make_rom(0x03, 0x02)).unwrap();
+    cart.ram[0] = 0x42;
+    cart.ram[0x1FFF] = 0x99;
+    let data = cart.save_data();
+
+    let mut restored = Cartridge::from_rom(make_rom(0x03, 0x02)).unwrap();
+    restored.load_save_data(&data).unwrap();
+    assert_eq!(restored.ram, cart.ram);
+}
+
+#[test]
+fn handle_runs_a_frame_from_rom_bytes() {
+    let mut handle = GbHandle::new(&make_rom(0x03, 0x02)).unwrap();
+    assert_eq!(handle.gameboy().cpu.pc, 0x0100);
+
+    handle.set_buttons(Button::Right.mask() | Button::Start.mask());
+    handle.run_frame();
+    let gb = handle.gameboy_mut();
+    assert_eq!(gb.cpu.a, 0x42);
+    assert_eq!(gb.frames(), 1);
+    gb.bus.write8(0xFF00, 0x20); // select directions
+    assert_eq!(gb.bus.read8(0xFF00) & 0x0F, 0x0E);
+
+    let rgba = handle.framebuffer_rgba();
+    assert_eq!(rgba.len(), 160 * 144 * 4);
+    assert!(rgba.chunks_exact(4).all(|px| px[3] == 0xFF));
+    assert!(!handle.audio_samples().is_empty());
+    assert!(handle.audio_samples().is_empty());
+
+    let mut save = handle.save_data();
+    assert_eq!(save.len(), 0x2000);
+    save[0] = 0x99;
+    handle.load_save_data(&save).unwrap();
+    assert_eq!(handle.save_data()[0], 0x99);
+}
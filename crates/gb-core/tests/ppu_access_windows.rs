@@ -67,3 +67,59 @@ fn vram_and_oam_are_accessible_when_lcd_is_disabled() {
     assert_eq!(bus.read8(0x8000), 0xAA);
     assert_eq!(bus.read8(0xFE00), 0xBB);
 }
+
+/// Row `row` of OAM after the DMG's write-style corruption (16-bit INC/DEC, PUSH): word 0
+/// becomes `((a ^ c) & (b ^ c)) ^ c` of this row's and the previous row's words, and the
+/// rest of the row is copied from the previous one.
+fn oam_after_idu_write(oam: &[u8; 0xA0], row: usize) -> [u8; 0xA0] {
+    let word = |oam: &[u8; 0xA0], row: usize, w: usize| {
+        u16::from_le_bytes([oam[row * 8 + w * 2], oam[row * 8 + w * 2 + 1]])
+    };
+    let mut out = *oam;
+    let (a, b, c) = (
+        word(oam, row, 0),
+        word(oam, row - 1, 0),
+        word(oam, row - 1, 2),
+    );
+    out[row * 8..row * 8 + 2].copy_from_slice(&(((a ^ c) & (b ^ c)) ^ c).to_le_bytes());
+    out.copy_within((row - 1) * 8 + 2..row * 8, row * 8 + 2);
+    out
+}
+
+#[test]
+fn inc_hl_in_oam_range_during_mode2_corrupts_oam() {
+    use gb_core::cpu::Cpu;
+
+    let mut rom = make_rom();
+    rom[0] = 0x23; // INC HL
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut bus = Bus::new(cart);
+    for (i, b) in bus.oam.iter_mut().enumerate() {
+        *b = (i as u8).wrapping_mul(37) ^ 0x5A;
+    }
+    let original = bus.oam;
+
+    bus.write8(0xFF40, 0x80); // LCD on
+    bus.tick(0);
+    bus.tick(16);
+    assert_eq!(bus.ppu_status().mode, 2);
+    // The IDU cycle follows the 4-cycle opcode fetch.
+    let row = (bus.ppu.current_dots() as usize + 4) / 4;
+    assert!((2..20).contains(&row));
+
+    let mut cpu = Cpu::new();
+    cpu.set_hl(0xFE00);
+    assert_eq!(cpu.step(&mut bus), 8);
+    assert_eq!(cpu.hl(), 0xFE01);
+
+    let expected = oam_after_idu_write(&original, row);
+    assert_ne!(expected, original);
+    assert_eq!(bus.oam, expected);
+
+    // Outside OAM the same instruction leaves it alone.
+    let before = bus.oam;
+    let mut cpu = Cpu::new();
+    cpu.set_hl(0xC000);
+    cpu.step(&mut bus);
+    assert_eq!(bus.oam, before);
+}
@@ -1,4 +1,4 @@
-use gb_core::bus::Bus;
+use gb_core::bus::{Bus, EmulationMode};
 use gb_core::cartridge::Cartridge;
 
 fn make_rom() -> Vec<u8> {
@@ -68,3 +68,123 @@ fn stat_mode_interrupt_bits_request_interrupts() {
     bus.tick(456 * 143); // reach LY=144 from LY=1
     assert_ne!(bus.iflag & 0x02, 0);
 }
+
+#[test]
+fn stat_irq_line_blocks_back_to_back_conditions() {
+    let cart = Cartridge::from_rom(make_rom()).unwrap();
+    let mut bus = Bus::new(cart);
+
+    bus.write8(0xFF40, 0x80); // LCD on
+    bus.tick(4);
+    // HBlank, OAM and LY == LYC (for line 1) sources all enabled. Written in mode 2, which
+    // already raises the line.
+    bus.write8(0xFF45, 1);
+    bus.write8(0xFF41, 0x68);
+    assert_ne!(bus.iflag & 0x02, 0);
+
+    // Mode 3 drops the line, so HBlank is a new rising edge.
+    bus.iflag = 0;
+    bus.tick(248);
+    assert_eq!(bus.read8(0xFF41) & 0x03, 0);
+    assert_ne!(bus.iflag & 0x02, 0);
+
+    // Line 1 starts in mode 2 with LY == LYC, but the line never went low after HBlank.
+    bus.iflag = 0;
+    bus.tick(204);
+    assert_eq!(bus.read8(0xFF44), 1);
+    assert_eq!(bus.read8(0xFF41) & 0x07, 0x06);
+    assert_eq!(bus.iflag & 0x02, 0);
+}
+
+#[test]
+fn dmg_stat_write_during_hblank_requests_spurious_interrupt() {
+    let cart = Cartridge::from_rom(make_rom()).unwrap();
+    let mut bus = Bus::new(cart);
+
+    bus.write8(0xFF45, 0x90); // keep LY == LYC out of the way
+    bus.write8(0xFF40, 0x80);
+    bus.tick(84);
+    assert_eq!(bus.read8(0xFF41) & 0x03, 3);
+
+    // Mode 3: no condition holds, so the write is harmless.
+    bus.write8(0xFF41, 0x00);
+    assert_eq!(bus.iflag & 0x02, 0);
+
+    bus.tick(200);
+    assert_eq!(bus.read8(0xFF41) & 0x03, 0);
+    assert_eq!(bus.iflag & 0x02, 0);
+
+    // HBlank: writing STAT, even with no enables, fires once.
+    bus.write8(0xFF41, 0x00);
+    assert_ne!(bus.iflag & 0x02, 0);
+    bus.iflag = 0;
+    bus.tick(4);
+    assert_eq!(bus.iflag & 0x02, 0);
+
+    // CGB fixed the bug.
+    let mut rom = make_rom();
+    rom[0x0143] = 0x80;
+    let mut bus = Bus::new_with_mode(Cartridge::from_rom(rom).unwrap(), EmulationMode::Cgb);
+    bus.write8(0xFF45, 0x90);
+    bus.write8(0xFF40, 0x80);
+    bus.tick(284);
+    assert_eq!(bus.read8(0xFF41) & 0x03, 0);
+    bus.iflag = 0;
+    bus.write8(0xFF41, 0x00);
+    assert_eq!(bus.iflag & 0x02, 0);
+}
+
+#[test]
+fn lyc_zero_matches_early_in_line_153() {
+    let cart = Cartridge::from_rom(make_rom()).unwrap();
+    let mut bus = Bus::new(cart);
+
+    bus.write8(0xFF45, 0x00);
+    bus.write8(0xFF41, 0x40); // LY == LYC interrupt
+    bus.write8(0xFF40, 0x80);
+    bus.tick(456 * 153);
+    bus.iflag = 0;
+
+    assert_eq!(bus.read8(0xFF44), 153);
+    assert_eq!(bus.read8(0xFF41) & 0x04, 0);
+
+    // A few dots in, LY already reads 0 and the coincidence interrupt fires, still in VBlank.
+    bus.tick(4);
+    assert_eq!(bus.read8(0xFF44), 0);
+    assert_eq!(bus.read8(0xFF41) & 0x07, 0x05);
+    assert_ne!(bus.iflag & 0x02, 0);
+
+    // The match carries into line 0 without a second interrupt.
+    bus.iflag = 0;
+    bus.tick(452);
+    assert_eq!(bus.read8(0xFF44), 0);
+    assert_eq!(bus.read8(0xFF41) & 0x07, 0x06);
+    assert_eq!(bus.iflag & 0x02, 0);
+}
+
+#[test]
+fn ppu_status_reflects_mid_frame_registers() {
+    use gb_core::bus::PpuStatus;
+
+    let cart = Cartridge::from_rom(make_rom()).unwrap();
+    let mut bus = Bus::new(cart);
+
+    bus.write8(0xFF45, 10); // LYC
+    bus.write8(0xFF40, 0x91); // LCD and BG on
+    bus.tick(1);
+    // Line 10, partway into pixel transfer.
+    bus.tick(456 * 10 + 120);
+
+    let status = bus.ppu_status();
+    assert_eq!(
+        status,
+        PpuStatus {
+            ly: 10,
+            mode: 3,
+            lcdc: 0x91,
+            stat: bus.read8(0xFF41),
+        }
+    );
+    assert_eq!(status.stat & 0x07, 0x04 | 3, "LYC match and mode 3");
+    assert_eq!(bus.lcd_ly(), bus.read8(0xFF44));
+}
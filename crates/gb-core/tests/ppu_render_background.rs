@@ -28,7 +28,7 @@ fn bg_renders_tile_pixels_unsigned_tiledata() {
     let mut bus = setup_bus();
 
     // Tile 1, row 0: color nums 0,1,2,3,0,1,2,3.
-    write_tile_row(&mut bus.vram, 1, 0, 0x55, 0x33);
+    write_tile_row(&mut bus.vram[..], 1, 0, 0x55, 0x33);
     bus.vram[0x1800] = 1; // BG map (0x9800) tile (0,0)
 
     bus.write8(0xFF47, 0xE4); // BGP identity
@@ -47,7 +47,7 @@ fn bg_renders_tile_pixels_unsigned_tiledata() {
 fn bg_scx_scrolls_horizontally() {
     let mut bus = setup_bus();
 
-    write_tile_row(&mut bus.vram, 1, 0, 0x55, 0x33);
+    write_tile_row(&mut bus.vram[..], 1, 0, 0x55, 0x33);
     bus.vram[0x1800] = 1;
 
     bus.write8(0xFF43, 4); // SCX
@@ -66,7 +66,7 @@ fn bg_scy_scrolls_vertically() {
 
     // Tile 2: all pixels color 3 (black with identity palette).
     for row in 0..8u8 {
-        write_tile_row(&mut bus.vram, 2, row, 0xFF, 0xFF);
+        write_tile_row(&mut bus.vram[..], 2, row, 0xFF, 0xFF);
     }
     // BG map row 1, col 0.
     bus.vram[0x1800 + 32] = 2;
@@ -105,7 +105,7 @@ fn bg_bgp_palette_maps_color_numbers_to_shades() {
     let mut bus = setup_bus();
 
     // Tile 1, first pixel color num 1.
-    write_tile_row(&mut bus.vram, 1, 0, 0x80, 0x00);
+    write_tile_row(&mut bus.vram[..], 1, 0, 0x80, 0x00);
     bus.vram[0x1800] = 1;
 
     bus.write8(0xFF47, 0x1B); // invert mapping: 0->3, 1->2, 2->1, 3->0
@@ -117,3 +117,99 @@ fn bg_bgp_palette_maps_color_numbers_to_shades() {
     assert_eq!(bus.ppu.framebuffer()[0], DARK); // color 1 -> shade 2
     assert_eq!(bus.ppu.framebuffer()[1], BLACK); // color 0 -> shade 3
 }
+
+#[test]
+fn window_line_counter_pauses_while_window_is_disabled() {
+    let mut bus = setup_bus();
+
+    // Window tile 1: row 2 is black, row 5 is light.
+    write_tile_row(&mut bus.vram[..], 1, 2, 0xFF, 0xFF);
+    write_tile_row(&mut bus.vram[..], 1, 5, 0xFF, 0x00);
+    bus.vram[0x1C00] = 1; // Window map (0x9C00) tile (0,0)
+
+    bus.write8(0xFF47, 0xE4);
+    bus.write8(0xFF4A, 0); // WY
+    bus.write8(0xFF4B, 7); // WX
+    bus.write8(0xFF40, 0xF1); // LCD on, window on (0x9C00 map), BG on
+
+    // Lines 0-1 draw the window, lines 2-4 do not.
+    bus.tick(0);
+    bus.tick(456 * 2);
+    bus.write8(0xFF40, 0xD1);
+    bus.tick(456 * 3);
+    bus.write8(0xFF40, 0xF1);
+    bus.tick(456);
+
+    // Line 5 resumes at window row 2, not LY - WY = 5.
+    assert_eq!(bus.ppu.framebuffer()[5 * 160], BLACK);
+    assert_eq!(bus.ppu.framebuffer()[3 * 160], WHITE);
+}
+
+#[test]
+fn force_render_frame_matches_scanline_rendering() {
+    let setup = || {
+        let mut bus = setup_bus();
+        for tile in 1..4u8 {
+            for row in 0..8u8 {
+                write_tile_row(
+                    &mut bus.vram[..],
+                    tile,
+                    row,
+                    tile.wrapping_mul(0x35) ^ row,
+                    row,
+                );
+            }
+        }
+        for i in 0..0x400usize {
+            bus.vram[0x1800 + i] = (i % 4) as u8;
+            bus.vram[0x1C00 + i] = ((i + 1) % 4) as u8;
+        }
+        bus.write8(0xFF42, 3); // SCY
+        bus.write8(0xFF43, 5); // SCX
+        bus.write8(0xFF4A, 40); // WY
+        bus.write8(0xFF4B, 87); // WX
+        bus.write8(0xFF47, 0xE4);
+        bus.write8(0xFF40, 0xF1); // LCD, window (0x9C00 map), BG on
+        bus
+    };
+
+    let mut ticked = setup();
+    ticked.tick(0);
+    ticked.tick(456 * 144);
+
+    let mut forced = setup();
+    assert_ne!(forced.ppu.framebuffer()[..], ticked.ppu.framebuffer()[..]);
+    forced.force_render_frame();
+    assert_eq!(forced.ppu.framebuffer()[..], ticked.ppu.framebuffer()[..]);
+    assert_eq!(forced.ppu.current_ly(), 0);
+}
+
+#[test]
+fn first_frame_after_lcd_enable_is_presented_blank() {
+    let mut bus = setup_bus();
+
+    write_tile_row(&mut bus.vram[..], 1, 0, 0xFF, 0xFF);
+    bus.vram[0x1800] = 1;
+    bus.write8(0xFF47, 0xE4);
+    bus.write8(0xFF40, 0x91);
+
+    let next_frame = |bus: &mut Bus| {
+        while !bus.ppu.frame_ready() {
+            bus.tick(4);
+        }
+        bus.ppu.clear_frame_ready();
+        bus.ppu.present_framebuffer()[0]
+    };
+    assert_eq!(next_frame(&mut bus), WHITE);
+    assert!(bus.ppu.present_framebuffer().iter().all(|&px| px == WHITE));
+    // Only the presented image is blank: the frame was rendered and reported as usual.
+    assert_eq!(bus.ppu.framebuffer()[0], BLACK);
+    assert_eq!(next_frame(&mut bus), BLACK);
+
+    // Switching the LCD off and on again hides the next frame once more.
+    bus.write8(0xFF40, 0x11);
+    bus.tick(4);
+    bus.write8(0xFF40, 0x91);
+    assert_eq!(next_frame(&mut bus), WHITE);
+    assert_eq!(next_frame(&mut bus), BLACK);
+}
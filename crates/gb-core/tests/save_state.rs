@@ -0,0 +1,251 @@
+use gb_core::bus::Bus;
+use gb_core::cartridge::Cartridge;
+use gb_core::cpu::Cpu;
+use gb_core::gb::{GameBoy, StateError, STATE_MAGIC, STATE_VERSION};
+
+fn make_gameboy() -> GameBoy {
+    make_gameboy_titled(b"STATETEST")
+}
+
+fn make_gameboy_titled(title: &[u8]) -> GameBoy {
+    // INC A ; LD (HL+), A ; JR -4
+    let program = [0x3C, 0x22, 0x18, 0xFC];
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x0134..0x0134 + title.len()].copy_from_slice(title);
+    rom[0x0147] = 0x00;
+    rom[0x0148] = 0x00;
+    rom[0x0149] = 0x00;
+    rom[0x0100..0x0100 + program.len()].copy_from_slice(&program);
+
+    let cart = Cartridge::from_rom(rom).unwrap();
+    let mut gb = GameBoy::new(Cpu::new(), Bus::new(cart));
+    gb.cpu.pc = 0x0100;
+    gb.cpu.sp = 0xFFFE;
+    gb.cpu.h = 0xC0;
+    gb.bus.write8(0xFF47, 0xE4);
+    gb.bus.write8(0xFF40, 0x91);
+    gb
+}
+
+#[test]
+fn boxed_memory_round_trips_on_the_default_stack() {
+    let mut gb = make_gameboy();
+    for (i, b) in gb.bus.wram.iter_mut().enumerate() {
+        *b = (i * 7) as u8;
+    }
+    for (i, b) in gb.bus.vram.iter_mut().enumerate() {
+        *b = (i * 13) as u8;
+    }
+
+    let bytes = gb.to_state_bytes();
+    let restored = GameBoy::from_state_bytes(&bytes, gb.bus.cart.rom.clone()).unwrap();
+    assert_eq!(restored.bus.wram[..], gb.bus.wram[..]);
+    assert_eq!(restored.bus.vram[..], gb.bus.vram[..]);
+}
+
+#[test]
+fn state_bytes_round_trip_a_running_machine() {
+    let mut gb = make_gameboy();
+    for _ in 0..3 {
+        gb.run_frame();
+    }
+
+    let bytes = gb.to_state_bytes();
+    assert_eq!(&bytes[..4], STATE_MAGIC);
+    assert_eq!(&bytes[4..8], &STATE_VERSION.to_le_bytes());
+    assert_eq!(&bytes[8..12], &gb.bus.cart.rom_fingerprint().to_le_bytes());
+
+    let mut restored = GameBoy::from_state_bytes(&bytes, gb.bus.cart.rom.clone()).unwrap();
+    assert_eq!(restored.cpu.pc, gb.cpu.pc);
+    assert_eq!(restored.cpu.a, gb.cpu.a);
+    assert_eq!(restored.bus.wram[..], gb.bus.wram[..]);
+
+    // Both machines continue identically.
+    gb.run_frame();
+    restored.run_frame();
+    assert_eq!(restored.cpu.pc, gb.cpu.pc);
+    assert_eq!(restored.cpu.hl(), gb.cpu.hl());
+    assert_eq!(
+        restored.bus.ppu.framebuffer()[..],
+        gb.bus.ppu.framebuffer()[..]
+    );
+}
+
+#[test]
+fn state_bytes_reject_bad_magic_and_other_versions() {
+    let gb = make_gameboy();
+    let mut bytes = gb.to_state_bytes();
+
+    bytes[4..8].copy_from_slice(&(STATE_VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        GameBoy::from_state_bytes(&bytes, gb.bus.cart.rom.clone()),
+        Err(StateError::UnsupportedVersion(v)) if v == STATE_VERSION + 1
+    ));
+
+    bytes[0] = b'X';
+    assert!(matches!(
+        GameBoy::from_state_bytes(&bytes, gb.bus.cart.rom.clone()),
+        Err(StateError::InvalidMagic)
+    ));
+
+    let truncated = gb.to_state_bytes();
+    assert!(matches!(
+        GameBoy::from_state_bytes(&truncated[..12], gb.bus.cart.rom.clone()),
+        Err(StateError::Decode(_))
+    ));
+}
+
+#[test]
+fn state_from_another_rom_is_refused() {
+    let mut rom_a = make_gameboy_titled(b"GAME A");
+    rom_a.run_frame();
+    let state_a = rom_a.to_state_bytes();
+
+    let mut rom_b = make_gameboy_titled(b"GAME B");
+    let fingerprint_b = rom_b.bus.cart.rom_fingerprint();
+    assert_ne!(fingerprint_b, rom_a.bus.cart.rom_fingerprint());
+
+    let pc_before = rom_b.cpu.pc;
+    assert_eq!(
+        rom_b.load_state_bytes_for(&state_a, fingerprint_b),
+        Err(StateError::RomMismatch {
+            expected: fingerprint_b,
+            found: rom_a.bus.cart.rom_fingerprint(),
+        })
+    );
+    assert_eq!(rom_b.cpu.pc, pc_before);
+
+    // The matching ROM accepts it.
+    let mut rom_a2 = make_gameboy_titled(b"GAME A");
+    let fingerprint_a = rom_a2.bus.cart.rom_fingerprint();
+    rom_a2
+        .load_state_bytes_for(&state_a, fingerprint_a)
+        .unwrap();
+    assert_eq!(rom_a2.cpu.pc, rom_a.cpu.pc);
+}
+
+#[test]
+fn state_bytes_are_compressed_and_leave_out_the_rom() {
+    // 1 MiB MBC1 ROM with 8 KiB of RAM.
+    let mut rom = vec![0u8; 0x10_0000];
+    rom[0x0134..0x0134 + 5].copy_from_slice(b"LARGE");
+    rom[0x0147] = 0x03;
+    rom[0x0148] = 0x05;
+    rom[0x0149] = 0x02;
+    rom[0x0100..0x0104].copy_from_slice(&[0x3C, 0x22, 0x18, 0xFC]);
+    for (i, byte) in rom.iter_mut().enumerate().skip(0x4000) {
+        *byte = (i >> 14) as u8;
+    }
+
+    let cart = Cartridge::from_rom(rom.clone()).unwrap();
+    let mut gb = GameBoy::new(Cpu::new(), Bus::new(cart));
+    gb.cpu.pc = 0x0100;
+    gb.cpu.sp = 0xFFFE;
+    gb.cpu.h = 0xC0;
+    gb.bus.write8(0xFF40, 0x91);
+    gb.run_frame();
+
+    let raw_len = bincode::serialize(&gb).unwrap().len();
+    let bytes = gb.to_state_bytes();
+    assert!(raw_len < rom.len(), "raw state still contains the ROM");
+    assert!(
+        bytes.len() * 4 < raw_len,
+        "compressed {} bytes vs raw {raw_len}",
+        bytes.len()
+    );
+
+    let mut restored = GameBoy::from_state_bytes(&bytes, rom).unwrap();
+    assert_eq!(restored.cpu.pc, gb.cpu.pc);
+    assert_eq!(restored.bus.wram[..], gb.bus.wram[..]);
+
+    // ROM banking still works on the restored machine.
+    restored.bus.write8(0x2000, 0x13);
+    assert_eq!(restored.bus.read8(0x4000), 0x13);
+
+    gb.run_frame();
+    restored.run_frame();
+    assert_eq!(restored.cpu.hl(), gb.cpu.hl());
+}
+
+#[test]
+fn from_state_bytes_checks_the_supplied_rom() {
+    let gb = make_gameboy_titled(b"GAME A");
+    let other = make_gameboy_titled(b"GAME B");
+    assert!(matches!(
+        GameBoy::from_state_bytes(&gb.to_state_bytes(), other.bus.cart.rom.clone()),
+        Err(StateError::RomMismatch { .. })
+    ));
+}
+
+#[test]
+fn loading_a_state_keeps_host_and_tool_settings() {
+    use gb_core::cpu::cpu::{IllegalOpcodeMode, StopMode};
+    use gb_core::input::SocdMode;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let mut gb = make_gameboy();
+    let state = gb.to_state_bytes();
+    let fingerprint = gb.bus.cart.rom_fingerprint();
+
+    let frames = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&frames);
+    gb.on_vblank(Box::new(move |_| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }));
+    gb.cpu.illegal_opcode_mode = IllegalOpcodeMode::Lockup;
+    gb.cpu.stop_mode = StopMode::TreatAsNop;
+    gb.cpu.set_opcode_profiling(true);
+    gb.cpu.set_call_stack_tracking(true);
+    gb.set_cpu_clock_multiplier(2);
+    gb.bus.ppu.set_fifo_rendering(true);
+    gb.bus.ppu.set_frame_blend(0.5);
+    gb.bus.ppu.set_rendering_enabled(false);
+    gb.bus.apu.set_mono(true);
+    gb.bus.apu.set_highpass(false);
+    gb.bus.apu.set_sample_rate(96_000);
+    gb.bus.input.set_socd_mode(SocdMode::Neutral);
+    gb.bus.serial.set_loopback(true);
+
+    gb.load_state_bytes_for(&state, fingerprint).unwrap();
+    assert_eq!(gb.cpu.illegal_opcode_mode, IllegalOpcodeMode::Lockup);
+    assert_eq!(gb.cpu.stop_mode, StopMode::TreatAsNop);
+    assert!(gb.cpu.take_opcode_histogram().is_some());
+    assert!(gb.cpu.call_stack_tracking());
+    assert_eq!(gb.cpu_clock_multiplier(), 2);
+    assert!(gb.bus.ppu.fifo_rendering());
+    assert_eq!(gb.bus.ppu.frame_blend(), 0.5);
+    assert!(!gb.bus.ppu.rendering_enabled());
+    assert!(gb.bus.apu.mono());
+    assert!(!gb.bus.apu.highpass_enabled());
+    assert_eq!(gb.bus.apu.sample_rate(), 96_000);
+    assert_eq!(gb.bus.input.socd_mode(), SocdMode::Neutral);
+    assert!(gb.bus.serial.loopback());
+
+    gb.run_frame();
+    assert_eq!(frames.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn restored_machine_filters_audio_like_the_original() {
+    let mut gb = make_gameboy();
+    // CH3 holding a constant level, so the output is all high-pass decay.
+    for addr in 0xFF30..0xFF40 {
+        gb.bus.write8(addr, 0xFF);
+    }
+    gb.bus.write8(0xFF24, 0x77);
+    gb.bus.write8(0xFF25, 0x44);
+    gb.bus.write8(0xFF1A, 0x80);
+    gb.bus.write8(0xFF1C, 0x20);
+    gb.bus.write8(0xFF1E, 0x80);
+    gb.run_frame();
+    gb.bus.apu.take_samples();
+
+    let mut restored =
+        GameBoy::from_state_bytes(&gb.to_state_bytes(), gb.bus.cart.rom.clone()).unwrap();
+    gb.run_frame();
+    restored.run_frame();
+    let samples = gb.bus.apu.take_samples();
+    assert!(samples.iter().any(|&s| s != 0.0));
+    assert_eq!(restored.bus.apu.take_samples(), samples);
+}
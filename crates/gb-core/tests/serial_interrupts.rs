@@ -76,3 +76,87 @@ fn serial_interrupt_services_vector_when_ime_true() {
     assert_eq!(bus.read8(0xFFFD), 0x12);
     assert_eq!(bus.iflag & Interrupt::Serial.bit(), 0);
 }
+
+#[test]
+fn internal_clock_shifts_one_bit_every_512_cycles() {
+    let (_cpu, mut bus) = setup(&[0x00]);
+
+    bus.write8(0xFF01, 0x81);
+    bus.write8(0xFF02, 0x81);
+
+    bus.tick(511);
+    assert_eq!(bus.read8(0xFF01), 0x81);
+    bus.tick(1);
+    // Top bit shifted out, a 1 (idle line) shifted in.
+    assert_eq!(bus.read8(0xFF01), 0x03);
+
+    bus.tick(512 * 6 + 511);
+    assert_eq!(bus.iflag & Interrupt::Serial.bit(), 0);
+    assert_ne!(bus.read8(0xFF02) & 0x80, 0);
+
+    bus.tick(1);
+    assert_ne!(bus.iflag & Interrupt::Serial.bit(), 0);
+    assert_eq!(bus.read8(0xFF02) & 0x80, 0);
+    assert_eq!(bus.read8(0xFF01), 0xFF);
+    assert_eq!(bus.serial.take_output(), vec![0x81]);
+}
+
+#[test]
+fn internal_clock_is_twice_as_fast_in_double_speed() {
+    use gb_core::serial::Serial;
+
+    let mut serial = Serial::new();
+    let (mut iflag, mut sb, mut sc) = (0u8, 0x12u8, 0x81u8);
+    serial.start_transfer(sb, &mut sc, true);
+
+    serial.tick(2047, &mut iflag, &mut sb, &mut sc);
+    assert_eq!(iflag, 0);
+    serial.tick(1, &mut iflag, &mut sb, &mut sc);
+    assert_eq!(iflag, Interrupt::Serial.bit());
+    assert_eq!(sc & 0x80, 0);
+}
+
+#[test]
+fn external_clock_waits_for_a_peer() {
+    let (_cpu, mut bus) = setup(&[0x00]);
+
+    bus.write8(0xFF01, 0xA5);
+    bus.write8(0xFF02, 0x80); // start transfer, external clock
+
+    bus.tick(4096 * 8);
+    assert_eq!(bus.iflag & Interrupt::Serial.bit(), 0);
+    assert_ne!(bus.read8(0xFF02) & 0x80, 0);
+    assert_eq!(bus.read8(0xFF01), 0xA5);
+
+    // The peer clocks in 0x3C while receiving 0xA5, MSB first.
+    let mut received = 0u8;
+    for i in (0..8).rev() {
+        let bit = bus.clock_serial_external((0x3C >> i) & 1 != 0).unwrap();
+        received = (received << 1) | u8::from(bit);
+    }
+    assert_eq!(received, 0xA5);
+    assert_eq!(bus.read8(0xFF01), 0x3C);
+    assert_ne!(bus.iflag & Interrupt::Serial.bit(), 0);
+    assert_eq!(bus.read8(0xFF02) & 0x80, 0);
+    assert_eq!(bus.clock_serial_external(true), None);
+}
+
+#[test]
+fn loopback_transfer_receives_the_byte_it_sent() {
+    let (_cpu, mut bus) = setup(&[0x00]);
+    bus.set_serial_loopback(true);
+
+    bus.write8(0xFF01, 0xA5);
+    bus.write8(0xFF02, 0x81);
+    bus.tick(4096);
+
+    assert_ne!(bus.iflag & Interrupt::Serial.bit(), 0);
+    assert_eq!(bus.read8(0xFF01), 0xA5);
+    assert_eq!(bus.serial.take_output(), vec![0xA5]);
+
+    // Without loopback nothing is connected and 1 bits come in.
+    bus.set_serial_loopback(false);
+    bus.write8(0xFF02, 0x81);
+    bus.tick(4096);
+    assert_eq!(bus.read8(0xFF01), 0xFF);
+}
@@ -78,12 +78,67 @@ fn tima_overflow_reloads_tma_and_requests_interrupt() {
     bus.write8(0xFF05, 0xFF); // TIMA
     bus.write8(0xFF07, 0x05); // enable + fastest
 
+    // Overflow happens at 16 cycles; TIMA reads 0 for one M-cycle before the reload.
     bus.tick(16);
+    assert_eq!(bus.read8(0xFF05), 0x00);
+    assert_eq!(bus.iflag & (1 << 2), 0);
 
+    bus.tick(4);
     assert_eq!(bus.read8(0xFF05), 0xAB);
     assert_ne!(bus.iflag & (1 << 2), 0);
 }
 
+#[test]
+fn tima_write_during_reload_delay_cancels_reload_and_interrupt() {
+    let cart = Cartridge::from_rom(make_rom()).unwrap();
+    let mut bus = Bus::new(cart);
+
+    bus.write8(0xFF06, 0xAB); // TMA
+    bus.write8(0xFF05, 0xFF); // TIMA
+    bus.write8(0xFF07, 0x05); // enable + fastest
+
+    bus.tick(16);
+    bus.write8(0xFF05, 0x42);
+    bus.tick(4);
+
+    assert_eq!(bus.read8(0xFF05), 0x42);
+    assert_eq!(bus.iflag & (1 << 2), 0);
+}
+
+#[test]
+fn tma_write_during_reload_delay_uses_new_value() {
+    let cart = Cartridge::from_rom(make_rom()).unwrap();
+    let mut bus = Bus::new(cart);
+
+    bus.write8(0xFF06, 0xAB); // TMA
+    bus.write8(0xFF05, 0xFF); // TIMA
+    bus.write8(0xFF07, 0x05); // enable + fastest
+
+    bus.tick(16);
+    bus.write8(0xFF06, 0xCD);
+    bus.tick(4);
+
+    assert_eq!(bus.read8(0xFF05), 0xCD);
+    assert_ne!(bus.iflag & (1 << 2), 0);
+}
+
+#[test]
+fn writes_during_reload_cycle_follow_tma() {
+    let cart = Cartridge::from_rom(make_rom()).unwrap();
+    let mut bus = Bus::new(cart);
+
+    bus.write8(0xFF06, 0xAB); // TMA
+    bus.write8(0xFF05, 0xFF); // TIMA
+    bus.write8(0xFF07, 0x05); // enable + fastest
+
+    bus.tick(20);
+    // TIMA writes are ignored while TMA is being loaded; TMA writes pass through.
+    bus.write8(0xFF05, 0x11);
+    assert_eq!(bus.read8(0xFF05), 0xAB);
+    bus.write8(0xFF06, 0x22);
+    assert_eq!(bus.read8(0xFF05), 0x22);
+}
+
 #[test]
 fn timer_interrupt_can_be_serviced_by_cpu() {
     let cart = Cartridge::from_rom(make_rom()).unwrap();
@@ -100,8 +155,8 @@ fn timer_interrupt_can_be_serviced_by_cpu() {
     bus.write8(0xFF05, 0xFF); // TIMA
     bus.write8(0xFF07, 0x05); // enable + fastest
 
-    // Trigger overflow => request interrupt.
-    bus.tick(16);
+    // Trigger overflow => request interrupt after the reload delay.
+    bus.tick(20);
     assert_ne!(bus.iflag & (1 << 2), 0);
 
     let cycles = cpu.step(&mut bus);
@@ -180,6 +235,40 @@ fn oam_dma_blocks_cpu_bus_except_hram() {
     assert_eq!(bus.read8(0xC000), 0x99);
 }
 
+#[test]
+fn oam_dma_restart_mid_transfer_copies_from_new_source() {
+    let cart = Cartridge::from_rom(make_rom()).unwrap();
+    let mut bus = Bus::new(cart);
+
+    for i in 0..0xA0u16 {
+        bus.write8(0xC000 + i, 0x10);
+        bus.write8(0xC100 + i, (i as u8) ^ 0xA5);
+    }
+
+    bus.write8(0xFF46, 0xC0);
+    // Startup delay, then 4 bytes from the first source.
+    bus.tick(4 * 5);
+    assert_eq!(bus.oam[3], 0x10);
+    assert_eq!(bus.oam[4], 0x00);
+
+    // Restarting re-arms the 1 M-cycle delay and starts over at byte 0.
+    bus.write8(0xFF46, 0xC1);
+    bus.tick(4);
+    assert_eq!(bus.oam[0], 0x10);
+    assert_eq!(
+        bus.read8(0xC000),
+        0xFF,
+        "bus stays blocked across the restart"
+    );
+    bus.tick(4);
+    assert_eq!(bus.oam[0], 0xA5);
+
+    bus.tick(4 * 0x9F);
+    for i in 0..0xA0u16 {
+        assert_eq!(bus.read8(0xFE00 + i), (i as u8) ^ 0xA5, "OAM byte {i}");
+    }
+}
+
 #[test]
 fn oam_dma_start_timing_can_miss_current_scanline_sprite_fetch() {
     let cart = Cartridge::from_rom(make_rom()).unwrap();
@@ -211,3 +300,66 @@ fn oam_dma_start_timing_can_miss_current_scanline_sprite_fetch() {
     // Sprite X has not yet copied by mode 3 start, so pixel remains white.
     assert_eq!(bus.ppu.framebuffer()[0], 0xFFFF_FFFF);
 }
+
+#[test]
+fn long_tick_at_fast_rate_counts_every_overflow() {
+    use gb_core::timer::Timer;
+
+    // 16 cycles per TIMA increment from TMA = 0: an overflow every 4096 cycles, with the
+    // interrupt 4 cycles later.
+    let new_timer = || {
+        let mut timer = Timer::new();
+        timer.write_tac(0x05);
+        timer
+    };
+    const CYCLES: u32 = 100_000;
+
+    let mut batched = new_timer();
+    let mut iflag = 0;
+    batched.tick(CYCLES, &mut iflag);
+    assert_eq!(iflag & 0x04, 0x04);
+    assert_eq!(batched.read_tima(), ((CYCLES / 16) % 256) as u8);
+    assert_eq!(batched.read_div(), ((CYCLES & 0xFFFF) >> 8) as u8);
+
+    // Chunks shorter than an overflow period see each interrupt separately.
+    let mut chunked = new_timer();
+    let mut interrupts = 0;
+    for _ in 0..CYCLES / 1000 {
+        let mut iflag = 0;
+        chunked.tick(1000, &mut iflag);
+        interrupts += u32::from(iflag & 0x04 != 0);
+    }
+    assert_eq!(interrupts, (CYCLES - 4) / 4096);
+
+    let mut stepped = new_timer();
+    let mut iflag = 0;
+    for _ in 0..CYCLES {
+        stepped.tick(1, &mut iflag);
+    }
+    for timer in [&chunked, &stepped] {
+        assert_eq!(timer.read_tima(), batched.read_tima());
+        assert_eq!(timer.read_div(), batched.read_div());
+    }
+
+    // Ending partway through the reload matches single-cycle stepping too.
+    for tma in [0x00, 0xF0, 0xFF] {
+        let mut batched = new_timer();
+        let mut stepped = new_timer();
+        batched.write_tma(tma);
+        stepped.write_tma(tma);
+        for len in [4094, 1, 2, 3, 5, 77, 4099, 123_457] {
+            let (mut batched_if, mut stepped_if) = (0, 0);
+            batched.tick(len, &mut batched_if);
+            for _ in 0..len {
+                stepped.tick(1, &mut stepped_if);
+            }
+            assert_eq!(
+                batched.read_tima(),
+                stepped.read_tima(),
+                "tma={tma:02X} len={len}"
+            );
+            assert_eq!(batched.read_div(), stepped.read_div());
+            assert_eq!(batched_if, stepped_if);
+        }
+    }
+}
@@ -1,43 +1,122 @@
 use sdl2::audio::{AudioQueue, AudioSpecDesired};
 
+// Upper bound on queued audio (for the default buffer size); `pump_apu_to_sdl` drops samples
+// beyond it.
+const MAX_QUEUE_MS: u32 = 120;
+/// Queue depth targeted when emulation is paced by audio; well under `MAX_QUEUE_MS` so a
+/// catch-up burst never gets dropped.
+pub const PACING_TARGET_MS: f64 = 60.0;
+/// Length of the ramp to silence queued when emulation pauses.
+const PAUSE_FADE_MS: u32 = 5;
+
+/// SDL device buffer size (sample frames per channel) for about `latency_ms` of audio at
+/// `sample_rate_hz`: rounded up to a power of two, as most backends want, within the
+/// 64..=32768 range SDL accepts.
+pub fn buffer_samples(latency_ms: u32, sample_rate_hz: u32) -> u16 {
+    let samples = u64::from(sample_rate_hz) * u64::from(latency_ms) / 1000;
+    samples.next_power_of_two().clamp(64, 32_768) as u16
+}
+
 pub struct SdlAudio {
     queue: AudioQueue<f32>,
+    // The device's rate; queued audio has been resampled to it.
     sample_rate_hz: u32,
     channels: u8,
+    resampler: Resampler,
+    // Length of the device buffer SDL actually gave us.
+    buffer_ms: f64,
+    // The last sample frame queued, where a pause fade-out starts from.
+    last_frame: Vec<f32>,
 }
 
 impl SdlAudio {
+    /// Opens the default output device at its preferred rate (or `source_rate_hz` when SDL
+    /// can't tell) and resamples the `source_rate_hz` stream to it. `latency_ms` picks the
+    /// device buffer size (see [`buffer_samples`]); `None` leaves it to SDL. Bigger buffers
+    /// add latency but survive longer hitches without underrunning.
     pub fn new(
         audio: &sdl2::AudioSubsystem,
-        sample_rate_hz: i32,
+        source_rate_hz: i32,
         channels: u8,
+        latency_ms: Option<u32>,
     ) -> Result<Self, String> {
-        let sample_rate_hz = u32::try_from(sample_rate_hz)
-            .map_err(|_| format!("invalid sample rate: {sample_rate_hz}"))?;
+        let source_rate_hz = u32::try_from(source_rate_hz)
+            .ok()
+            .filter(|&hz| hz > 0)
+            .ok_or_else(|| format!("invalid sample rate: {source_rate_hz}"))?;
+        let sample_rate_hz = audio
+            .audio_playback_device_spec(0)
+            .ok()
+            .and_then(|spec| u32::try_from(spec.freq).ok())
+            .filter(|&hz| hz > 0)
+            .unwrap_or(source_rate_hz);
         let desired = AudioSpecDesired {
             freq: Some(sample_rate_hz as i32),
             channels: Some(channels),
-            samples: None,
+            samples: latency_ms.map(|ms| buffer_samples(ms, sample_rate_hz)),
         };
 
         let queue = audio.open_queue::<f32, _>(None, &desired)?;
         queue.resume();
 
+        let spec = queue.spec();
+        let buffer_ms = f64::from(spec.samples) * 1000.0 / f64::from(spec.freq.max(1));
+        let sample_rate_hz = u32::try_from(spec.freq).unwrap_or(sample_rate_hz);
         Ok(Self {
             queue,
             sample_rate_hz,
             channels,
+            resampler: Resampler::new(source_rate_hz, sample_rate_hz, channels),
+            buffer_ms,
+            last_frame: vec![0.0; usize::from(channels)],
         })
     }
 
-    pub fn enqueue(&self, samples: &[f32]) -> Result<(), String> {
+    /// Queue depth to pace emulation at: [`PACING_TARGET_MS`], or more when the device
+    /// buffer is large enough that the queue would otherwise drain between callbacks.
+    pub fn pacing_target_ms(&self) -> f64 {
+        PACING_TARGET_MS.max(self.buffer_ms * 2.0)
+    }
+
+    // Upper bound on queued audio; keeps the same headroom over the pacing target.
+    fn max_queue_ms(&self) -> u32 {
+        MAX_QUEUE_MS.max((self.pacing_target_ms() * 2.0).ceil() as u32)
+    }
+
+    /// Queues one device buffer of silence. After an underrun this rebuilds a cushion, so
+    /// the next samples don't each play the moment they arrive and stutter.
+    fn queue_silence(&mut self) -> Result<(), String> {
+        let frames = (self.buffer_ms * f64::from(self.sample_rate_hz) / 1000.0).ceil() as usize;
+        self.enqueue(&vec![0.0; frames * usize::from(self.channels)])
+    }
+
+    pub fn enqueue(&mut self, samples: &[f32]) -> Result<(), String> {
+        let channels = usize::from(self.channels);
+        if samples.len() >= channels {
+            self.last_frame
+                .copy_from_slice(&samples[samples.len() - channels..]);
+        }
         self.queue.queue_audio(samples)
     }
 
+    /// Converts samples from the source rate to the device's.
+    fn resample(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.resampler.process(samples)
+    }
+
     pub fn queued_bytes(&self) -> u32 {
         self.queue.size()
     }
 
+    /// Milliseconds of audio waiting to be played.
+    pub fn queued_ms(&self) -> f64 {
+        let bytes_per_ms = self.max_queue_bytes(1000) as f64 / 1000.0;
+        if bytes_per_ms == 0.0 {
+            return 0.0;
+        }
+        self.queued_bytes() as f64 / bytes_per_ms
+    }
+
     pub fn clear(&self) {
         self.queue.clear();
     }
@@ -50,31 +129,35 @@ impl SdlAudio {
             .saturating_mul(max_queue_ms)
             / 1000
     }
+
+    /// Queued audio as a fraction of the most `pump_apu_to_sdl` will queue.
+    pub fn fill_ratio(&self) -> f32 {
+        let max = self.max_queue_bytes(self.max_queue_ms());
+        if max == 0 {
+            return 0.0;
+        }
+        self.queued_bytes() as f32 / max as f32
+    }
 }
 
 pub fn pump_apu_to_sdl(
     apu: &mut gb_core::apu::Apu,
-    audio: &SdlAudio,
+    audio: &mut SdlAudio,
     volume: f32,
 ) -> Result<(), String> {
-    let mut samples = apu.take_samples();
+    let mut samples = audio.resample(&apply_volume(apu.take_samples(), volume));
     if samples.is_empty() {
         return Ok(());
     }
 
-    let volume = volume.clamp(0.0, 2.0);
-    if (volume - 1.0).abs() > f32::EPSILON {
-        for sample in &mut samples {
-            *sample = (*sample * volume).clamp(-1.0, 1.0);
-        }
-    }
-
-    const MAX_QUEUE_MS: u32 = 120;
-    let max_queue_bytes = audio.max_queue_bytes(MAX_QUEUE_MS);
+    let max_queue_bytes = audio.max_queue_bytes(audio.max_queue_ms());
 
     if audio.queued_bytes() > max_queue_bytes {
         audio.clear();
     }
+    if audio.queued_bytes() == 0 {
+        audio.queue_silence()?;
+    }
 
     let queued_bytes = audio.queued_bytes();
     if queued_bytes >= max_queue_bytes {
@@ -93,3 +176,171 @@ pub fn pump_apu_to_sdl(
 
     audio.enqueue(&samples)
 }
+
+/// Called once when emulation pauses, instead of clearing the queue: queues whatever the APU
+/// already produced followed by a short fade to silence, so the queue plays out without a
+/// click. Resuming refills it through `pump_apu_to_sdl` like after any underrun.
+pub fn drain_apu_on_pause(
+    apu: &mut gb_core::apu::Apu,
+    audio: &mut SdlAudio,
+    volume: f32,
+) -> Result<(), String> {
+    let fade_frames = (audio.sample_rate_hz * PAUSE_FADE_MS / 1000) as usize;
+    let pending = audio.resample(&apply_volume(apu.take_samples(), volume));
+    let samples = with_fade_out(pending, &audio.last_frame, fade_frames);
+    audio.enqueue(&samples)
+}
+
+/// Linear-interpolating sample rate converter for an interleaved stream fed in chunks.
+struct Resampler {
+    channels: usize,
+    // Input frames advanced per output frame.
+    step: f64,
+    // Position of the next output frame, in input frames after `prev`.
+    pos: f64,
+    // The last input frame of the previous chunk, interpolated from at the next one's start.
+    prev: Vec<f32>,
+}
+
+impl Resampler {
+    fn new(from_hz: u32, to_hz: u32, channels: u8) -> Self {
+        let channels = usize::from(channels).max(1);
+        Self {
+            channels,
+            step: f64::from(from_hz) / f64::from(to_hz.max(1)),
+            pos: 0.0,
+            prev: vec![0.0; channels],
+        }
+    }
+
+    fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        if self.step == 1.0 {
+            return input.to_vec();
+        }
+        let ch = self.channels;
+        let frames = input.len() / ch;
+        if frames == 0 {
+            return Vec::new();
+        }
+        // Frame 0 is `prev`, frame `i` is the input's `i - 1`th.
+        let frame = |i: usize| {
+            if i == 0 {
+                &self.prev[..]
+            } else {
+                &input[(i - 1) * ch..i * ch]
+            }
+        };
+
+        let mut out = Vec::with_capacity(((frames as f64 / self.step) as usize + 1) * ch);
+        let mut pos = self.pos;
+        while (pos as usize) < frames {
+            let i = pos as usize;
+            let t = (pos - i as f64) as f32;
+            let (a, b) = (frame(i), frame(i + 1));
+            out.extend(a.iter().zip(b).map(|(&a, &b)| a + (b - a) * t));
+            pos += self.step;
+        }
+        self.pos = pos - frames as f64;
+        self.prev
+            .copy_from_slice(&input[(frames - 1) * ch..frames * ch]);
+        out
+    }
+}
+
+fn apply_volume(mut samples: Vec<f32>, volume: f32) -> Vec<f32> {
+    let volume = volume.clamp(0.0, 2.0);
+    if (volume - 1.0).abs() > f32::EPSILON {
+        for sample in &mut samples {
+            *sample = (*sample * volume).clamp(-1.0, 1.0);
+        }
+    }
+    samples
+}
+
+/// `pending` followed by `fade_frames` sample frames ramping linearly from its last frame
+/// (or `last_queued`, the frame already at the end of the queue, when `pending` is empty)
+/// down to silence.
+fn with_fade_out(mut pending: Vec<f32>, last_queued: &[f32], fade_frames: usize) -> Vec<f32> {
+    let channels = last_queued.len();
+    if channels == 0 {
+        return pending;
+    }
+    let start = if pending.len() >= channels {
+        pending[pending.len() - channels..].to_vec()
+    } else {
+        last_queued.to_vec()
+    };
+    for i in 1..=fade_frames {
+        let gain = 1.0 - i as f32 / fade_frames as f32;
+        pending.extend(start.iter().map(|s| s * gain));
+    }
+    pending
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{buffer_samples, with_fade_out, Resampler};
+
+    #[test]
+    fn latency_is_converted_to_a_power_of_two_sample_count() {
+        assert_eq!(buffer_samples(10, 48_000), 512); // 480
+        assert_eq!(buffer_samples(20, 44_100), 1024); // 882
+        assert_eq!(buffer_samples(50, 48_000), 4096); // 2400
+        assert_eq!(buffer_samples(100, 22_050), 4096); // 2205
+
+        // An exact power of two is kept as is.
+        assert_eq!(buffer_samples(32, 32_000), 1024);
+        // Clamped to what SDL accepts.
+        assert_eq!(buffer_samples(0, 48_000), 64);
+        assert_eq!(buffer_samples(2000, 96_000), 32_768);
+    }
+
+    #[test]
+    fn pausing_keeps_pending_samples_and_fades_out_after_them() {
+        let pending = vec![0.5, -0.5, 0.25, -1.0];
+        let out = with_fade_out(pending.clone(), &[0.0, 0.0], 4);
+        assert_eq!(&out[..4], &pending[..]);
+        assert_eq!(
+            &out[4..],
+            &[0.1875, -0.75, 0.125, -0.5, 0.0625, -0.25, 0.0, 0.0]
+        );
+
+        // Nothing pending: the fade starts from what the queue ends with.
+        let out = with_fade_out(Vec::new(), &[0.8, 0.4], 2);
+        assert_eq!(out, vec![0.4, 0.2, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn resampling_a_tone_keeps_its_frequency_and_duration() {
+        // One second of a 1 kHz stereo sine at 48 kHz, fed in frame-sized chunks.
+        let input: Vec<f32> = (0..48_000)
+            .flat_map(|i| {
+                let s = (i as f32 * 1000.0 * std::f32::consts::TAU / 48_000.0).sin();
+                [s, -s]
+            })
+            .collect();
+        let mut resampler = Resampler::new(48_000, 44_100, 2);
+        let output: Vec<f32> = input
+            .chunks(1600)
+            .flat_map(|chunk| resampler.process(chunk))
+            .collect();
+
+        let frames = output.len() / 2;
+        assert!(frames.abs_diff(44_100) <= 1, "{frames} frames");
+        // Rising zero crossings of the left channel: one per cycle.
+        let left: Vec<f32> = output.iter().step_by(2).copied().collect();
+        let cycles = left
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        assert!(cycles.abs_diff(1000) <= 2, "{cycles} cycles");
+        // The right channel is still the inverse of the left.
+        assert!(output.chunks(2).all(|f| (f[0] + f[1]).abs() < 1e-6));
+
+        // Equal rates pass samples through untouched.
+        assert_eq!(
+            Resampler::new(48_000, 48_000, 2).process(&input[..8]),
+            &input[..8]
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use std::path::{Path, PathBuf};
+
+/// Frontend state kept between runs, stored as `key = value` lines.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub settings: Settings,
+    /// Recently opened ROMs, most recent first; one `recent_rom` line each.
+    pub recent_roms: Vec<PathBuf>,
+}
+
+/// User-adjustable options restored at startup. Enum-like options are stored by their menu
+/// label, so this module doesn't depend on the frontend's types.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub volume: f32,
+    /// `TurboMode` label.
+    pub turbo: String,
+    /// Multiplier for the `Custom` turbo mode.
+    pub custom_speed: f32,
+    /// `TurboMode` label used while the hold-turbo key is down.
+    pub hold_turbo: String,
+    /// `DisplayScale` label.
+    pub display_scale: String,
+    pub integer_scale: bool,
+    pub auto_pause_on_ui: bool,
+    pub pause_on_focus_loss: bool,
+    pub mid_frame_input: bool,
+    /// Name of a `DmgPalette` preset.
+    pub dmg_palette: String,
+    /// `AutosaveInterval` label.
+    pub autosave_interval: String,
+    /// Directory for `.sav` and `.state` files; empty keeps them next to the ROM.
+    pub save_dir: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            turbo: "1x".to_string(),
+            custom_speed: 1.5,
+            hold_turbo: "Uncapped".to_string(),
+            display_scale: "3x".to_string(),
+            integer_scale: false,
+            auto_pause_on_ui: true,
+            pause_on_focus_loss: false,
+            mid_frame_input: false,
+            dmg_palette: gb_core::ppu::dmg_palette::DmgPalette::GRAYSCALE
+                .name
+                .to_string(),
+            autosave_interval: "10 s".to_string(),
+            save_dir: String::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Applies one config line; false if `key` isn't a setting. Unparsable values are ignored.
+    fn set(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "volume" => {
+                if let Ok(v) = value.parse::<f32>() {
+                    self.volume = v.clamp(0.0, 2.0);
+                }
+            }
+            "turbo" => self.turbo = value.to_string(),
+            "hold_turbo" => self.hold_turbo = value.to_string(),
+            "custom_speed" => {
+                if let Ok(v) = value.parse::<f32>() {
+                    self.custom_speed = v.clamp(0.25, 16.0);
+                }
+            }
+            "display_scale" => self.display_scale = value.to_string(),
+            "integer_scale" => self.integer_scale = value.parse().unwrap_or(self.integer_scale),
+            "auto_pause_on_ui" => {
+                self.auto_pause_on_ui = value.parse().unwrap_or(self.auto_pause_on_ui);
+            }
+            "pause_on_focus_loss" => {
+                self.pause_on_focus_loss = value.parse().unwrap_or(self.pause_on_focus_loss);
+            }
+            "mid_frame_input" => {
+                self.mid_frame_input = value.parse().unwrap_or(self.mid_frame_input);
+            }
+            "dmg_palette" => self.dmg_palette = value.to_string(),
+            "autosave_interval" => self.autosave_interval = value.to_string(),
+            "save_dir" => self.save_dir = value.to_string(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn write_to(&self, text: &mut String) {
+        text.push_str(&format!("volume = {}\n", self.volume));
+        text.push_str(&format!("turbo = {}\n", self.turbo));
+        text.push_str(&format!("custom_speed = {}\n", self.custom_speed));
+        text.push_str(&format!("hold_turbo = {}\n", self.hold_turbo));
+        text.push_str(&format!("display_scale = {}\n", self.display_scale));
+        text.push_str(&format!("integer_scale = {}\n", self.integer_scale));
+        text.push_str(&format!("auto_pause_on_ui = {}\n", self.auto_pause_on_ui));
+        text.push_str(&format!(
+            "pause_on_focus_loss = {}\n",
+            self.pause_on_focus_loss
+        ));
+        text.push_str(&format!("mid_frame_input = {}\n", self.mid_frame_input));
+        text.push_str(&format!("dmg_palette = {}\n", self.dmg_palette));
+        text.push_str(&format!("autosave_interval = {}\n", self.autosave_interval));
+        text.push_str(&format!("save_dir = {}\n", self.save_dir));
+    }
+}
+
+impl Config {
+    pub const MAX_RECENT_ROMS: usize = 8;
+
+    /// Moves `path` to the front of the recent list, dropping any older entry for it and the
+    /// oldest entries beyond [`Config::MAX_RECENT_ROMS`].
+    pub fn add_recent_rom(&mut self, path: &Path) {
+        self.recent_roms.retain(|p| p != path);
+        self.recent_roms.insert(0, path.to_path_buf());
+        self.recent_roms.truncate(Self::MAX_RECENT_ROMS);
+    }
+
+    /// `gb-rust/gb-sdl.cfg` under the platform's per-user config directory.
+    pub fn path() -> Option<PathBuf> {
+        let base = if cfg!(windows) {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            std::env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        };
+        base.map(|dir| dir.join("gb-rust").join("gb-sdl.cfg"))
+    }
+
+    /// Loads the config, falling back to defaults when it is missing or unreadable.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map_or_else(Self::default, |text| Self::parse(&text))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("no config directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+        }
+        std::fs::write(&path, self.to_text())
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+
+    /// Unknown keys and malformed lines are ignored; missing keys keep their defaults.
+    fn parse(text: &str) -> Self {
+        let mut config = Self::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            if config.settings.set(key, value) {
+                continue;
+            }
+            if key == "recent_rom" && config.recent_roms.len() < Self::MAX_RECENT_ROMS {
+                config.recent_roms.push(PathBuf::from(value));
+            }
+        }
+        config
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        self.settings.write_to(&mut text);
+        for path in &self.recent_roms {
+            text.push_str(&format!("recent_rom = {}\n", path.display()));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, Settings};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn config_round_trips_and_ignores_unknown_lines() {
+        let config = Config {
+            settings: Settings {
+                volume: 0.35,
+                turbo: "Uncapped".to_string(),
+                custom_speed: 0.5,
+                hold_turbo: "4x".to_string(),
+                display_scale: "1.5x".to_string(),
+                integer_scale: true,
+                auto_pause_on_ui: false,
+                pause_on_focus_loss: true,
+                mid_frame_input: true,
+                dmg_palette: "Inverted".to_string(),
+                autosave_interval: "On exit only".to_string(),
+                save_dir: "/home/me/gb saves".to_string(),
+            },
+            recent_roms: vec![PathBuf::from("/roms/a.gb"), PathBuf::from("/roms/b c.gbc")],
+        };
+        assert_eq!(Config::parse(&config.to_text()), config);
+
+        let parsed = Config::parse("# comment\nshader = crt\n dmg_palette =  High contrast \n");
+        assert_eq!(parsed.settings.dmg_palette, "High contrast");
+        assert_eq!(Config::parse("garbage"), Config::default());
+    }
+
+    #[test]
+    fn malformed_setting_values_keep_defaults() {
+        let parsed = Config::parse("volume = loud\ninteger_scale = yes\nauto_pause_on_ui = 0\n");
+        assert_eq!(parsed.settings, Settings::default());
+        assert_eq!(Config::parse("volume = 9").settings.volume, 2.0);
+        assert_eq!(
+            Config::parse("custom_speed = 0").settings.custom_speed,
+            0.25
+        );
+    }
+
+    #[test]
+    fn recent_roms_are_deduped_and_capped_most_recent_first() {
+        let mut config = Config::default();
+        for i in 0..Config::MAX_RECENT_ROMS + 2 {
+            config.add_recent_rom(Path::new(&format!("{i}.gb")));
+        }
+        assert_eq!(config.recent_roms.len(), Config::MAX_RECENT_ROMS);
+        assert_eq!(config.recent_roms[0], Path::new("9.gb"));
+        assert_eq!(config.recent_roms.last().unwrap(), Path::new("2.gb"));
+
+        // Reopening moves the existing entry to the front instead of duplicating it.
+        config.add_recent_rom(Path::new("5.gb"));
+        assert_eq!(config.recent_roms.len(), Config::MAX_RECENT_ROMS);
+        assert_eq!(config.recent_roms[0], Path::new("5.gb"));
+        assert_eq!(config.recent_roms[1], Path::new("9.gb"));
+        assert_eq!(
+            config
+                .recent_roms
+                .iter()
+                .filter(|p| *p == Path::new("5.gb"))
+                .count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Frames per second averaged over the last `capacity` frame times.
+pub struct RollingFps {
+    frame_times: VecDeque<Duration>,
+    total: Duration,
+    capacity: usize,
+}
+
+impl RollingFps {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            frame_times: VecDeque::with_capacity(capacity),
+            total: Duration::ZERO,
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == self.capacity {
+            if let Some(oldest) = self.frame_times.pop_front() {
+                self.total -= oldest;
+            }
+        }
+        self.frame_times.push_back(frame_time);
+        self.total += frame_time;
+    }
+
+    /// 0 until a frame with a measurable duration has been pushed.
+    pub fn fps(&self) -> f64 {
+        let secs = self.total.as_secs_f64();
+        if secs > 0.0 {
+            self.frame_times.len() as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Measurements for the performance overlay: how fast the main loop runs, and how fast the
+/// emulated machine runs relative to real hardware.
+pub struct FrameStats {
+    fps: RollingFps,
+    last_frame_at: Instant,
+    // Emulated frames are counted over windows of `SPEED_WINDOW` wall time.
+    speed_window_start: Instant,
+    speed_window_frames: u64,
+    speed_percent: f64,
+}
+
+impl FrameStats {
+    const FPS_WINDOW_FRAMES: usize = 60;
+    const SPEED_WINDOW: Duration = Duration::from_millis(500);
+
+    pub fn new(now: Instant) -> Self {
+        Self {
+            fps: RollingFps::new(Self::FPS_WINDOW_FRAMES),
+            last_frame_at: now,
+            speed_window_start: now,
+            speed_window_frames: 0,
+            speed_percent: 0.0,
+        }
+    }
+
+    /// Records the end of a main-loop iteration. `total_frames` is [`GameBoy::frames`] and
+    /// `full_speed_fps` the frame rate of real hardware.
+    ///
+    /// [`GameBoy::frames`]: gb_core::gb::GameBoy::frames
+    pub fn record_frame(&mut self, now: Instant, total_frames: u64, full_speed_fps: f64) {
+        self.fps
+            .push(now.saturating_duration_since(self.last_frame_at));
+        self.last_frame_at = now;
+
+        let elapsed = now.saturating_duration_since(self.speed_window_start);
+        if elapsed >= Self::SPEED_WINDOW {
+            // Loading a ROM resets the frame count; that window just reads as 0%.
+            let frames = total_frames.saturating_sub(self.speed_window_frames);
+            self.speed_percent = frames as f64 / elapsed.as_secs_f64() / full_speed_fps * 100.0;
+            self.speed_window_start = now;
+            self.speed_window_frames = total_frames;
+        }
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.fps.fps()
+    }
+
+    pub fn speed_percent(&self) -> f64 {
+        self.speed_percent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RollingFps;
+    use std::time::Duration;
+
+    #[test]
+    fn rolling_fps_averages_the_most_recent_frames() {
+        let mut fps = RollingFps::new(4);
+        assert_eq!(fps.fps(), 0.0);
+
+        for _ in 0..4 {
+            fps.push(Duration::from_millis(20));
+        }
+        assert!((fps.fps() - 50.0).abs() < 1e-9);
+
+        // Two faster frames replace the two oldest: (10 + 10 + 20 + 20) ms over 4 frames.
+        fps.push(Duration::from_millis(10));
+        fps.push(Duration::from_millis(10));
+        assert!((fps.fps() - 4.0 / 0.06).abs() < 1e-9);
+
+        for _ in 0..4 {
+            fps.push(Duration::from_millis(10));
+        }
+        assert!((fps.fps() - 100.0).abs() < 1e-9);
+    }
+}
@@ -1,36 +1,56 @@
 mod audio;
+mod config;
+mod frame_stats;
+mod pacing;
+mod screenshot;
+mod turbo_buttons;
 
+use config::{Config, Settings};
 use egui::{Context, Key, Modifiers, TopBottomPanel, Window};
 use egui_sdl2_gl::painter::Painter;
 use egui_sdl2_gl::{with_sdl2, DpiScaling, EguiStateHandler, ShaderVersion};
-use gb_core::bus::Bus;
+use frame_stats::FrameStats;
+use gb_core::bus::{Bus, EmulationMode};
 use gb_core::cartridge::Cartridge;
 use gb_core::cpu::Cpu;
-use gb_core::gb::GameBoy;
+use gb_core::gb::{GameBoy, StateError};
+use gb_core::ppu::dmg_palette::DmgPalette;
 use gb_core::ppu::{LCD_HEIGHT, LCD_WIDTH};
-use sdl2::event::Event;
+use pacing::AudioPacer;
+use sdl2::controller::GameController;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
 use sdl2::video::FullscreenType;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
+use turbo_buttons::TurboButtons;
 
 const GB_FPS: f64 = 4_194_304.0 / (456.0 * 154.0);
-const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_WINDOW_TITLE: &str = "gb-sdl";
+const CUSTOM_SPEED_RANGE: std::ops::RangeInclusive<f32> = 0.25..=16.0;
+const LCD_GHOSTING_BLEND: f32 = 0.5;
+// Each rumble request outlasts a frame a few times over, so a stalled loop stops on its own.
+const RUMBLE_DURATION_MS: u32 = 100;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum TurboMode {
     Normal,
     X2,
     X4,
+    /// The user's own multiplier (`App::custom_speed`), from half speed and below up to many
+    /// times normal.
+    Custom,
     Uncapped,
 }
 
 impl TurboMode {
-    fn speed_multiplier(self) -> Option<u32> {
+    /// Emulated frames per real frame time, or `None` for as fast as possible.
+    fn speed_multiplier(self, custom_speed: f32) -> Option<f64> {
         match self {
-            Self::Normal => Some(1),
-            Self::X2 => Some(2),
-            Self::X4 => Some(4),
+            Self::Normal => Some(1.0),
+            Self::X2 => Some(2.0),
+            Self::X4 => Some(4.0),
+            Self::Custom => Some(f64::from(custom_speed)),
             Self::Uncapped => None,
         }
     }
@@ -40,9 +60,80 @@ impl TurboMode {
             Self::Normal => "1x",
             Self::X2 => "2x",
             Self::X4 => "4x",
+            Self::Custom => "Custom",
             Self::Uncapped => "Uncapped",
         }
     }
+
+    fn from_label(label: &str) -> Option<Self> {
+        Self::all().into_iter().find(|m| m.label() == label)
+    }
+
+    fn all() -> [Self; 5] {
+        [
+            Self::Normal,
+            Self::X2,
+            Self::X4,
+            Self::Custom,
+            Self::Uncapped,
+        ]
+    }
+}
+
+/// Wall-clock time per emulated frame at `multiplier` times normal speed.
+fn frame_duration(multiplier: f64) -> Duration {
+    Duration::from_secs_f64(1.0 / (GB_FPS * multiplier))
+}
+
+/// How often battery RAM is written to the `.sav` file while a game runs. It is always
+/// written when the ROM is closed or the emulator exits.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum AutosaveInterval {
+    Secs5,
+    Secs10,
+    Secs30,
+    Min1,
+    Min5,
+    OnExitOnly,
+}
+
+impl AutosaveInterval {
+    fn duration(self) -> Option<Duration> {
+        match self {
+            Self::Secs5 => Some(Duration::from_secs(5)),
+            Self::Secs10 => Some(Duration::from_secs(10)),
+            Self::Secs30 => Some(Duration::from_secs(30)),
+            Self::Min1 => Some(Duration::from_secs(60)),
+            Self::Min5 => Some(Duration::from_secs(300)),
+            Self::OnExitOnly => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Secs5 => "5 s",
+            Self::Secs10 => "10 s",
+            Self::Secs30 => "30 s",
+            Self::Min1 => "1 min",
+            Self::Min5 => "5 min",
+            Self::OnExitOnly => "On exit only",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        Self::all().into_iter().find(|i| i.label() == label)
+    }
+
+    fn all() -> [Self; 6] {
+        [
+            Self::Secs5,
+            Self::Secs10,
+            Self::Secs30,
+            Self::Min1,
+            Self::Min5,
+            Self::OnExitOnly,
+        ]
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -102,6 +193,14 @@ impl DisplayScale {
         }
     }
 
+    /// Parses a `--scale` value: `fit`, or a factor with or without the `x` (`2`, `1.5x`).
+    fn parse(v: &str) -> Option<Self> {
+        let v = v.trim_end_matches(['x', 'X']);
+        Self::all()
+            .into_iter()
+            .find(|s| s.label().trim_end_matches('x').eq_ignore_ascii_case(v))
+    }
+
     fn all() -> [Self; 7] {
         [
             Self::Fit,
@@ -118,47 +217,184 @@ impl DisplayScale {
 struct App {
     gb: GameBoy,
     rom_path: Option<PathBuf>,
+    // IPS/BPS patch applied to `rom_path`, reapplied on hard reset.
+    patch_path: Option<PathBuf>,
     sav_path: Option<PathBuf>,
     state_path: Option<PathBuf>,
+    // The machine as it was before the last state load (see `undo_load_state`).
+    undo_state: Option<Vec<u8>>,
+    // Where `.sav`/`.state` files go instead of next to the ROM.
+    save_dir: Option<PathBuf>,
+    // Set while `save_dir` comes from `--save-dir`, so the configured directory is saved
+    // in its place.
+    save_dir_from_cli: bool,
+    mode_override: Option<EmulationMode>,
     paused: bool,
     turbo: TurboMode,
+    custom_speed: f32,
+    /// Mode used instead of `turbo` while the hold-turbo key (Tab) is down.
+    hold_turbo: TurboMode,
+    turbo_held: bool,
+    turbo_buttons: TurboButtons,
     volume: f32,
+    // Set by `--mute`; kept apart from `volume` so it isn't saved as a setting.
+    muted: bool,
+    audio_sync: bool,
+    mono_audio: bool,
     display_scale: DisplayScale,
     integer_scale: bool,
     fullscreen: bool,
+    lcd_ghosting: bool,
+    fifo_rendering: bool,
+    dmg_palette: DmgPalette,
+    config: Config,
+    // Whether `config` is written back to disk; off for apps built by `with_config`.
+    persist_config: bool,
     auto_pause_on_ui: bool,
+    pause_on_focus_loss: bool,
+    // Re-reads the keyboard every scanline instead of once per frame.
+    mid_frame_input: bool,
+    // Paused because the window lost focus (and `pause_on_focus_loss` is on).
+    focus_paused: bool,
     show_audio_settings: bool,
     show_video_settings: bool,
     show_debug_window: bool,
+    show_perf_overlay: bool,
+    frame_stats: FrameStats,
+    audio_fill: f32,
     status: String,
     last_frame_cycles: u64,
-    total_frames: u64,
     last_battery_save_at: Instant,
+    autosave_interval: AutosaveInterval,
 }
 
 impl App {
+    /// An app using the config file, saving changes back to it.
     fn new() -> Result<Self, String> {
+        let mut app = Self::with_config(Config::load())?;
+        app.persist_config = true;
+        Ok(app)
+    }
+
+    /// An app starting from `config` that never writes it to disk.
+    fn with_config(config: Config) -> Result<Self, String> {
         let gb = Self::default_gameboy()?;
-        Ok(Self {
+        let settings = &config.settings;
+        let mut app = Self {
             gb,
             rom_path: None,
+            patch_path: None,
             sav_path: None,
             state_path: None,
+            undo_state: None,
+            save_dir: (!settings.save_dir.is_empty()).then(|| PathBuf::from(&settings.save_dir)),
+            save_dir_from_cli: false,
+            mode_override: None,
             paused: false,
-            turbo: TurboMode::Normal,
-            volume: 1.0,
-            display_scale: DisplayScale::Scale3x,
-            integer_scale: false,
+            turbo: TurboMode::from_label(&settings.turbo).unwrap_or(TurboMode::Normal),
+            custom_speed: settings.custom_speed,
+            hold_turbo: TurboMode::from_label(&settings.hold_turbo).unwrap_or(TurboMode::Uncapped),
+            turbo_held: false,
+            turbo_buttons: TurboButtons::new(),
+            volume: settings.volume,
+            muted: false,
+            audio_sync: true,
+            mono_audio: false,
+            display_scale: DisplayScale::parse(&settings.display_scale)
+                .unwrap_or(DisplayScale::Scale3x),
+            integer_scale: settings.integer_scale,
             fullscreen: false,
-            auto_pause_on_ui: true,
+            lcd_ghosting: false,
+            fifo_rendering: false,
+            dmg_palette: DmgPalette::by_name(&settings.dmg_palette)
+                .unwrap_or(DmgPalette::GRAYSCALE),
+            auto_pause_on_ui: settings.auto_pause_on_ui,
+            pause_on_focus_loss: settings.pause_on_focus_loss,
+            mid_frame_input: settings.mid_frame_input,
+            focus_paused: false,
+            persist_config: false,
             show_audio_settings: false,
             show_video_settings: false,
             show_debug_window: false,
+            show_perf_overlay: false,
+            frame_stats: FrameStats::new(Instant::now()),
+            audio_fill: 0.0,
             status: "Ready".to_string(),
             last_frame_cycles: 0,
-            total_frames: 0,
             last_battery_save_at: Instant::now(),
-        })
+            autosave_interval: AutosaveInterval::from_label(&settings.autosave_interval)
+                .unwrap_or(AutosaveInterval::Secs10),
+            // Last: `settings` borrows from it.
+            config,
+        };
+        app.apply_frontend_settings();
+        Ok(app)
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            volume: self.volume,
+            turbo: self.turbo.label().to_string(),
+            custom_speed: self.custom_speed,
+            hold_turbo: self.hold_turbo.label().to_string(),
+            display_scale: self.display_scale.label().to_string(),
+            integer_scale: self.integer_scale,
+            auto_pause_on_ui: self.auto_pause_on_ui,
+            pause_on_focus_loss: self.pause_on_focus_loss,
+            mid_frame_input: self.mid_frame_input,
+            dmg_palette: self.dmg_palette.name.to_string(),
+            autosave_interval: self.autosave_interval.label().to_string(),
+            save_dir: if self.save_dir_from_cli {
+                self.config.settings.save_dir.clone()
+            } else {
+                self.save_dir
+                    .as_ref()
+                    .map_or_else(String::new, |dir| dir.display().to_string())
+            },
+        }
+    }
+
+    /// Starts or ends the hold-turbo override. The selected `turbo` mode is left alone, so
+    /// releasing the key goes back to it.
+    fn set_turbo_held(&mut self, held: bool) {
+        self.turbo_held = held;
+    }
+
+    /// The turbo mode in effect: `hold_turbo` while the hold key is down, `turbo` otherwise.
+    fn effective_turbo(&self) -> TurboMode {
+        if self.turbo_held {
+            self.hold_turbo
+        } else {
+            self.turbo
+        }
+    }
+
+    /// The turbo mode in effect for display, with the multiplier spelled out for
+    /// [`TurboMode::Custom`].
+    fn turbo_label(&self) -> String {
+        match self.effective_turbo() {
+            TurboMode::Custom => format!("{}x", self.custom_speed),
+            mode => mode.label().to_string(),
+        }
+    }
+
+    /// Writes the config file if any setting differs from what was last saved or loaded.
+    fn save_settings_if_changed(&mut self) {
+        let settings = self.settings();
+        if settings == self.config.settings {
+            return;
+        }
+        self.config.settings = settings;
+        self.save_config();
+    }
+
+    fn save_config(&mut self) {
+        if !self.persist_config {
+            return;
+        }
+        if let Err(e) = self.config.save() {
+            self.status = format!("Config save failed: {e}");
+        }
     }
 
     fn default_gameboy() -> Result<GameBoy, String> {
@@ -167,39 +403,98 @@ impl App {
         rom[0x0148] = 0x00;
         rom[0x0149] = 0x00;
         let cart = Cartridge::from_rom(rom).map_err(|e| format!("{e:?}"))?;
-        let mut gb = GameBoy {
-            cpu: Cpu::new(),
-            bus: Bus::new(cart),
-        };
-        init_post_boot(&mut gb);
+        let mut gb = GameBoy::new(Cpu::new(), Bus::new(cart));
+        gb.init_post_boot();
         Ok(gb)
     }
 
     fn state_slot_path(&self, slot: QuickSlot) -> Option<PathBuf> {
         self.rom_path.as_ref().map(|rom| {
-            let stem = rom
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .filter(|s| !s.is_empty())
-                .unwrap_or("rom");
-            rom.with_file_name(format!("{stem}.slot{}.state", slot.index()))
+            save_file_path(
+                self.save_dir.as_deref(),
+                rom,
+                &format!("slot{}.state", slot.index()),
+            )
         })
     }
 
+    /// Writes `.sav` and `.state` files for this and later ROMs into `dir` (created if
+    /// missing), or next to the ROM for `None`. Files already written stay where they are;
+    /// only the loaded game's battery RAM is saved to its new location right away.
+    fn set_save_dir(&mut self, dir: Option<PathBuf>) -> Result<(), String> {
+        if let Some(dir) = &dir {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+        }
+        self.battery_save_now();
+        self.save_dir = dir;
+        self.save_dir_from_cli = false;
+        if let Some(rom) = &self.rom_path {
+            self.sav_path = Some(save_file_path(self.save_dir.as_deref(), rom, "sav"));
+            self.state_path = Some(save_file_path(self.save_dir.as_deref(), rom, "state"));
+            self.battery_save_now();
+        }
+        Ok(())
+    }
+
     fn save_state(&mut self, path: &Path) -> Result<(), String> {
-        let bytes = bincode::serialize(&self.gb)
-            .map_err(|e| format!("failed to encode save state: {e}"))?;
+        let bytes = self.gb.to_state_bytes();
         std::fs::write(path, bytes).map_err(|e| format!("failed to write state: {e}"))
     }
 
     fn load_state(&mut self, path: &Path) -> Result<(), String> {
         let bytes = std::fs::read(path).map_err(|e| format!("failed to read state: {e}"))?;
-        let loaded: GameBoy = bincode::deserialize(&bytes)
-            .map_err(|e| format!("failed to decode save state: {e}"))?;
-        self.gb = loaded;
+        self.load_state_bytes(&bytes)
+    }
+
+    /// Replaces the machine with a save state, keeping the replaced one for
+    /// [`App::undo_load_state`].
+    fn load_state_bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let previous = self.gb.to_state_bytes();
+        self.restore_state_bytes(bytes)?;
+        self.undo_state = Some(previous);
+        Ok(())
+    }
+
+    /// Restores the machine as it was before the last state load. One level only.
+    fn undo_load_state(&mut self) -> Result<(), String> {
+        let previous = self.undo_state.take().ok_or("no state load to undo")?;
+        self.restore_state_bytes(&previous)
+    }
+
+    fn restore_state_bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
+        // The state brings its own cartridge RAM; keep the current game's progress on disk.
+        self.battery_save_now();
+        let fingerprint = self.gb.bus.cart.rom_fingerprint();
+        self.gb
+            .load_state_bytes_for(bytes, fingerprint)
+            .map_err(|e| match e {
+                StateError::RomMismatch { .. } => {
+                    "save state belongs to a different ROM; not loaded".to_string()
+                }
+                e => format!("failed to decode save state: {e:?}"),
+            })?;
+        // The DMG palette is part of the PPU state, so the state brought its own.
+        self.apply_frontend_settings();
         Ok(())
     }
 
+    /// Pushes the emulation-side frontend settings (mono audio, FIFO rendering, LCD ghosting
+    /// and the DMG palette) into the machine. The core keeps them across resets and state
+    /// loads, so this runs only when one changes or a new machine is built.
+    fn apply_frontend_settings(&mut self) {
+        self.gb.bus.apu.set_mono(self.mono_audio);
+        self.gb.bus.ppu.set_fifo_rendering(self.fifo_rendering);
+        self.gb.bus.ppu.set_frame_blend(if self.lcd_ghosting {
+            LCD_GHOSTING_BLEND
+        } else {
+            0.0
+        });
+        if self.gb.bus.mode == EmulationMode::Dmg {
+            self.gb.bus.ppu.set_dmg_palette(&self.dmg_palette);
+        }
+    }
+
     fn battery_save_now(&mut self) {
         if let Some(path) = &self.sav_path {
             if let Err(e) = self.gb.bus.save_to_path(path) {
@@ -210,38 +505,195 @@ impl App {
     }
 
     fn maybe_battery_autosave(&mut self) {
-        if self.last_battery_save_at.elapsed() >= AUTOSAVE_INTERVAL {
+        let due = self
+            .autosave_interval
+            .duration()
+            .is_some_and(|interval| self.last_battery_save_at.elapsed() >= interval);
+        if due {
+            self.battery_save_now();
+        }
+    }
+
+    /// Flushes battery RAM when the window loses focus, and pauses until it comes back if
+    /// `pause_on_focus_loss` is set. The MBC3 clock counts emulated cycles, so it stops too.
+    /// Hold-turbo ends as well, since the key's release goes to the other window.
+    fn set_window_focused(&mut self, focused: bool) {
+        if !focused {
             self.battery_save_now();
+            self.set_turbo_held(false);
         }
+        self.focus_paused = !focused && self.pause_on_focus_loss;
+    }
+
+    /// Whether emulation is stopped: by the user, by UI focus, or by the window losing focus.
+    fn emulation_paused(&self, ui_wants_input: bool) -> bool {
+        self.paused || self.focus_paused || (self.auto_pause_on_ui && ui_wants_input)
+    }
+
+    /// Runs `frames` frames unless emulation is paused; returns false (having run nothing)
+    /// if it is. With `mid_frame_input` on, `poll_keys` (the held keyboard buttons as a
+    /// `Button::mask` bitmask) is called every scanline and changes are applied right away.
+    fn run_frames(
+        &mut self,
+        frames: u32,
+        ui_wants_input: bool,
+        mut poll_keys: impl FnMut() -> u8,
+    ) -> bool {
+        if self.emulation_paused(ui_wants_input) {
+            return false;
+        }
+        for _ in 0..frames {
+            self.turbo_buttons.apply(&mut self.gb.bus);
+            let cycles_before = self.gb.cycles();
+            if self.mid_frame_input && !ui_wants_input {
+                let turbo_buttons = &mut self.turbo_buttons;
+                let mut keys = poll_keys();
+                self.gb.run_to_vblank(|bus, _| {
+                    let now = poll_keys();
+                    apply_key_changes(turbo_buttons, bus, keys, now);
+                    keys = now;
+                });
+            } else {
+                self.gb.run_frame();
+            }
+            self.last_frame_cycles = self.gb.cycles() - cycles_before;
+        }
+        self.maybe_battery_autosave();
+        true
+    }
+
+    /// Advances exactly one frame while paused; does nothing while running.
+    fn step_frame(&mut self) {
+        if !self.paused {
+            return;
+        }
+        let cycles_before = self.gb.cycles();
+        self.gb.run_frame();
+        self.last_frame_cycles = self.gb.cycles() - cycles_before;
+        self.status = format!("Stepped to frame {}", self.gb.frames());
+    }
+
+    /// Advances to the next scanline while paused; does nothing while running.
+    fn step_scanline(&mut self) {
+        if !self.paused {
+            return;
+        }
+        self.gb.run_to_next_scanline();
+        self.status = format!("Stepped to LY {}", self.gb.bus.ppu.current_ly());
+    }
+
+    /// Like the console's power switch with the cartridge left in: cartridge RAM and the RTC
+    /// are kept.
+    fn soft_reset(&mut self) {
+        self.battery_save_now();
+        self.gb.reset(false);
+        self.gb.init_post_boot();
+        self.last_frame_cycles = 0;
+        self.status = "Soft reset".to_string();
+    }
+
+    /// Copies the displayed frame to the clipboard as a PNG, or saves it next to the ROM
+    /// (timestamped) when no image clipboard is available.
+    fn copy_screenshot(&mut self) {
+        let mut rgba = vec![0u8; LCD_WIDTH * LCD_HEIGHT * 4];
+        gb_core::ppu::framebuffer_to_rgba8888(self.gb.bus.ppu.present_framebuffer(), &mut rgba);
+        let png = screenshot::encode_png(LCD_WIDTH as u32, LCD_HEIGHT as u32, &rgba);
+        let clipboard_err = match screenshot::copy_png_to_clipboard(&png) {
+            Ok(()) => {
+                self.status = "Copied screenshot to clipboard".to_string();
+                return;
+            }
+            Err(e) => e,
+        };
+
+        let dir = self
+            .rom_path
+            .as_deref()
+            .and_then(Path::parent)
+            .unwrap_or(Path::new("."));
+        let path = dir.join(screenshot::timestamped_filename(
+            self.rom_path.as_deref(),
+            std::time::SystemTime::now(),
+        ));
+        self.status = match std::fs::write(&path, &png) {
+            Ok(()) => format!("{clipboard_err}; saved screenshot to {}", path.display()),
+            Err(e) => format!("{clipboard_err}; failed to save {}: {e}", path.display()),
+        };
+    }
+
+    /// Re-reads the ROM (and patch) from disk. Battery RAM is written out first and read back
+    /// from the `.sav` file; RAM without a battery starts cleared.
+    fn hard_reset(&mut self) -> Result<(), String> {
+        let rom_path = self.rom_path.clone().ok_or("no ROM loaded")?;
+        self.battery_save_now();
+        let patch_path = self.patch_path.clone();
+        self.load_rom_with_patch(rom_path, patch_path.as_deref())?;
+        self.status = "Hard reset".to_string();
+        Ok(())
     }
 
     fn load_rom(&mut self, rom_path: PathBuf) -> Result<(), String> {
+        self.load_rom_with_patch(rom_path, None)
+    }
+
+    fn load_rom_with_patch(
+        &mut self,
+        rom_path: PathBuf,
+        patch_path: Option<&Path>,
+    ) -> Result<(), String> {
         self.battery_save_now();
 
-        let rom = std::fs::read(&rom_path)
+        let mut rom = std::fs::read(&rom_path)
             .map_err(|e| format!("failed to read ROM {}: {e}", rom_path.display()))?;
-        let cart = Cartridge::from_rom(rom).map_err(|e| format!("invalid ROM: {e:?}"))?;
-        let mut gb = GameBoy {
-            cpu: Cpu::new(),
-            bus: Bus::new(cart),
+        if let Some(patch_path) = patch_path {
+            let patch = std::fs::read(patch_path)
+                .map_err(|e| format!("failed to read patch {}: {e}", patch_path.display()))?;
+            gb_core::patch::apply_patch(&mut rom, &patch)
+                .map_err(|e| format!("failed to apply patch {}: {e:?}", patch_path.display()))?;
+        }
+        let (cart, cart_warning) =
+            Cartridge::from_rom_checked(rom).map_err(|e| format!("invalid ROM: {e:?}"))?;
+        let bus = match self.mode_override {
+            Some(mode) => Bus::new_with_mode(cart, mode),
+            None => Bus::new(cart),
         };
-        init_post_boot(&mut gb);
+        let mut gb = GameBoy::new(Cpu::new(), bus);
+        gb.init_post_boot();
 
-        let sav_path = rom_path.with_extension("sav");
-        let state_path = rom_path.with_extension("state");
+        if let Some(dir) = &self.save_dir {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+        }
+        let sav_path = save_file_path(self.save_dir.as_deref(), &rom_path, "sav");
+        let state_path = save_file_path(self.save_dir.as_deref(), &rom_path, "state");
         if let Err(e) = gb.bus.load_from_path(&sav_path) {
             self.status = format!("ROM loaded, save load failed: {e:?}");
         }
+        let save_warning = gb.bus.cart.take_save_warning();
 
         self.gb = gb;
+        self.apply_frontend_settings();
         self.rom_path = Some(rom_path.clone());
+        self.patch_path = patch_path.map(Path::to_path_buf);
         self.sav_path = Some(sav_path);
         self.state_path = Some(state_path);
+        self.undo_state = None;
         self.paused = false;
-        self.total_frames = 0;
         self.last_frame_cycles = 0;
         self.last_battery_save_at = Instant::now();
-        self.status = format!("Loaded {}", rom_path.display());
+        let notes: Vec<String> = cart_warning
+            .map(|w| w.to_string())
+            .into_iter()
+            .chain(save_warning.map(|w| w.to_string()))
+            .collect();
+        self.status = if notes.is_empty() {
+            format!("Loaded {}", rom_path.display())
+        } else {
+            format!("Loaded {} ({})", rom_path.display(), notes.join("; "))
+        };
+
+        self.config.add_recent_rom(&rom_path);
+        self.save_config();
         Ok(())
     }
 
@@ -252,12 +704,17 @@ impl App {
         gb_texture: egui::TextureId,
     ) -> bool {
         let mut request_open_rom = false;
+        let mut request_open_recent: Option<PathBuf> = None;
         let mut request_save_state = false;
         let mut request_load_state = false;
+        let mut request_undo_load = false;
         let mut request_exit = false;
         let mut request_quick_save: Option<QuickSlot> = None;
         let mut request_quick_load: Option<QuickSlot> = None;
         let mut request_resize = false;
+        let mut request_pick_save_dir = false;
+        let mut request_clear_save_dir = false;
+        let mut request_apply_settings = false;
 
         TopBottomPanel::top("menu_top").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
@@ -266,6 +723,24 @@ impl App {
                         request_open_rom = true;
                         ui.close();
                     }
+                    ui.add_enabled_ui(!self.config.recent_roms.is_empty(), |ui| {
+                        ui.menu_button("Open Recent", |ui| {
+                            for path in &self.config.recent_roms {
+                                let label = path.file_name().map_or_else(
+                                    || path.display().to_string(),
+                                    |name| name.to_string_lossy().into_owned(),
+                                );
+                                if ui
+                                    .button(label)
+                                    .on_hover_text(path.display().to_string())
+                                    .clicked()
+                                {
+                                    request_open_recent = Some(path.clone());
+                                    ui.close();
+                                }
+                            }
+                        });
+                    });
                     if ui.button("Save State").clicked() {
                         request_save_state = true;
                         ui.close();
@@ -274,6 +749,16 @@ impl App {
                         request_load_state = true;
                         ui.close();
                     }
+                    if ui
+                        .add_enabled(
+                            self.undo_state.is_some(),
+                            egui::Button::new("Undo Load State"),
+                        )
+                        .clicked()
+                    {
+                        request_undo_load = true;
+                        ui.close();
+                    }
                     ui.separator();
                     for slot in QuickSlot::all() {
                         if ui
@@ -292,6 +777,27 @@ impl App {
                         }
                     }
                     ui.separator();
+                    if ui.button("Save Directory...").clicked() {
+                        request_pick_save_dir = true;
+                        ui.close();
+                    }
+                    if ui
+                        .add_enabled(
+                            self.save_dir.is_some(),
+                            egui::Button::new("Save Next to ROM"),
+                        )
+                        .on_hover_text("Write .sav and .state files beside the ROM")
+                        .clicked()
+                    {
+                        request_clear_save_dir = true;
+                        ui.close();
+                    }
+                    ui.menu_button("Battery Autosave", |ui| {
+                        for interval in AutosaveInterval::all() {
+                            ui.radio_value(&mut self.autosave_interval, interval, interval.label());
+                        }
+                    });
+                    ui.separator();
                     if ui.button("Exit").clicked() {
                         request_exit = true;
                         ui.close();
@@ -306,17 +812,67 @@ impl App {
                         self.paused = !self.paused;
                         ui.close();
                     }
+                    if ui
+                        .add_enabled(self.paused, egui::Button::new("Step Frame"))
+                        .clicked()
+                    {
+                        self.step_frame();
+                    }
+                    if ui
+                        .add_enabled(self.paused, egui::Button::new("Step Scanline"))
+                        .clicked()
+                    {
+                        self.step_scanline();
+                    }
+                    ui.menu_button("Reset", |ui| {
+                        if ui.button("Soft Reset").clicked() {
+                            self.soft_reset();
+                            ui.close();
+                        }
+                        if ui
+                            .add_enabled(self.rom_path.is_some(), egui::Button::new("Hard Reset"))
+                            .clicked()
+                        {
+                            if let Err(e) = self.hard_reset() {
+                                self.status = e;
+                            }
+                            ui.close();
+                        }
+                    });
                     ui.checkbox(&mut self.auto_pause_on_ui, "Auto-pause on UI focus");
+                    ui.checkbox(
+                        &mut self.pause_on_focus_loss,
+                        "Pause when window loses focus",
+                    );
+                    ui.checkbox(&mut self.mid_frame_input, "Poll input every scanline");
                     ui.separator();
                     ui.label("Turbo");
-                    for mode in [
-                        TurboMode::Normal,
-                        TurboMode::X2,
-                        TurboMode::X4,
-                        TurboMode::Uncapped,
-                    ] {
+                    for mode in TurboMode::all() {
                         ui.radio_value(&mut self.turbo, mode, mode.label());
                     }
+                    let slider = ui.add(
+                        egui::Slider::new(&mut self.custom_speed, CUSTOM_SPEED_RANGE)
+                            .logarithmic(true)
+                            .max_decimals(2)
+                            .suffix("x")
+                            .text("Custom speed"),
+                    );
+                    if slider.changed() {
+                        self.turbo = TurboMode::Custom;
+                    }
+                    ui.menu_button("While Holding Tab", |ui| {
+                        for mode in TurboMode::all() {
+                            ui.radio_value(&mut self.hold_turbo, mode, mode.label());
+                        }
+                    });
+                    ui.separator();
+                    ui.label("Auto-fire");
+                    ui.checkbox(&mut self.turbo_buttons.a, "Auto-fire A");
+                    ui.checkbox(&mut self.turbo_buttons.b, "Auto-fire B");
+                    ui.add(
+                        egui::Slider::new(&mut self.turbo_buttons.period_frames, 1..=30)
+                            .text("Frames per toggle"),
+                    );
                 });
 
                 ui.menu_button("Audio", |ui| {
@@ -325,6 +881,10 @@ impl App {
                         ui.close();
                     }
                     ui.add(egui::Slider::new(&mut self.volume, 0.0..=2.0).text("Volume"));
+                    ui.checkbox(&mut self.audio_sync, "Sync to audio");
+                    if ui.checkbox(&mut self.mono_audio, "Mono").changed() {
+                        request_apply_settings = true;
+                    }
                 });
 
                 ui.menu_button("Video", |ui| {
@@ -344,6 +904,12 @@ impl App {
                     }
                     ui.separator();
                     ui.checkbox(&mut self.integer_scale, "Integer scaling");
+                    if ui
+                        .checkbox(&mut self.lcd_ghosting, "LCD ghosting")
+                        .changed()
+                    {
+                        request_apply_settings = true;
+                    }
                     if ui.checkbox(&mut self.fullscreen, "Fullscreen").changed() {
                         let mode = if self.fullscreen {
                             FullscreenType::Desktop
@@ -358,6 +924,7 @@ impl App {
 
                 ui.menu_button("Debug", |ui| {
                     ui.checkbox(&mut self.show_debug_window, "Show debug window");
+                    ui.checkbox(&mut self.show_perf_overlay, "Show FPS overlay");
                 });
             });
         });
@@ -371,7 +938,7 @@ impl App {
                     if self.paused { "Paused" } else { "Running" }
                 ));
                 ui.separator();
-                ui.label(format!("Turbo: {}", self.turbo.label()));
+                ui.label(format!("Turbo: {}", self.turbo_label()));
                 ui.separator();
                 ui.label(format!("Volume: {:.0}%", self.volume * 100.0));
                 ui.separator();
@@ -392,6 +959,8 @@ impl App {
             }
         }
 
+        let overlay_pos = ctx.available_rect().min + egui::vec2(8.0, 8.0);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             let available = ui.available_size();
             let base_w = LCD_WIDTH as f32;
@@ -426,11 +995,28 @@ impl App {
             ui.put(image_rect, image);
         });
 
+        if self.show_perf_overlay {
+            egui::Area::new(egui::Id::new("perf_overlay"))
+                .fixed_pos(overlay_pos)
+                .interactable(false)
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(format!("FPS: {:.1}", self.frame_stats.fps()));
+                        ui.label(format!("Speed: {:.0}%", self.frame_stats.speed_percent()));
+                        ui.label(format!("Audio buffer: {:.0}%", self.audio_fill * 100.0));
+                    });
+                });
+        }
+
         if self.show_audio_settings {
             Window::new("Audio Settings")
                 .open(&mut self.show_audio_settings)
                 .show(ctx, |ui| {
                     ui.add(egui::Slider::new(&mut self.volume, 0.0..=2.0).text("Volume"));
+                    ui.checkbox(&mut self.audio_sync, "Sync to audio");
+                    if ui.checkbox(&mut self.mono_audio, "Mono").changed() {
+                        request_apply_settings = true;
+                    }
                 });
         }
 
@@ -449,6 +1035,33 @@ impl App {
                     }
                     ui.separator();
                     ui.checkbox(&mut self.integer_scale, "Integer scaling");
+                    if ui
+                        .checkbox(&mut self.lcd_ghosting, "LCD ghosting")
+                        .changed()
+                    {
+                        request_apply_settings = true;
+                    }
+                    if ui
+                        .checkbox(
+                            &mut self.fifo_rendering,
+                            "Per-pixel rendering (raster effects)",
+                        )
+                        .changed()
+                    {
+                        request_apply_settings = true;
+                    }
+                    egui::ComboBox::from_label("DMG palette")
+                        .selected_text(self.dmg_palette.name)
+                        .show_ui(ui, |ui| {
+                            for palette in DmgPalette::PRESETS {
+                                if ui
+                                    .selectable_value(&mut self.dmg_palette, palette, palette.name)
+                                    .changed()
+                                {
+                                    request_apply_settings = true;
+                                }
+                            }
+                        });
                     if ui.checkbox(&mut self.fullscreen, "Fullscreen").changed() {
                         let mode = if self.fullscreen {
                             FullscreenType::Desktop
@@ -464,11 +1077,12 @@ impl App {
 
         if self.show_debug_window {
             let paused = self.paused;
-            let turbo = self.turbo.label().to_string();
+            let turbo = self.turbo_label();
             let frame_cycles = self.last_frame_cycles;
-            let total_frames = self.total_frames;
+            let total_frames = self.gb.frames();
             let rom_name = self.rom_display_name();
             let status = self.status.clone();
+            let dump = self.gb.debug_dump();
             Window::new("Debug")
                 .open(&mut self.show_debug_window)
                 .show(ctx, |ui| {
@@ -478,9 +1092,15 @@ impl App {
                     ui.label(format!("Frames: {}", total_frames));
                     ui.label(format!("ROM: {}", rom_name));
                     ui.label(format!("Status: {}", status));
+                    ui.separator();
+                    ui.monospace(dump);
                 });
         }
 
+        if request_apply_settings {
+            self.apply_frontend_settings();
+        }
+
         if request_open_rom {
             if let Some(path) = rfd::FileDialog::new()
                 .add_filter("Game Boy ROM", &["gb", "gbc"])
@@ -492,6 +1112,26 @@ impl App {
             }
         }
 
+        if request_pick_save_dir {
+            if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                if let Err(e) = self.set_save_dir(Some(dir)) {
+                    self.status = e;
+                }
+            }
+        }
+
+        if request_clear_save_dir {
+            if let Err(e) = self.set_save_dir(None) {
+                self.status = e;
+            }
+        }
+
+        if let Some(path) = request_open_recent {
+            if let Err(e) = self.load_rom(path) {
+                self.status = e;
+            }
+        }
+
         if request_save_state {
             if let Some(path) = self.state_path.clone() {
                 if let Err(e) = self.save_state(&path) {
@@ -512,6 +1152,13 @@ impl App {
             }
         }
 
+        if request_undo_load {
+            self.status = match self.undo_load_state() {
+                Ok(()) => "Restored the state from before the last load".to_string(),
+                Err(e) => e,
+            };
+        }
+
         if let Some(slot) = request_quick_save {
             if let Some(path) = self.state_slot_path(slot) {
                 if let Err(e) = self.save_state(&path) {
@@ -545,6 +1192,105 @@ impl App {
     }
 }
 
+/// `<stem>.<extension>` for `rom`'s save files, inside `save_dir` if set and next to the
+/// ROM otherwise. ROMs without a usable stem are saved as `rom.<extension>`.
+fn save_file_path(save_dir: Option<&Path>, rom: &Path, extension: &str) -> PathBuf {
+    let stem = rom
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("rom");
+    let dir = save_dir.or_else(|| rom.parent()).unwrap_or(Path::new(""));
+    dir.join(format!("{stem}.{extension}"))
+}
+
+/// Options given on the command line; see [`parse_args`].
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CliArgs {
+    rom_path: Option<PathBuf>,
+    mode: Option<EmulationMode>,
+    patch_path: Option<PathBuf>,
+    scale: Option<DisplayScale>,
+    fullscreen: bool,
+    mute: bool,
+    save_dir: Option<PathBuf>,
+    audio_latency_ms: Option<u32>,
+}
+
+/// Parses `[--mode auto|dmg|cgb] [--patch FILE] [--scale N] [--fullscreen] [--mute]
+/// [--save-dir DIR] [--audio-latency MS] [rom]` from the process arguments.
+fn parse_args(args: &[String]) -> Result<CliArgs, String> {
+    let mut cli = CliArgs::default();
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--mode" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--mode requires a value".to_string())?;
+                cli.mode = EmulationMode::parse_override(v)?;
+            }
+            "--patch" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--patch requires a value".to_string())?;
+                cli.patch_path = Some(PathBuf::from(v));
+            }
+            "--scale" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--scale requires a value".to_string())?;
+                cli.scale = Some(DisplayScale::parse(v).ok_or_else(|| {
+                    format!("invalid --scale value: {v} (expected fit, 1, 1.5, 2, 3, 4, or 5)")
+                })?);
+            }
+            "--fullscreen" => cli.fullscreen = true,
+            "--mute" => cli.mute = true,
+            "--save-dir" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--save-dir requires a value".to_string())?;
+                cli.save_dir = Some(PathBuf::from(v));
+            }
+            "--audio-latency" => {
+                let v = it
+                    .next()
+                    .ok_or_else(|| "--audio-latency requires a value".to_string())?;
+                cli.audio_latency_ms = Some(
+                    v.parse::<u32>()
+                        .ok()
+                        .filter(|ms| (1..=1000).contains(ms))
+                        .ok_or_else(|| {
+                            format!("invalid --audio-latency value: {v} (expected 1-1000 ms)")
+                        })?,
+                );
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown flag: {arg}")),
+            _ => cli.rom_path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    Ok(cli)
+}
+
+/// `"<game title> — gb-rust"` for a loaded ROM, using the file name when the header title is
+/// blank; the default title when no ROM is loaded.
+fn window_title(header_title: &str, rom_path: Option<&Path>) -> String {
+    let Some(rom_path) = rom_path else {
+        return DEFAULT_WINDOW_TITLE.to_string();
+    };
+    let name = if header_title.is_empty() {
+        rom_path.file_stem().map_or_else(
+            || "Untitled".to_string(),
+            |s| s.to_string_lossy().into_owned(),
+        )
+    } else {
+        header_title.to_string()
+    };
+    format!("{name} — gb-rust")
+}
+
 fn keycode_to_button(key: sdl2::keyboard::Keycode) -> Option<gb_core::input::Button> {
     use gb_core::input::Button;
     use sdl2::keyboard::Keycode;
@@ -562,123 +1308,27 @@ fn keycode_to_button(key: sdl2::keyboard::Keycode) -> Option<gb_core::input::But
     }
 }
 
-fn init_common_io_post_boot(gb: &mut gb_core::gb::GameBoy) {
-    let io_inits: &[(u16, u8)] = &[
-        (0xFF00, 0xCF),
-        (0xFF05, 0x00),
-        (0xFF06, 0x00),
-        (0xFF07, 0x00),
-        (0xFF10, 0x80),
-        (0xFF11, 0xBF),
-        (0xFF12, 0xF3),
-        (0xFF14, 0xBF),
-        (0xFF16, 0x3F),
-        (0xFF17, 0x00),
-        (0xFF19, 0xBF),
-        (0xFF1A, 0x7F),
-        (0xFF1B, 0xFF),
-        (0xFF1C, 0x9F),
-        (0xFF1E, 0xBF),
-        (0xFF20, 0xFF),
-        (0xFF21, 0x00),
-        (0xFF22, 0x00),
-        (0xFF23, 0xBF),
-        (0xFF24, 0x77),
-        (0xFF25, 0xF3),
-        (0xFF26, 0xF1),
-        (0xFF40, 0x91),
-        (0xFF42, 0x00),
-        (0xFF43, 0x00),
-        (0xFF45, 0x00),
-        (0xFF47, 0xFC),
-        (0xFF48, 0xFF),
-        (0xFF49, 0xFF),
-        (0xFF4A, 0x00),
-        (0xFF4B, 0x00),
-    ];
-
-    for &(addr, val) in io_inits {
-        gb.bus.write8(addr, val);
-    }
-}
-
-fn init_dmg_post_boot(gb: &mut gb_core::gb::GameBoy) {
-    // DMG (no-boot-rom) register values commonly used by emulators.
-    gb.cpu.a = 0x01;
-    gb.cpu.f = 0xB0;
-    gb.cpu.b = 0x00;
-    gb.cpu.c = 0x13;
-    gb.cpu.d = 0x00;
-    gb.cpu.e = 0xD8;
-    gb.cpu.h = 0x01;
-    gb.cpu.l = 0x4D;
-    gb.cpu.sp = 0xFFFE;
-    gb.cpu.pc = 0x0100;
-
-    gb.bus.ie = 0x00;
-    gb.bus.iflag = 0x01;
-
-    init_common_io_post_boot(gb);
-}
-
-fn init_cgb_post_boot(gb: &mut gb_core::gb::GameBoy) {
-    // CGB (no-boot-rom) register values commonly used by emulators.
-    gb.cpu.a = 0x11;
-    gb.cpu.f = 0x80;
-    gb.cpu.b = 0x00;
-    gb.cpu.c = 0x00;
-    gb.cpu.d = 0xFF;
-    gb.cpu.e = 0x56;
-    gb.cpu.h = 0x00;
-    gb.cpu.l = 0x0D;
-    gb.cpu.sp = 0xFFFE;
-    gb.cpu.pc = 0x0100;
-
-    gb.bus.ie = 0x00;
-    gb.bus.iflag = 0x01;
-
-    init_common_io_post_boot(gb);
-
-    let cgb_io_inits: &[(u16, u8)] = &[
-        (0xFF4D, 0x00), // KEY1
-        (0xFF4F, 0x00), // VBK
-        (0xFF70, 0x01), // SVBK
-        (0xFF68, 0x00), // BCPS
-        (0xFF69, 0x00), // BCPD
-        (0xFF6A, 0x00), // OCPS
-        (0xFF6B, 0x00), // OCPD
-    ];
-    for &(addr, val) in cgb_io_inits {
-        gb.bus.write8(addr, val);
-    }
-
-    // CGB boot ROM sets BG palette 0 color 0 to white (0x7FFF).
-    // Without this, many CGB games start with a black screen because
-    // palette RAM defaults to zero.
-    gb.bus.ppu.write_bgpi(0x80); // auto-increment, index 0
-    gb.bus.ppu.write_bgpd(0xFF); // low byte of 0x7FFF
-    gb.bus.ppu.write_bgpd(0x7F); // high byte of 0x7FFF
-}
-
-fn init_post_boot(gb: &mut gb_core::gb::GameBoy) {
-    if gb.bus.mode == gb_core::bus::EmulationMode::Cgb {
-        init_cgb_post_boot(gb);
-    } else {
-        init_dmg_post_boot(gb);
-    }
+/// Keyboard buttons held according to SDL's keyboard state, as a `Button::mask` bitmask.
+fn keyboard_buttons(keyboard: &sdl2::keyboard::KeyboardState) -> u8 {
+    keyboard
+        .pressed_scancodes()
+        .filter_map(Keycode::from_scancode)
+        .filter_map(keycode_to_button)
+        .fold(0, |mask, button| mask | button.mask())
 }
 
-fn write_framebuffer_rgba8888_bytes(fb: &gb_core::ppu::Framebuffer, out: &mut [u8]) {
-    assert_eq!(out.len(), fb.len() * 4);
-    for (px, chunk) in fb.iter().zip(out.chunks_exact_mut(4)) {
-        let a = (px >> 24) as u8;
-        let r = (px >> 16) as u8;
-        let g = (px >> 8) as u8;
-        let b = *px as u8;
-        chunk[0] = r;
-        chunk[1] = g;
-        chunk[2] = b;
-        chunk[3] = a;
+/// Presses and releases the buttons whose keys changed between two `keyboard_buttons`
+/// readings, as the KeyDown/KeyUp handlers do.
+fn apply_key_changes(turbo_buttons: &mut TurboButtons, bus: &mut Bus, before: u8, now: u8) {
+    for button in gb_core::input::Button::ALL {
+        if (before ^ now) & button.mask() == 0 {
+            continue;
+        }
+        let pressed = now & button.mask() != 0;
+        turbo_buttons.set_held(button, pressed);
+        if !pressed || !turbo_buttons.handles(button) {
+            bus.set_joypad_button(button, pressed);
+        }
     }
 }
 
@@ -712,16 +1362,29 @@ fn scale_mouse_motion_event_for_egui(event: Event, pixels_per_point: f32) -> Eve
 }
 
 fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cli = parse_args(&args)?;
+
     let sdl = sdl2::init()?;
     let video_subsystem = sdl.video()?;
     let audio_subsystem = sdl.audio()?;
+    // Opened only to drive rumble; SDL reports already-connected controllers as added events.
+    let controller_subsystem = sdl.game_controller()?;
+    let mut controllers: Vec<GameController> = Vec::new();
+    let mut rumbling = false;
 
     let gl_attr = video_subsystem.gl_attr();
     gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
     gl_attr.set_context_version(3, 3);
 
+    // `Fit` has no factor of its own; it starts at the default 3x and follows the window.
+    let initial_scale = cli.scale.and_then(DisplayScale::factor).unwrap_or(3.0);
     let mut window = video_subsystem
-        .window("gb-sdl", (LCD_WIDTH as u32) * 3, (LCD_HEIGHT as u32) * 3)
+        .window(
+            DEFAULT_WINDOW_TITLE,
+            (LCD_WIDTH as f32 * initial_scale) as u32,
+            (LCD_HEIGHT as f32 * initial_scale) as u32,
+        )
         .position_centered()
         .allow_highdpi()
         .opengl()
@@ -774,20 +1437,39 @@ fn main() -> Result<(), String> {
 
     let mut framebuffer_bytes = vec![0u8; LCD_WIDTH * LCD_HEIGHT * 4];
 
-    let audio_out = audio::SdlAudio::new(
+    let mut audio_out = audio::SdlAudio::new(
         &audio_subsystem,
         gb_core::apu::Apu::DEFAULT_SAMPLE_RATE_HZ as i32,
         gb_core::apu::Apu::DEFAULT_CHANNELS,
+        cli.audio_latency_ms,
     )?;
+    // Whether the queue was last left to drain for a pause.
+    let mut audio_paused = false;
 
     let mut app = App::new()?;
-    if let Some(path) = std::env::args().nth(1).map(PathBuf::from) {
-        if let Err(e) = app.load_rom(path) {
+    app.mode_override = cli.mode;
+    if cli.save_dir.is_some() {
+        app.set_save_dir(cli.save_dir)?;
+        app.save_dir_from_cli = true;
+    }
+    if let Some(scale) = cli.scale {
+        app.display_scale = scale;
+    }
+    if cli.mute {
+        app.muted = true;
+    }
+    if cli.fullscreen {
+        app.fullscreen = true;
+        window.set_fullscreen(FullscreenType::Desktop)?;
+    }
+    if let Some(path) = cli.rom_path {
+        if let Err(e) = app.load_rom_with_patch(path, cli.patch_path.as_deref()) {
             app.status = e;
         }
     }
 
     let mut next_frame_at = Instant::now();
+    let audio_pacer = AudioPacer::new(audio_out.pacing_target_ms(), 1000.0 / GB_FPS);
     let app_start = Instant::now();
     let mut event_pump = sdl.event_pump()?;
     let mut ui_wants_input = false;
@@ -836,6 +1518,21 @@ fn main() -> Result<(), String> {
                         continue;
                     }
 
+                    if command && key == Keycode::R {
+                        app.soft_reset();
+                        continue;
+                    }
+
+                    if key == Keycode::Period {
+                        app.step_frame();
+                        continue;
+                    }
+
+                    if key == Keycode::Comma {
+                        app.step_scanline();
+                        continue;
+                    }
+
                     if command && key == Keycode::S {
                         if let Some(path) = app.state_path.clone() {
                             if let Err(e) = app.save_state(&path) {
@@ -869,6 +1566,11 @@ fn main() -> Result<(), String> {
                         continue;
                     }
 
+                    if key == Keycode::F12 {
+                        app.copy_screenshot();
+                        continue;
+                    }
+
                     if key == Keycode::F8 {
                         if let Some(path) = app.state_slot_path(QuickSlot::Slot1) {
                             if let Err(e) = app.load_state(&path) {
@@ -880,20 +1582,59 @@ fn main() -> Result<(), String> {
                         continue;
                     }
 
+                    if key == Keycode::Tab && !ui_wants_input {
+                        app.set_turbo_held(true);
+                        continue;
+                    }
+
                     if !ui_wants_input {
                         if let Some(btn) = keycode_to_button(key) {
-                            app.gb.bus.set_joypad_button(btn, true);
+                            app.turbo_buttons.set_held(btn, true);
+                            if !app.turbo_buttons.handles(btn) {
+                                app.gb.bus.set_joypad_button(btn, true);
+                            }
                         }
                     }
                 }
 
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } => app.set_window_focused(false),
+
+                Event::Window {
+                    win_event: WindowEvent::FocusGained,
+                    ..
+                } => app.set_window_focused(true),
+
+                Event::DropFile { filename, .. } => {
+                    if let Err(e) = app.load_rom(PathBuf::from(filename)) {
+                        app.status = e;
+                    }
+                }
+
+                Event::ControllerDeviceAdded { which, .. } => {
+                    if let Ok(controller) = controller_subsystem.open(which) {
+                        controllers.push(controller);
+                    }
+                }
+
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    controllers.retain(|c| c.instance_id() != which);
+                }
+
+                // Released even while the UI has focus, so turbo can't stick on.
+                Event::KeyUp {
+                    keycode: Some(Keycode::Tab),
+                    ..
+                } => app.set_turbo_held(false),
+
                 Event::KeyUp {
                     keycode: Some(key), ..
-                } => {
-                    if !ui_wants_input {
-                        if let Some(btn) = keycode_to_button(key) {
-                            app.gb.bus.set_joypad_button(btn, false);
-                        }
+                } if !ui_wants_input => {
+                    if let Some(btn) = keycode_to_button(key) {
+                        app.turbo_buttons.set_held(btn, false);
+                        app.gb.bus.set_joypad_button(btn, false);
                     }
                 }
 
@@ -914,16 +1655,23 @@ fn main() -> Result<(), String> {
         if request_exit {
             break 'running;
         }
+        // Not mid-drag, so a slider doesn't rewrite the file every frame.
+        if !ui_wants_input {
+            app.save_settings_if_changed();
+        }
 
-        let should_pause = app.paused || (app.auto_pause_on_ui && ui_wants_input);
+        // At normal speed the audio queue can pace emulation; turbo modes use the wall clock.
+        let turbo = app.effective_turbo();
+        let audio_paced = app.audio_sync && turbo == TurboMode::Normal;
 
         let now = Instant::now();
-        if let Some(multiplier) = app.turbo.speed_multiplier() {
-            let frame_duration = Duration::from_secs_f64(1.0 / (GB_FPS * multiplier as f64));
+        if audio_paced {
+            next_frame_at = now;
+        } else if let Some(multiplier) = turbo.speed_multiplier(app.custom_speed) {
             if now < next_frame_at {
                 std::thread::sleep(next_frame_at - now);
             }
-            next_frame_at += frame_duration;
+            next_frame_at += frame_duration(multiplier);
             if next_frame_at < Instant::now() {
                 next_frame_at = Instant::now();
             }
@@ -931,17 +1679,54 @@ fn main() -> Result<(), String> {
             next_frame_at = now;
         }
 
-        if !should_pause {
-            app.gb.run_frame();
-            app.last_frame_cycles = 0;
-            app.total_frames = app.total_frames.saturating_add(1);
-            app.maybe_battery_autosave();
+        let frames = if audio_paced {
+            audio_pacer.frames_to_run(audio_out.queued_ms())
         } else {
-            audio_out.clear();
+            1
+        };
+        let should_pause = !app.run_frames(frames, ui_wants_input, || {
+            event_pump.pump_events();
+            keyboard_buttons(&event_pump.keyboard_state())
+        });
+        if !should_pause && frames == 0 {
+            // Queue is full; without vsync this would otherwise spin.
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        // Polled once per frame: the cart reports whether the motor ran at all since the last
+        // poll. Refreshed while on; stopped once when it turns off.
+        let rumble = !should_pause && app.gb.bus.take_rumble_state();
+        if rumble || rumbling {
+            let strength = if rumble { u16::MAX } else { 0 };
+            for controller in &mut controllers {
+                let _ = controller.set_rumble(strength, strength, RUMBLE_DURATION_MS);
+            }
+        }
+        rumbling = rumble;
+
+        let title = window_title(app.gb.bus.cart.title(), app.rom_path.as_deref());
+        if window.title() != title {
+            let _ = window.set_title(&title);
         }
 
-        audio::pump_apu_to_sdl(&mut app.gb.bus.apu, &audio_out, app.volume)?;
-        write_framebuffer_rgba8888_bytes(app.gb.bus.ppu.framebuffer(), &mut framebuffer_bytes);
+        let volume = if app.muted { 0.0 } else { app.volume };
+        if !should_pause {
+            audio::pump_apu_to_sdl(&mut app.gb.bus.apu, &mut audio_out, volume)?;
+        } else if !audio_paused {
+            audio::drain_apu_on_pause(&mut app.gb.bus.apu, &mut audio_out, volume)?;
+        } else {
+            // Stepping from the debugger still runs the APU; queued on resume, its samples
+            // would play as a burst of stale sound.
+            app.gb.bus.apu.take_samples();
+        }
+        audio_paused = should_pause;
+        app.audio_fill = audio_out.fill_ratio();
+        app.frame_stats
+            .record_frame(Instant::now(), app.gb.frames(), GB_FPS);
+        gb_core::ppu::framebuffer_to_rgba8888(
+            app.gb.bus.ppu.present_framebuffer(),
+            &mut framebuffer_bytes,
+        );
         painter.update_user_texture_rgba8_data(gb_texture, framebuffer_bytes.clone());
 
         let clipped = egui_ctx.tessellate(full_output.shapes, full_output.pixels_per_point);
@@ -965,6 +1750,7 @@ fn main() -> Result<(), String> {
         }
     }
 
+    app.save_settings_if_changed();
     app.battery_save_now();
 
     Ok(())
@@ -972,10 +1758,35 @@ fn main() -> Result<(), String> {
 
 #[cfg(test)]
 mod tests {
-    use super::{keycode_to_button, write_framebuffer_rgba8888_bytes};
+    use super::{
+        frame_duration, keycode_to_button, parse_args, save_file_path, window_title, App,
+        AutosaveInterval, CliArgs, Config, DisplayScale, QuickSlot, TurboMode,
+        DEFAULT_WINDOW_TITLE, GB_FPS,
+    };
+    use gb_core::bus::{Bus, EmulationMode};
+    use gb_core::cartridge::Cartridge;
+    use gb_core::cpu::Cpu;
+    use gb_core::gb::GameBoy;
     use gb_core::input::Button;
-    use gb_core::ppu::FRAMEBUFFER_LEN;
     use sdl2::keyboard::Keycode;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant};
+
+    /// An app running a blank MBC1 cartridge with 8 KiB of battery RAM, saving to a fresh
+    /// temporary `.sav` path (returned too) tagged with `name`.
+    fn battery_app(name: &str) -> (App, PathBuf) {
+        let mut rom = vec![0u8; 0x8000];
+        rom[0x0147] = 0x03; // MBC1 + RAM + battery
+        rom[0x0149] = 0x02; // 8 KiB
+        let sav_path =
+            std::env::temp_dir().join(format!("gb-sdl-{name}-{}.sav", std::process::id()));
+        let _ = std::fs::remove_file(&sav_path);
+
+        let mut app = App::with_config(Config::default()).unwrap();
+        app.gb = GameBoy::new(Cpu::new(), Bus::new(Cartridge::from_rom(rom).unwrap()));
+        app.sav_path = Some(sav_path.clone());
+        (app, sav_path)
+    }
 
     #[test]
     fn keycode_mapping_matches_expected_buttons() {
@@ -986,15 +1797,290 @@ mod tests {
     }
 
     #[test]
-    fn framebuffer_argb_to_rgba_conversion_is_stable() {
-        let mut fb = [0u32; FRAMEBUFFER_LEN];
-        fb[0] = 0xFF00_0000; // opaque black
-        fb[1] = 0x1122_3344; // A,R,G,B
+    fn args_accept_mode_override_and_rom_path() {
+        let args: Vec<String> = ["--mode", "dmg", "game.gbc"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let cli = parse_args(&args).unwrap();
+        assert_eq!(
+            cli.rom_path.as_deref(),
+            Some(std::path::Path::new("game.gbc"))
+        );
+        assert_eq!(cli.mode, Some(EmulationMode::Dmg));
+
+        let args = vec!["--mode".to_string(), "auto".to_string()];
+        assert_eq!(parse_args(&args).unwrap(), CliArgs::default());
+    }
+
+    #[test]
+    fn args_accept_patch_path() {
+        let args: Vec<String> = ["game.gb", "--patch", "hack.ips"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let cli = parse_args(&args).unwrap();
+        assert_eq!(
+            cli.rom_path.as_deref(),
+            Some(std::path::Path::new("game.gb"))
+        );
+        assert_eq!(
+            cli.patch_path.as_deref(),
+            Some(std::path::Path::new("hack.ips"))
+        );
+        assert!(parse_args(&["--patch".to_string()]).is_err());
+    }
+
+    #[test]
+    fn args_configure_scale_fullscreen_mute_and_save_dir() {
+        let args: Vec<String> = [
+            "--scale",
+            "1.5",
+            "--fullscreen",
+            "--mute",
+            "--mode",
+            "cgb",
+            "--save-dir",
+            "saves",
+            "--audio-latency",
+            "40",
+            "game.gb",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            parse_args(&args).unwrap(),
+            CliArgs {
+                rom_path: Some(PathBuf::from("game.gb")),
+                mode: Some(EmulationMode::Cgb),
+                patch_path: None,
+                scale: Some(DisplayScale::Scale1_5x),
+                fullscreen: true,
+                mute: true,
+                save_dir: Some(PathBuf::from("saves")),
+                audio_latency_ms: Some(40),
+            }
+        );
+
+        assert_eq!(DisplayScale::parse("4x"), Some(DisplayScale::Scale4x));
+        assert_eq!(DisplayScale::parse("FIT"), Some(DisplayScale::Fit));
+        assert!(parse_args(&["--scale".to_string(), "7".to_string()]).is_err());
+        assert!(parse_args(&["--save-dir".to_string()]).is_err());
+        assert!(parse_args(&["--audio-latency".to_string(), "0".to_string()]).is_err());
+    }
+
+    #[test]
+    fn undo_load_state_restores_the_machine_replaced_by_a_load() {
+        let mut app = App::with_config(Config::default()).unwrap();
+        assert!(app.undo_load_state().is_err());
+
+        app.gb.bus.write8(0xC000, 0x11);
+        let saved = app.gb.to_state_bytes();
+        app.gb.bus.write8(0xC000, 0x22);
+
+        app.load_state_bytes(&saved).unwrap();
+        assert_eq!(app.gb.bus.read8(0xC000), 0x11);
+
+        app.undo_load_state().unwrap();
+        assert_eq!(app.gb.bus.read8(0xC000), 0x22);
+        assert!(app.undo_load_state().is_err());
+
+        // A failed load leaves the machine and the undo buffer alone.
+        app.load_state_bytes(&saved).unwrap();
+        assert!(app.load_state_bytes(b"not a state").is_err());
+        assert_eq!(app.gb.bus.read8(0xC000), 0x11);
+        app.undo_load_state().unwrap();
+        assert_eq!(app.gb.bus.read8(0xC000), 0x22);
+    }
 
-        let mut bytes = vec![0u8; FRAMEBUFFER_LEN * 4];
-        write_framebuffer_rgba8888_bytes(&fb, &mut bytes);
+    #[test]
+    fn on_exit_only_autosave_never_saves_on_a_timer() {
+        let (mut app, sav_path) = battery_app("autosave");
+        app.autosave_interval = AutosaveInterval::OnExitOnly;
+        // Longer than any timed interval.
+        app.last_battery_save_at = Instant::now() - Duration::from_secs(600);
+
+        app.maybe_battery_autosave();
+        assert!(!sav_path.exists());
+
+        app.battery_save_now();
+        assert_eq!(std::fs::read(&sav_path).unwrap().len(), 0x2000);
+        let _ = std::fs::remove_file(&sav_path);
+    }
+
+    #[test]
+    fn frame_duration_scales_inversely_with_the_multiplier() {
+        let normal = frame_duration(1.0);
+        assert!((normal.as_secs_f64() - 1.0 / GB_FPS).abs() < 1e-9);
+        assert!((normal.as_secs_f64() * 1000.0 - 16.74).abs() < 0.01);
+        for (multiplier, factor) in [(0.5, 2.0), (0.25, 4.0), (1.5, 1.0 / 1.5), (8.0, 0.125)] {
+            let expected = normal.as_secs_f64() * factor;
+            assert!(
+                (frame_duration(multiplier).as_secs_f64() - expected).abs() < 1e-9,
+                "{multiplier}x"
+            );
+        }
+
+        assert_eq!(TurboMode::Custom.speed_multiplier(0.5), Some(0.5));
+        assert_eq!(TurboMode::X4.speed_multiplier(0.5), Some(4.0));
+        assert_eq!(TurboMode::Uncapped.speed_multiplier(0.5), None);
+    }
+
+    #[test]
+    fn holding_turbo_overrides_the_selected_mode_until_release() {
+        let mut app = App::with_config(Config::default()).unwrap();
+        app.turbo = TurboMode::X2;
+        app.hold_turbo = TurboMode::Uncapped;
+        assert_eq!(app.effective_turbo(), TurboMode::X2);
+
+        app.set_turbo_held(true);
+        assert_eq!(app.effective_turbo(), TurboMode::Uncapped);
+        assert_eq!(app.turbo, TurboMode::X2);
+        assert_eq!(app.settings().turbo, "2x");
+
+        // Picking another mode while held takes effect on release.
+        app.turbo = TurboMode::X4;
+        assert_eq!(app.effective_turbo(), TurboMode::Uncapped);
+        app.set_turbo_held(false);
+        assert_eq!(app.effective_turbo(), TurboMode::X4);
+
+        // Losing focus ends the hold, as the key's release never arrives.
+        app.set_turbo_held(true);
+        app.set_window_focused(false);
+        assert_eq!(app.effective_turbo(), TurboMode::X4);
+    }
 
-        assert_eq!(&bytes[0..4], &[0x00, 0x00, 0x00, 0xFF]);
-        assert_eq!(&bytes[4..8], &[0x22, 0x33, 0x44, 0x11]);
+    #[test]
+    fn save_files_go_under_the_save_dir_named_by_the_rom_stem() {
+        let saves = Path::new("/saves");
+        let rom = Path::new("/roms/Pokemon Red (v1.1) [!].gb");
+        assert_eq!(
+            save_file_path(Some(saves), rom, "sav"),
+            Path::new("/saves/Pokemon Red (v1.1) [!].sav")
+        );
+        assert_eq!(
+            save_file_path(None, rom, "state"),
+            Path::new("/roms/Pokemon Red (v1.1) [!].state")
+        );
+        // Only the last extension is dropped, so dotted names keep their version suffix.
+        assert_eq!(
+            save_file_path(Some(saves), Path::new("/roms/game.v1.2.gbc"), "slot3.state"),
+            Path::new("/saves/game.v1.2.slot3.state")
+        );
+        assert_eq!(
+            save_file_path(Some(saves), Path::new("/roms/.gb"), "sav"),
+            Path::new("/saves/.gb.sav")
+        );
+        assert_eq!(
+            save_file_path(Some(saves), Path::new("/"), "sav"),
+            Path::new("/saves/rom.sav")
+        );
+        assert_eq!(
+            save_file_path(None, Path::new("game.gb"), "sav"),
+            Path::new("game.sav")
+        );
+
+        let mut app = App::with_config(Config::default()).unwrap();
+        app.save_dir = Some(PathBuf::from("/saves"));
+        app.rom_path = Some(PathBuf::from("/roms/tetris.gb"));
+        assert_eq!(
+            app.state_slot_path(QuickSlot::Slot2).unwrap(),
+            Path::new("/saves/tetris.slot2.state")
+        );
+    }
+
+    #[test]
+    fn save_dir_from_the_command_line_is_not_saved_as_a_setting() {
+        let mut config = Config::default();
+        config.settings.save_dir = "/configured".to_string();
+        let mut app = App::with_config(config).unwrap();
+        app.save_dir = Some(PathBuf::from("/from-cli"));
+        app.save_dir_from_cli = true;
+        assert_eq!(app.settings().save_dir, "/configured");
+
+        // Picking a directory in the menu makes it the setting again.
+        let dir = std::env::temp_dir().join(format!("gb-sdl-saves-{}", std::process::id()));
+        app.set_save_dir(Some(dir.clone())).unwrap();
+        assert_eq!(app.settings().save_dir, dir.display().to_string());
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn battery_ram_is_flushed_before_a_state_load_replaces_it() {
+        let (mut app, sav_path) = battery_app("flush");
+        app.gb.bus.write8(0x0000, 0x0A); // enable cartridge RAM
+        let saved = app.gb.to_state_bytes();
+        app.gb.bus.write8(0xA000, 0x42);
+
+        app.load_state_bytes(&saved).unwrap();
+        assert_eq!(app.gb.bus.read8(0xA000), 0x00);
+        assert_eq!(std::fs::read(&sav_path).unwrap()[0], 0x42);
+        let _ = std::fs::remove_file(&sav_path);
+    }
+
+    #[test]
+    fn no_frames_run_while_paused_for_focus_loss() {
+        let mut app = App::with_config(Config::default()).unwrap();
+        app.pause_on_focus_loss = true;
+        assert!(app.run_frames(1, false, || 0));
+        let frames = app.gb.frames();
+
+        app.set_window_focused(false);
+        assert!(!app.run_frames(3, false, || 0));
+        assert_eq!(app.gb.frames(), frames);
+        // The user's own pause state is untouched.
+        assert!(!app.paused);
+
+        app.set_window_focused(true);
+        assert!(app.run_frames(1, false, || 0));
+        assert_eq!(app.gb.frames(), frames + 1);
+
+        // With the option off, losing focus doesn't pause.
+        app.pause_on_focus_loss = false;
+        app.set_window_focused(false);
+        assert!(app.run_frames(1, false, || 0));
+    }
+
+    #[test]
+    fn mid_frame_input_presses_buttons_before_the_frame_ends() {
+        let mut app = App::with_config(Config::default()).unwrap();
+        app.mid_frame_input = true;
+        app.gb.bus.write8(0xFF00, 0x10); // select the action buttons
+
+        let mut polls = 0;
+        assert!(app.run_frames(1, false, || {
+            polls += 1;
+            if polls > 20 {
+                Button::A.mask()
+            } else {
+                0
+            }
+        }));
+        assert!(polls > 100);
+        assert_eq!(app.gb.bus.read8(0xFF00) & 0x01, 0);
+
+        // Releases go through too; with the option off the keyboard isn't polled.
+        assert!(app.run_frames(1, false, || 0));
+        assert_eq!(app.gb.bus.read8(0xFF00) & 0x01, 0x01);
+        app.mid_frame_input = false;
+        assert!(app.run_frames(1, false, || Button::A.mask()));
+        assert_eq!(app.gb.bus.read8(0xFF00) & 0x01, 0x01);
+    }
+
+    #[test]
+    fn window_title_prefers_the_header_title_over_the_file_name() {
+        let mut rom = vec![0u8; 0x8000];
+        rom[0x0134..0x013A].copy_from_slice(b"TETRIS");
+        let titled = Cartridge::from_rom(rom).unwrap();
+        let untitled = Cartridge::from_rom(vec![0u8; 0x8000]).unwrap();
+        let path = Path::new("/roms/tetris (world).gb");
+
+        assert_eq!(window_title(titled.title(), Some(path)), "TETRIS — gb-rust");
+        assert_eq!(
+            window_title(untitled.title(), Some(path)),
+            "tetris (world) — gb-rust"
+        );
+        assert_eq!(window_title(titled.title(), None), DEFAULT_WINDOW_TITLE);
     }
 }
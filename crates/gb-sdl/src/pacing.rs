@@ -0,0 +1,79 @@
+/// Paces emulation by the audio queue instead of the wall clock: each main-loop iteration
+/// runs however many frames keep the queue near `target_ms`, so the audio device's clock
+/// sets the speed and the queue neither drains nor overflows.
+pub struct AudioPacer {
+    target_ms: f64,
+    frame_ms: f64,
+}
+
+impl AudioPacer {
+    /// Catch-up limit per iteration, so a stall (window drag, file dialog) doesn't turn into
+    /// a burst of fast-forward.
+    pub const MAX_FRAMES_PER_STEP: u32 = 4;
+
+    /// `frame_ms` is the audio produced by one emulated frame.
+    pub fn new(target_ms: f64, frame_ms: f64) -> Self {
+        Self {
+            target_ms,
+            frame_ms: frame_ms.max(f64::EPSILON),
+        }
+    }
+
+    /// Frames to run now given `queued_ms` of audio still waiting to be played; 0 when the
+    /// queue is already at or above target.
+    pub fn frames_to_run(&self, queued_ms: f64) -> u32 {
+        let deficit = self.target_ms - queued_ms;
+        if deficit <= 0.0 {
+            return 0;
+        }
+        ((deficit / self.frame_ms).ceil() as u32).min(Self::MAX_FRAMES_PER_STEP)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AudioPacer;
+
+    const FRAME_MS: f64 = 1000.0 / 59.7275;
+
+    #[test]
+    fn pacer_keeps_queue_near_target_while_consumer_drains() {
+        let target = 60.0;
+        let pacer = AudioPacer::new(target, FRAME_MS);
+
+        // A 60 Hz display loop (slightly faster than the Game Boy) and a 144 Hz one.
+        for loop_ms in [1000.0 / 60.0, 1000.0 / 144.0] {
+            let mut queued: f64 = 0.0;
+            let mut frames = 0u64;
+            let iterations = 10_000;
+            for i in 0..iterations {
+                let run = pacer.frames_to_run(queued);
+                frames += u64::from(run);
+                queued += f64::from(run) * FRAME_MS;
+                if i > 10 {
+                    assert!(queued >= target, "underrun at iteration {i}: {queued}");
+                    assert!(
+                        queued < target + FRAME_MS,
+                        "overrun at iteration {i}: {queued}"
+                    );
+                }
+                queued = (queued - loop_ms).max(0.0);
+            }
+
+            // Emulation ran at the audio device's rate, not the loop's.
+            let expected = iterations as f64 * loop_ms / FRAME_MS;
+            assert!(
+                (frames as f64 - expected).abs() < 10.0,
+                "{frames} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn pacer_limits_catch_up_after_a_stall() {
+        let pacer = AudioPacer::new(60.0, FRAME_MS);
+        assert_eq!(pacer.frames_to_run(0.0), AudioPacer::MAX_FRAMES_PER_STEP);
+        assert_eq!(pacer.frames_to_run(60.0), 0);
+        assert_eq!(pacer.frames_to_run(50.0), 1);
+    }
+}
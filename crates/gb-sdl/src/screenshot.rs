@@ -0,0 +1,189 @@
+use gb_core::util::crc32;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `<rom name>-YYYYMMDD-HHMMSS.png` in UTC, so screenshots sort by time next to the ROM.
+pub fn timestamped_filename(rom_path: Option<&Path>, now: SystemTime) -> String {
+    let stem = rom_path
+        .and_then(Path::file_stem)
+        .map_or_else(|| "screenshot".into(), |s| s.to_string_lossy());
+    let secs = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, time) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{stem}-{year:04}{month:02}{day:02}-{:02}{:02}{:02}.png",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Gregorian date of a day count since 1970-01-01 (Howard Hinnant's `civil_from_days`).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Encodes 8-bit RGBA pixels (as produced by `framebuffer_to_rgba8888`) as a PNG.
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let row_len = width as usize * 4;
+    assert_eq!(rgba.len(), row_len * height as usize);
+    // Every scanline starts with its filter type; 0 = none.
+    let mut raw = Vec::with_capacity(rgba.len() + height as usize);
+    for row in rgba.chunks_exact(row_len) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, default compression/filtering, no interlacing.
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    push_chunk(&mut png, b"IHDR", &ihdr);
+    push_chunk(
+        &mut png,
+        b"IDAT",
+        &miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6),
+    );
+    push_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn push_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Puts a PNG on the system clipboard. SDL2's clipboard is text-only, so this goes through
+/// the platform's own tool: `wl-copy` or `xclip` on Linux/BSD, AppleScript on macOS and
+/// PowerShell on Windows. Fails if the tool is missing or refuses the image.
+pub fn copy_png_to_clipboard(png: &[u8]) -> Result<(), String> {
+    let (mut command, via_file) = clipboard_command()?;
+    let temp_path = std::env::temp_dir().join(format!("gb-sdl-clip-{}.png", std::process::id()));
+    if via_file {
+        std::fs::write(&temp_path, png).map_err(|e| format!("failed to write image: {e}"))?;
+        command.arg(clipboard_script(&temp_path));
+    }
+
+    let result = (|| {
+        let mut child = command
+            .stdin(if via_file {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            })
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("no clipboard tool: {e}"))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(png)
+                .map_err(|e| format!("clipboard tool failed: {e}"))?;
+        }
+        let status = child
+            .wait()
+            .map_err(|e| format!("clipboard tool failed: {e}"))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("clipboard tool failed: {status}"))
+        }
+    })();
+    if via_file {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// The clipboard command, and whether it reads the image from a file named in its last
+/// argument (see `clipboard_script`) rather than from stdin.
+fn clipboard_command() -> Result<(Command, bool), String> {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e");
+        Ok((command, true))
+    } else if cfg!(windows) {
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-STA", "-Command"]);
+        Ok((command, true))
+    } else if cfg!(unix) {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            let mut command = Command::new("wl-copy");
+            command.args(["--type", "image/png"]);
+            Ok((command, false))
+        } else {
+            let mut command = Command::new("xclip");
+            command.args(["-selection", "clipboard", "-t", "image/png", "-i"]);
+            Ok((command, false))
+        }
+    } else {
+        Err("image clipboard not supported on this platform".to_string())
+    }
+}
+
+fn clipboard_script(path: &Path) -> String {
+    let path = path.display();
+    if cfg!(target_os = "macos") {
+        format!("set the clipboard to (read (POSIX file \"{path}\") as «class PNGf»)")
+    } else {
+        format!(
+            "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+             [Windows.Forms.Clipboard]::SetImage([Drawing.Image]::FromFile('{path}'))"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32, encode_png, timestamped_filename};
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn timestamped_filename_uses_rom_name_and_utc_time() {
+        // 2024-02-29 13:05:09 UTC.
+        let at = UNIX_EPOCH + Duration::from_secs(1_709_211_909);
+        assert_eq!(
+            timestamped_filename(Some(Path::new("/roms/Tetris DX.gbc")), at),
+            "Tetris DX-20240229-130509.png"
+        );
+        assert_eq!(
+            timestamped_filename(None, UNIX_EPOCH),
+            "screenshot-19700101-000000.png"
+        );
+        // The last second of a year.
+        let at = UNIX_EPOCH + Duration::from_secs(1_735_689_599);
+        assert_eq!(
+            timestamped_filename(Some(Path::new("a.gb")), at),
+            "a-20241231-235959.png"
+        );
+    }
+
+    #[test]
+    fn png_has_signature_header_and_valid_chunk_crcs() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        let png = encode_png(2, 1, &[0xFF, 0, 0, 0xFF, 0, 0xFF, 0, 0xFF]);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xAE\x42\x60\x82");
+    }
+}
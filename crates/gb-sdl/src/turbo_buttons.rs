@@ -0,0 +1,128 @@
+use gb_core::bus::Bus;
+use gb_core::input::Button;
+
+/// Auto-fire for A and B: while the key of a turbo-enabled button is held, the emulated
+/// button alternates between pressed and released every `period_frames` frames.
+pub struct TurboButtons {
+    pub a: bool,
+    pub b: bool,
+    /// Frames spent in each pressed/released phase; 0 is treated as 1.
+    pub period_frames: u32,
+    // Frames each physical key (A, B) has been held; `None` while released.
+    held_frames: [Option<u32>; 2],
+}
+
+impl TurboButtons {
+    pub const DEFAULT_PERIOD_FRAMES: u32 = 2;
+
+    pub fn new() -> Self {
+        Self {
+            a: false,
+            b: false,
+            period_frames: Self::DEFAULT_PERIOD_FRAMES,
+            held_frames: [None; 2],
+        }
+    }
+
+    fn slot(button: Button) -> Option<usize> {
+        match button {
+            Button::A => Some(0),
+            Button::B => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Whether key presses for `button` are driven by [`TurboButtons::apply`] instead of
+    /// going straight to the joypad.
+    pub fn handles(&self, button: Button) -> bool {
+        match button {
+            Button::A => self.a,
+            Button::B => self.b,
+            _ => false,
+        }
+    }
+
+    /// Records the physical key state; call for every key press and release.
+    pub fn set_held(&mut self, button: Button, held: bool) {
+        if let Some(slot) = Self::slot(button) {
+            self.held_frames[slot] = match (held, self.held_frames[slot]) {
+                (true, Some(frames)) => Some(frames),
+                (true, None) => Some(0),
+                (false, _) => None,
+            };
+        }
+    }
+
+    /// Effective pressed state after a key has been held for `frames_held` frames.
+    fn pressed_after(&self, frames_held: u32) -> bool {
+        (frames_held / self.period_frames.max(1)).is_multiple_of(2)
+    }
+
+    /// Drives the held turbo buttons for the next frame; call once before each `run_frame`.
+    pub fn apply(&mut self, bus: &mut Bus) {
+        for button in [Button::A, Button::B] {
+            if !self.handles(button) {
+                continue;
+            }
+            let slot = Self::slot(button).expect("A and B have slots");
+            if let Some(frames) = self.held_frames[slot] {
+                bus.set_joypad_button(button, self.pressed_after(frames));
+                self.held_frames[slot] = Some(frames.saturating_add(1));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TurboButtons;
+    use gb_core::bus::Bus;
+    use gb_core::cartridge::Cartridge;
+    use gb_core::input::Button;
+
+    fn make_bus() -> Bus {
+        let mut rom = vec![0u8; 0x8000];
+        rom[0x0147] = 0x00;
+        rom[0x0148] = 0x00;
+        rom[0x0149] = 0x00;
+        Bus::new(Cartridge::from_rom(rom).unwrap())
+    }
+
+    fn a_pressed(bus: &mut Bus) -> bool {
+        bus.write8(0xFF00, 0x10); // select action buttons
+        bus.read8(0xFF00) & 0x01 == 0
+    }
+
+    #[test]
+    fn held_turbo_button_toggles_every_period() {
+        let mut bus = make_bus();
+        let mut turbo = TurboButtons::new();
+        turbo.a = true;
+        turbo.period_frames = 3;
+        assert!(turbo.handles(Button::A));
+        assert!(!turbo.handles(Button::B));
+
+        turbo.set_held(Button::A, true);
+        let mut schedule = Vec::new();
+        for _ in 0..12 {
+            turbo.apply(&mut bus);
+            schedule.push(a_pressed(&mut bus));
+        }
+        let expected = [true, true, true, false, false, false];
+        assert_eq!(schedule[..6], expected);
+        assert_eq!(schedule[6..], expected);
+
+        // Repeated key-down events (e.g. focus changes) don't restart the schedule.
+        turbo.set_held(Button::A, true);
+        turbo.apply(&mut bus);
+        assert!(a_pressed(&mut bus));
+
+        // Releasing stops auto-fire; the frontend releases the joypad button itself.
+        turbo.set_held(Button::A, false);
+        bus.set_joypad_button(Button::A, false);
+        for _ in 0..4 {
+            turbo.apply(&mut bus);
+            assert!(!a_pressed(&mut bus));
+        }
+    }
+}